@@ -0,0 +1,2 @@
+-- Record whether default features were disabled for the build
+ALTER TABLE solana_program_builds ADD COLUMN no_default_features BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Workspace member to build with `cargo build -p` from the workspace root
+ALTER TABLE solana_program_builds ADD COLUMN workspace_member VARCHAR;
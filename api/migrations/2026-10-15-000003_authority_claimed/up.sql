@@ -0,0 +1,2 @@
+-- Whether the build was claimed by the program upgrade authority through a signed challenge
+ALTER TABLE solana_program_builds ADD COLUMN authority_claimed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Ref and commit resolved by the default ref strategy when no commit hash was given
+ALTER TABLE solana_program_builds ADD COLUMN resolved_ref VARCHAR;
+ALTER TABLE solana_program_builds ADD COLUMN resolved_commit VARCHAR;
@@ -0,0 +1,11 @@
+-- Append-only timeline of every status transition and phase of a build
+CREATE TABLE IF NOT EXISTS build_events (
+    id VARCHAR PRIMARY KEY,
+    build_id VARCHAR NOT NULL,
+    event VARCHAR NOT NULL,
+    detail VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (build_id) REFERENCES solana_program_builds (id)
+);
+
+CREATE INDEX IF NOT EXISTS build_events_build_id_idx ON build_events (build_id);
@@ -0,0 +1,2 @@
+-- Record whether the build ran offline against a vendored registry
+ALTER TABLE solana_program_builds ADD COLUMN vendored BOOLEAN NOT NULL DEFAULT FALSE;
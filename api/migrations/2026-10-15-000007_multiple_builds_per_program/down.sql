@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE solana_program_builds ADD CONSTRAINT solana_program_builds_program_id_key UNIQUE (program_id);
@@ -0,0 +1,2 @@
+-- Allow more than one build per program, e.g. one per pushed commit
+ALTER TABLE solana_program_builds DROP CONSTRAINT IF EXISTS solana_program_builds_program_id_key;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE verified_programs DROP COLUMN extra_digests;
+ALTER TABLE solana_program_builds DROP COLUMN extra_hashes;
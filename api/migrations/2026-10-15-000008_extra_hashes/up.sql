@@ -0,0 +1,3 @@
+-- Extra digest algorithms requested for the build and the resulting digests
+ALTER TABLE solana_program_builds ADD COLUMN extra_hashes TEXT[];
+ALTER TABLE verified_programs ADD COLUMN extra_digests TEXT[];
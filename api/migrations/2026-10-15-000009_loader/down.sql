@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE verified_programs DROP COLUMN loader;
@@ -0,0 +1,2 @@
+-- Loader owning the program when it was verified
+ALTER TABLE verified_programs ADD COLUMN loader VARCHAR;
@@ -0,0 +1,2 @@
+-- Git tag requested for the build
+ALTER TABLE solana_program_builds ADD COLUMN tag VARCHAR;
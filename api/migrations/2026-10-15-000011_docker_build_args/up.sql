@@ -0,0 +1,2 @@
+-- Allowlisted docker build args of the build, stored as sorted KEY=VALUE entries
+ALTER TABLE solana_program_builds ADD COLUMN docker_build_args TEXT[];
@@ -0,0 +1,3 @@
+-- Build command template of the build, replacing the bpf_flag boolean
+ALTER TABLE solana_program_builds ADD COLUMN build_system VARCHAR NOT NULL DEFAULT 'cargo-sbf';
+UPDATE solana_program_builds SET build_system = 'cargo-bpf' WHERE bpf_flag;
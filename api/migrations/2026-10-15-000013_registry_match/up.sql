@@ -0,0 +1,2 @@
+-- Whether the build matched the hash in the on-chain hash registry, NULL without an entry
+ALTER TABLE verified_programs ADD COLUMN registry_match BOOLEAN;
@@ -0,0 +1,3 @@
+-- Dockerfile committed in the repository and the path of the executable in the built image
+ALTER TABLE solana_program_builds ADD COLUMN dockerfile_path VARCHAR;
+ALTER TABLE solana_program_builds ADD COLUMN artifact_path VARCHAR;
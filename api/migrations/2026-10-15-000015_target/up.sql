@@ -0,0 +1,2 @@
+-- Target triple the program is built for
+ALTER TABLE solana_program_builds ADD COLUMN target VARCHAR NOT NULL DEFAULT 'sbf-solana-solana';
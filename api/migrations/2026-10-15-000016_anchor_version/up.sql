@@ -0,0 +1,2 @@
+-- Anchor version the program was built with
+ALTER TABLE solana_program_builds ADD COLUMN anchor_version VARCHAR;
@@ -0,0 +1,2 @@
+-- Whether the latest verification changed whether the program is verified
+ALTER TABLE verified_programs ADD COLUMN status_changed BOOLEAN NOT NULL DEFAULT false;
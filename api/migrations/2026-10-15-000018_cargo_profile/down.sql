@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE solana_program_builds DROP COLUMN profile_overrides;
+ALTER TABLE solana_program_builds DROP COLUMN cargo_profile;
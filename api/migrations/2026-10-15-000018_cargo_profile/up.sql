@@ -0,0 +1,3 @@
+-- Cargo profile the program was built with and the release profile settings overridden
+ALTER TABLE solana_program_builds ADD COLUMN cargo_profile VARCHAR NOT NULL DEFAULT 'release';
+ALTER TABLE solana_program_builds ADD COLUMN profile_overrides TEXT[];
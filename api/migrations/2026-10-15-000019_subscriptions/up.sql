@@ -0,0 +1,9 @@
+-- Webhooks notified of the status changes of a program
+CREATE TABLE IF NOT EXISTS subscriptions (
+    id VARCHAR PRIMARY KEY,
+    program_id VARCHAR NOT NULL,
+    webhook_url VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS subscriptions_program_id_idx ON subscriptions (program_id);
@@ -0,0 +1,2 @@
+-- RUSTFLAGS the program was built with
+ALTER TABLE solana_program_builds ADD COLUMN rustflags TEXT[];
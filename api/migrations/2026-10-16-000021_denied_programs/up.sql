@@ -0,0 +1,6 @@
+-- Programs operators refuse to verify or report the status of
+CREATE TABLE IF NOT EXISTS denied_programs (
+    program_id VARCHAR PRIMARY KEY,
+    reason VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
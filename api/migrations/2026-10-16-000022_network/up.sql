@@ -0,0 +1,2 @@
+-- Cluster the program is deployed to, all builds so far ran against mainnet
+ALTER TABLE solana_program_builds ADD COLUMN network VARCHAR NOT NULL DEFAULT 'mainnet';
@@ -0,0 +1,2 @@
+-- Look up verifications by the hash of the built executable
+CREATE INDEX IF NOT EXISTS verified_programs_executable_hash_idx ON verified_programs (executable_hash);
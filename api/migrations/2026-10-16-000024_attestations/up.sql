@@ -0,0 +1,8 @@
+-- Executable hashes attested by the upgrade authority of programs without source
+CREATE TABLE IF NOT EXISTS attestations (
+    program_id VARCHAR PRIMARY KEY,
+    executable_hash VARCHAR NOT NULL,
+    authority VARCHAR NOT NULL,
+    signature VARCHAR NOT NULL,
+    attested_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+-- SOURCE_DATE_EPOCH requested for the build, the commit date is used when null
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS source_date_epoch BIGINT;
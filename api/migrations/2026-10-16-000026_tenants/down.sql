@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS solana_program_builds_tenant_id_program_id_idx;
+DELETE FROM verified_programs WHERE tenant_id <> 'default';
+ALTER TABLE verified_programs DROP CONSTRAINT IF EXISTS verified_programs_tenant_id_program_id_key;
+ALTER TABLE verified_programs ADD CONSTRAINT verified_programs_program_id_key UNIQUE (program_id);
+
+ALTER TABLE verified_programs DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE solana_program_builds DROP COLUMN IF EXISTS tenant_id;
@@ -0,0 +1,7 @@
+-- Scope builds and verifications to a tenant, each tenant verifying a program on its own
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS tenant_id VARCHAR NOT NULL DEFAULT 'default';
+ALTER TABLE verified_programs ADD COLUMN IF NOT EXISTS tenant_id VARCHAR NOT NULL DEFAULT 'default';
+
+ALTER TABLE verified_programs DROP CONSTRAINT IF EXISTS verified_programs_program_id_key;
+ALTER TABLE verified_programs ADD CONSTRAINT verified_programs_tenant_id_program_id_key UNIQUE (tenant_id, program_id);
+CREATE INDEX IF NOT EXISTS solana_program_builds_tenant_id_program_id_idx ON solana_program_builds (tenant_id, program_id);
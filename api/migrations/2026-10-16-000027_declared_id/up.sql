@@ -0,0 +1,2 @@
+-- Program id declared in the source with declare_id!, null when it could not be read
+ALTER TABLE verified_programs ADD COLUMN IF NOT EXISTS declared_id VARCHAR;
@@ -0,0 +1,3 @@
+-- Cargo.lock supplied to build with in place of the one in the repository
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS lock_file TEXT;
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS lock_file_hash VARCHAR;
@@ -0,0 +1,2 @@
+-- Language the program is written in, naming the builder it is built with
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS language VARCHAR NOT NULL DEFAULT 'rust';
@@ -0,0 +1,2 @@
+-- Key/value labels organizing verifications
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS labels JSONB;
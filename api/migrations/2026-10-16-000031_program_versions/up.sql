@@ -0,0 +1,21 @@
+-- On-chain hashes observed for each program, with the build which verified each of them
+CREATE TABLE IF NOT EXISTS program_versions (
+    tenant_id VARCHAR NOT NULL,
+    program_id VARCHAR NOT NULL,
+    on_chain_hash VARCHAR NOT NULL,
+    first_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    verified_build_id VARCHAR,
+    verified_at TIMESTAMP,
+    PRIMARY KEY (tenant_id, program_id, on_chain_hash)
+);
+
+-- The hash of the latest verification of each program is its first known version
+INSERT INTO program_versions
+    (tenant_id, program_id, on_chain_hash, first_seen_at, last_seen_at, verified_build_id, verified_at)
+SELECT tenant_id, program_id, on_chain_hash, verified_at, verified_at,
+    CASE WHEN is_verified THEN solana_build_id END,
+    CASE WHEN is_verified THEN verified_at END
+FROM verified_programs
+WHERE on_chain_hash <> ''
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,2 @@
+-- Upstream repository a build from a fork declares it was forked from
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS upstream_repo VARCHAR;
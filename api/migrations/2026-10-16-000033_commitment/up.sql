@@ -0,0 +1,2 @@
+-- Commitment level the deployed program was read at
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS commitment VARCHAR NOT NULL DEFAULT 'finalized';
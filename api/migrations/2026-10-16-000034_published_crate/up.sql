@@ -0,0 +1,3 @@
+-- crates.io crate and version of builds from a published crate instead of git
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS crate_name VARCHAR;
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS crate_version VARCHAR;
@@ -0,0 +1,10 @@
+-- Repository or domain owners who claimed a program by proving they control it
+CREATE TABLE IF NOT EXISTS program_claims (
+    tenant_id VARCHAR NOT NULL,
+    program_id VARCHAR NOT NULL,
+    method VARCHAR NOT NULL,
+    claimed_by VARCHAR NOT NULL,
+    proof VARCHAR NOT NULL,
+    claimed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, program_id)
+);
@@ -0,0 +1,2 @@
+-- Warnings raised while building, as a JSON array of `{code, message}`
+ALTER TABLE solana_program_builds ADD COLUMN IF NOT EXISTS warnings JSONB;
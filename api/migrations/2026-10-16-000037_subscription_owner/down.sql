@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS subscriptions_owner_idx;
+ALTER TABLE subscriptions DROP COLUMN IF EXISTS owner;
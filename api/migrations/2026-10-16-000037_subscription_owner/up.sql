@@ -0,0 +1,4 @@
+-- Client which created the subscription, its API key tenant or its IP
+ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS owner VARCHAR NOT NULL DEFAULT '';
+
+CREATE INDEX IF NOT EXISTS subscriptions_owner_idx ON subscriptions (owner);
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use sha2::{Digest, Sha256};
+use tracing::Span;
+
+use crate::tenant::{tenant_of, API_KEY_HEADER};
+
+// Hex characters of the sha256 of an API key kept in the log, enough to tell keys apart
+// without revealing them
+const API_KEY_PREFIX_LEN: usize = 12;
+
+/// Marks a response to a request the report-only rate limiter would have rejected.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitReported;
+
+/// What the rate limiter did with a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    Allowed,
+    // Over the limit, but let through as the limits are not enforced
+    WouldLimit,
+    Limited,
+}
+
+impl RateLimitDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitDecision::Allowed => "allowed",
+            RateLimitDecision::WouldLimit => "would_limit",
+            RateLimitDecision::Limited => "limited",
+        }
+    }
+}
+
+/// How a request was authenticated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthDecision {
+    // No API key nor admin token
+    Anonymous,
+    // A known API key or an admin token which was accepted
+    Authenticated,
+    // Rejected for a missing or wrong admin token, or an unknown API key
+    Denied,
+}
+
+impl AuthDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthDecision::Anonymous => "anonymous",
+            AuthDecision::Authenticated => "authenticated",
+            AuthDecision::Denied => "denied",
+        }
+    }
+}
+
+/// Audit fields of a request, attached to its response for the access log.
+#[derive(Debug, Clone)]
+pub struct AccessAudit {
+    // Prefix of the sha256 of the API key, if one was sent
+    pub api_key: Option<String>,
+    // Tenant the API key resolved to, if it is known
+    pub tenant: Option<String>,
+    pub auth: AuthDecision,
+    pub rate_limit: RateLimitDecision,
+}
+
+// Prefix of the sha256 of an API key, identifying it in the log
+fn api_key_prefix(key: &[u8]) -> String {
+    let mut digest = hex::encode(Sha256::digest(key));
+    digest.truncate(API_KEY_PREFIX_LEN);
+    digest
+}
+
+fn rate_limit_decision(status: StatusCode, reported: bool) -> RateLimitDecision {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        RateLimitDecision::Limited
+    } else if reported {
+        RateLimitDecision::WouldLimit
+    } else {
+        RateLimitDecision::Allowed
+    }
+}
+
+// Credentials are only ever rejected with 401, 403 being for denied programs
+fn auth_decision(status: StatusCode, has_credential: bool) -> AuthDecision {
+    if status == StatusCode::UNAUTHORIZED {
+        AuthDecision::Denied
+    } else if has_credential {
+        AuthDecision::Authenticated
+    } else {
+        AuthDecision::Anonymous
+    }
+}
+
+// Whether the request carries an API key or an admin token
+fn has_credential(headers: &HeaderMap) -> bool {
+    headers.contains_key(API_KEY_HEADER) || headers.contains_key(header::AUTHORIZATION)
+}
+
+/// Attach the `AccessAudit` of each request to its response, for `log_access`.
+pub async fn audit_access<B>(request: Request<B>, next: Next<B>) -> Response {
+    let headers = request.headers();
+    let api_key = headers
+        .get(API_KEY_HEADER)
+        .map(|key| api_key_prefix(key.as_bytes()));
+    let tenant = api_key.as_ref().and_then(|_| tenant_of(headers).ok());
+    let has_credential = has_credential(headers);
+
+    let mut response = next.run(request).await;
+    let audit = AccessAudit {
+        api_key,
+        tenant,
+        auth: auth_decision(response.status(), has_credential),
+        rate_limit: rate_limit_decision(
+            response.status(),
+            response.extensions().get::<RateLimitReported>().is_some(),
+        ),
+    };
+    response.extensions_mut().insert(audit);
+    response
+}
+
+/// Log the response to a request with its audit fields, as the `on_response` hook of the
+/// trace layer.
+pub fn log_access<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    let audit = response.extensions().get::<AccessAudit>();
+    tracing::info!(
+        parent: span,
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        rate_limit = audit.map_or("allowed", |audit| audit.rate_limit.as_str()),
+        auth = audit.map_or("anonymous", |audit| audit.auth.as_str()),
+        api_key = audit.and_then(|audit| audit.api_key.as_deref()),
+        tenant = audit.and_then(|audit| audit.tenant.as_deref()),
+        "finished processing request"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decisions() {
+        assert_eq!(
+            rate_limit_decision(StatusCode::TOO_MANY_REQUESTS, false),
+            RateLimitDecision::Limited
+        );
+        assert_eq!(
+            rate_limit_decision(StatusCode::OK, true),
+            RateLimitDecision::WouldLimit
+        );
+        assert_eq!(
+            auth_decision(StatusCode::UNAUTHORIZED, true),
+            AuthDecision::Denied
+        );
+        assert_eq!(
+            auth_decision(StatusCode::FORBIDDEN, true),
+            AuthDecision::Authenticated
+        );
+        assert_eq!(
+            auth_decision(StatusCode::OK, true),
+            AuthDecision::Authenticated
+        );
+    }
+
+    #[test]
+    fn test_api_key_prefix() {
+        let prefix = api_key_prefix(b"k3y-a");
+        assert_eq!(prefix.len(), API_KEY_PREFIX_LEN);
+        assert_ne!(prefix, api_key_prefix(b"k3y-b"));
+    }
+}
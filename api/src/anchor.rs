@@ -0,0 +1,162 @@
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+use crate::git::{resolve_named_tag, BareClone};
+use crate::Result;
+
+// Anchor releases are tagged in this repository
+const ANCHOR_REPOSITORY: &str = "https://github.com/coral-xyz/anchor";
+
+// Build images published for each Anchor release, by the Solana Foundation since 0.31.0
+const ANCHOR_IMAGE: &str = "solanafoundation/anchor";
+const LEGACY_ANCHOR_IMAGE: &str = "backpackapp/build";
+
+// Printed by docker when the build image of the requested Anchor version cannot be pulled
+const ANCHOR_IMAGE_ERROR_MARKERS: [&str; 2] = ["manifest unknown", "pull access denied"];
+
+#[derive(Debug, Default, Deserialize)]
+struct AnchorToml {
+    #[serde(default)]
+    toolchain: AnchorToolchain,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnchorToolchain {
+    anchor_version: Option<String>,
+}
+
+/// Anchor versions are release versions like `0.29.0` or `0.30.0-rc.1`.
+pub fn is_valid_anchor_version(version: &str) -> bool {
+    let (release, pre_release) = match version.split_once('-') {
+        Some((release, pre_release)) => (release, Some(pre_release)),
+        None => (version, None),
+    };
+    let release_parts: Vec<&str> = release.split('.').collect();
+    release_parts.len() == 3
+        && release_parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        && match pre_release {
+            Some(pre_release) => {
+                !pre_release.is_empty()
+                    && pre_release
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '.')
+            }
+            None => true,
+        }
+}
+
+// Anchor version pinned in the `[toolchain]` section of an Anchor.toml
+fn parse_anchor_version(anchor_toml: &str) -> Option<String> {
+    toml::from_str::<AnchorToml>(anchor_toml)
+        .ok()?
+        .toolchain
+        .anchor_version
+        .filter(|version| is_valid_anchor_version(version))
+}
+
+/// Read the Anchor version pinned by the Anchor.toml of the project at `commit`, found
+/// at the root of the repository or of `mount_path`.
+pub async fn read_anchor_version(
+    clone: &BareClone,
+    commit: Option<&str>,
+    mount_path: Option<&str>,
+) -> Result<Option<String>> {
+    let path = match mount_path.map(|path| path.trim_matches('/')) {
+        Some(mount_path) if !mount_path.is_empty() => format!("{}/Anchor.toml", mount_path),
+        _ => "Anchor.toml".to_string(),
+    };
+    Ok(clone
+        .read_file(commit, &path)
+        .await?
+        .as_deref()
+        .and_then(parse_anchor_version))
+}
+
+/// The build image with the given Anchor release installed.
+pub fn anchor_build_image(version: &str) -> String {
+    let mut parts = version
+        .split(['.', '-'])
+        .map(|part| part.parse::<u64>().unwrap_or_default());
+    let release = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    let image = if release < (0, 31) {
+        LEGACY_ANCHOR_IMAGE
+    } else {
+        ANCHOR_IMAGE
+    };
+    format!("{}:v{}", image, version)
+}
+
+/// Fail with a build error if there is no Anchor release to build with.
+pub async fn ensure_anchor_release(version: &str) -> Result<()> {
+    resolve_named_tag(ANCHOR_REPOSITORY, &format!("v{}", version))
+        .await
+        .map(|_| ())
+        .map_err(|_| {
+            ApiError::Build(format!(
+                "Anchor version {} cannot be used: there is no such Anchor release",
+                version
+            ))
+        })
+}
+
+/// Whether the build output shows that the build image of the Anchor version could not be
+/// pulled.
+pub fn is_anchor_image_failure(output: &str) -> bool {
+    ANCHOR_IMAGE_ERROR_MARKERS
+        .iter()
+        .any(|marker| output.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor_version_validation() {
+        assert!(is_valid_anchor_version("0.29.0"));
+        assert!(is_valid_anchor_version("0.30.0-rc.1"));
+        assert!(!is_valid_anchor_version("0.29"));
+        assert!(!is_valid_anchor_version("latest"));
+        assert!(!is_valid_anchor_version("0.29.0; rm -rf /"));
+        assert!(!is_valid_anchor_version("0.29.0-"));
+    }
+
+    #[test]
+    fn test_anchor_build_image() {
+        assert_eq!(anchor_build_image("0.29.0"), "backpackapp/build:v0.29.0");
+        assert_eq!(anchor_build_image("0.30.1"), "backpackapp/build:v0.30.1");
+        assert_eq!(
+            anchor_build_image("0.31.0-rc.1"),
+            "solanafoundation/anchor:v0.31.0-rc.1"
+        );
+        assert_eq!(
+            anchor_build_image("1.0.0"),
+            "solanafoundation/anchor:v1.0.0"
+        );
+    }
+
+    #[test]
+    fn test_parse_anchor_version() {
+        let anchor_toml = r#"
+[toolchain]
+anchor_version = "0.29.0"
+
+[programs.mainnet]
+phoenix = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"
+"#;
+        assert_eq!(
+            parse_anchor_version(anchor_toml),
+            Some("0.29.0".to_string())
+        );
+        assert_eq!(parse_anchor_version("[programs.mainnet]\n"), None);
+        assert_eq!(
+            parse_anchor_version("[toolchain]\nanchor_version = \"x\"\n"),
+            None
+        );
+    }
+}
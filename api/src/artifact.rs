@@ -0,0 +1,47 @@
+use std::env;
+
+use crate::errors::ApiError;
+use crate::Result;
+
+// Default cap on the size of downloaded artifacts (10 MiB)
+const DEFAULT_MAX_ARTIFACT_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Largest artifact accepted, downloaded or uploaded, from `ARTIFACT_MAX_SIZE`.
+pub fn max_artifact_size() -> u64 {
+    env::var("ARTIFACT_MAX_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_ARTIFACT_SIZE)
+}
+
+/// Download a published executable, failing if it exceeds `ARTIFACT_MAX_SIZE` bytes.
+pub async fn download_artifact(url: &str) -> Result<Vec<u8>> {
+    let max_size = max_artifact_size();
+
+    let mut response = reqwest::Client::new()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?;
+    if let Some(size) = response.content_length() {
+        if size > max_size {
+            return Err(ApiError::ProgramTooLarge {
+                size,
+                limit: max_size,
+            });
+        }
+    }
+
+    // The declared length can not be trusted, so the limit is also checked while reading
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > max_size {
+            return Err(ApiError::ProgramTooLarge {
+                size: bytes.len() as u64,
+                limit: max_size,
+            });
+        }
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,139 @@
+use std::future::Future;
+
+use crate::builder::verify_build;
+use crate::db::DbClient;
+use crate::errors::ApiError;
+use crate::git::list_commits;
+use crate::models::{SolanaProgramBuildParams, VerifiedProgram};
+use crate::Result;
+
+// Outcome of bisecting a commit span for the commit which was deployed
+#[derive(Debug)]
+pub struct BisectResult {
+    pub commit: String,
+    pub builds: usize,
+    pub verified_build: VerifiedProgram,
+}
+
+/// Binary search `0..len` for the first index where `probe` returns a value, assuming
+/// every index after it matches as well. The last index is assumed to match without
+/// being probed. Returns the matching index, the value of the probe if one was run for
+/// it, and the number of probes performed.
+async fn first_match<T, F, Fut>(len: usize, mut probe: F) -> (usize, Option<T>, usize)
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let (mut low, mut high) = (0, len.saturating_sub(1));
+    let mut matched = None;
+    let mut probes = 0;
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        probes += 1;
+        match probe(mid).await {
+            Some(value) => {
+                high = mid;
+                matched = Some(value);
+            }
+            None => low = mid + 1,
+        }
+    }
+    (high, matched, probes)
+}
+
+// Build a single commit, treating build failures as a mismatch
+async fn build_commit(
+    db: &DbClient,
+    payload: &SolanaProgramBuildParams,
+    build_id: &str,
+    commit: &str,
+) -> Option<VerifiedProgram> {
+    let mut params = payload.clone();
+    params.commit_hash = Some(commit.to_string());
+
+    match verify_build(db, params, build_id).await {
+        Ok((verified_build, _)) => Some(verified_build),
+        Err(err) => {
+            tracing::warn!("Bisect build of {} failed: {:?}", commit, err);
+            None
+        }
+    }
+}
+
+/// Find the first commit after `bad_commit` up to `good_commit` whose build matches the
+/// on-chain program, building as few commits as possible.
+pub async fn bisect_build(
+    db: &DbClient,
+    payload: SolanaProgramBuildParams,
+    build_id: &str,
+    bad_commit: &str,
+    good_commit: &str,
+) -> Result<BisectResult> {
+    let commits = list_commits(&payload.repository, bad_commit, good_commit).await?;
+    if commits.is_empty() {
+        return Err(ApiError::Build(format!(
+            "{} is not a descendant of {}",
+            good_commit, bad_commit
+        )));
+    }
+    db.record_event(
+        build_id,
+        "bisect_started",
+        Some(&format!("{} commits to search", commits.len())),
+    )
+    .await;
+
+    let (payload, commits) = (&payload, &commits);
+    let (index, matched, mut builds) = first_match(commits.len(), move |i| {
+        let commit = &commits[i];
+        async move {
+            build_commit(db, payload, build_id, commit)
+                .await
+                .filter(|verified_build| verified_build.is_verified)
+        }
+    })
+    .await;
+    let commit = commits[index].clone();
+
+    // The known-good commit is only built if no earlier commit matched
+    let verified_build = match matched {
+        Some(verified_build) => verified_build,
+        None => {
+            builds += 1;
+            match build_commit(db, payload, build_id, &commit).await {
+                Some(verified_build) if verified_build.is_verified => verified_build,
+                _ => {
+                    return Err(ApiError::Build(format!(
+                        "Known-good commit {} does not match the on-chain program",
+                        commit
+                    )))
+                }
+            }
+        }
+    };
+
+    Ok(BisectResult {
+        commit,
+        builds,
+        verified_build,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn search(len: usize, first: usize) -> (usize, Option<usize>, usize) {
+        first_match(len, |i| async move { (i >= first).then_some(i) }).await
+    }
+
+    #[tokio::test]
+    async fn test_first_match() {
+        assert_eq!(search(100, 37).await, (37, Some(37), 6));
+        assert_eq!(search(100, 0).await, (0, Some(0), 7));
+        // Only the assumed last index matches, so it is never probed
+        assert_eq!(search(100, 99).await, (99, None, 6));
+        assert_eq!(search(1, 0).await, (0, None, 0));
+    }
+}
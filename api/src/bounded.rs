@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use serde::de::{Error, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+/// Most elements accepted in an array or object of a request body. Longer ones are rejected
+/// while they are parsed, before being collected, so a huge body cannot exhaust memory. The
+/// validation of each field applies its own, lower, limit afterwards.
+pub const MAX_ELEMENTS: usize = 256;
+
+struct VecVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for VecVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an array of at most {} elements", MAX_ELEMENTS)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_ELEMENTS));
+        while let Some(value) = seq.next_element()? {
+            if values.len() == MAX_ELEMENTS {
+                return Err(A::Error::invalid_length(MAX_ELEMENTS + 1, &self));
+            }
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+struct MapVisitor<K, V>(PhantomData<(K, V)>);
+
+impl<'de, K, V> Visitor<'de> for MapVisitor<K, V>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    type Value = HashMap<K, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an object of at most {} entries", MAX_ELEMENTS)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = HashMap::with_capacity(map.size_hint().unwrap_or(0).min(MAX_ELEMENTS));
+        while let Some((key, value)) = map.next_entry()? {
+            if entries.len() == MAX_ELEMENTS {
+                return Err(A::Error::invalid_length(MAX_ELEMENTS + 1, &self));
+            }
+            entries.insert(key, value);
+        }
+        Ok(entries)
+    }
+}
+
+struct BoundedVec<T>(Vec<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for BoundedVec<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_seq(VecVisitor(PhantomData))
+            .map(BoundedVec)
+    }
+}
+
+struct BoundedMap<K, V>(HashMap<K, V>);
+
+impl<'de, K, V> Deserialize<'de> for BoundedMap<K, V>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_map(MapVisitor(PhantomData))
+            .map(BoundedMap)
+    }
+}
+
+/// Deserialize an array of at most `MAX_ELEMENTS` elements.
+pub fn vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    BoundedVec::deserialize(deserializer).map(|values| values.0)
+}
+
+/// Deserialize an optional array of at most `MAX_ELEMENTS` elements. The field needs
+/// `#[serde(default)]` to still be optional.
+pub fn option_vec<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<BoundedVec<T>>::deserialize(deserializer).map(|values| values.map(|values| values.0))
+}
+
+/// Deserialize an optional object of at most `MAX_ELEMENTS` entries. The field needs
+/// `#[serde(default)]` to still be optional.
+pub fn option_map<'de, D, K, V>(deserializer: D) -> Result<Option<HashMap<K, V>>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    Option::<BoundedMap<K, V>>::deserialize(deserializer)
+        .map(|entries| entries.map(|entries| entries.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::models::{BatchStatusParams, SolanaProgramBuildParams};
+
+    use super::*;
+
+    fn params(extra: serde_json::Value) -> serde_json::Result<SolanaProgramBuildParams> {
+        let mut params = json!({
+            "repository": "https://github.com/Ellipsis-Labs/phoenix-v1",
+            "program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+        });
+        params
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(params)
+    }
+
+    #[test]
+    fn test_bounded_arrays() {
+        assert!(params(json!({})).unwrap().cargo_args.is_none());
+        assert!(params(json!({ "cargo_args": null }))
+            .unwrap()
+            .cargo_args
+            .is_none());
+        let args = params(json!({ "cargo_args": vec!["--locked"; MAX_ELEMENTS] })).unwrap();
+        assert_eq!(args.cargo_args.unwrap().len(), MAX_ELEMENTS);
+        assert!(params(json!({ "cargo_args": vec!["--locked"; MAX_ELEMENTS + 1] })).is_err());
+
+        let build_args: serde_json::Map<String, serde_json::Value> = (0..=MAX_ELEMENTS)
+            .map(|index| (format!("ARG_{}", index), json!("1")))
+            .collect();
+        assert!(params(json!({ "docker_build_args": build_args })).is_err());
+
+        let addresses = vec!["PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"; MAX_ELEMENTS + 1];
+        assert!(
+            serde_json::from_value::<BatchStatusParams>(json!({ "addresses": addresses })).is_err()
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::{BTreeMap, HashMap};
+
+// Docker build args which can be passed through `docker_build_args`
+pub const ALLOWED_BUILD_ARGS: [&str; 4] = [
+    "IMAGE_VARIANT",
+    "RUST_VERSION",
+    "SOLANA_VERSION",
+    "ANCHOR_VERSION",
+];
+
+// Longest build arg value accepted
+pub const MAX_BUILD_ARG_VALUE_LENGTH: usize = 128;
+
+pub fn is_allowed_build_arg(key: &str) -> bool {
+    ALLOWED_BUILD_ARGS.contains(&key)
+}
+
+/// Values are limited to characters found in versions and image tags so they can
+/// not inject other options into the docker invocation.
+pub fn is_valid_build_arg_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_BUILD_ARG_VALUE_LENGTH
+        && !value.starts_with('-')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-+:@/".contains(c))
+}
+
+/// Turn the requested build args into `KEY=VALUE` entries, sorted by key so equal
+/// maps are always stored the same way.
+pub fn build_args_to_entries(args: &HashMap<String, String>) -> Vec<String> {
+    args.iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect()
+}
+
+/// Turn stored `KEY=VALUE` entries into a map for responses.
+pub fn build_args_to_map(entries: &[String]) -> BTreeMap<String, String> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .split_once('=')
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_args_round_trip() {
+        let args = HashMap::from([
+            ("SOLANA_VERSION".to_string(), "1.18.26".to_string()),
+            ("IMAGE_VARIANT".to_string(), "slim".to_string()),
+        ]);
+        let entries = build_args_to_entries(&args);
+        assert_eq!(
+            entries,
+            vec!["IMAGE_VARIANT=slim", "SOLANA_VERSION=1.18.26"]
+        );
+        assert_eq!(
+            build_args_to_map(&entries),
+            args.into_iter().collect::<BTreeMap<_, _>>()
+        );
+    }
+
+    #[test]
+    fn test_build_arg_values() {
+        assert!(is_valid_build_arg_value("1.18.26"));
+        assert!(is_valid_build_arg_value("ghcr.io/org/image:slim"));
+        assert!(!is_valid_build_arg_value(""));
+        assert!(!is_valid_build_arg_value("--network=host"));
+        assert!(!is_valid_build_arg_value("slim; rm -rf /"));
+        assert!(!is_valid_build_arg_value("a b"));
+    }
+}
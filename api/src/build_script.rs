@@ -0,0 +1,71 @@
+use std::env;
+use std::path::Path;
+
+use crate::git::BareClone;
+use crate::Result;
+
+/// What happens to builds of repositories with a cargo build script, from
+/// `BUILD_SCRIPT_POLICY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuildScriptPolicy {
+    // Build them with the custom build system, compiling without network access, and warn
+    // that the result may not be reproducible. Other builds cannot be isolated and are
+    // rejected.
+    #[default]
+    Warn,
+    // Reject them before building
+    Strict,
+}
+
+impl BuildScriptPolicy {
+    /// Read the policy from `BUILD_SCRIPT_POLICY`, warning unless it is `strict`.
+    pub fn from_env() -> Self {
+        match env::var("BUILD_SCRIPT_POLICY").as_deref() {
+            Ok("strict") => Self::Strict,
+            _ => Self::Warn,
+        }
+    }
+}
+
+// Build scripts cargo runs implicitly: a build.rs next to the Cargo.toml of a package
+pub(crate) fn find_build_scripts(files: &[String]) -> Vec<String> {
+    files
+        .iter()
+        .filter(|file| Path::new(file.as_str()).file_name() == Some("build.rs".as_ref()))
+        .filter(|file| {
+            let manifest = Path::new(file.as_str()).with_file_name("Cargo.toml");
+            files
+                .iter()
+                .any(|other| Path::new(other.as_str()) == manifest)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Paths of the build scripts of the packages in the repository at `commit`, or the
+/// default branch if none is given.
+pub async fn detect_build_scripts(clone: &BareClone, commit: Option<&str>) -> Result<Vec<String>> {
+    Ok(find_build_scripts(&clone.list_files(commit).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_build_scripts() {
+        let files = [
+            "Cargo.toml",
+            "programs/phoenix/Cargo.toml",
+            "programs/phoenix/build.rs",
+            "programs/phoenix/src/lib.rs",
+            "scripts/build.rs",
+        ]
+        .map(String::from);
+        assert_eq!(
+            find_build_scripts(&files),
+            vec!["programs/phoenix/build.rs"]
+        );
+        assert!(find_build_scripts(&files[..1]).is_empty());
+    }
+}
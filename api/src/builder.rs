@@ -1,11 +1,59 @@
+use std::collections::BTreeMap;
 use std::env;
+use std::path::{Path, PathBuf};
 
 use tokio::process::Command;
 
+use crate::anchor::{
+    anchor_build_image, ensure_anchor_release, is_anchor_image_failure, read_anchor_version,
+};
+use crate::build_args::build_args_to_entries;
+use crate::build_script::{detect_build_scripts, BuildScriptPolicy};
+use crate::cargo_mirror::{cargo_mirror, mirror_config_args};
+use crate::cargo_profile::{
+    merge_profile_settings, profile_config_args, read_profile_settings, DEFAULT_CARGO_PROFILE,
+};
+use crate::db::DbClient;
+use crate::declared_id::read_declared_id;
+use crate::digests::compute_digests;
+use crate::dockerfile::{build_artifact, build_with_image, ImageBuild, ImageOptions};
 use crate::errors::ApiError;
-use crate::models::{SolanaProgramBuild, SolanaProgramBuildParams, VerifiedProgram};
+use crate::git::{
+    commit_file, resolve_named_tag, resolve_ref, resolve_repository_redirect, shallow_mirror,
+    BareClone,
+};
+use crate::hash_provider::{match_executable_hash, HashMatch};
+use crate::language::find_builder;
+use crate::lfs::{is_lfs_failure, lfs_enabled, lfs_git_env, repository_uses_lfs};
+use crate::lock_file::{lock_file_hash, lock_file_path};
+use crate::models::{
+    BuildSystem, Commitment, HashMode, Network, ProgramLoader, RefStrategy, SolanaProgramBuild,
+    SolanaProgramBuildParams, VerifiedProgram, Warning, WarningCode,
+};
+use crate::published_crate::checkout_published_crate;
+use crate::resolver::read_feature_resolver;
+use crate::rpc;
+use crate::rustflags::{normalize_rustflags, rustflags_env};
+use crate::storage::{artifact_key, log_key};
 use crate::Result;
-use libc::{c_ulong, getrlimit, rlimit, setrlimit, RLIMIT_AS};
+use libc::{rlim_t, rlimit, setrlimit, RLIMIT_AS};
+use sha2::{Digest, Sha256};
+
+// sha256 of no data, reported by solana-verify for programs without executable data
+const EMPTY_PROGRAM_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+// Shared compiler cache used when `SCCACHE_ENABLED` is set and `SCCACHE_DIR` is not
+const DEFAULT_SCCACHE_DIR: &str = "/var/cache/sccache";
+
+const NO_DEFAULT_FEATURES_FLAG: &str = "--no-default-features";
+// Variable and build arg pinning the timestamps embedded by reproducible build tools
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+// Printed by cargo when a dependency is missing from the vendored registry
+const OFFLINE_ERROR_MARKERS: [&str; 2] = [
+    "attempting to make an HTTP request, but --offline was specified",
+    "failed to download from",
+];
 
 fn get_last_line(output: &str) -> Option<String> {
     output.lines().last().map(ToOwned::to_owned)
@@ -24,6 +72,68 @@ pub fn get_repo_url(build_params: &SolanaProgramBuild) -> String {
     )
 }
 
+// Cargo package names may only contain alphanumerics, `-` and `_`
+pub fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Build the arguments forwarded to cargo after `--`.
+/// Workspace members are selected with `-p <member>` so the build runs from the
+/// workspace root and uses the workspace's unified Cargo.lock.
+fn build_cargo_args(payload: &SolanaProgramBuildParams) -> Vec<String> {
+    let mut cargo_args = payload.cargo_args.clone().unwrap_or_default();
+
+    if payload.no_default_features.unwrap_or(false)
+        && !cargo_args.iter().any(|arg| arg == NO_DEFAULT_FEATURES_FLAG)
+    {
+        cargo_args.push(NO_DEFAULT_FEATURES_FLAG.to_string());
+    }
+
+    if let Some(arch) = payload.target().sbf_arch() {
+        if !cargo_args.iter().any(|arg| arg == "--arch") {
+            cargo_args.push("--arch".to_string());
+            cargo_args.push(arch.to_string());
+        }
+    }
+
+    if let Some(member) = &payload.workspace_member {
+        let has_package_arg = cargo_args
+            .iter()
+            .any(|arg| arg == "-p" || arg == "--package" || arg.starts_with("--package="));
+        if !has_package_arg {
+            cargo_args.push("-p".to_string());
+            cargo_args.push(member.clone());
+        }
+    }
+
+    cargo_args
+}
+
+/// Collect the non-fatal issues with the build parameters that make a
+/// verification result harder to reproduce. Whether the commit is pinned is only known
+/// once the ref is resolved, so it is checked then.
+fn collect_build_warnings(payload: &SolanaProgramBuildParams) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    if let Some(base_image) = &payload.base_image {
+        if !base_image.contains("@sha256:") {
+            warnings.push(Warning::new(
+                WarningCode::MutableBaseImage,
+                format!(
+                    "Base image {} is not pinned to a digest and may change over time.",
+                    base_image
+                ),
+            ));
+        }
+    }
+
+    warnings
+}
+
 fn extract_hash(output: &str, prefix: &str) -> Option<String> {
     output
         .lines()
@@ -34,87 +144,826 @@ fn extract_hash(output: &str, prefix: &str) -> Option<String> {
         })
 }
 
-/// The `verify_build` function verifies a Solana program build by executing the `solana-verify` command
-/// and parsing the output to determine if the program hash matches and storing the verified build
-/// information in a database.
+/// The `verify_build` function verifies a Solana program build with the builder of the language
+/// it is written in, `rust` unless the `language` param names another.
 ///
 /// Arguments:
 ///
-/// * `pool`: `pool` is an Arc of a connection pool to a PostgreSQL database. It is used to interact
-/// with the database and perform database operations.
+/// * `db`: The `db` parameter is the `DbClient` used to record what happened during the build.
 /// * `payload`: The `payload` parameter is of type `SolanaProgramBuildParams`
+/// * `build_id`: The id of the `solana_program_builds` record being verified.
 ///
 /// Returns:
 ///
-/// The function `verify_build` returns a `Result` with the success case containing a `VerifiedProgram`
-/// struct and the error case containing an `ApiError`.
+/// The result of the builder, or an `ApiError::Build` if no builder is registered for the language.
 pub async fn verify_build(
+    db: &DbClient,
     payload: SolanaProgramBuildParams,
     build_id: &str,
-) -> Result<VerifiedProgram> {
+) -> Result<(VerifiedProgram, Vec<Warning>)> {
+    let language = payload.language().to_string();
+    let builder = find_builder(&language).ok_or_else(|| {
+        ApiError::Build(format!(
+            "No builder is registered for language {}",
+            language
+        ))
+    })?;
+    builder.build(db, payload, build_id).await
+}
+
+/// The `build_rust_program` function verifies a Rust program build, from its git repository or its
+/// source published on crates.io, by executing the `solana-verify` command
+/// and parsing the output to determine if the program hash matches and storing the verified build
+/// information in a database.
+///
+/// Arguments:
+///
+/// * `db`: The `db` parameter is the `DbClient` used to record what happened during the build,
+/// such as the ref resolved when no commit hash was given.
+/// * `payload`: The `payload` parameter is of type `SolanaProgramBuildParams`
+/// * `build_id`: The id of the `solana_program_builds` record being verified.
+///
+/// Returns:
+///
+/// The function `build_rust_program` returns a `Result` with the success case containing a `VerifiedProgram`
+/// struct along with any non-fatal `Warning`s, and the error case containing an `ApiError`.
+pub async fn build_rust_program(
+    db: &DbClient,
+    mut payload: SolanaProgramBuildParams,
+    build_id: &str,
+) -> Result<(VerifiedProgram, Vec<Warning>)> {
+    let Some((name, version)) = payload
+        .published_crate()
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+    else {
+        return build_from_repository(db, payload, build_id).await;
+    };
+
+    // Published crates are built from a local repository holding the source of their
+    // tarball, pinned to its only commit
+    let published = match checkout_published_crate(&name, &version).await {
+        Ok(published) => published,
+        Err(err) => {
+            db.record_event(build_id, "build_failed", Some(&err.to_string()))
+                .await;
+            return Err(err);
+        }
+    };
+    tracing::info!(
+        "Building {} {} from crates.io ({})",
+        name,
+        version,
+        published.checksum
+    );
+    db.record_event(
+        build_id,
+        "published_crate",
+        Some(&format!(
+            "{}@{} sha256:{}{}",
+            name,
+            version,
+            published.checksum,
+            if published.yanked { " (yanked)" } else { "" }
+        )),
+    )
+    .await;
+    payload.repository = published.repository.clone();
+    payload.commit_hash = Some(published.commit.clone());
+    payload.tag = None;
+
+    let result = build_from_repository(db, payload, build_id).await;
+    let _ = tokio::fs::remove_dir_all(&published.path).await;
+    result
+}
+
+// How a program which does not build through solana-verify is built
+enum OwnBuild<'a> {
+    Dockerfile {
+        dockerfile: &'a str,
+        artifact_path: &'a str,
+    },
+    Image(ImageBuild<'a>),
+}
+
+// Build the program from the git repository and commit of the payload
+async fn build_from_repository(
+    db: &DbClient,
+    mut payload: SolanaProgramBuildParams,
+    build_id: &str,
+) -> Result<(VerifiedProgram, Vec<Warning>)> {
     tracing::info!("Verifying build..");
 
-    // Original R limit
-    let mut original_rlimit = rlimit {
-        rlim_cur: 0,
-        rlim_max: 0,
+    let mut warnings = collect_build_warnings(&payload);
+    let extra_hashes = payload.extra_hashes.clone().unwrap_or_default();
+
+    if let Some(member) = &payload.workspace_member {
+        if !is_valid_package_name(member) {
+            return Err(ApiError::Build(format!(
+                "Invalid workspace member name: {}",
+                member
+            )));
+        }
+    }
+    let mut cargo_args = build_cargo_args(&payload);
+
+    // Renamed repositories redirect to their new URL, which is stored so the build is
+    // displayed and deduplicated under it
+    match resolve_repository_redirect(&payload.repository).await {
+        Ok(canonical) if canonical != payload.repository => {
+            tracing::info!(
+                "Repository {} redirects to {}",
+                payload.repository,
+                canonical
+            );
+            db.update_repository(build_id, &canonical).await?;
+            db.record_event(build_id, "repository_redirected", Some(&canonical))
+                .await;
+            payload.repository = canonical;
+        }
+        Ok(_) => {}
+        Err(err) => tracing::warn!(
+            "Failed to check {} for redirects: {}",
+            payload.repository,
+            err
+        ),
+    }
+
+    // Pin the build to the commit the requested tag points to
+    if let (None, Some(tag)) = (&payload.commit_hash, &payload.tag) {
+        let resolved = resolve_named_tag(&payload.repository, tag)
+            .await
+            .map_err(|err| {
+                ApiError::Build(format!(
+                    "Tag {} not found in {}: {}",
+                    tag, payload.repository, err
+                ))
+            })?;
+        tracing::info!("Resolved tag {} to {}", resolved.name, resolved.commit);
+        db.update_resolved_ref(build_id, &resolved.name, &resolved.commit)
+            .await?;
+        db.record_event(
+            build_id,
+            "ref_resolved",
+            Some(&format!("{}@{}", resolved.name, resolved.commit)),
+        )
+        .await;
+        payload.commit_hash = Some(resolved.commit);
+    }
+
+    // Pick the commit to build according to the ref strategy if none was given
+    let commit_hash = match payload.commit_hash.clone() {
+        Some(commit) => Some(commit),
+        None => {
+            let strategy = payload
+                .default_ref_strategy
+                .unwrap_or_else(RefStrategy::from_env);
+            // Tags are pinned, while the head of the default branch moves on
+            if matches!(strategy, RefStrategy::Head) {
+                warnings.push(Warning::new(
+                    WarningCode::UnpinnedCommit,
+                    "No commit hash specified. The latest commit of the default branch was used.",
+                ));
+            }
+            match resolve_ref(&payload.repository, strategy).await {
+                Ok(resolved) => {
+                    tracing::info!(
+                        "Resolved {:?} of {} to {} ({})",
+                        strategy,
+                        payload.repository,
+                        resolved.name,
+                        resolved.commit
+                    );
+                    db.update_resolved_ref(build_id, &resolved.name, &resolved.commit)
+                        .await?;
+                    db.record_event(
+                        build_id,
+                        "ref_resolved",
+                        Some(&format!("{}@{}", resolved.name, resolved.commit)),
+                    )
+                    .await;
+                    Some(resolved.commit)
+                }
+                Err(err) if matches!(strategy, RefStrategy::Head) => {
+                    tracing::warn!("Failed to resolve HEAD, using the default branch: {}", err);
+                    None
+                }
+                Err(err) => return Err(err),
+            }
+        }
     };
-    // 1 GB memory limit
-    let max_ram_usage_bytes: c_ulong = 1024 * 1024 * 1024;
-    unsafe {
-        getrlimit(RLIMIT_AS, &mut original_rlimit);
-        setrlimit(
-            libc::RLIMIT_AS,
-            &libc::rlimit {
-                rlim_cur: max_ram_usage_bytes,
-                rlim_max: max_ram_usage_bytes,
+
+    // The files read before building come from a single clone without file contents
+    let clone = match BareClone::fetch(&payload.repository, commit_hash.as_deref()).await {
+        Ok(clone) => clone,
+        Err(err) => {
+            db.record_event(build_id, "build_failed", Some("repository not cloned"))
+                .await;
+            return Err(err);
+        }
+    };
+
+    // Build scripts run arbitrary code at compile time, which may fetch data and make the
+    // build non-reproducible
+    let build_scripts = match detect_build_scripts(&clone, commit_hash.as_deref()).await {
+        Ok(build_scripts) => build_scripts,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to look for build scripts in {}: {}",
+                payload.repository,
+                err
+            );
+            Vec::new()
+        }
+    };
+    let has_build_script = !build_scripts.is_empty();
+    if has_build_script {
+        let paths = build_scripts.join(", ");
+        db.record_event(build_id, "build_script", Some(&paths))
+            .await;
+        if BuildScriptPolicy::from_env() == BuildScriptPolicy::Strict {
+            db.record_event(build_id, "build_failed", Some("build script rejected"))
+                .await;
+            return Err(ApiError::Build(format!(
+                "Programs with a build script are not verified: {}",
+                paths
+            )));
+        }
+        // Only the custom build system runs the compile step in a container of its own,
+        // which is cut off from the network once the dependencies are fetched. solana-verify
+        // and docker build fetch and compile with the same network, so they fail closed.
+        if payload.build_system() != BuildSystem::Custom || payload.dockerfile_path.is_some() {
+            db.record_event(build_id, "build_failed", Some("build script not isolated"))
+                .await;
+            return Err(ApiError::Build(format!(
+                "Programs with a build script are only verified with the custom build system, which compiles them without network access: {}",
+                paths
+            )));
+        }
+        warnings.push(Warning::new(
+            WarningCode::BuildScript,
+            format!(
+                "The repository has build scripts ({}) which run at compile time and may make the build non-reproducible. They were compiled without network access.",
+                paths
+            ),
+        ));
+    }
+
+    // The program id declared in the source is compared with the verified one once built
+    let declared_id = match read_declared_id(
+        &clone,
+        commit_hash.as_deref(),
+        payload.mount_path.as_deref(),
+        payload.lib_name.as_deref(),
+        &payload.program_id,
+    )
+    .await
+    {
+        Ok(declared_id) => declared_id,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to read the declared program id of {}: {}",
+                payload.repository,
+                err
+            );
+            None
+        }
+    };
+    if let Some(declared_id) = &declared_id {
+        db.record_event(build_id, "declared_id", Some(declared_id))
+            .await;
+    }
+
+    // Cargo builds with the feature resolver of the Cargo.toml, which only defaults to the
+    // same version for the same edition, so it is recorded to explain hash mismatches
+    match read_feature_resolver(
+        &clone,
+        commit_hash.as_deref(),
+        payload.mount_path.as_deref(),
+    )
+    .await
+    {
+        Ok(resolver) => {
+            db.record_event(build_id, "feature_resolver", Some(&resolver.describe()))
+                .await;
+            if !resolver.declared {
+                warnings.push(Warning::new(
+                    WarningCode::ResolverUnspecified,
+                    format!(
+                        "Cargo.toml does not declare a feature resolver, so resolver {} was used. The default depends on the edition and whether the manifest is a virtual workspace; declare `resolver` so every build enables the same features.",
+                        resolver.version
+                    ),
+                ));
+            }
+        }
+        Err(err) => tracing::warn!(
+            "Failed to read the feature resolver of {}: {}",
+            payload.repository,
+            err
+        ),
+    }
+
+    // Files stored with git LFS are only pointers in a plain checkout, so their objects are
+    // fetched for the repositories which use it
+    let uses_lfs = match repository_uses_lfs(&clone, commit_hash.as_deref()).await {
+        Ok(uses_lfs) => uses_lfs,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to check whether {} uses git LFS: {}",
+                payload.repository,
+                err
+            );
+            false
+        }
+    };
+    let lfs = uses_lfs && lfs_enabled();
+    if uses_lfs {
+        let detail = if lfs {
+            "fetched"
+        } else {
+            "disabled by GIT_LFS"
+        };
+        db.record_event(build_id, "git_lfs", Some(detail)).await;
+    }
+
+    // Projects with their own deterministic Dockerfile are built with it, and projects with
+    // their own build image with its default command, instead of solana-verify
+    let own_build = match (&payload.dockerfile_path, &payload.artifact_path) {
+        (Some(dockerfile), Some(artifact_path)) => Some(OwnBuild::Dockerfile {
+            dockerfile,
+            artifact_path,
+        }),
+        _ => match (
+            payload.build_system(),
+            &payload.base_image,
+            &payload.lib_name,
+        ) {
+            (BuildSystem::Custom, Some(image), Some(lib_name)) => {
+                Some(OwnBuild::Image(ImageBuild {
+                    image,
+                    mount_path: payload.mount_path.as_deref(),
+                    lib_name,
+                }))
+            }
+            _ => None,
+        },
+    };
+    if let Some(own_build) = own_build {
+        // Timestamps embedded in the executable are pinned to SOURCE_DATE_EPOCH, the date
+        // of the commit being built unless the request gives one. solana-verify runs its
+        // container without it, so only the builds made here get it.
+        let source_date_epoch = match payload.source_date_epoch {
+            Some(epoch) => Some((epoch, "param")),
+            None => match clone.commit_timestamp(commit_hash.as_deref()).await {
+                Ok(epoch) => Some((epoch, "commit")),
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to read the commit date of {}: {}",
+                        payload.repository,
+                        err
+                    );
+                    None
+                }
             },
-        );
+        };
+        if let Some((epoch, source)) = source_date_epoch {
+            db.record_event(
+                build_id,
+                "source_date_epoch",
+                Some(&format!("{} ({})", epoch, source)),
+            )
+            .await;
+        }
+        let source_date_epoch = source_date_epoch.map(|(epoch, _)| epoch.to_string());
+        let mut build_args = payload
+            .docker_build_args
+            .as_ref()
+            .map(build_args_to_entries)
+            .unwrap_or_default();
+        if let Some(epoch) = &source_date_epoch {
+            build_args.push(format!("{}={}", SOURCE_DATE_EPOCH, epoch));
+        }
+        // RUSTFLAGS change the output as much as the profile, so they are recorded with the
+        // build. Only the custom build system accepts them, as RUSTFLAGS of its container.
+        let rustflags = normalize_rustflags(payload.rustflags.as_deref().unwrap_or_default())
+            .map_err(ApiError::Build)?;
+        let rustflags = (!rustflags.is_empty()).then(|| rustflags_env(&rustflags));
+        if let Some(rustflags) = &rustflags {
+            db.record_event(build_id, "rustflags", Some(rustflags))
+                .await;
+        }
+        let lock_mirror = match &payload.lock_file_content {
+            Some(lock_file) => Some(
+                apply_lock_file(
+                    db,
+                    build_id,
+                    &payload.repository,
+                    commit_hash.as_deref(),
+                    payload.mount_path.as_deref(),
+                    lock_file,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+        let (repository, commit) = match &lock_mirror {
+            Some((mirror, lock_commit)) => (
+                mirror.to_string_lossy().into_owned(),
+                Some(lock_commit.as_str()),
+            ),
+            None => (payload.repository.clone(), commit_hash.as_deref()),
+        };
+        let lfs_repository = lfs.then_some(payload.repository.as_str());
+        let artifact = match own_build {
+            OwnBuild::Dockerfile {
+                dockerfile,
+                artifact_path,
+            } => {
+                db.record_event(build_id, "build_started", Some("dockerfile"))
+                    .await;
+                build_artifact(
+                    &repository,
+                    commit,
+                    dockerfile,
+                    artifact_path,
+                    &build_args,
+                    lfs_repository,
+                )
+                .await
+            }
+            OwnBuild::Image(image_build) => {
+                db.record_event(
+                    build_id,
+                    "build_started",
+                    Some(BuildSystem::Custom.as_str()),
+                )
+                .await;
+                let mut env = build_args;
+                if let Some(rustflags) = &rustflags {
+                    env.push(format!("RUSTFLAGS={}", rustflags));
+                }
+                // The shared compiler cache and the vendored registry of offline builds are
+                // mounted into the build container, which only the custom build system
+                // runs itself
+                let compiler_cache = compiler_cache_dir();
+                let vendored_cargo_home = payload
+                    .vendored
+                    .unwrap_or(false)
+                    .then(vendored_cargo_home)
+                    .flatten();
+                let options = ImageOptions {
+                    env,
+                    network_isolated: has_build_script,
+                    compiler_cache: compiler_cache.as_deref(),
+                    vendored_cargo_home: vendored_cargo_home.as_deref(),
+                };
+                let built =
+                    build_with_image(&repository, commit, image_build, options, lfs_repository)
+                        .await;
+                if let (Some(_), Ok((_, output))) = (&compiler_cache, &built) {
+                    record_compiler_cache_stats(db, build_id, output).await;
+                }
+                built
+                    .map(|(executable, _)| executable)
+                    .map_err(|err| match err {
+                        ApiError::Build(output)
+                            if vendored_cargo_home.is_some()
+                                && OFFLINE_ERROR_MARKERS
+                                    .iter()
+                                    .any(|marker| output.contains(marker)) =>
+                        {
+                            ApiError::Build(
+                                "Offline build failed: a dependency is missing from the vendored registry"
+                                    .to_string(),
+                            )
+                        }
+                        err => err,
+                    })
+            }
+        };
+        if let Some((mirror, _)) = &lock_mirror {
+            let _ = tokio::fs::remove_dir_all(mirror).await;
+        }
+        let artifact = match artifact {
+            Ok(artifact) => artifact,
+            Err(err) => {
+                db.record_event(build_id, "build_failed", Some(&err.to_string()))
+                    .await;
+                return Err(err);
+            }
+        };
+        let build_hash = executable_hash(&artifact);
+        let executable = (!extra_hashes.is_empty()).then(|| artifact.clone());
+        store_build_output(db, &artifact_key(build_id), artifact).await;
+
+        let hash_match = match match_executable_hash(
+            db.hash_provider.as_ref(),
+            &payload.program_id,
+            payload.commitment(),
+            &build_hash,
+        )
+        .await
+        {
+            Ok(hash_match) => hash_match,
+            Err(ApiError::RpcCircuitOpen) => {
+                warnings.push(defer_on_chain_check(db, build_id).await);
+                HashMatch {
+                    on_chain_hash: String::new(),
+                    is_verified: false,
+                }
+            }
+            Err(err) => {
+                db.record_event(build_id, "build_failed", Some(&err.to_string()))
+                    .await;
+                return Err(err);
+            }
+        };
+        let loader = rpc::get_program_loader(&payload.program_id).await.ok();
+        let (verified_build, verification_warnings) = finish_verification(
+            db,
+            payload.tenant().to_string(),
+            payload.program_id,
+            build_id,
+            build_hash,
+            hash_match,
+            loader,
+            &extra_hashes,
+            executable.as_deref(),
+            declared_id,
+        )
+        .await;
+        warnings.extend(verification_warnings);
+        return Ok((verified_build, warnings));
     }
+
+    // Anchor projects are built in the image of the Anchor release they pin, unless they
+    // bring their own base image
+    let anchor_version = match payload.anchor_version.clone() {
+        Some(version) => Some(version),
+        None if payload.build_system() == BuildSystem::Anchor && payload.base_image.is_none() => {
+            match read_anchor_version(
+                &clone,
+                commit_hash.as_deref(),
+                payload.mount_path.as_deref(),
+            )
+            .await
+            {
+                Ok(version) => version,
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to read the Anchor.toml of {}: {}",
+                        payload.repository,
+                        err
+                    );
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    if let Some(version) = &anchor_version {
+        if let Err(err) = ensure_anchor_release(version).await {
+            db.record_event(
+                build_id,
+                "build_failed",
+                Some("anchor version not released"),
+            )
+            .await;
+            return Err(err);
+        }
+        db.update_anchor_version(build_id, version).await?;
+        db.record_event(build_id, "anchor_version", Some(version))
+            .await;
+        payload.base_image = Some(anchor_build_image(version));
+    }
+
+    // cargo build-sbf always builds with the release profile, so the settings of another
+    // profile and the explicit overrides are applied onto it with `--config`
+    let profile_settings = if payload.cargo_profile() == DEFAULT_CARGO_PROFILE {
+        BTreeMap::new()
+    } else {
+        match read_profile_settings(
+            &clone,
+            commit_hash.as_deref(),
+            payload.mount_path.as_deref(),
+            payload.cargo_profile(),
+        )
+        .await
+        {
+            Ok(settings) => settings,
+            Err(err) => {
+                db.record_event(
+                    build_id,
+                    "build_failed",
+                    Some("cargo profile not applicable"),
+                )
+                .await;
+                return Err(err);
+            }
+        }
+    };
+    drop(clone);
+    let profile_settings =
+        merge_profile_settings(profile_settings, payload.profile_overrides.as_ref());
+    let profile_detail = profile_settings
+        .iter()
+        .map(|(key, value)| format!(" {}={}", key, value))
+        .collect::<String>();
+    db.record_event(
+        build_id,
+        "cargo_profile",
+        Some(&format!("{}{}", payload.cargo_profile(), profile_detail)),
+    )
+    .await;
+    cargo_args.extend(profile_config_args(&profile_settings));
+
+    // Crates are downloaded from the mirror when one is configured
+    if let Some(mirror) = cargo_mirror() {
+        db.record_event(build_id, "cargo_mirror", Some(&mirror))
+            .await;
+        cargo_args.extend(mirror_config_args(&mirror));
+    }
+
+    // A supplied lock file is committed onto the commit to build in a shallow mirror
+    let lock_mirror = match &payload.lock_file_content {
+        Some(lock_file) => Some(
+            apply_lock_file(
+                db,
+                build_id,
+                &payload.repository,
+                commit_hash.as_deref(),
+                payload.mount_path.as_deref(),
+                lock_file,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    // Only the requested commit is fetched when the server serves it directly, which is
+    // much faster than the full clone solana-verify makes of repositories with a long history
+    let mirror = match (&lock_mirror, &commit_hash) {
+        (Some((mirror, _)), _) => Some(mirror.clone()),
+        (None, Some(commit)) => match shallow_mirror(&payload.repository, commit).await {
+            Ok(mirror) => Some(mirror),
+            Err(err) => {
+                tracing::info!(
+                    "Cloning {} in full as {} can not be fetched alone: {}",
+                    payload.repository,
+                    commit,
+                    err
+                );
+                None
+            }
+        },
+        (None, None) => None,
+    };
+    let commit_hash = match lock_mirror {
+        Some((_, lock_commit)) => Some(lock_commit),
+        None => commit_hash,
+    };
+    let clone_strategy = if mirror.is_some() { "shallow" } else { "full" };
+    db.record_event(build_id, "clone_strategy", Some(clone_strategy))
+        .await;
+
     // Run solana-verify command
+    let build_system = payload.build_system();
     let mut cmd = Command::new("solana-verify");
-    cmd.arg("verify-from-repo").arg("-um");
+    // 1 GB memory limit, set in the child only so concurrent builds and the server keep theirs
+    let max_ram_usage_bytes: rlim_t = 1024 * 1024 * 1024;
+    // SAFETY: setrlimit is async-signal-safe and the closure does not allocate
+    unsafe {
+        cmd.pre_exec(move || {
+            let limit = rlimit {
+                rlim_cur: max_ram_usage_bytes,
+                rlim_max: max_ram_usage_bytes,
+            };
+            if setrlimit(RLIMIT_AS, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    cmd.arg("verify-from-repo")
+        .arg(format!("-u{}", payload.network().cli_moniker()));
 
     // Add optional arguments
-    if let Some(commit) = payload.commit_hash {
+    if let Some(commit) = commit_hash {
         cmd.arg("--commit-hash").arg(commit);
     }
 
-    if let Some(library_name) = payload.lib_name {
+    if let Some(library_name) = &payload.lib_name {
         cmd.arg("--library-name").arg(library_name);
     }
 
-    if let Some(base_image) = payload.base_image {
+    if let Some(base_image) = &payload.base_image {
         cmd.arg("--base-image").arg(base_image);
     }
 
-    if let Some(mount_path) = payload.mount_path {
+    if let Some(mount_path) = &payload.mount_path {
         cmd.arg("--mount-path").arg(mount_path);
     }
 
-    if let Some(bpf_flag) = payload.bpf_flag {
-        if bpf_flag {
-            cmd.arg("--bpf");
-        }
+    if build_system.uses_bpf() {
+        cmd.arg("--bpf");
     }
 
-    cmd.arg("--program-id")
-        .arg(&payload.program_id)
-        .arg(payload.repository);
+    cmd.arg("--program-id").arg(&payload.program_id);
+    // The checkout is kept in a directory of the build to read the built executable from
+    let work_dir = if extra_hashes.is_empty() {
+        None
+    } else {
+        let dir = env::temp_dir().join(format!("verify-{}", build_id));
+        tokio::fs::create_dir_all(&dir).await.ok().map(|_| dir)
+    };
+    if let Some(work_dir) = &work_dir {
+        cmd.arg("--current-dir").current_dir(work_dir);
+    }
+    match &mirror {
+        Some(mirror) => cmd.arg(mirror),
+        None => cmd.arg(&payload.repository),
+    };
+    // The LFS objects are fetched from the repository even when cloning a mirror of it
+    if lfs {
+        cmd.envs(lfs_git_env(&payload.repository));
+    }
 
-    if let Some(cargo_args) = payload.cargo_args {
+    if !cargo_args.is_empty() {
         cmd.arg("--").args(&cargo_args);
     }
 
+    // solana-verify only reads programs from the upgradeable loader, so the on-chain side
+    // of a loader-v4 program is fetched and compared here instead
+    let loader = match rpc::get_program_loader(&payload.program_id).await {
+        Ok(loader) => Some(loader),
+        Err(err) => {
+            tracing::warn!(
+                "Failed to detect the loader of {}: {}",
+                payload.program_id,
+                err
+            );
+            None
+        }
+    };
+    let is_loader_v4 = loader == Some(ProgramLoader::LoaderV4);
+
     tracing::info!("Running command: {:?}", cmd);
+    db.record_event(build_id, "build_started", Some(build_system.as_str()))
+        .await;
 
-    let output = cmd.output().await?;
+    let output = cmd.output().await;
+    if let Some(mirror) = &mirror {
+        let _ = tokio::fs::remove_dir_all(mirror).await;
+    }
+    let built_executables = match &work_dir {
+        Some(work_dir) => {
+            let executables = read_built_executables(work_dir.clone()).await;
+            let _ = tokio::fs::remove_dir_all(work_dir).await;
+            executables
+        }
+        None => Vec::new(),
+    };
+    let output = output?;
+    let mut build_log = output.stdout.clone();
+    build_log.extend_from_slice(&output.stderr);
+    store_build_output(db, &log_key(build_id), build_log).await;
     let result = String::from_utf8(output.stdout)?;
-    if !output.status.success() {
+    let built_loader_v4 =
+        is_loader_v4 && extract_hash(&result, "Executable Program Hash from repo:").is_some();
+    if !output.status.success() && !built_loader_v4 {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if lfs && (is_lfs_failure(&stderr) || is_lfs_failure(&result)) {
+            db.record_event(
+                build_id,
+                "build_failed",
+                Some("git LFS objects not fetched"),
+            )
+            .await;
+            return Err(ApiError::LfsFetch(stderr.trim().to_string()));
+        }
+        if let Some(version) = &anchor_version {
+            if is_anchor_image_failure(&stderr) || is_anchor_image_failure(&result) {
+                db.record_event(
+                    build_id,
+                    "build_failed",
+                    Some("anchor build image not pulled"),
+                )
+                .await;
+                return Err(ApiError::Build(format!(
+                    "The build image of Anchor version {} could not be pulled",
+                    version
+                )));
+            }
+        }
+        db.record_event(
+            build_id,
+            "build_failed",
+            Some(&format!("solana-verify exited with {}", output.status)),
+        )
+        .await;
         return Err(ApiError::Build(result));
     }
 
-    let onchain_hash = extract_hash(&result, "On-chain Program Hash:").unwrap_or_default();
+    let reported_onchain_hash = extract_hash(&result, "On-chain Program Hash:").unwrap_or_default();
     let build_hash =
         extract_hash(&result, "Executable Program Hash from repo:").unwrap_or_default();
 
@@ -123,35 +972,436 @@ pub async fn verify_build(
         ApiError::Build("Failed to build and get output from program".to_string())
     })?;
 
+    // Compare against the on-chain hash from the provider, falling back to the result
+    // reported by solana-verify if the provider is unavailable
+    let hash_match = match match_executable_hash(
+        db.hash_provider.as_ref(),
+        &payload.program_id,
+        payload.commitment(),
+        &build_hash,
+    )
+    .await
+    {
+        Ok(hash_match) => hash_match,
+        Err(err @ ApiError::ProgramClosed(_)) => {
+            db.record_event(build_id, "build_failed", Some("program closed"))
+                .await;
+            return Err(err);
+        }
+        Err(ApiError::RpcCircuitOpen) => {
+            warnings.push(defer_on_chain_check(db, build_id).await);
+            HashMatch {
+                on_chain_hash: reported_onchain_hash,
+                is_verified: last_line.contains("Program hash matches"),
+            }
+        }
+        Err(err) => {
+            tracing::warn!("Failed to get on-chain hash from provider: {}", err);
+            HashMatch {
+                on_chain_hash: reported_onchain_hash,
+                is_verified: last_line.contains("Program hash matches"),
+            }
+        }
+    };
+
+    let executable = built_executables
+        .into_iter()
+        .find(|bytes| executable_hash(bytes) == build_hash);
+    let (verified_build, verification_warnings) = finish_verification(
+        db,
+        payload.tenant().to_string(),
+        payload.program_id,
+        build_id,
+        build_hash,
+        hash_match,
+        loader,
+        &extra_hashes,
+        executable.as_deref(),
+        declared_id,
+    )
+    .await;
+    warnings.extend(verification_warnings);
+
+    Ok((verified_build, warnings))
+    // let _ = self.insert_or_update_verified_build(&verified_build).await;
+}
+
+// Commit the supplied Cargo.lock onto the commit to build, in a shallow mirror the build
+// uses in place of the repository. Returns the mirror and the commit to build.
+async fn apply_lock_file(
+    db: &DbClient,
+    build_id: &str,
+    repository: &str,
+    commit: Option<&str>,
+    mount_path: Option<&str>,
+    lock_file: &str,
+) -> Result<(PathBuf, String)> {
+    let path = lock_file_path(mount_path);
+    let result = async {
+        let commit = commit.ok_or_else(|| {
+            ApiError::Build("The lock file can only be applied to a resolved commit".to_string())
+        })?;
+        let mirror = shallow_mirror(repository, commit).await.map_err(|err| {
+            ApiError::Build(format!(
+                "The lock file can not be applied as commit {} can not be fetched alone: {}",
+                commit, err
+            ))
+        })?;
+        match commit_file(&mirror, commit, &path, lock_file).await {
+            Ok(lock_commit) => Ok((mirror, lock_commit)),
+            Err(err) => {
+                let _ = tokio::fs::remove_dir_all(&mirror).await;
+                Err(err)
+            }
+        }
+    }
+    .await;
+
+    match &result {
+        Ok(_) => {
+            db.record_event(
+                build_id,
+                "lock_file",
+                Some(&format!("{} sha256 {}", path, lock_file_hash(lock_file))),
+            )
+            .await
+        }
+        Err(_) => {
+            db.record_event(build_id, "build_failed", Some("lock file not applicable"))
+                .await
+        }
+    }
+    result
+}
+
+/// Record the outcome of a build and compute what is stored with the verification:
+/// the extra digests and the result of the hash registry check. Returns the verification
+/// along with any warnings raised while doing so.
+async fn finish_verification(
+    db: &DbClient,
+    tenant_id: String,
+    program_id: String,
+    build_id: &str,
+    build_hash: String,
+    HashMatch {
+        on_chain_hash: onchain_hash,
+        is_verified,
+    }: HashMatch,
+    loader: Option<ProgramLoader>,
+    extra_hashes: &[String],
+    executable: Option<&[u8]>,
+    declared_id: Option<String>,
+) -> (VerifiedProgram, Vec<Warning>) {
+    let mut warnings = Vec::new();
     tracing::info!(
         "{} build hash {} On chain hash {}",
-        payload.program_id,
+        program_id,
         build_hash,
         onchain_hash
     );
+    db.record_event(
+        build_id,
+        "build_finished",
+        Some(&format!(
+            "executable hash {}, on-chain hash {}",
+            build_hash, onchain_hash
+        )),
+    )
+    .await;
+
+    // Extra digests are computed over the built executable, so they describe the build
+    // even when it does not match the deployed program
+    let extra_digests = match (extra_hashes.is_empty(), executable) {
+        (true, _) => None,
+        (false, Some(bytes)) => Some(compute_digests(bytes, extra_hashes)),
+        (false, None) => {
+            tracing::warn!("Built executable of {} not found", program_id);
+            warnings.push(Warning::new(
+                WarningCode::ExtraHashesUnavailable,
+                "The built executable was not found to compute the extra hashes.",
+            ));
+            None
+        }
+    };
+
+    // Compare the build against the hash published in the on-chain registry, if configured
+    let registry_match = match rpc::hash_registry_program_id() {
+        Some(registry_program_id) => {
+            match rpc::get_registry_hash(&registry_program_id, &program_id).await {
+                Ok(Some(registry_hash)) => Some(registry_hash == build_hash),
+                Ok(None) => {
+                    warnings.push(Warning::new(
+                        WarningCode::NoRegistryEntry,
+                        "The program has no entry in the on-chain hash registry.",
+                    ));
+                    None
+                }
+                Err(err) => {
+                    tracing::warn!("Failed to read the hash registry entry: {}", err);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
 
     let verified_build = VerifiedProgram {
         id: uuid::Uuid::new_v4().to_string(),
-        program_id: payload.program_id,
-        is_verified: last_line.contains("Program hash matches"),
+        program_id,
+        is_verified,
         on_chain_hash: onchain_hash,
         executable_hash: build_hash,
         verified_at: chrono::Utc::now().naive_utc(),
         solana_build_id: build_id.to_string(),
+        extra_digests,
+        loader: loader.map(|loader| loader.as_str().to_string()),
+        registry_match,
+        status_changed: false,
+        tenant_id,
+        declared_id,
     };
+    if verified_build.declared_id_matches() == Some(false) {
+        tracing::warn!(
+            "{} declares program id {:?}",
+            verified_build.program_id,
+            verified_build.declared_id
+        );
+        db.record_event(
+            build_id,
+            "declared_id_mismatch",
+            verified_build.declared_id.as_deref(),
+        )
+        .await;
+        warnings.push(Warning::new(
+            WarningCode::DeclaredIdMismatch,
+            format!(
+                "The source declares program id {} with declare_id!, not {}. The program may have been deployed to an unexpected address.",
+                verified_build.declared_id.as_deref().unwrap_or_default(),
+                verified_build.program_id
+            ),
+        ));
+    }
+    (verified_build, warnings)
+}
 
-    // Reset R limit
-    unsafe {
-        setrlimit(RLIMIT_AS, &original_rlimit);
+// Record that the on-chain hash was not fetched because the RPC circuit breaker is open.
+// The status endpoints compare against the on-chain hash again once the RPC recovers.
+async fn defer_on_chain_check(db: &DbClient, build_id: &str) -> Warning {
+    db.record_event(build_id, "onchain_check_deferred", None)
+        .await;
+    Warning::new(
+        WarningCode::OnChainCheckDeferred,
+        "The RPC is failing, so the comparison with the on-chain program is deferred.",
+    )
+}
+
+// Keep the output of a build in the storage backend. A storage failure only loses the
+// retrievable copy, so the build carries on
+async fn store_build_output(db: &DbClient, key: &str, data: Vec<u8>) {
+    if let Err(err) = db.storage.put(key, data).await {
+        tracing::warn!("Failed to store {}: {}", key, err);
     }
+}
 
-    Ok(verified_build)
-    // let _ = self.insert_or_update_verified_build(&verified_build).await;
+/// Cargo home holding the vendored registry offline builds are made against, from
+/// `VENDORED_CARGO_HOME`. Offline builds are not available without it.
+pub fn vendored_cargo_home() -> Option<String> {
+    env::var("VENDORED_CARGO_HOME")
+        .ok()
+        .filter(|cargo_home| !cargo_home.is_empty())
+}
+
+// Host directory of the shared compiler cache, if it is enabled with `SCCACHE_ENABLED`
+fn compiler_cache_dir() -> Option<String> {
+    let enabled = env::var("SCCACHE_ENABLED").is_ok_and(|value| value == "true" || value == "1");
+    enabled.then(|| env::var("SCCACHE_DIR").unwrap_or_else(|_| DEFAULT_SCCACHE_DIR.to_string()))
+}
+
+/// Parse the cache hits and misses from the output of `sccache --show-stats`.
+fn parse_sccache_stats(output: &str) -> Option<(u64, u64)> {
+    let count = |label: &str| {
+        output.lines().find_map(|line| {
+            line.strip_prefix(label)
+                .filter(|rest| rest.starts_with(char::is_whitespace))
+                .and_then(|rest| rest.trim().parse().ok())
+        })
+    };
+    Some((count("Cache hits")?, count("Cache misses")?))
+}
+
+// Record the hit and miss counts of the compiler cache printed after a build
+async fn record_compiler_cache_stats(db: &DbClient, build_id: &str, output: &str) {
+    match parse_sccache_stats(output) {
+        Some((hits, misses)) => {
+            tracing::info!("Compiler cache hits {}, misses {}", hits, misses);
+            db.record_event(
+                build_id,
+                "compiler_cache",
+                Some(&format!("{} hits, {} misses", hits, misses)),
+            )
+            .await;
+        }
+        None => tracing::info!("Build {} did not compile through sccache", build_id),
+    }
+}
+
+/// Checks that `address` looks like a base58 encoded public key.
+pub fn is_valid_address(address: &str) -> bool {
+    (32..=44).contains(&address.len())
+        && address
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l'))
+}
+
+/// Dump the executable bytes of a deployed program using `solana-verify dump`.
+/// Programs larger than `max_size` bytes are rejected.
+pub async fn get_on_chain_bytes(program_id: &str, max_size: u64) -> Result<Vec<u8>> {
+    if !is_valid_address(program_id) {
+        return Err(ApiError::InvalidAddress(program_id.to_string()));
+    }
+    ensure_program_not_closed(program_id).await?;
+
+    if matches!(
+        rpc::get_program_loader(program_id).await,
+        Ok(ProgramLoader::LoaderV4)
+    ) {
+        let bytes = rpc::get_loader_v4_executable(program_id).await?;
+        let size = bytes.len() as u64;
+        if size > max_size {
+            return Err(ApiError::ProgramTooLarge {
+                size,
+                limit: max_size,
+            });
+        }
+        return Ok(bytes);
+    }
+
+    let rpc_url = Network::default().rpc_url();
+    let dump_path = env::temp_dir().join(format!("{}.so", uuid::Uuid::new_v4()));
+
+    let mut cmd = Command::new("solana-verify");
+    cmd.arg("dump").arg(program_id).arg(&dump_path);
+    cmd.arg("--url").arg(rpc_url);
+
+    let output = cmd
+        .output()
+        .await
+        .map_err(|_| ApiError::Custom("Failed to run process dump".to_string()))?;
+
+    if !output.status.success() {
+        tracing::error!(
+            "Failed to dump on-chain program {}",
+            String::from_utf8(output.stderr)?
+        );
+        let _ = tokio::fs::remove_file(&dump_path).await;
+        return Err(ApiError::Custom(
+            "Failed to dump on-chain program".to_string(),
+        ));
+    }
+
+    let size = tokio::fs::metadata(&dump_path).await?.len();
+    let bytes = if size > max_size {
+        Err(ApiError::ProgramTooLarge {
+            size,
+            limit: max_size,
+        })
+    } else {
+        tokio::fs::read(&dump_path).await.map_err(Into::into)
+    };
+    let _ = tokio::fs::remove_file(&dump_path).await;
+    bytes
+}
+
+// Executables built in `dir`, the `.so` files of the `target/deploy` directories below it
+async fn read_built_executables(dir: PathBuf) -> Vec<Vec<u8>> {
+    fn visit(dir: &Path, executables: &mut Vec<Vec<u8>>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                visit(&path, executables);
+            } else if file_type.is_file()
+                && path.extension().is_some_and(|extension| extension == "so")
+                && path
+                    .parent()
+                    .is_some_and(|parent| parent.ends_with("target/deploy"))
+            {
+                if let Ok(bytes) = std::fs::read(&path) {
+                    executables.push(bytes);
+                }
+            }
+        }
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let mut executables = Vec::new();
+        visit(&dir, &mut executables);
+        executables
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Hash an executable the same way as `solana-verify`: the sha256 of the bytes
+/// with the trailing zero padding removed.
+pub fn executable_hash(bytes: &[u8]) -> String {
+    let len = bytes
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |i| i + 1);
+    hex::encode(Sha256::digest(&bytes[..len]))
+}
+
+/// Hash the account storing a deployed executable, made of a `metadata_size` loader header
+/// followed by the zero padded executable, the way `mode` defines.
+pub fn storage_hash(storage: &[u8], metadata_size: usize, mode: HashMode) -> String {
+    let executable = storage.get(metadata_size..).unwrap_or_default();
+    match mode {
+        HashMode::Trimmed => executable_hash(executable),
+        HashMode::Padded => hex::encode(Sha256::digest(executable)),
+        HashMode::Account => hex::encode(Sha256::digest(storage)),
+    }
 }
 
-pub async fn get_on_chain_hash(program_id: &str) -> Result<String> {
-    let rpc_url =
-        env::var("RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+/// Hash of the deployed program computed the way `mode` defines, read from the RPC.
+pub async fn get_on_chain_hash_in_mode(program_id: &str, mode: HashMode) -> Result<String> {
+    let (storage, metadata_size) = rpc::get_program_storage(program_id).await?;
+    Ok(storage_hash(&storage, metadata_size, mode))
+}
+
+// Fail with `ProgramClosed` instead of hashing a closed or empty program
+async fn ensure_program_not_closed(program_id: &str) -> Result<()> {
+    match rpc::get_program_account_info(program_id).await {
+        Ok(info) if info.program_size == 0 => Err(ApiError::ProgramClosed(program_id.to_string())),
+        Err(err @ ApiError::ProgramClosed(_)) => Err(err),
+        // Other failures are left for solana-verify to report
+        _ => Ok(()),
+    }
+}
+
+pub async fn get_on_chain_hash(program_id: &str, commitment: Commitment) -> Result<String> {
+    // solana-verify reads finalized state, so fresher commitments hash the account storing
+    // the executable directly, as `trimmed` does
+    if commitment != Commitment::Finalized {
+        let (storage, metadata_size) = rpc::get_program_storage_at(program_id, commitment).await?;
+        return Ok(storage_hash(&storage, metadata_size, HashMode::Trimmed));
+    }
+
+    ensure_program_not_closed(program_id).await?;
+    if matches!(
+        rpc::get_program_loader(program_id).await,
+        Ok(ProgramLoader::LoaderV4)
+    ) {
+        let bytes = rpc::get_loader_v4_executable(program_id).await?;
+        return Ok(executable_hash(&bytes));
+    }
+
+    let rpc_url = Network::default().rpc_url();
     let mut cmd = Command::new("solana-verify");
     cmd.arg("get-program-hash").arg(program_id);
     cmd.arg("--url").arg(rpc_url);
@@ -172,5 +1422,128 @@ pub async fn get_on_chain_hash(program_id: &str) -> Result<String> {
     let hash = get_last_line(&result).ok_or_else(|| {
         ApiError::Custom("Failed to build and get output from program".to_string())
     })?;
+    if hash == EMPTY_PROGRAM_HASH {
+        return Err(ApiError::ProgramClosed(program_id.to_string()));
+    }
     Ok(hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BuildTarget;
+
+    fn workspace_params(member: Option<&str>) -> SolanaProgramBuildParams {
+        SolanaProgramBuildParams {
+            repository: "https://github.com/Squads-Protocol/v4".to_string(),
+            program_id: "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf".to_string(),
+            commit_hash: None,
+            lib_name: Some("squads_multisig_program".to_string()),
+            bpf_flag: None,
+            base_image: None,
+            mount_path: None,
+            cargo_args: None,
+            no_default_features: None,
+            workspace_member: member.map(ToOwned::to_owned),
+            signature: None,
+            nonce: None,
+            default_ref_strategy: None,
+            vendored: None,
+            extra_hashes: None,
+            tag: None,
+            docker_build_args: None,
+            build_system: None,
+            dockerfile_path: None,
+            artifact_path: None,
+            target: None,
+            anchor_version: None,
+            cargo_profile: None,
+            profile_overrides: None,
+            rustflags: None,
+            network: None,
+            source_date_epoch: None,
+            lock_file_content: None,
+            language: None,
+            labels: None,
+            upstream_repo: None,
+            commitment: None,
+            crate_name: None,
+            crate_version: None,
+            tenant_id: None,
+        }
+    }
+
+    #[test]
+    fn test_workspace_member_builds_from_root() {
+        let params = workspace_params(Some("squads-multisig-program"));
+        let args = build_cargo_args(&params);
+        assert_eq!(args, vec!["-p", "squads-multisig-program"]);
+    }
+
+    #[test]
+    fn test_workspace_member_keeps_explicit_package() {
+        let mut params = workspace_params(Some("squads-multisig-program"));
+        params.cargo_args = Some(vec!["--package".to_string(), "other".to_string()]);
+        params.no_default_features = Some(true);
+        let args = build_cargo_args(&params);
+        assert_eq!(args, vec!["--package", "other", "--no-default-features"]);
+    }
+
+    #[test]
+    fn test_without_workspace_member() {
+        let params = workspace_params(None);
+        assert!(build_cargo_args(&params).is_empty());
+    }
+
+    #[test]
+    fn test_target_selects_arch() {
+        let mut params = workspace_params(None);
+        params.target = Some(BuildTarget::SbpfV2);
+        assert_eq!(build_cargo_args(&params), vec!["--arch", "v2"]);
+
+        params.target = Some(BuildTarget::Sbf);
+        assert!(build_cargo_args(&params).is_empty());
+    }
+
+    #[test]
+    fn test_executable_hash_ignores_trailing_zeros() {
+        let hash = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(executable_hash(b"abc"), hash);
+        assert_eq!(executable_hash(b"abc\0\0\0\0"), hash);
+    }
+
+    #[test]
+    fn test_storage_hash_modes() {
+        let storage = b"head\x01\x02\0\0";
+        assert_eq!(
+            storage_hash(storage, 4, HashMode::Trimmed),
+            executable_hash(b"\x01\x02")
+        );
+        assert_eq!(
+            storage_hash(storage, 4, HashMode::Padded),
+            hex::encode(Sha256::digest(b"\x01\x02\0\0"))
+        );
+        assert_eq!(
+            storage_hash(storage, 4, HashMode::Account),
+            hex::encode(Sha256::digest(storage))
+        );
+    }
+
+    #[test]
+    fn test_parse_sccache_stats() {
+        let output = "Compile requests                    40\n\
+                      Cache hits                          31\n\
+                      Cache hits (Rust)                   31\n\
+                      Cache misses                         9\n";
+        assert_eq!(parse_sccache_stats(output), Some((31, 9)));
+        assert_eq!(parse_sccache_stats("sccache: error"), None);
+    }
+
+    #[test]
+    fn test_workspace_member_name_validation() {
+        assert!(is_valid_package_name("squads-multisig_program2"));
+        assert!(!is_valid_package_name("--features"));
+        assert!(!is_valid_package_name("member; rm -rf /"));
+        assert!(!is_valid_package_name(""));
+    }
+}
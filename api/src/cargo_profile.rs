@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::errors::ApiError;
+use crate::git::BareClone;
+use crate::Result;
+
+/// Profile `cargo build-sbf` builds with. It always passes `--release`, so the settings of
+/// other profiles are applied onto this one.
+pub const DEFAULT_CARGO_PROFILE: &str = "release";
+
+// Profile settings which change the generated code and can be overridden
+pub const PROFILE_SETTINGS: [&str; 6] = [
+    "opt-level",
+    "lto",
+    "codegen-units",
+    "overflow-checks",
+    "debug-assertions",
+    "incremental",
+];
+
+// Profile names follow the same rules as package names
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Values are limited to integers and booleans, which cargo parses without quoting, so
+/// they can be forwarded as `--config` values without any shell metacharacters.
+pub fn is_valid_profile_setting(key: &str, value: &str) -> bool {
+    match key {
+        "opt-level" => matches!(value, "0" | "1" | "2" | "3"),
+        "codegen-units" => value.parse::<u32>().is_ok_and(|units| units > 0),
+        "lto" | "overflow-checks" | "debug-assertions" | "incremental" => {
+            matches!(value, "true" | "false")
+        }
+        _ => false,
+    }
+}
+
+// Settings of `[profile.<profile>]` in a Cargo.toml which can be applied to the release
+// profile. `lto = "fat"` and `lto = "off"` are the same as `true` and `false`.
+fn parse_profile_settings(cargo_toml: &str, profile: &str) -> Result<BTreeMap<String, String>> {
+    let manifest: toml::Table = toml::from_str(cargo_toml)
+        .map_err(|err| ApiError::Build(format!("Failed to parse Cargo.toml: {}", err)))?;
+    let settings = manifest
+        .get("profile")
+        .and_then(|profiles| profiles.get(profile))
+        .and_then(toml::Value::as_table)
+        .ok_or_else(|| {
+            ApiError::Build(format!("Profile {} is not defined in Cargo.toml", profile))
+        })?;
+
+    let mut applied = BTreeMap::new();
+    for key in PROFILE_SETTINGS {
+        let Some(raw) = settings.get(key) else {
+            continue;
+        };
+        let value = match (key, raw) {
+            ("lto", toml::Value::String(lto)) if lto == "fat" => "true".to_string(),
+            ("lto", toml::Value::String(lto)) if lto == "off" => "false".to_string(),
+            (_, toml::Value::Integer(value)) => value.to_string(),
+            (_, toml::Value::Boolean(value)) => value.to_string(),
+            _ => String::new(),
+        };
+        if !is_valid_profile_setting(key, &value) {
+            return Err(ApiError::Build(format!(
+                "Setting {} = {} of profile {} cannot be applied to the build",
+                key, raw, profile
+            )));
+        }
+        applied.insert(key.to_string(), value);
+    }
+    Ok(applied)
+}
+
+/// Read the settings of a profile from the Cargo.toml of the project at `commit`, found at
+/// the root of the repository or of `mount_path`.
+pub async fn read_profile_settings(
+    clone: &BareClone,
+    commit: Option<&str>,
+    mount_path: Option<&str>,
+    profile: &str,
+) -> Result<BTreeMap<String, String>> {
+    let path = match mount_path.map(|path| path.trim_matches('/')) {
+        Some(mount_path) if !mount_path.is_empty() => format!("{}/Cargo.toml", mount_path),
+        _ => "Cargo.toml".to_string(),
+    };
+    let cargo_toml = clone
+        .read_file(commit, &path)
+        .await?
+        .ok_or_else(|| ApiError::Build(format!("{} not found in the repository", path)))?;
+    parse_profile_settings(&cargo_toml, profile)
+}
+
+/// Settings applied to the release profile: those of `profile` if it is another one,
+/// then the explicit overrides.
+pub fn merge_profile_settings(
+    profile_settings: BTreeMap<String, String>,
+    overrides: Option<&HashMap<String, String>>,
+) -> BTreeMap<String, String> {
+    let mut settings = profile_settings;
+    if let Some(overrides) = overrides {
+        settings.extend(
+            overrides
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+    }
+    settings
+}
+
+/// Cargo args overriding the release profile with `settings`.
+pub fn profile_config_args(settings: &BTreeMap<String, String>) -> Vec<String> {
+    settings
+        .iter()
+        .flat_map(|(key, value)| {
+            [
+                "--config".to_string(),
+                format!("profile.{}.{}={}", DEFAULT_CARGO_PROFILE, key, value),
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_TOML: &str = r#"
+[workspace]
+members = ["programs/*"]
+
+[profile.release]
+overflow-checks = true
+
+[profile.deploy]
+inherits = "release"
+opt-level = 3
+lto = "fat"
+codegen-units = 1
+debug = true
+
+[profile.small]
+opt-level = "z"
+"#;
+
+    #[test]
+    fn test_parse_profile_settings() {
+        let settings = parse_profile_settings(CARGO_TOML, "deploy").unwrap();
+        assert_eq!(
+            settings,
+            BTreeMap::from([
+                ("codegen-units".to_string(), "1".to_string()),
+                ("lto".to_string(), "true".to_string()),
+                ("opt-level".to_string(), "3".to_string()),
+            ])
+        );
+
+        assert!(parse_profile_settings(CARGO_TOML, "small").is_err());
+        assert!(parse_profile_settings(CARGO_TOML, "missing").is_err());
+    }
+
+    #[test]
+    fn test_profile_config_args() {
+        let settings = merge_profile_settings(
+            parse_profile_settings(CARGO_TOML, "deploy").unwrap(),
+            Some(&HashMap::from([("lto".to_string(), "false".to_string())])),
+        );
+        assert_eq!(
+            profile_config_args(&settings),
+            vec![
+                "--config",
+                "profile.release.codegen-units=1",
+                "--config",
+                "profile.release.lto=false",
+                "--config",
+                "profile.release.opt-level=3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profile_setting_validation() {
+        assert!(is_valid_profile_setting("opt-level", "2"));
+        assert!(is_valid_profile_setting("overflow-checks", "false"));
+        assert!(!is_valid_profile_setting("opt-level", "z"));
+        assert!(!is_valid_profile_setting("codegen-units", "0"));
+        assert!(!is_valid_profile_setting("lto", "thin"));
+        assert!(!is_valid_profile_setting("debug", "true"));
+        assert!(!is_valid_profile_setting("lto", "true; rm -rf /"));
+    }
+}
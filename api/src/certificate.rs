@@ -0,0 +1,137 @@
+use std::env;
+
+use ed25519_dalek::{Signer, SigningKey};
+
+use crate::build_args::build_args_to_map;
+use crate::errors::ApiError;
+use crate::models::{
+    CertificateSignature, CertificateToolchain, SolanaProgramBuild, VerificationCertificate,
+    VerifiedProgram,
+};
+use crate::Result;
+
+// Version of the certificate format
+const CERTIFICATE_VERSION: u32 = 1;
+
+/// Assemble the certificate of a verification from the build it was produced by.
+pub fn build_certificate(
+    verified_build: VerifiedProgram,
+    build: SolanaProgramBuild,
+) -> VerificationCertificate {
+    VerificationCertificate {
+        version: CERTIFICATE_VERSION,
+        program_id: verified_build.program_id,
+        repository: build.repository,
+        commit: build.commit_hash.or(build.resolved_commit),
+        executable_hash: verified_build.executable_hash,
+        on_chain_hash: verified_build.on_chain_hash,
+        is_verified: verified_build.is_verified,
+        toolchain: CertificateToolchain {
+            base_image: build.base_docker_image,
+            bpf: build.bpf_flag,
+            build_system: build.build_system,
+            anchor_version: build.anchor_version,
+            cargo_profile: build.cargo_profile,
+            profile_overrides: build
+                .profile_overrides
+                .as_deref()
+                .map(build_args_to_map)
+                .unwrap_or_default(),
+            rustflags: build.rustflags.unwrap_or_default(),
+            lib_name: build.lib_name,
+            mount_path: build.mount_path,
+            workspace_member: build.workspace_member,
+            no_default_features: build.no_default_features,
+            vendored: build.vendored,
+            cargo_args: build.cargo_args.unwrap_or_default(),
+        },
+        build_id: build.id,
+        submitted_at: build.created_at,
+        verified_at: verified_build.verified_at,
+        signature: None,
+    }
+}
+
+/// Sign the serialized certificate, without its signature, with the ed25519 key.
+pub fn sign_certificate(
+    mut certificate: VerificationCertificate,
+    key: &SigningKey,
+) -> Result<VerificationCertificate> {
+    certificate.signature = None;
+    let message = serde_json::to_vec(&certificate)
+        .map_err(|err| ApiError::Custom(format!("Failed to serialize certificate: {}", err)))?;
+
+    certificate.signature = Some(CertificateSignature {
+        algorithm: "ed25519".to_string(),
+        public_key: bs58::encode(key.verifying_key().as_bytes()).into_string(),
+        signature: bs58::encode(key.sign(&message).to_bytes()).into_string(),
+    });
+    Ok(certificate)
+}
+
+/// Read the server signing key, a hex encoded ed25519 seed, from
+/// `CERTIFICATE_SIGNING_KEY`. Certificates are unsigned when it is not set.
+pub fn signing_key_from_env() -> Result<Option<SigningKey>> {
+    let Ok(seed) = env::var("CERTIFICATE_SIGNING_KEY") else {
+        return Ok(None);
+    };
+    let seed: [u8; 32] = hex::decode(seed.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            ApiError::Custom("CERTIFICATE_SIGNING_KEY must be a 32 byte hex seed".to_string())
+        })?;
+    Ok(Some(SigningKey::from_bytes(&seed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier};
+
+    fn certificate() -> VerificationCertificate {
+        let params = serde_json::from_value(serde_json::json!({
+            "repository": "https://github.com/Ellipsis-Labs/phoenix-v1",
+            "program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+            "commit_hash": "3742e55",
+        }))
+        .unwrap();
+        let build = SolanaProgramBuild::from(&params);
+        let verified_build = VerifiedProgram {
+            id: "1".to_string(),
+            program_id: build.program_id.clone(),
+            is_verified: true,
+            on_chain_hash: "5bdb733d".to_string(),
+            executable_hash: "5bdb733d".to_string(),
+            verified_at: build.created_at,
+            solana_build_id: build.id.clone(),
+            extra_digests: None,
+            loader: None,
+            registry_match: None,
+            status_changed: false,
+            tenant_id: build.tenant_id.clone(),
+            declared_id: None,
+        };
+        build_certificate(verified_build, build)
+    }
+
+    #[test]
+    fn test_signed_certificate_verifies() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let unsigned = certificate();
+        let signed = sign_certificate(unsigned.clone(), &key).unwrap();
+
+        let signature = signed.signature.as_ref().unwrap();
+        let signature_bytes: [u8; 64] = bs58::decode(&signature.signature)
+            .into_vec()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let message = serde_json::to_vec(&unsigned).unwrap();
+        assert!(key
+            .verifying_key()
+            .verify(&message, &Signature::from_bytes(&signature_bytes))
+            .is_ok());
+        assert_eq!(signed.commit.as_deref(), Some("3742e55"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::errors::ApiError;
+use crate::hash_provider::OnChainHashProvider;
+use crate::models::{CircuitState, CircuitStatus, Commitment};
+use crate::Result;
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_COOLDOWN_SECS: u64 = 30;
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    // Fetches fail fast until then once the breaker has opened
+    open_until: Option<Instant>,
+}
+
+/// Wraps an on-chain hash provider so that an RPC outage does not pile up fetches.
+/// After `failure_threshold` consecutive failures the breaker opens and fetches fail
+/// with [`ApiError::RpcCircuitOpen`] for the cooldown. The first fetch after the
+/// cooldown is let through as a trial, closing the breaker on success and reopening
+/// it on failure.
+pub struct CircuitBreaker<P> {
+    inner: P,
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl<P> CircuitBreaker<P> {
+    pub fn new(inner: P, failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            inner,
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Configure the breaker with `RPC_BREAKER_THRESHOLD` and `RPC_BREAKER_COOLDOWN_SECS`.
+    pub fn from_env(inner: P) -> Self {
+        fn parse_env<T: std::str::FromStr>(key: &str) -> Option<T> {
+            env::var(key).ok().and_then(|value| value.parse().ok())
+        }
+
+        Self::new(
+            inner,
+            parse_env("RPC_BREAKER_THRESHOLD").unwrap_or(DEFAULT_FAILURE_THRESHOLD),
+            Duration::from_secs(
+                parse_env("RPC_BREAKER_COOLDOWN_SECS").unwrap_or(DEFAULT_COOLDOWN_SECS),
+            ),
+        )
+    }
+
+    // Whether a fetch may go through. Admitting the trial fetch after the cooldown
+    // holds the others back until it finishes or another cooldown passes.
+    fn admit(&self) -> bool {
+        let mut state = self.state.lock().expect("circuit breaker poisoned");
+        match state.open_until {
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                state.open_until = Some(Instant::now() + self.cooldown);
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let mut state = self.state.lock().expect("circuit breaker poisoned");
+        if success {
+            *state = BreakerState::default();
+            return;
+        }
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold {
+            if state.open_until.is_none() {
+                tracing::warn!(
+                    "Opening the RPC circuit breaker after {} consecutive failures",
+                    state.consecutive_failures
+                );
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    pub fn status(&self) -> CircuitStatus {
+        let state = self.state.lock().expect("circuit breaker poisoned");
+        let now = Instant::now();
+        let (state_name, retry_in_secs) = match state.open_until {
+            Some(until) if now < until => (CircuitState::Open, Some((until - now).as_secs())),
+            Some(_) => (CircuitState::HalfOpen, None),
+            None => (CircuitState::Closed, None),
+        };
+        CircuitStatus {
+            state: state_name,
+            consecutive_failures: state.consecutive_failures,
+            retry_in_secs,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: OnChainHashProvider> OnChainHashProvider for CircuitBreaker<P> {
+    async fn get_on_chain_hash(&self, program_id: &str, commitment: Commitment) -> Result<String> {
+        if !self.admit() {
+            return Err(ApiError::RpcCircuitOpen);
+        }
+        let result = self.inner.get_on_chain_hash(program_id, commitment).await;
+        // A closed program is an answer from the RPC, not a failure of it
+        self.record(matches!(result, Ok(_) | Err(ApiError::ProgramClosed(_))));
+        result
+    }
+
+    fn circuit_status(&self) -> Option<CircuitStatus> {
+        Some(self.status())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_provider::mock::MockHashProvider;
+
+    const PROGRAM_ID: &str = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY";
+    const UNKNOWN_PROGRAM_ID: &str = "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf";
+    const HASH: &str = "5bdb733d10c170fbe08912d258bca0bd15dc52ae4919b7db162f44fa0608516b";
+
+    fn breaker(cooldown: Duration) -> CircuitBreaker<MockHashProvider> {
+        CircuitBreaker::new(
+            MockHashProvider::default().with_hash(PROGRAM_ID, HASH),
+            2,
+            cooldown,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_consecutive_failures() {
+        let breaker = breaker(Duration::from_secs(60));
+        assert!(breaker
+            .get_on_chain_hash(UNKNOWN_PROGRAM_ID, Commitment::Finalized)
+            .await
+            .is_err());
+        assert_eq!(breaker.status().state, CircuitState::Closed);
+        assert!(breaker
+            .get_on_chain_hash(UNKNOWN_PROGRAM_ID, Commitment::Finalized)
+            .await
+            .is_err());
+        assert_eq!(breaker.status().state, CircuitState::Open);
+
+        // Fetches which would succeed fail fast while the breaker is open
+        assert!(matches!(
+            breaker
+                .get_on_chain_hash(PROGRAM_ID, Commitment::Finalized)
+                .await,
+            Err(ApiError::RpcCircuitOpen)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_failures() {
+        let breaker = breaker(Duration::from_secs(60));
+        assert!(breaker
+            .get_on_chain_hash(UNKNOWN_PROGRAM_ID, Commitment::Finalized)
+            .await
+            .is_err());
+        assert_eq!(
+            breaker
+                .get_on_chain_hash(PROGRAM_ID, Commitment::Finalized)
+                .await
+                .unwrap(),
+            HASH
+        );
+        assert!(breaker
+            .get_on_chain_hash(UNKNOWN_PROGRAM_ID, Commitment::Finalized)
+            .await
+            .is_err());
+        assert_eq!(breaker.status().state, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_trial_after_cooldown_closes() {
+        let breaker = breaker(Duration::ZERO);
+        for _ in 0..2 {
+            assert!(breaker
+                .get_on_chain_hash(UNKNOWN_PROGRAM_ID, Commitment::Finalized)
+                .await
+                .is_err());
+        }
+        assert_eq!(breaker.status().state, CircuitState::HalfOpen);
+
+        assert_eq!(
+            breaker
+                .get_on_chain_hash(PROGRAM_ID, Commitment::Finalized)
+                .await
+                .unwrap(),
+            HASH
+        );
+        assert_eq!(breaker.status().state, CircuitState::Closed);
+        assert_eq!(breaker.status().consecutive_failures, 0);
+    }
+}
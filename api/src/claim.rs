@@ -0,0 +1,212 @@
+use std::env;
+
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+use crate::git::read_file;
+use crate::models::{ClaimMethod, ProgramClaim};
+use crate::Result;
+
+/// File at the root of a repository listing the program ids its owners claim, one per line,
+/// and the domains allowed to claim its programs as `domain: <domain>` lines.
+pub const CLAIM_FILE: &str = ".solana-verify-claim";
+// Prefix of the claim file lines naming a domain
+const CLAIM_FILE_DOMAIN_PREFIX: &str = "domain:";
+/// Subdomain whose TXT records claim programs for a domain.
+pub const CLAIM_DNS_LABEL: &str = "_solana-verify";
+// Prefix of the TXT records claiming a program, followed by its id
+const CLAIM_RECORD_PREFIX: &str = "solana-verify-claim=";
+
+// DNS over HTTPS resolver used unless `DNS_OVER_HTTPS_URL` is set
+const DEFAULT_DOH_URL: &str = "https://cloudflare-dns.com/dns-query";
+// Type of TXT records in DNS JSON answers
+const TXT_RECORD_TYPE: u16 = 16;
+
+// Answer of a DNS over HTTPS resolver in the JSON format of Cloudflare and Google
+#[derive(Debug, Deserialize)]
+struct DnsResponse {
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DnsAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DnsAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+// Lines of a claim file without blank lines and `#` comments
+fn claim_file_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+}
+
+// Whether a claim file lists `program_id`
+fn claim_file_lists(content: &str, program_id: &str) -> bool {
+    claim_file_lines(content).any(|line| line == program_id)
+}
+
+// Whether a claim file names `domain` on a `domain:` line
+fn claim_file_names_domain(content: &str, domain: &str) -> bool {
+    claim_file_lines(content)
+        .filter_map(|line| line.strip_prefix(CLAIM_FILE_DOMAIN_PREFIX))
+        .any(|named| named.trim().eq_ignore_ascii_case(domain))
+}
+
+// Text of a TXT record from its presentation format, in which long records are split into
+// several quoted strings
+fn txt_record_text(data: &str) -> String {
+    let data = data.trim();
+    if !data.starts_with('"') {
+        return data.to_string();
+    }
+    data.split('"')
+        .skip(1)
+        .step_by(2)
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+// Content of the claim file on the default branch of `repository`
+async fn read_claim_file(repository: &str) -> Result<String> {
+    read_file(repository, None, CLAIM_FILE)
+        .await
+        .map_err(|err| ApiError::ClaimNotProven(format!("Failed to read {}: {}", repository, err)))?
+        .ok_or_else(|| {
+            ApiError::ClaimNotProven(format!(
+                "{} has no {} file on its default branch",
+                repository, CLAIM_FILE
+            ))
+        })
+}
+
+/// Check that the default branch of `repository` lists `program_id` in its claim file.
+/// Returns where the proof was found.
+pub async fn check_repository_claim(repository: &str, program_id: &str) -> Result<String> {
+    let content = read_claim_file(repository).await?;
+    if !claim_file_lists(&content, program_id) {
+        return Err(ApiError::ClaimNotProven(format!(
+            "{} of {} does not list {}",
+            CLAIM_FILE, repository, program_id
+        )));
+    }
+    Ok(format!(
+        "{}/{}",
+        repository.trim_end_matches('/'),
+        CLAIM_FILE
+    ))
+}
+
+/// Check that the claim file of `repository`, which `program_id` was verified from, names
+/// `domain`, and that `domain` has a TXT record claiming `program_id`, resolved over HTTPS
+/// with the resolver at `DNS_OVER_HTTPS_URL`. Returns the name of the record.
+pub async fn check_dns_claim(domain: &str, program_id: &str, repository: &str) -> Result<String> {
+    // Without it, any domain owner could claim any program
+    let content = read_claim_file(repository).await?;
+    if !claim_file_names_domain(&content, domain) {
+        return Err(ApiError::ClaimNotProven(format!(
+            "{} of {} does not name {}",
+            CLAIM_FILE, repository, domain
+        )));
+    }
+
+    let name = format!("{}.{}", CLAIM_DNS_LABEL, domain.to_ascii_lowercase());
+    let resolver = env::var("DNS_OVER_HTTPS_URL").unwrap_or_else(|_| DEFAULT_DOH_URL.to_string());
+    let response: DnsResponse = reqwest::Client::new()
+        .get(&resolver)
+        .query(&[("name", name.as_str()), ("type", "TXT")])
+        .header("Accept", "application/dns-json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let expected = format!("{}{}", CLAIM_RECORD_PREFIX, program_id);
+    if !response
+        .answer
+        .iter()
+        .filter(|answer| answer.record_type == TXT_RECORD_TYPE)
+        .any(|answer| txt_record_text(&answer.data) == expected)
+    {
+        return Err(ApiError::ClaimNotProven(format!(
+            "No TXT record \"{}\" found at {}",
+            expected, name
+        )));
+    }
+    Ok(name)
+}
+
+/// Check whether an existing claim is still proven against `repository`, which the program
+/// is now verified from. A claim proven against a repository the program is no longer
+/// verified from, such as a fork, is stale and can be replaced.
+pub async fn is_claim_still_proven(claim: &ProgramClaim, repository: &str) -> Result<bool> {
+    let proven = if claim.method == ClaimMethod::Dns.as_str() {
+        check_dns_claim(&claim.claimed_by, &claim.program_id, repository).await
+    } else if claim.claimed_by == repository {
+        check_repository_claim(repository, &claim.program_id).await
+    } else {
+        return Ok(false);
+    };
+    match proven {
+        Ok(_) => Ok(true),
+        Err(ApiError::ClaimNotProven(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_file_lists() {
+        let content = "# Programs of Ellipsis Labs\n\
+                       PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY  # phoenix\n\n";
+        assert!(claim_file_lists(
+            content,
+            "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"
+        ));
+        assert!(!claim_file_lists(
+            content,
+            "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf"
+        ));
+    }
+
+    #[test]
+    fn test_claim_file_names_domain() {
+        let content = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY\n\
+                       domain: EllipsisLabs.xyz  # docs site\n";
+        assert!(claim_file_names_domain(content, "ellipsislabs.xyz"));
+        assert!(!claim_file_names_domain(content, "example.com"));
+    }
+
+    #[test]
+    fn test_txt_record_text() {
+        assert_eq!(
+            txt_record_text("\"solana-verify-claim=\" \"PhoeNiX\""),
+            "solana-verify-claim=PhoeNiX"
+        );
+        assert_eq!(txt_record_text("plain"), "plain");
+    }
+
+    #[tokio::test]
+    async fn test_claim_of_fork_is_stale() {
+        let claim = ProgramClaim {
+            tenant_id: "default".to_string(),
+            program_id: "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY".to_string(),
+            method: ClaimMethod::Repo.as_str().to_string(),
+            claimed_by: "https://github.com/squatter/phoenix-v1".to_string(),
+            proof: "https://github.com/squatter/phoenix-v1/.solana-verify-claim".to_string(),
+            claimed_at: chrono::Utc::now().naive_utc(),
+        };
+        assert!(
+            !is_claim_still_proven(&claim, "https://github.com/Ellipsis-Labs/phoenix-v1")
+                .await
+                .unwrap()
+        );
+    }
+}
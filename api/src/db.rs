@@ -1,68 +1,392 @@
-use diesel::{expression_methods::ExpressionMethods, query_dsl::QueryDsl};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use diesel::{
+    expression_methods::ExpressionMethods, query_dsl::QueryDsl, BoolExpressionMethods,
+    ConnectionError, OptionalExtension,
+};
 use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::RunQueryDsl;
-use diesel_async::{pooled_connection::deadpool::Pool, AsyncPgConnection};
+use diesel_async::{
+    pooled_connection::deadpool::{Object, Pool},
+    AsyncConnection, AsyncPgConnection,
+};
+use diesel_async::{RunQueryDsl, SimpleAsyncConnection};
+use futures::future::BoxFuture;
 use r2d2_redis::redis::{Commands, FromRedisValue, Value};
 use r2d2_redis::{r2d2, RedisConnectionManager};
 
-use crate::builder::{self, get_on_chain_hash};
+use crate::build_args::{build_args_to_entries, build_args_to_map};
+use crate::builder;
+use crate::digests::digests_to_map;
 use crate::errors::ApiError;
+use crate::events::BuildEventBus;
+use crate::hash_provider::OnChainHashProvider;
+use crate::labels::{labels_from_json, labels_to_json};
 use crate::models::{
-    JobStatus, SolanaProgramBuild, SolanaProgramBuildParams, VerificationResponse, VerifiedProgram,
+    Attestation, BuildEvent, BuildMetadataChanges, Commitment, DeniedProgram, JobStatus,
+    PoolStatus, ProgramClaim, ProgramVersion, ReverifyParams, SolanaProgramBuild,
+    SolanaProgramBuildParams, Subscription, VerificationResponse, VerifiedProgram, VerifiedState,
+    Warning, WarningCode,
 };
+use crate::params_hash::params_hash;
+use crate::rpc;
+use crate::rustflags::normalize_rustflags;
+use crate::status_change;
+use crate::storage::BuildStorage;
+use crate::worker::WorkerPool;
 use crate::Result;
 
+// Challenge nonces expire after 5 minutes
+pub const CHALLENGE_TTL_SECS: u64 = 300;
+
+// Limits applied to both the Postgres and Redis connection pools
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    pub max_size: usize,
+    pub min_idle: u32,
+    pub acquire_timeout: Duration,
+    // Postgres `statement_timeout` of each connection, zero disables it
+    pub statement_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 16,
+            min_idle: 0,
+            acquire_timeout: Duration::from_secs(5),
+            statement_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Hours after which a build no longer deduplicates identical requests, from
+/// `DEDUP_WINDOW_HOURS`. Unset or `0` keeps builds deduplicating forever.
+pub fn dedup_window_hours() -> Option<u64> {
+    env::var("DEDUP_WINDOW_HOURS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|hours| *hours > 0)
+}
+
+impl PoolConfig {
+    /// Read the pool limits from `DB_POOL_MAX_SIZE`, `DB_POOL_MIN_IDLE`,
+    /// `DB_POOL_ACQUIRE_TIMEOUT_SECS` and `DB_STATEMENT_TIMEOUT_SECS`, falling back to the
+    /// defaults for unset values.
+    pub fn from_env() -> Self {
+        fn parse_env<T: std::str::FromStr>(key: &str) -> Option<T> {
+            env::var(key).ok().and_then(|value| value.parse().ok())
+        }
+
+        let default = Self::default();
+        Self {
+            max_size: parse_env("DB_POOL_MAX_SIZE").unwrap_or(default.max_size),
+            min_idle: parse_env("DB_POOL_MIN_IDLE").unwrap_or(default.min_idle),
+            acquire_timeout: parse_env("DB_POOL_ACQUIRE_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(default.acquire_timeout),
+            statement_timeout: parse_env("DB_STATEMENT_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(default.statement_timeout),
+        }
+    }
+}
+
+// Establish a Postgres connection whose statements are cancelled after `statement_timeout`
+fn establish_with_statement_timeout(
+    db_url: &str,
+    statement_timeout: Duration,
+) -> BoxFuture<'_, diesel::ConnectionResult<AsyncPgConnection>> {
+    Box::pin(async move {
+        let mut conn = AsyncPgConnection::establish(db_url).await?;
+        conn.batch_execute(&format!(
+            "SET statement_timeout = {}",
+            statement_timeout.as_millis()
+        ))
+        .await
+        .map_err(ConnectionError::CouldntSetupConfiguration)?;
+        Ok(conn)
+    })
+}
+
+// Docker build args of a build as a map for responses
+fn build_args_of(build: &SolanaProgramBuild) -> BTreeMap<String, String> {
+    build
+        .docker_build_args
+        .as_deref()
+        .map(build_args_to_map)
+        .unwrap_or_default()
+}
+
+// Labels of a build for responses
+fn labels_of(build: &SolanaProgramBuild) -> BTreeMap<String, String> {
+    labels_from_json(build.labels.as_ref())
+}
+
+// Last stored verification of a program, without checking the program on-chain
+fn stored_verification(
+    verification: Option<VerifiedProgram>,
+    build: Option<&SolanaProgramBuild>,
+) -> VerificationResponse {
+    let Some(verification) = verification else {
+        return VerificationResponse {
+            is_verified: false,
+            on_chain_hash: "".to_string(),
+            executable_hash: "".to_string(),
+            repo_url: build.map(builder::get_repo_url).unwrap_or_default(),
+            last_verified_at: None,
+            verified_by_authority: false,
+            record_exists: build.is_some(),
+            extra_hashes: BTreeMap::new(),
+            loader: None,
+            registry_match: None,
+            program_size: None,
+            rent_exempt: None,
+            upgrade_authority: None,
+            immutable: None,
+            resolved_commit: build.and_then(|build| build.resolved_commit.clone()),
+            docker_build_args: build.map(build_args_of).unwrap_or_default(),
+            target: build.map(|build| build.target.clone()),
+            network: build.map(|build| build.network.clone()),
+            commitment: build.map(|build| build.commitment.clone()),
+            rustflags: build
+                .and_then(|build| build.rustflags.clone())
+                .unwrap_or_default(),
+            status_changed: false,
+            declared_id: None,
+            declared_id_matches: None,
+            labels: build.map(labels_of).unwrap_or_default(),
+            upstream_repo: build.and_then(|build| build.upstream_repo.clone()),
+            published_crate: build.and_then(SolanaProgramBuild::published_crate),
+            warnings: build
+                .map(SolanaProgramBuild::stored_warnings)
+                .unwrap_or_default(),
+        };
+    };
+
+    let declared_id_matches = verification.declared_id_matches();
+    VerificationResponse {
+        is_verified: verification.is_verified,
+        on_chain_hash: verification.on_chain_hash,
+        executable_hash: verification.executable_hash,
+        repo_url: build.map(builder::get_repo_url).unwrap_or_default(),
+        last_verified_at: Some(verification.verified_at),
+        verified_by_authority: verification.is_verified
+            && build.is_some_and(|build| build.authority_claimed),
+        record_exists: true,
+        extra_hashes: digests_to_map(verification.extra_digests),
+        loader: verification.loader,
+        registry_match: verification.registry_match,
+        program_size: None,
+        rent_exempt: None,
+        upgrade_authority: None,
+        immutable: None,
+        resolved_commit: build.and_then(|build| build.resolved_commit.clone()),
+        docker_build_args: build.map(build_args_of).unwrap_or_default(),
+        target: build.map(|build| build.target.clone()),
+        network: build.map(|build| build.network.clone()),
+        commitment: build.map(|build| build.commitment.clone()),
+        rustflags: build
+            .and_then(|build| build.rustflags.clone())
+            .unwrap_or_default(),
+        status_changed: verification.status_changed,
+        declared_id: verification.declared_id,
+        declared_id_matches,
+        labels: build.map(labels_of).unwrap_or_default(),
+        upstream_repo: build.and_then(|build| build.upstream_repo.clone()),
+        published_crate: build.and_then(SolanaProgramBuild::published_crate),
+        warnings: build
+            .map(SolanaProgramBuild::stored_warnings)
+            .unwrap_or_default(),
+    }
+}
+
 #[derive(Clone)]
 pub struct DbClient {
     pub db_pool: Pool<AsyncPgConnection>,
     pub redis_pool: r2d2::Pool<RedisConnectionManager>,
+    pub acquire_timeout: Duration,
+    pub statement_timeout: Duration,
+    pub hash_provider: Arc<dyn OnChainHashProvider>,
+    pub workers: WorkerPool,
+    pub events: BuildEventBus,
+    pub storage: Arc<dyn BuildStorage>,
 }
 
 impl DbClient {
-    pub fn new(db_url: &str, redis_url: &str) -> Self {
-        let config = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new(db_url);
+    pub fn new(
+        db_url: &str,
+        redis_url: &str,
+        pool_config: PoolConfig,
+        hash_provider: Arc<dyn OnChainHashProvider>,
+        workers: WorkerPool,
+        storage: Arc<dyn BuildStorage>,
+    ) -> Self {
+        let statement_timeout = pool_config.statement_timeout;
+        let config =
+            AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new_with_setup(
+                db_url,
+                move |url| establish_with_statement_timeout(url, statement_timeout),
+            );
         let postgres_pool = Pool::builder(config)
+            .max_size(pool_config.max_size)
             .build()
             .expect("Failed to create DB Pool");
         let manager = RedisConnectionManager::new(redis_url).expect(
             "Failed to create Redis connection manager. Check that REDIS_URL is set in .env file",
         );
-        let redis_pool = r2d2::Pool::builder().build(manager).expect(
-            "Failed to create Redis connection pool. Check that REDIS_URL is set in .env file",
-        );
+        let redis_pool = r2d2::Pool::builder()
+            .max_size(pool_config.max_size as u32)
+            .min_idle(Some(pool_config.min_idle))
+            .connection_timeout(pool_config.acquire_timeout)
+            .build(manager)
+            .expect(
+                "Failed to create Redis connection pool. Check that REDIS_URL is set in .env file",
+            );
 
         Self {
             db_pool: postgres_pool,
             redis_pool,
+            acquire_timeout: pool_config.acquire_timeout,
+            statement_timeout,
+            hash_provider,
+            workers,
+            events: BuildEventBus::default(),
+            storage,
         }
     }
 
+    // Get a Postgres connection, giving up once the acquire timeout elapses
+    async fn get_db_conn(&self) -> Result<Object<AsyncPgConnection>> {
+        tokio::time::timeout(self.acquire_timeout, self.db_pool.get())
+            .await
+            .map_err(|_| ApiError::PoolTimeout)?
+            .map_err(Into::into)
+    }
+
+    // Idle and active connection counts of the Postgres and Redis pools
+    pub fn pool_status(&self) -> (PoolStatus, PoolStatus) {
+        let db_status = self.db_pool.status();
+        let db_idle = db_status.available.max(0) as usize;
+        let redis_state = self.redis_pool.state();
+
+        (
+            PoolStatus {
+                max_size: db_status.max_size,
+                size: db_status.size,
+                idle: db_idle,
+                active: db_status.size.saturating_sub(db_idle),
+            },
+            PoolStatus {
+                max_size: self.redis_pool.max_size() as usize,
+                size: redis_state.connections as usize,
+                idle: redis_state.idle_connections as usize,
+                active: (redis_state.connections - redis_state.idle_connections) as usize,
+            },
+        )
+    }
+
     pub async fn insert_build_params(&self, payload: &SolanaProgramBuild) -> Result<usize> {
         use crate::schema::solana_program_builds::dsl::*;
 
-        let conn = &mut self.db_pool.get().await?;
-        diesel::insert_into(solana_program_builds)
+        // Every new build is recorded here first, so draining refuses them all
+        self.workers.ensure_accepting_builds()?;
+        let conn = &mut self.get_db_conn().await?;
+        let inserted = diesel::insert_into(solana_program_builds)
             .values(payload)
             .execute(conn)
+            .await?;
+        self.record_event(&payload.id, &payload.status, None).await;
+        Ok(inserted)
+    }
+
+    // Append an event to the timeline of a build. Failures are logged and otherwise
+    // ignored so that auditing never aborts a build.
+    pub async fn record_event(&self, uid: &str, event_name: &str, event_detail: Option<&str>) {
+        use crate::schema::build_events::dsl::*;
+
+        let new_event = BuildEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            build_id: uid.to_string(),
+            event: event_name.to_string(),
+            detail: event_detail.map(ToOwned::to_owned),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        let result = match self.get_db_conn().await {
+            Ok(mut conn) => diesel::insert_into(build_events)
+                .values(&new_event)
+                .execute(&mut conn)
+                .await
+                .map_err(ApiError::from),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            tracing::error!(
+                "Failed to record build event {} for {}: {}",
+                event_name,
+                uid,
+                err
+            );
+        }
+        self.events.publish(&new_event);
+    }
+
+    // Get the events of a build ordered by time
+    pub async fn get_build_events(&self, uid: &str) -> Result<Vec<BuildEvent>> {
+        use crate::schema::build_events::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        build_events
+            .filter(build_id.eq(uid))
+            .order(created_at.asc())
+            .load::<BuildEvent>(conn)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn insert_or_update_verified_build(
-        &self,
-        payload: &VerifiedProgram,
-    ) -> Result<usize> {
+    // Insert or replace the verification of a program for its tenant. Returns whether it
+    // changed whether the program is verified, flagging programs which are no longer verified.
+    pub async fn insert_or_update_verified_build(&self, payload: &VerifiedProgram) -> Result<bool> {
         use crate::schema::verified_programs::dsl::*;
 
-        let conn = &mut self.db_pool.get().await?;
+        let previous = self
+            .get_verified_build(&payload.tenant_id, &payload.program_id)
+            .await
+            .ok();
+        let record = VerifiedProgram {
+            status_changed: status_change::status_changed(previous.as_ref(), payload.is_verified),
+            ..payload.clone()
+        };
+
+        let conn = &mut self.get_db_conn().await?;
         diesel::insert_into(verified_programs)
-            .values(payload)
-            .on_conflict(program_id)
+            .values(&record)
+            .on_conflict((tenant_id, program_id))
             .do_update()
-            .set(payload)
+            .set(&record)
             .execute(conn)
-            .await
-            .map_err(Into::into)
+            .await?;
+
+        let verified_by = record
+            .is_verified
+            .then_some((record.solana_build_id.as_str(), record.verified_at));
+        self.record_program_version(
+            &record.tenant_id,
+            &record.program_id,
+            &record.on_chain_hash,
+            verified_by,
+        )
+        .await;
+
+        if status_change::verification_lost(previous.as_ref(), record.is_verified) {
+            status_change::report_verification_lost(self, &record).await;
+        }
+        status_change::notify_subscribers(self, "new_build", previous.as_ref(), &record).await;
+        Ok(record.status_changed)
     }
 
     pub async fn check_for_dupliate(
@@ -71,10 +395,11 @@ impl DbClient {
     ) -> Result<SolanaProgramBuild> {
         use crate::schema::solana_program_builds::dsl::*;
 
-        let conn = &mut self.db_pool.get().await?;
+        let conn = &mut self.get_db_conn().await?;
 
         let mut query = solana_program_builds.into_boxed();
 
+        query = query.filter(tenant_id.eq(payload.tenant().to_owned()));
         query = query.filter(program_id.eq(payload.program_id.to_owned()));
         query = query.filter(repository.eq(payload.repository.to_owned()));
 
@@ -83,6 +408,14 @@ impl DbClient {
             query = query.filter(commit_hash.eq(hash));
         }
 
+        // crate_name and crate_version are optional
+        if let Some(name) = &payload.crate_name {
+            query = query.filter(crate_name.eq(name));
+        }
+        if let Some(version) = &payload.crate_version {
+            query = query.filter(crate_version.eq(version));
+        }
+
         // lib_name is optional
         if let Some(lib) = &payload.lib_name {
             query = query.filter(lib_name.eq(lib));
@@ -108,50 +441,296 @@ impl DbClient {
             query = query.filter(cargo_args.eq(args));
         }
 
+        // no_default_features is optional
+        if let Some(no_default) = &payload.no_default_features {
+            query = query.filter(no_default_features.eq(no_default));
+        }
+
+        // workspace_member is optional
+        if let Some(member) = &payload.workspace_member {
+            query = query.filter(workspace_member.eq(member));
+        }
+
+        // vendored is optional
+        if let Some(offline) = &payload.vendored {
+            query = query.filter(vendored.eq(offline));
+        }
+
+        // tag is optional
+        if let Some(git_tag) = &payload.tag {
+            query = query.filter(tag.eq(git_tag));
+        }
+
+        // dockerfile_path is optional
+        if let Some(dockerfile) = &payload.dockerfile_path {
+            query = query.filter(dockerfile_path.eq(dockerfile));
+        }
+
+        // artifact_path is optional
+        if let Some(artifact) = &payload.artifact_path {
+            query = query.filter(artifact_path.eq(artifact));
+        }
+
+        // build_system is optional
+        if payload.build_system.is_some() {
+            query = query.filter(build_system.eq(payload.build_system().as_str()));
+        }
+
+        // target defaults to the sbf target, as for builds made before it existed
+        query = query.filter(target.eq(payload.target().as_str()));
+
+        // network aliases are stored under their canonical name
+        query = query.filter(network.eq(payload.network().as_str()));
+
+        // anchor_version is optional
+        if let Some(version) = &payload.anchor_version {
+            query = query.filter(anchor_version.eq(version));
+        }
+
+        // docker_build_args is optional
+        if let Some(build_args) = &payload.docker_build_args {
+            query = query.filter(docker_build_args.eq(build_args_to_entries(build_args)));
+        }
+
+        // cargo_profile defaults to the release profile, as for builds made before it existed
+        query = query.filter(cargo_profile.eq(payload.cargo_profile()));
+
+        // profile_overrides is optional
+        if let Some(overrides) = &payload.profile_overrides {
+            query = query.filter(profile_overrides.eq(build_args_to_entries(overrides)));
+        }
+
+        // rustflags is optional
+        if let Some(flags) = &payload.rustflags {
+            if let Ok(flags) = normalize_rustflags(flags) {
+                query = query.filter(rustflags.eq(flags));
+            }
+        }
+
+        // source_date_epoch is optional
+        if let Some(epoch) = payload.source_date_epoch {
+            query = query.filter(source_date_epoch.eq(epoch));
+        }
+
+        // lock_file_content is optional
+        if let Some(content) = &payload.lock_file_content {
+            query = query.filter(lock_file_hash.eq(crate::lock_file::lock_file_hash(content)));
+        }
+
+        // language defaults to rust, as for builds made before it existed
+        query = query.filter(language.eq(payload.language()));
+
+        // Builds older than the dedup window let the request run again as a fresh build,
+        // unless they are still running
+        if let Some(hours) = dedup_window_hours() {
+            let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::hours(hours as i64);
+            query = query.filter(
+                created_at
+                    .gt(cutoff)
+                    .or(status.eq(String::from(JobStatus::InProgress))),
+            );
+        }
+
+        // The filters above only narrow down the candidates, as unset params match any
+        // value. A build is a duplicate when all its params, defaults included, are the same.
+        let expected_hash = params_hash(&SolanaProgramBuild::from(payload));
         query
+            .order(created_at.desc())
+            .load::<SolanaProgramBuild>(conn)
+            .await?
+            .into_iter()
+            .find(|build| params_hash(build) == expected_hash)
+            .ok_or(ApiError::from(diesel::result::Error::NotFound))
+    }
+
+    pub async fn get_build_params(
+        &self,
+        tenant: &str,
+        program_address: &str,
+    ) -> Result<SolanaProgramBuild> {
+        use crate::schema::solana_program_builds::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        solana_program_builds
+            .filter(tenant_id.eq(tenant))
+            .filter(crate::schema::solana_program_builds::program_id.eq(program_address))
+            .order(created_at.desc())
             .first::<SolanaProgramBuild>(conn)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn get_build_params(&self, program_address: &str) -> Result<SolanaProgramBuild> {
+    // Ids of the builds of a program which have not finished yet, oldest first
+    pub async fn get_in_progress_builds(
+        &self,
+        tenant: &str,
+        program_address: &str,
+    ) -> Result<Vec<String>> {
         use crate::schema::solana_program_builds::dsl::*;
 
-        let conn = &mut self.db_pool.get().await?;
+        let conn = &mut self.get_db_conn().await?;
         solana_program_builds
+            .filter(tenant_id.eq(tenant))
             .filter(crate::schema::solana_program_builds::program_id.eq(program_address))
-            .first::<SolanaProgramBuild>(conn)
+            .filter(
+                crate::schema::solana_program_builds::status
+                    .eq(String::from(JobStatus::InProgress)),
+            )
+            .order(created_at.asc())
+            .select(id)
+            .load::<String>(conn)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn get_verified_build(&self, program_address: &str) -> Result<VerifiedProgram> {
+    pub async fn get_verified_build(
+        &self,
+        tenant: &str,
+        program_address: &str,
+    ) -> Result<VerifiedProgram> {
         use crate::schema::verified_programs::dsl::*;
 
-        let conn = &mut self.db_pool.get().await?;
+        let conn = &mut self.get_db_conn().await?;
         verified_programs
+            .filter(tenant_id.eq(tenant))
             .filter(crate::schema::verified_programs::program_id.eq(program_address))
             .first::<VerifiedProgram>(conn)
             .await
             .map_err(Into::into)
     }
 
+    // Record the current on-chain hash of a verified program. Returns whether it changed
+    // whether the program is verified, flagging programs which are no longer verified.
     pub async fn update_onchain_hash(
         &self,
+        tenant: &str,
         program_address: &str,
         on_chainhash: &str,
         isverified: bool,
-    ) -> Result<usize> {
+    ) -> Result<bool> {
         use crate::schema::verified_programs::dsl::*;
-        let conn = &mut self.db_pool.get().await?;
+
+        let previous = self.get_verified_build(tenant, program_address).await.ok();
+        let changed = status_change::status_changed(previous.as_ref(), isverified);
+
+        let conn = &mut self.get_db_conn().await?;
         diesel::update(verified_programs)
+            .filter(tenant_id.eq(tenant))
             .filter(program_id.eq(program_address))
             .set((
                 crate::schema::verified_programs::on_chain_hash.eq(on_chainhash),
                 crate::schema::verified_programs::is_verified.eq(isverified),
                 crate::schema::verified_programs::verified_at.eq(chrono::Utc::now().naive_utc()),
+                crate::schema::verified_programs::status_changed.eq(changed),
             ))
             .execute(conn)
+            .await?;
+
+        let verified_by = previous.as_ref().filter(|_| isverified).map(|previous| {
+            (
+                previous.solana_build_id.as_str(),
+                chrono::Utc::now().naive_utc(),
+            )
+        });
+        self.record_program_version(tenant, program_address, on_chainhash, verified_by)
+            .await;
+
+        if let Some(previous) = previous {
+            let verification = VerifiedProgram {
+                on_chain_hash: on_chainhash.to_string(),
+                is_verified: isverified,
+                status_changed: changed,
+                ..previous.clone()
+            };
+            if status_change::verification_lost(Some(&previous), isverified) {
+                status_change::report_verification_lost(self, &verification).await;
+            }
+            if changed {
+                status_change::notify_subscribers(
+                    self,
+                    "status_changed",
+                    Some(&previous),
+                    &verification,
+                )
+                .await;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Record that `hash` was seen deployed for a program, and the build verifying it if its
+    /// executable matched. A version stays verified once a build matched it. Failures are
+    /// only logged, as the history is informational.
+    pub async fn record_program_version(
+        &self,
+        tenant: &str,
+        program_address: &str,
+        hash: &str,
+        verified_by: Option<(&str, NaiveDateTime)>,
+    ) {
+        use crate::schema::program_versions::dsl::*;
+
+        // Closed programs have no hash to record
+        if hash.is_empty() {
+            return;
+        }
+        let now = chrono::Utc::now().naive_utc();
+        let version = ProgramVersion {
+            tenant_id: tenant.to_string(),
+            program_id: program_address.to_string(),
+            on_chain_hash: hash.to_string(),
+            first_seen_at: now,
+            last_seen_at: now,
+            verified_build_id: verified_by.map(|(build_id, _)| build_id.to_string()),
+            verified_at: verified_by.map(|(_, at)| at),
+        };
+        let result = match self.get_db_conn().await {
+            Ok(mut conn) => {
+                let upsert = diesel::insert_into(program_versions)
+                    .values(&version)
+                    .on_conflict((tenant_id, program_id, on_chain_hash))
+                    .do_update();
+                match verified_by {
+                    Some(_) => {
+                        upsert
+                            .set((
+                                last_seen_at.eq(now),
+                                verified_build_id.eq(&version.verified_build_id),
+                                verified_at.eq(version.verified_at),
+                            ))
+                            .execute(&mut conn)
+                            .await
+                    }
+                    None => upsert.set(last_seen_at.eq(now)).execute(&mut conn).await,
+                }
+                .map_err(ApiError::from)
+            }
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            tracing::warn!(
+                "Failed to record version {} of {}: {}",
+                hash,
+                program_address,
+                err
+            );
+        }
+    }
+
+    /// Versions of a program seen while verifying it, oldest first.
+    pub async fn get_program_versions(
+        &self,
+        tenant: &str,
+        program_address: &str,
+    ) -> Result<Vec<ProgramVersion>> {
+        use crate::schema::program_versions::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        program_versions
+            .filter(tenant_id.eq(tenant))
+            .filter(program_id.eq(program_address))
+            .order(first_seen_at.asc())
+            .load::<ProgramVersion>(conn)
             .await
             .map_err(Into::into)
     }
@@ -202,6 +781,157 @@ impl DbClient {
         }
     }
 
+    // Store a fresh challenge nonce for the program. Nonces are keyed by themselves, so
+    // issuing one never invalidates those pending for the same program.
+    pub async fn set_challenge(&self, program_address: &str, nonce: &str) -> Result<()> {
+        let mut redis_conn = self.redis_pool.get()?;
+        let _: () = redis_conn.set_ex(
+            format!("challenge:{}", nonce),
+            program_address,
+            CHALLENGE_TTL_SECS as usize,
+        )?;
+        Ok(())
+    }
+
+    // Remove a pending challenge nonce so it can only be used once, returning whether it
+    // was issued for the program
+    pub async fn take_challenge(&self, program_address: &str, nonce: &str) -> Result<bool> {
+        let mut redis_conn = self.redis_pool.get()?;
+        let key = format!("challenge:{}", nonce);
+        let issued_for: Option<String> = redis_conn.get(&key)?;
+        // Only the request which deletes the key may use it
+        let removed: usize = redis_conn.del(&key)?;
+        Ok(removed == 1 && issued_for.as_deref() == Some(program_address))
+    }
+
+    /// Check the optional authority signature of a verification request, made over its
+    /// `claim_message` with a pending challenge nonce for the program.
+    ///
+    /// Returns `Ok(false)` if no signature was provided, `Ok(true)` if the signature was made
+    /// by the on-chain upgrade authority and an `ApiError::InvalidSignature` otherwise.
+    pub async fn check_authority_claim(&self, payload: &SolanaProgramBuildParams) -> Result<bool> {
+        let Some(signature) = &payload.signature else {
+            return Ok(false);
+        };
+        let Some(nonce) = &payload.nonce else {
+            return Err(ApiError::InvalidSignature(
+                "A nonce from /challenge is required with the signature".to_string(),
+            ));
+        };
+
+        if !self.take_challenge(&payload.program_id, nonce).await? {
+            return Err(ApiError::InvalidSignature(
+                "No pending challenge with this nonce for this program".to_string(),
+            ));
+        }
+        let message = payload.claim_message(nonce);
+        rpc::verify_authority_signature(&payload.program_id, &message, signature).await?;
+        Ok(true)
+    }
+
+    // Record the ref and commit a build resolved to when no commit hash was given
+    pub async fn update_resolved_ref(
+        &self,
+        uid: &str,
+        ref_name: &str,
+        commit: &str,
+    ) -> Result<usize> {
+        use crate::schema::solana_program_builds::dsl::*;
+        let conn = &mut self.get_db_conn().await?;
+        diesel::update(solana_program_builds)
+            .filter(id.eq(uid))
+            .set((resolved_ref.eq(ref_name), resolved_commit.eq(commit)))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Store the URL a redirecting repository of a build leads to
+    pub async fn update_repository(&self, uid: &str, url: &str) -> Result<usize> {
+        use crate::schema::solana_program_builds::dsl::*;
+        let conn = &mut self.get_db_conn().await?;
+        diesel::update(solana_program_builds)
+            .filter(id.eq(uid))
+            .set(repository.eq(url))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Correct the metadata of a build without touching its verification.
+    pub async fn update_build_metadata(
+        &self,
+        uid: &str,
+        changes: &BuildMetadataChanges,
+    ) -> Result<usize> {
+        use crate::schema::solana_program_builds::dsl::*;
+        let conn = &mut self.get_db_conn().await?;
+        diesel::update(solana_program_builds)
+            .filter(id.eq(uid))
+            .set(changes)
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Record the Anchor version a build is made with
+    pub async fn update_anchor_version(&self, uid: &str, version: &str) -> Result<usize> {
+        use crate::schema::solana_program_builds::dsl::*;
+        let conn = &mut self.get_db_conn().await?;
+        diesel::update(solana_program_builds)
+            .filter(id.eq(uid))
+            .set(anchor_version.eq(version))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Store the warnings raised while building, leaving out `on_chain_check_deferred` as
+    /// the deferred check is redone when the status is requested.
+    pub async fn set_build_warnings(&self, uid: &str, build_warnings: &[Warning]) -> Result<usize> {
+        use crate::schema::solana_program_builds::dsl::*;
+        let stored = build_warnings
+            .iter()
+            .filter(|warning| !matches!(warning.code, WarningCode::OnChainCheckDeferred))
+            .collect::<Vec<_>>();
+        let conn = &mut self.get_db_conn().await?;
+        diesel::update(solana_program_builds)
+            .filter(id.eq(uid))
+            .set(warnings.eq(serde_json::to_value(stored).ok()))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Replace the labels of a build, as corrected by an operator.
+    pub async fn set_labels(
+        &self,
+        uid: &str,
+        new_labels: &HashMap<String, String>,
+    ) -> Result<usize> {
+        use crate::schema::solana_program_builds::dsl::*;
+        let conn = &mut self.get_db_conn().await?;
+        diesel::update(solana_program_builds)
+            .filter(id.eq(uid))
+            .set(labels.eq(labels_to_json(new_labels)))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Mark an existing build as claimed by the program upgrade authority
+
+    pub async fn set_authority_claimed(&self, uid: &str) -> Result<usize> {
+        use crate::schema::solana_program_builds::dsl::*;
+        let conn = &mut self.get_db_conn().await?;
+        diesel::update(solana_program_builds)
+            .filter(id.eq(uid))
+            .set(authority_claimed.eq(true))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn check_cache(&self, hash: &str, program_address: &str) -> Result<bool> {
         // Try to get the program from the cache and check if the hash matches
         let cache_res = self.get_cache(program_address).await;
@@ -235,85 +965,101 @@ impl DbClient {
     ///
     /// Arguments:
     ///
+    /// * `tenant`: The tenant whose verification of the program is checked.
     /// * `program_address`: The `program_address` parameter is a string that represents the address of a
     /// program. It is used to query the database and check if the program is verified.
     ///
     /// Returns: Whether the program is verified or not.
-    pub async fn check_is_verified(self, program_address: String) -> Result<VerificationResponse> {
-        let res = self.get_verified_build(&program_address).await;
+    pub async fn check_is_verified(
+        self,
+        tenant: String,
+        program_address: String,
+    ) -> Result<VerificationResponse> {
+        let res = self.get_verified_build(&tenant, &program_address).await;
         match res {
             Ok(res) => {
                 let cache_result = self
                     .check_cache(&res.executable_hash, &program_address)
                     .await;
 
-                let build_params = self.get_build_params(&program_address).await?;
+                let build_params = self.get_build_params(&tenant, &program_address).await?;
+                let mut response = stored_verification(Some(res), Some(&build_params));
 
                 if let Ok(matched) = cache_result {
                     if matched {
                         tracing::info!("Cache mached for program: {}", program_address);
-                        return Ok({
-                            VerificationResponse {
-                                is_verified: true,
-                                on_chain_hash: res.on_chain_hash,
-                                executable_hash: res.executable_hash,
-                                repo_url: builder::get_repo_url(&build_params),
-                                last_verified_at: Some(res.verified_at),
-                            }
-                        });
+                        response.is_verified = true;
+                        response.verified_by_authority = build_params.authority_claimed;
+                        return Ok(response);
                     }
                 }
 
-                let on_chain_hash = get_on_chain_hash(&program_address).await;
+                let on_chain_hash = self
+                    .hash_provider
+                    .get_on_chain_hash(&program_address, Commitment::default())
+                    .await;
 
                 if let Ok(on_chain_hash) = on_chain_hash {
                     self.set_cache(&program_address, &on_chain_hash).await?;
-                    if on_chain_hash == res.on_chain_hash {
+                    let is_verified = on_chain_hash == response.executable_hash;
+                    if on_chain_hash == response.on_chain_hash {
                         tracing::info!("On chain hash matches. Returning the cached value.");
                     } else {
                         tracing::info!("On chain hash doesn't match.");
-                        self.update_onchain_hash(
-                            &program_address,
-                            &on_chain_hash,
-                            on_chain_hash == res.executable_hash,
-                        )
-                        .await?;
+                        response.status_changed = self
+                            .update_onchain_hash(
+                                &tenant,
+                                &program_address,
+                                &on_chain_hash,
+                                is_verified,
+                            )
+                            .await?;
                         self.reverify_program(build_params.clone());
                     }
-                    Ok({
-                        VerificationResponse {
-                            is_verified: on_chain_hash == res.executable_hash,
-                            on_chain_hash,
-                            executable_hash: res.executable_hash,
-                            repo_url: builder::get_repo_url(&build_params),
-                            last_verified_at: Some(res.verified_at),
+
+                    match rpc::get_program_account_info(&program_address).await {
+                        Ok(account_info) => {
+                            response.program_size = Some(account_info.program_size);
+                            response.rent_exempt = Some(account_info.rent_exempt);
+                            response.immutable = Some(account_info.upgrade_authority.is_none());
+                            response.upgrade_authority = account_info.upgrade_authority;
                         }
-                    })
+                        Err(err) => {
+                            tracing::warn!("Failed to get program account info: {}", err);
+                        }
+                    }
+                    response.is_verified = is_verified;
+                    response.verified_by_authority = is_verified && build_params.authority_claimed;
+                    response.on_chain_hash = on_chain_hash;
+                } else if matches!(on_chain_hash, Err(ApiError::ProgramClosed(_))) {
+                    tracing::info!("{}: Program account is closed", program_address);
+                    response.is_verified = false;
+                    response.verified_by_authority = false;
+                    response.on_chain_hash = "".to_string();
+                    response.extra_hashes.clear();
+                    response.program_size = Some(0);
+                    response.warnings.push(Warning::new(
+                        WarningCode::ProgramClosed,
+                        "The program account is closed or has no executable data.",
+                    ));
                 } else {
                     tracing::info!("Failed to get On chain hash. Returning the cached value.");
-                    Ok({
-                        VerificationResponse {
-                            is_verified: res.on_chain_hash == res.executable_hash,
-                            on_chain_hash: res.on_chain_hash,
-                            executable_hash: res.executable_hash,
-                            repo_url: builder::get_repo_url(&build_params),
-                            last_verified_at: Some(res.verified_at),
-                        }
-                    })
+                    response.is_verified = response.on_chain_hash == response.executable_hash;
+                    response.verified_by_authority =
+                        response.is_verified && build_params.authority_claimed;
+                    response.warnings.push(Warning::new(
+                        WarningCode::RpcUnreachable,
+                        "Failed to fetch the on-chain hash. Returning the last known result.",
+                    ));
                 }
+                Ok(response)
             }
             Err(err) => {
                 if err.to_string() == "Record not found" {
                     tracing::info!("{}: Program record not found in database", program_address);
-                    return Ok({
-                        VerificationResponse {
-                            is_verified: false,
-                            on_chain_hash: "".to_string(),
-                            executable_hash: "".to_string(),
-                            repo_url: "".to_string(),
-                            last_verified_at: None,
-                        }
-                    });
+                    // A build may exist which has not produced a verification yet
+                    let build_params = self.get_build_params(&tenant, &program_address).await.ok();
+                    return Ok(stored_verification(None, build_params.as_ref()));
                 }
                 Err(err)
             }
@@ -324,7 +1070,7 @@ impl DbClient {
     pub async fn get_job(&self, uid: &str) -> Result<SolanaProgramBuild> {
         use crate::schema::solana_program_builds::dsl::*;
 
-        let conn = &mut self.db_pool.get().await?;
+        let conn = &mut self.get_db_conn().await?;
         solana_program_builds
             .filter(id.eq(uid))
             .first::<SolanaProgramBuild>(conn)
@@ -335,61 +1081,539 @@ impl DbClient {
     // Update solana_program_builds by id and set status
     pub async fn update_build_status(&self, uid: &str, job_status: String) -> Result<usize> {
         use crate::schema::solana_program_builds::dsl::*;
-        let conn = &mut self.db_pool.get().await?;
-        diesel::update(solana_program_builds)
+        let conn = &mut self.get_db_conn().await?;
+        let updated = diesel::update(solana_program_builds)
             .filter(id.eq(uid))
-            .set(crate::schema::solana_program_builds::status.eq(job_status))
+            .set(crate::schema::solana_program_builds::status.eq(&job_status))
             .execute(conn)
-            .await
-            .map_err(Into::into)
+            .await?;
+        self.record_event(uid, &job_status, None).await;
+        Ok(updated)
     }
 
     // get all verified programs from verified_programs table
-    pub async fn get_verified_programs(&self) -> Result<Vec<VerifiedProgram>> {
+    // Verified programs with the build they were verified with, ordered by program id
+    pub async fn get_verified_programs(
+        &self,
+        tenant: &str,
+    ) -> Result<Vec<(VerifiedProgram, SolanaProgramBuild)>> {
+        use crate::schema::solana_program_builds;
         use crate::schema::verified_programs::dsl::*;
 
-        let conn = &mut self.db_pool.get().await?;
+        let conn = &mut self.get_db_conn().await?;
         // get all verified programs where is_verified is true
         verified_programs
+            .inner_join(solana_program_builds::table)
+            .filter(tenant_id.eq(tenant))
             .filter(is_verified.eq(true))
-            .load::<VerifiedProgram>(conn)
+            .order(program_id.asc())
+            .load::<(VerifiedProgram, SolanaProgramBuild)>(conn)
             .await
             .map_err(Into::into)
     }
 
-    pub fn reverify_program(self, build_params: SolanaProgramBuild) {
-        let payload = SolanaProgramBuildParams {
-            program_id: build_params.program_id,
-            repository: build_params.repository,
-            commit_hash: build_params.commit_hash,
-            lib_name: build_params.lib_name,
-            base_image: build_params.base_docker_image,
-            mount_path: build_params.mount_path,
-            bpf_flag: Some(build_params.bpf_flag),
-            cargo_args: build_params.cargo_args,
+    /// Verifications of `tenant` whose build produced `hash`, with their build, by program id.
+    pub async fn get_verifications_by_executable_hash(
+        &self,
+        tenant: &str,
+        hash: &str,
+    ) -> Result<Vec<(VerifiedProgram, SolanaProgramBuild)>> {
+        use crate::schema::solana_program_builds;
+        use crate::schema::verified_programs::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        verified_programs
+            .inner_join(solana_program_builds::table)
+            .filter(tenant_id.eq(tenant))
+            .filter(executable_hash.eq(hash))
+            .order(program_id.asc())
+            .load::<(VerifiedProgram, SolanaProgramBuild)>(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn insert_subscription(&self, subscription: &Subscription) -> Result<usize> {
+        use crate::schema::subscriptions::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        diesel::insert_into(subscriptions)
+            .values(subscription)
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Number of subscriptions created by a client
+    pub async fn count_subscriptions_of_owner(&self, client: &str) -> Result<i64> {
+        use crate::schema::subscriptions::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        subscriptions
+            .filter(owner.eq(client))
+            .count()
+            .get_result(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Delete a subscription, returning the number of deleted rows
+    pub async fn delete_subscription(&self, subscription_id: &str) -> Result<usize> {
+        use crate::schema::subscriptions::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        diesel::delete(subscriptions.filter(id.eq(subscription_id)))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Subscriptions of a program, oldest first
+    pub async fn get_subscriptions(&self, program_address: &str) -> Result<Vec<Subscription>> {
+        use crate::schema::subscriptions::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        subscriptions
+            .filter(program_id.eq(program_address))
+            .order(created_at.asc())
+            .load::<Subscription>(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn get_denied_program(&self, program_address: &str) -> Result<Option<DeniedProgram>> {
+        use crate::schema::denied_programs::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        denied_programs
+            .filter(program_id.eq(program_address))
+            .first::<DeniedProgram>(conn)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Store the attestation of a program, replacing its previous one.
+    pub async fn upsert_attestation(&self, attestation: &Attestation) -> Result<usize> {
+        use crate::schema::attestations::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        diesel::insert_into(attestations)
+            .values(attestation)
+            .on_conflict(program_id)
+            .do_update()
+            .set(attestation)
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn get_attestation(&self, program_address: &str) -> Result<Option<Attestation>> {
+        use crate::schema::attestations::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        attestations
+            .filter(program_id.eq(program_address))
+            .first::<Attestation>(conn)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
+
+    // Attestations of several programs by program id
+    pub async fn get_attestations(
+        &self,
+        addresses: &[String],
+    ) -> Result<HashMap<String, Attestation>> {
+        use crate::schema::attestations::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        Ok(attestations
+            .filter(program_id.eq_any(addresses))
+            .load::<Attestation>(conn)
+            .await?
+            .into_iter()
+            .map(|attestation| (attestation.program_id.clone(), attestation))
+            .collect())
+    }
+
+    /// Store the claim of a program for its tenant. A previous claim is only replaced by one
+    /// proven the same way for the same repository or domain, otherwise this fails with
+    /// `AlreadyClaimed`.
+    pub async fn upsert_claim(&self, claim: &ProgramClaim) -> Result<usize> {
+        use crate::schema::program_claims::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        let inserted = diesel::insert_into(program_claims)
+            .values(claim)
+            .on_conflict((tenant_id, program_id))
+            .do_nothing()
+            .execute(conn)
+            .await?;
+        if inserted > 0 {
+            return Ok(inserted);
+        }
+
+        // Refresh the proof of the existing claim when it is the same one
+        let updated = diesel::update(program_claims)
+            .filter(tenant_id.eq(&claim.tenant_id))
+            .filter(program_id.eq(&claim.program_id))
+            .filter(method.eq(&claim.method))
+            .filter(claimed_by.eq(&claim.claimed_by))
+            .set((proof.eq(&claim.proof), claimed_at.eq(claim.claimed_at)))
+            .execute(conn)
+            .await?;
+        if updated == 0 {
+            let existing = program_claims
+                .filter(tenant_id.eq(&claim.tenant_id))
+                .filter(program_id.eq(&claim.program_id))
+                .select(claimed_by)
+                .first::<String>(conn)
+                .await?;
+            return Err(ApiError::AlreadyClaimed(existing));
+        }
+        Ok(updated)
+    }
+
+    /// Store the claim of a program for its tenant, replacing a previous claim which is no
+    /// longer proven.
+    pub async fn replace_claim(&self, claim: &ProgramClaim) -> Result<usize> {
+        use crate::schema::program_claims::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        diesel::insert_into(program_claims)
+            .values(claim)
+            .on_conflict((tenant_id, program_id))
+            .do_update()
+            .set((
+                method.eq(&claim.method),
+                claimed_by.eq(&claim.claimed_by),
+                proof.eq(&claim.proof),
+                claimed_at.eq(claim.claimed_at),
+            ))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn get_claim(
+        &self,
+        tenant: &str,
+        program_address: &str,
+    ) -> Result<Option<ProgramClaim>> {
+        use crate::schema::program_claims::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        program_claims
+            .filter(tenant_id.eq(tenant))
+            .filter(program_id.eq(program_address))
+            .first::<ProgramClaim>(conn)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
+
+    // Claims of several programs of a tenant by program id
+    pub async fn get_claims(
+        &self,
+        tenant: &str,
+        addresses: &[String],
+    ) -> Result<HashMap<String, ProgramClaim>> {
+        use crate::schema::program_claims::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        Ok(program_claims
+            .filter(tenant_id.eq(tenant))
+            .filter(program_id.eq_any(addresses))
+            .load::<ProgramClaim>(conn)
+            .await?
+            .into_iter()
+            .map(|claim| (claim.program_id.clone(), claim))
+            .collect())
+    }
+
+    // Programs denylisted at runtime, most recent first
+    pub async fn get_denied_programs(&self) -> Result<Vec<DeniedProgram>> {
+        use crate::schema::denied_programs::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        denied_programs
+            .order(created_at.desc())
+            .load::<DeniedProgram>(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Denylist a program, replacing the reason if it already is
+    pub async fn insert_denied_program(&self, denied: &DeniedProgram) -> Result<usize> {
+        use crate::schema::denied_programs::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        diesel::insert_into(denied_programs)
+            .values(denied)
+            .on_conflict(program_id)
+            .do_update()
+            .set(reason.eq(&denied.reason))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    // Remove a program from the denylist, returning the number of deleted rows
+    pub async fn delete_denied_program(&self, program_address: &str) -> Result<usize> {
+        use crate::schema::denied_programs::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        diesel::delete(denied_programs.filter(program_id.eq(program_address)))
+            .execute(conn)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Delete up to `limit` finished builds created before `cutoff`, along with their
+    /// events. The latest build of each program and the builds of the current
+    /// verifications are kept. Returns the number of deleted builds and events.
+    pub async fn delete_expired_builds(
+        &self,
+        cutoff: NaiveDateTime,
+        limit: i64,
+    ) -> Result<(usize, usize)> {
+        let conn = &mut self.get_db_conn().await?;
+
+        let mut kept: Vec<String> = {
+            use crate::schema::verified_programs::dsl::*;
+
+            verified_programs
+                .select(solana_build_id)
+                .load::<String>(conn)
+                .await?
         };
+        let expired: Vec<String> = {
+            use crate::schema::solana_program_builds::dsl::*;
+
+            kept.extend(
+                solana_program_builds
+                    .select(id)
+                    .order((tenant_id, program_id, created_at.desc()))
+                    .distinct_on((tenant_id, program_id))
+                    .load::<String>(conn)
+                    .await?,
+            );
+            solana_program_builds
+                .select(id)
+                .filter(created_at.lt(cutoff))
+                .filter(status.ne(String::from(JobStatus::InProgress)))
+                .filter(id.ne_all(&kept))
+                .order(created_at.asc())
+                .limit(limit)
+                .load::<String>(conn)
+                .await?
+        };
+        if expired.is_empty() {
+            return Ok((0, 0));
+        }
 
-        let build_id = build_params.id;
+        let deleted_events = {
+            use crate::schema::build_events::dsl::*;
+
+            diesel::delete(build_events.filter(build_id.eq_any(&expired)))
+                .execute(conn)
+                .await?
+        };
+        let deleted_builds = {
+            use crate::schema::solana_program_builds::dsl::*;
+
+            diesel::delete(solana_program_builds.filter(id.eq_any(&expired)))
+                .execute(conn)
+                .await?
+        };
+        Ok((deleted_builds, deleted_events))
+    }
+
+    /// Get the stored status of several programs at once, with one query for their
+    /// verifications and one for their latest builds. Unlike `check_is_verified`, the
+    /// on-chain programs are not fetched, so the results are as of the last verification.
+    pub async fn get_statuses(
+        &self,
+        tenant: &str,
+        addresses: &[String],
+    ) -> Result<HashMap<String, VerificationResponse>> {
+        let conn = &mut self.get_db_conn().await?;
+
+        let mut verifications: HashMap<String, VerifiedProgram> = {
+            use crate::schema::verified_programs::dsl::*;
+
+            verified_programs
+                .filter(tenant_id.eq(tenant))
+                .filter(program_id.eq_any(addresses))
+                .load::<VerifiedProgram>(conn)
+                .await?
+                .into_iter()
+                .map(|verification| (verification.program_id.clone(), verification))
+                .collect()
+        };
+
+        let builds: HashMap<String, SolanaProgramBuild> = {
+            use crate::schema::solana_program_builds::dsl::*;
+
+            solana_program_builds
+                .filter(tenant_id.eq(tenant))
+                .filter(program_id.eq_any(addresses))
+                .order((program_id, created_at.desc()))
+                .distinct_on(program_id)
+                .load::<SolanaProgramBuild>(conn)
+                .await?
+                .into_iter()
+                .map(|build| (build.program_id.clone(), build))
+                .collect()
+        };
+
+        Ok(addresses
+            .iter()
+            .map(|address| {
+                let status =
+                    stored_verification(verifications.remove(address), builds.get(address));
+                (address.clone(), status)
+            })
+            .collect())
+    }
+
+    // Ids of the running builds of several programs, oldest first
+    pub async fn get_in_progress_builds_of(
+        &self,
+        tenant: &str,
+        addresses: &[String],
+    ) -> Result<HashMap<String, Vec<String>>> {
+        use crate::schema::solana_program_builds::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        let running = solana_program_builds
+            .filter(tenant_id.eq(tenant))
+            .filter(program_id.eq_any(addresses))
+            .filter(status.eq(String::from(JobStatus::InProgress)))
+            .order(created_at.asc())
+            .select((program_id, id))
+            .load::<(String, String)>(conn)
+            .await?;
+
+        let mut builds: HashMap<String, Vec<String>> = HashMap::new();
+        for (program, build) in running {
+            builds.entry(program).or_default().push(build);
+        }
+        Ok(builds)
+    }
+
+    /// In-progress builds of all tenants without activity since `cutoff`, with the time of
+    /// their last activity, oldest first. Activity is the latest build event, or the
+    /// submission for builds without any.
+    pub async fn get_stuck_builds(
+        &self,
+        cutoff: NaiveDateTime,
+    ) -> Result<Vec<(SolanaProgramBuild, NaiveDateTime)>> {
+        use crate::schema::build_events;
+        use crate::schema::solana_program_builds::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        // A build submitted after the cutoff has been active since
+        let candidates = solana_program_builds
+            .filter(status.eq(String::from(JobStatus::InProgress)))
+            .filter(created_at.lt(cutoff))
+            .order(created_at.asc())
+            .load::<SolanaProgramBuild>(conn)
+            .await?;
+        let ids: Vec<&str> = candidates.iter().map(|build| build.id.as_str()).collect();
+        let last_events: HashMap<String, Option<NaiveDateTime>> = build_events::table
+            .filter(build_events::build_id.eq_any(ids))
+            .group_by(build_events::build_id)
+            .select((
+                build_events::build_id,
+                diesel::dsl::max(build_events::created_at),
+            ))
+            .load::<(String, Option<NaiveDateTime>)>(conn)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(candidates
+            .into_iter()
+            .map(|build| {
+                let last_activity = last_events
+                    .get(&build.id)
+                    .copied()
+                    .flatten()
+                    .map_or(build.created_at, |last_event| {
+                        last_event.max(build.created_at)
+                    });
+                (build, last_activity)
+            })
+            .filter(|(_, last_activity)| *last_activity < cutoff)
+            .collect())
+    }
+
+    /// Builds of the verifications of all tenants matching the filters of a bulk
+    /// re-verification.
+    pub async fn get_builds_to_reverify(
+        &self,
+        filters: &ReverifyParams,
+    ) -> Result<Vec<SolanaProgramBuild>> {
+        use crate::schema::solana_program_builds;
+        use crate::schema::verified_programs::dsl::*;
+
+        let conn = &mut self.get_db_conn().await?;
+        let mut query = verified_programs
+            .inner_join(solana_program_builds::table)
+            .into_boxed();
+        if let Some(image) = &filters.base_image {
+            query = query.filter(solana_program_builds::base_docker_image.eq(image));
+        }
+        if let Some(before) = filters.older_than {
+            query = query.filter(verified_at.lt(before));
+        }
+        match filters.verified_state {
+            Some(VerifiedState::Verified) => query = query.filter(is_verified.eq(true)),
+            Some(VerifiedState::Unverified) => query = query.filter(is_verified.eq(false)),
+            Some(VerifiedState::Any) | None => {}
+        }
+        Ok(query
+            .order(verified_at.asc())
+            .load::<(VerifiedProgram, SolanaProgramBuild)>(conn)
+            .await?
+            .into_iter()
+            .map(|(_, build)| build)
+            .collect())
+    }
+
+    pub fn reverify_program(self, build_params: SolanaProgramBuild) {
+        // A draining instance starts no new build, the next status check re-verifies it
+        if let Err(err) = self.workers.ensure_accepting_builds() {
+            tracing::info!("Not re-verifying {}: {}", build_params.program_id, err);
+            return;
+        }
+        let build_id = build_params.id.clone();
+        let payload = SolanaProgramBuildParams::from(build_params);
 
         //run task in background
-        tokio::spawn(async move {
-            match builder::verify_build(payload, &build_id).await {
-                Ok(res) => {
-                    let _ = self.insert_or_update_verified_build(&res).await;
-                    let _ = self
-                        .update_build_status(&build_id, JobStatus::Completed.into())
-                        .await;
-                }
-                Err(err) => {
-                    let _ = self
-                        .update_build_status(&build_id, JobStatus::Failed.into())
-                        .await;
-                    tracing::error!("Error verifying build: {:?}", err);
-                    tracing::error!(
-                        "We encountered an unexpected error during the verification process."
-                    );
+        let repository = payload.repository.clone();
+        self.workers
+            .clone()
+            .spawn_for_repo(&repository, async move {
+                match builder::verify_build(&self, payload, &build_id).await {
+                    Ok((res, warnings)) => {
+                        let _ = self.insert_or_update_verified_build(&res).await;
+                        let _ = self.set_build_warnings(&build_id, &warnings).await;
+                        let _ = self
+                            .update_build_status(&build_id, JobStatus::Completed.into())
+                            .await;
+                    }
+                    Err(err) => {
+                        let _ = self
+                            .update_build_status(&build_id, JobStatus::Failed.into())
+                            .await;
+                        tracing::error!("Error verifying build: {:?}", err);
+                        tracing::error!(
+                            "We encountered an unexpected error during the verification process."
+                        );
+                    }
                 }
-            }
-        });
+            });
     }
 }
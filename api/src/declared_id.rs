@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use crate::builder::is_valid_address;
+use crate::git::BareClone;
+use crate::Result;
+
+const DECLARE_ID_MACRO: &str = "declare_id!(";
+
+// Most crate roots read to look for the declared program id
+const MAX_CRATE_ROOTS: usize = 32;
+
+// Program id declared on a line like `declare_id!("Phoe...");`
+fn parse_declare_id(line: &str) -> Option<String> {
+    let (_, rest) = line.split_once(DECLARE_ID_MACRO)?;
+    let (id, _) = rest.trim_start().strip_prefix('"')?.split_once('"')?;
+    is_valid_address(id).then(|| id.to_string())
+}
+
+// Program ids declared with `declare_id!` in a source file, skipping commented out lines.
+// Programs may declare several under `cfg` attributes, e.g. one per cluster.
+fn parse_declared_ids(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .filter_map(parse_declare_id)
+        .collect()
+}
+
+// Library crate roots of the repository, within `mount_path` if given
+fn crate_roots(files: &[String], mount_path: Option<&str>) -> Vec<String> {
+    let prefix = mount_path
+        .map(|path| path.trim_matches('/'))
+        .filter(|path| !path.is_empty())
+        .map(|path| format!("{}/", path))
+        .unwrap_or_default();
+    files
+        .iter()
+        .filter(|file| file.starts_with(&prefix))
+        .filter(|file| *file == "src/lib.rs" || file.ends_with("/src/lib.rs"))
+        .take(MAX_CRATE_ROOTS)
+        .cloned()
+        .collect()
+}
+
+// Library name of the crate rooted at `path`, assuming the directory is named after it
+fn crate_lib_name(path: &str) -> Option<String> {
+    let crate_dir = Path::new(path).parent()?.parent()?;
+    Some(crate_dir.file_name()?.to_str()?.replace('-', "_"))
+}
+
+// Id declared by the program among the ids declared by each crate: `program_id` if any
+// crate declares it, otherwise the only id declared by the crate of `lib_name`, or by all
+// crates. `None` when nothing, or several ids, are declared.
+fn select_declared_id(
+    declared: &[(String, Vec<String>)],
+    program_id: &str,
+    lib_name: Option<&str>,
+) -> Option<String> {
+    if declared
+        .iter()
+        .any(|(_, ids)| ids.iter().any(|id| id == program_id))
+    {
+        return Some(program_id.to_string());
+    }
+
+    let lib_name = lib_name.map(|name| name.replace('-', "_"));
+    let of_lib: Vec<&(String, Vec<String>)> = declared
+        .iter()
+        .filter(|(path, _)| lib_name.is_some() && crate_lib_name(path) == lib_name)
+        .collect();
+    let candidates = if of_lib.is_empty() {
+        declared.iter().collect()
+    } else {
+        of_lib
+    };
+
+    let mut ids: Vec<&String> = candidates.iter().flat_map(|(_, ids)| ids).collect();
+    ids.sort();
+    ids.dedup();
+    match ids.as_slice() {
+        [id] => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Program id the source at `commit` declares with `declare_id!`, which is embedded in the
+/// executable. Returns `None` when the program declares none, or it is ambiguous.
+pub async fn read_declared_id(
+    clone: &BareClone,
+    commit: Option<&str>,
+    mount_path: Option<&str>,
+    lib_name: Option<&str>,
+    program_id: &str,
+) -> Result<Option<String>> {
+    let roots = crate_roots(&clone.list_files(commit).await?, mount_path);
+    if roots.is_empty() {
+        return Ok(None);
+    }
+    let declared: Vec<(String, Vec<String>)> = clone
+        .read_files(commit, &roots)
+        .await?
+        .into_iter()
+        .zip(roots)
+        .filter_map(|(source, path)| Some((path, parse_declared_ids(&source?))))
+        .filter(|(_, ids)| !ids.is_empty())
+        .collect();
+    Ok(select_declared_id(&declared, program_id, lib_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHOENIX: &str = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY";
+    const SQUADS: &str = "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf";
+
+    #[test]
+    fn test_parse_declared_ids() {
+        let source = format!(
+            "use anchor_lang::prelude::*;\n\
+             #[cfg(feature = \"devnet\")]\n\
+             declare_id!( \"{}\" );\n\
+             // declare_id!(\"{}\");\n\
+             solana_program::declare_id!(\"{}\");\n\
+             declare_id!(\"not an address\");\n",
+            SQUADS, PHOENIX, PHOENIX
+        );
+        assert_eq!(parse_declared_ids(&source), vec![SQUADS, PHOENIX]);
+    }
+
+    #[test]
+    fn test_select_declared_id() {
+        let declared = vec![
+            (
+                "programs/phoenix-v1/src/lib.rs".to_string(),
+                vec![PHOENIX.to_string()],
+            ),
+            (
+                "programs/multisig/src/lib.rs".to_string(),
+                vec![SQUADS.to_string()],
+            ),
+        ];
+        assert_eq!(
+            select_declared_id(&declared, SQUADS, None),
+            Some(SQUADS.to_string())
+        );
+        let other = "11111111111111111111111111111111";
+        assert_eq!(
+            select_declared_id(&declared, other, Some("phoenix_v1")),
+            Some(PHOENIX.to_string())
+        );
+        assert_eq!(select_declared_id(&declared, other, None), None);
+        assert_eq!(
+            select_declared_id(&declared[1..], other, None),
+            Some(SQUADS.to_string())
+        );
+    }
+
+    #[test]
+    fn test_crate_roots() {
+        let files = [
+            "Cargo.toml",
+            "programs/phoenix/src/lib.rs",
+            "programs/phoenix/src/state.rs",
+            "sdk/src/lib.rs",
+        ]
+        .map(String::from);
+        assert_eq!(
+            crate_roots(&files, None),
+            vec!["programs/phoenix/src/lib.rs", "sdk/src/lib.rs"]
+        );
+        assert_eq!(
+            crate_roots(&files, Some("/programs/")),
+            vec!["programs/phoenix/src/lib.rs"]
+        );
+    }
+}
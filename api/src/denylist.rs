@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::OnceLock;
+
+use crate::db::DbClient;
+use crate::errors::ApiError;
+use crate::Result;
+
+// Reason given for programs denylisted in `DENIED_PROGRAM_IDS` without one
+const DEFAULT_DENIAL_REASON: &str = "Denied by the operator";
+
+// Parse `program_id` or `program_id=reason` entries separated by commas
+fn parse_denied_programs(value: &str) -> HashMap<String, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((program_id, reason)) => {
+                (program_id.trim().to_string(), reason.trim().to_string())
+            }
+            None => (entry.to_string(), DEFAULT_DENIAL_REASON.to_string()),
+        })
+        .collect()
+}
+
+/// Programs denylisted in `DENIED_PROGRAM_IDS`, with the reason they are denied. They can
+/// not be removed at runtime, unlike those added through the admin endpoints.
+pub fn configured_denials() -> &'static HashMap<String, String> {
+    static DENIALS: OnceLock<HashMap<String, String>> = OnceLock::new();
+    DENIALS
+        .get_or_init(|| parse_denied_programs(&env::var("DENIED_PROGRAM_IDS").unwrap_or_default()))
+}
+
+/// Fail with `ProgramDenied` if the program is denylisted in the config or the database.
+pub async fn check_not_denied(db: &DbClient, program_id: &str) -> Result<()> {
+    let reason = match configured_denials().get(program_id) {
+        Some(reason) => Some(reason.clone()),
+        None => db
+            .get_denied_program(program_id)
+            .await?
+            .map(|denied| denied.reason),
+    };
+    match reason {
+        Some(reason) => Err(ApiError::ProgramDenied {
+            program_id: program_id.to_string(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_denied_programs() {
+        let denials = parse_denied_programs(
+            "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY=Impersonates Phoenix, \
+             SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf,,",
+        );
+        assert_eq!(denials.len(), 2);
+        assert_eq!(
+            denials["PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"],
+            "Impersonates Phoenix"
+        );
+        assert_eq!(
+            denials["SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf"],
+            DEFAULT_DENIAL_REASON
+        );
+    }
+}
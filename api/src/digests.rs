@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256, Sha512};
+
+// Digest algorithms which can be requested through `extra_hashes`
+pub const SUPPORTED_ALGORITHMS: [&str; 3] = ["sha256", "sha512", "blake3"];
+
+pub fn is_supported_algorithm(algorithm: &str) -> bool {
+    SUPPORTED_ALGORITHMS.contains(&algorithm)
+}
+
+fn digest(bytes: &[u8], algorithm: &str) -> Option<String> {
+    match algorithm {
+        "sha256" => Some(hex::encode(Sha256::digest(bytes))),
+        "sha512" => Some(hex::encode(Sha512::digest(bytes))),
+        "blake3" => Some(blake3::hash(bytes).to_hex().to_string()),
+        _ => None,
+    }
+}
+
+/// Compute the requested digests of `bytes`, stored as `algorithm:hex` entries.
+/// Unsupported algorithms are skipped.
+pub fn compute_digests(bytes: &[u8], algorithms: &[String]) -> Vec<String> {
+    algorithms
+        .iter()
+        .filter_map(|algorithm| {
+            digest(bytes, algorithm).map(|hash| format!("{}:{}", algorithm, hash))
+        })
+        .collect()
+}
+
+/// Turn stored `algorithm:hex` entries into a map for responses.
+pub fn digests_to_map(entries: Option<Vec<String>>) -> BTreeMap<String, String> {
+    entries
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entry| {
+            entry
+                .split_once(':')
+                .map(|(algorithm, hash)| (algorithm.to_string(), hash.to_string()))
+        })
+        .collect()
+}
+
+/// Cheap fingerprint of an executable from its size and first and last bytes: the hex
+/// encoded sha256 of the size as little-endian u64 followed by `head` and `tail`.
+pub fn edge_fingerprint(size: u64, head: &[u8], tail: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+    hasher.update(head);
+    hasher.update(tail);
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_digests() {
+        let algorithms = vec!["sha256".to_string(), "md5".to_string()];
+        let digests = compute_digests(b"abc", &algorithms);
+        assert_eq!(
+            digests,
+            vec!["sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"]
+        );
+
+        let map = digests_to_map(Some(digests));
+        assert_eq!(map.len(), 1);
+        assert!(map["sha256"].starts_with("ba7816bf"));
+    }
+
+    #[test]
+    fn test_edge_fingerprint() {
+        let fingerprint = edge_fingerprint(1024, b"\x7fELF", b"\0\0");
+        assert_eq!(fingerprint.len(), 64);
+        assert_eq!(fingerprint, edge_fingerprint(1024, b"\x7fELF", b"\0\0"));
+        // A resize keeping the same edges still changes the fingerprint
+        assert_ne!(fingerprint, edge_fingerprint(2048, b"\x7fELF", b"\0\0"));
+        assert_ne!(fingerprint, edge_fingerprint(1024, b"\x7fELF", b"\0\x01"));
+    }
+}
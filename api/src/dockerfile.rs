@@ -0,0 +1,352 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::errors::ApiError;
+use crate::git::clone_repository;
+use crate::lfs::lfs_pull;
+use crate::Result;
+
+// Where the compiler cache is mounted in the container of a custom build
+const CONTAINER_SCCACHE_DIR: &str = "/sccache";
+
+// Where the vendored registry of offline builds is mounted in the container, as its cargo home
+const CONTAINER_CARGO_HOME: &str = "/cargo-home";
+
+// Runs the default command given as arguments through sccache if the image has it, then
+// prints the hits and misses of the cache
+const SCCACHE_WRAPPER_SCRIPT: &str = "if command -v sccache >/dev/null; then export RUSTC_WRAPPER=sccache; \"$@\" || exit $?; sccache --show-stats || true; else exec \"$@\"; fi";
+
+// Run a docker command and return its stdout, failing the build on a nonzero exit
+async fn docker(args: &[&str], step: &str) -> Result<String> {
+    let output = Command::new("docker").args(args).output().await?;
+    if !output.status.success() {
+        return Err(ApiError::Build(format!(
+            "docker {} failed: {}",
+            step,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+// Resolve the Dockerfile inside the checkout, rejecting paths which leave it through symlinks
+async fn resolve_dockerfile(checkout: &Path, dockerfile: &str) -> Result<String> {
+    let not_found = || {
+        ApiError::Build(format!(
+            "Dockerfile {} not found in the repository",
+            dockerfile
+        ))
+    };
+    let root = tokio::fs::canonicalize(checkout).await?;
+    let path = tokio::fs::canonicalize(checkout.join(dockerfile))
+        .await
+        .map_err(|_| not_found())?;
+    if !path.starts_with(&root) || !tokio::fs::metadata(&path).await?.is_file() {
+        return Err(not_found());
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+// Build the image and copy the artifact out of a container created from it
+async fn build_and_extract(
+    checkout: &Path,
+    dockerfile: &str,
+    artifact_path: &str,
+    build_args: &[String],
+    image: &str,
+    output_path: &Path,
+) -> Result<()> {
+    let dockerfile = resolve_dockerfile(checkout, dockerfile).await?;
+    let context = checkout.to_string_lossy().into_owned();
+
+    let mut args = vec![
+        "build",
+        "--quiet",
+        "--file",
+        dockerfile.as_str(),
+        "--tag",
+        image,
+    ];
+    for build_arg in build_args {
+        args.extend(["--build-arg", build_arg.as_str()]);
+    }
+    args.push(context.as_str());
+    docker(&args, "build").await?;
+
+    let container = docker(&["create", image], "create").await?;
+    let source = format!("{}:{}", container, artifact_path);
+    let destination = output_path.to_string_lossy().into_owned();
+    let copied = docker(&["cp", source.as_str(), destination.as_str()], "cp").await;
+    let _ = docker(&["rm", container.as_str()], "rm").await;
+    copied
+        .map(|_| ())
+        .map_err(|_| ApiError::Build(format!("Artifact {} not found in the image", artifact_path)))
+}
+
+/// Build a program with the Dockerfile committed in its repository and return the
+/// executable found at `artifact_path` in the resulting image. With `lfs_repository`, the
+/// git LFS objects of that repository are pulled into the checkout.
+pub async fn build_artifact(
+    repository: &str,
+    commit: Option<&str>,
+    dockerfile: &str,
+    artifact_path: &str,
+    build_args: &[String],
+    lfs_repository: Option<&str>,
+) -> Result<Vec<u8>> {
+    let checkout = clone_repository(repository, commit).await?;
+    if let Some(lfs_repository) = lfs_repository {
+        if let Err(err) = lfs_pull(&checkout, lfs_repository).await {
+            let _ = tokio::fs::remove_dir_all(&checkout).await;
+            return Err(err);
+        }
+    }
+    let id = uuid::Uuid::new_v4();
+    let image = format!("verify-{}", id);
+    let output_path = std::env::temp_dir().join(format!("{}.so", id));
+
+    let built = build_and_extract(
+        &checkout,
+        dockerfile,
+        artifact_path,
+        build_args,
+        &image,
+        &output_path,
+    )
+    .await;
+    let _ = tokio::fs::remove_dir_all(&checkout).await;
+    let _ = docker(&["rmi", "--force", image.as_str()], "rmi").await;
+
+    let bytes = match built {
+        Ok(()) => tokio::fs::read(&output_path).await.map_err(Into::into),
+        Err(err) => Err(err),
+    };
+    let _ = tokio::fs::remove_file(&output_path).await;
+    bytes
+}
+
+// Entrypoint and command of an image, as `docker image inspect` reports its config
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ImageConfig {
+    entrypoint: Option<Vec<String>>,
+    cmd: Option<Vec<String>>,
+}
+
+// Default command of an image, its entrypoint followed by its command, pulling it if needed
+async fn default_command(image: &str) -> Result<Vec<String>> {
+    let inspect = ["image", "inspect", "--format", "{{json .Config}}", image];
+    let config = match docker(&inspect, "inspect").await {
+        Ok(config) => config,
+        Err(_) => {
+            docker(&["pull", "--quiet", image], "pull").await?;
+            docker(&inspect, "inspect").await?
+        }
+    };
+    let config: ImageConfig = serde_json::from_str(&config).map_err(|err| {
+        ApiError::Build(format!("Failed to read the config of {}: {}", image, err))
+    })?;
+    let command: Vec<String> = config
+        .entrypoint
+        .unwrap_or_default()
+        .into_iter()
+        .chain(config.cmd.unwrap_or_default())
+        .collect();
+    if command.is_empty() {
+        return Err(ApiError::Build(format!(
+            "Image {} has no default command",
+            image
+        )));
+    }
+    Ok(command)
+}
+
+/// Build image of the custom build system and the program it builds.
+pub struct ImageBuild<'a> {
+    pub image: &'a str,
+    // Directory of the repository the image runs in
+    pub mount_path: Option<&'a str>,
+    pub lib_name: &'a str,
+}
+
+/// How the container of a custom build runs.
+#[derive(Debug, Default)]
+pub struct ImageOptions<'a> {
+    /// `KEY=VALUE` pairs set in the container.
+    pub env: Vec<String>,
+    /// Compile without network access, once the dependencies are fetched.
+    pub network_isolated: bool,
+    /// Host directory of the sccache cache mounted into the container.
+    pub compiler_cache: Option<&'a str>,
+    /// Host cargo home holding the vendored registry, which makes the build offline.
+    pub vendored_cargo_home: Option<&'a str>,
+}
+
+// Fetch the dependencies of the checkout mounted at /build into `cargo_home` with the cargo
+// of the image, from `work_dir`, so the build can then run offline
+async fn fetch_dependencies(
+    checkout: &Path,
+    image: &str,
+    work_dir: &str,
+    cargo_home: &Path,
+) -> Result<()> {
+    let volume = format!("{}:/build", checkout.to_string_lossy());
+    let cargo_home = format!("{}:{}", cargo_home.to_string_lossy(), CONTAINER_CARGO_HOME);
+    let cargo_home_env = format!("CARGO_HOME={}", CONTAINER_CARGO_HOME);
+    docker(
+        &[
+            "run",
+            "--rm",
+            "--volume",
+            volume.as_str(),
+            "--volume",
+            cargo_home.as_str(),
+            "--env",
+            cargo_home_env.as_str(),
+            "--workdir",
+            work_dir,
+            "--entrypoint",
+            "cargo",
+            image,
+            "fetch",
+        ],
+        "fetch",
+    )
+    .await
+    .map(|_| ())
+}
+
+// Run the default command of the image on the checkout mounted at /build, from `work_dir`,
+// returning its output. With `cargo_home`, the build runs offline from the dependencies
+// it holds. With a compiler cache, the command is wrapped to compile through sccache when
+// the image ships it and print the stats of the cache afterwards.
+async fn run_image(
+    checkout: &Path,
+    image: &str,
+    work_dir: &str,
+    cargo_home: Option<&str>,
+    options: &ImageOptions<'_>,
+) -> Result<String> {
+    let mut args: Vec<String> = vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "--volume".to_string(),
+        format!("{}:/build", checkout.to_string_lossy()),
+        "--workdir".to_string(),
+        work_dir.to_string(),
+    ];
+    for entry in &options.env {
+        args.extend(["--env".to_string(), entry.clone()]);
+    }
+    // Offline builds resolve their dependencies from the vendored or fetched registry only
+    if let Some(cargo_home) = cargo_home {
+        args.extend([
+            "--volume".to_string(),
+            format!("{}:{}", cargo_home, CONTAINER_CARGO_HOME),
+            "--env".to_string(),
+            format!("CARGO_HOME={}", CONTAINER_CARGO_HOME),
+            "--env".to_string(),
+            "CARGO_NET_OFFLINE=true".to_string(),
+        ]);
+    }
+    if options.network_isolated || cargo_home.is_some() {
+        args.push("--network=none".to_string());
+    }
+    match options.compiler_cache {
+        Some(cache_dir) => {
+            let command = default_command(image).await?;
+            args.extend([
+                "--volume".to_string(),
+                format!("{}:{}", cache_dir, CONTAINER_SCCACHE_DIR),
+                "--env".to_string(),
+                format!("SCCACHE_DIR={}", CONTAINER_SCCACHE_DIR),
+                "--entrypoint".to_string(),
+                "sh".to_string(),
+                image.to_string(),
+                "-c".to_string(),
+                SCCACHE_WRAPPER_SCRIPT.to_string(),
+                "sh".to_string(),
+            ]);
+            args.extend(command);
+        }
+        None => args.push(image.to_string()),
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    docker(&args, "run").await
+}
+
+/// Build a program by running the default command of its build image on a checkout of its
+/// repository, mounted at `/build` with the mount path as the working directory, and return
+/// the executable it leaves at `target/deploy/<lib_name>.so` there along with the output of
+/// the build. With `network_isolated` and no vendored registry, the dependencies are fetched
+/// with network access before the build runs without it. With `lfs_repository`, the git
+/// LFS objects of that repository are pulled into the checkout.
+pub async fn build_with_image(
+    repository: &str,
+    commit: Option<&str>,
+    build: ImageBuild<'_>,
+    options: ImageOptions<'_>,
+    lfs_repository: Option<&str>,
+) -> Result<(Vec<u8>, String)> {
+    let checkout = clone_repository(repository, commit).await?;
+    if let Some(lfs_repository) = lfs_repository {
+        if let Err(err) = lfs_pull(&checkout, lfs_repository).await {
+            let _ = tokio::fs::remove_dir_all(&checkout).await;
+            return Err(err);
+        }
+    }
+    let mount_path = build.mount_path.unwrap_or_default().trim_matches('/');
+    let work_dir = Path::new("/build").join(mount_path);
+    let artifact = checkout
+        .join(mount_path)
+        .join("target/deploy")
+        .join(format!("{}.so", build.lib_name));
+
+    let work_dir = work_dir.to_string_lossy();
+
+    // The cargo home the dependencies are fetched into is removed with the checkout
+    let fetched_cargo_home = (options.network_isolated && options.vendored_cargo_home.is_none())
+        .then(|| std::env::temp_dir().join(format!("cargo-home-{}", uuid::Uuid::new_v4())));
+    let fetched = match &fetched_cargo_home {
+        Some(cargo_home) => match tokio::fs::create_dir_all(cargo_home).await {
+            Ok(()) => fetch_dependencies(&checkout, build.image, &work_dir, cargo_home).await,
+            Err(err) => Err(err.into()),
+        },
+        None => Ok(()),
+    };
+    let cargo_home = fetched_cargo_home
+        .as_ref()
+        .map(|cargo_home| cargo_home.to_string_lossy().into_owned());
+    let ran = match fetched {
+        Ok(()) => {
+            run_image(
+                &checkout,
+                build.image,
+                &work_dir,
+                cargo_home.as_deref().or(options.vendored_cargo_home),
+                &options,
+            )
+            .await
+        }
+        Err(err) => Err(err),
+    };
+    let built = match ran {
+        Ok(output) => tokio::fs::read(&artifact)
+            .await
+            .map(|bytes| (bytes, output))
+            .map_err(|_| {
+                ApiError::Build(format!(
+                    "Executable target/deploy/{}.so not found after running {}",
+                    build.lib_name, build.image
+                ))
+            }),
+        Err(err) => Err(err),
+    };
+    let _ = tokio::fs::remove_dir_all(&checkout).await;
+    if let Some(cargo_home) = &fetched_cargo_home {
+        let _ = tokio::fs::remove_dir_all(cargo_home).await;
+    }
+    built
+}
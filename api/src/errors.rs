@@ -1,3 +1,8 @@
+use crate::models::{ApiResponse, ErrorResponse, Status};
+use axum::http::StatusCode;
+use axum::Json;
+use diesel::result::Error as DieselError;
+use diesel_async::pooled_connection::deadpool::PoolError;
 use std::{fmt, string::FromUtf8Error};
 use thiserror::Error;
 
@@ -28,12 +33,150 @@ pub enum ApiError {
     RedisPool(#[from] r2d2_redis::redis::RedisError),
 
     #[error(transparent)]
-    DbPool(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    DbPool(#[from] PoolError),
+
+    #[error("Timed out waiting for a database connection")]
+    PoolTimeout,
+
+    #[error("Invalid program address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Program is {size} bytes which exceeds the limit of {limit} bytes")]
+    ProgramTooLarge { size: u64, limit: u64 },
+
+    #[error("Repository is about {size} bytes which exceeds the limit of {limit} bytes")]
+    RepositoryTooLarge { size: u64, limit: u64 },
+
+    #[error(transparent)]
+    Rpc(#[from] reqwest::Error),
+
+    #[error("Authority signature rejected: {0}")]
+    InvalidSignature(String),
+
+    #[error("Program {0} is closed or has no executable data")]
+    ProgramClosed(String),
+
+    #[error("On-chain hash fetches are paused after repeated RPC failures")]
+    RpcCircuitOpen,
+
+    #[error("Program {program_id} is denied: {reason}")]
+    ProgramDenied { program_id: String, reason: String },
+
+    #[error("No upload found with id {0}")]
+    UploadNotFound(String),
+
+    #[error("Upload is at offset {expected}, not {received}")]
+    UploadOffsetMismatch { expected: u64, received: u64 },
+
+    #[error("Invalid API key")]
+    InvalidApiKey,
+
+    #[error("The instance is draining and no longer accepts builds")]
+    Draining,
+
+    #[error("Failed to fetch the git LFS objects of the repository: {0}")]
+    LfsFetch(String),
+
+    #[error("Failed to fetch the published crate: {0}")]
+    PublishedCrate(String),
+
+    #[error("Ownership claim not proven: {0}")]
+    ClaimNotProven(String),
+
+    #[error("Program is already claimed by {0}")]
+    AlreadyClaimed(String),
+
+    #[error("Build {0} with the same params is still in progress")]
+    BuildInProgress(String),
+}
+
+// Message of the error Postgres reports when a query exceeds `statement_timeout`
+const STATEMENT_TIMEOUT_MESSAGE: &str = "canceling statement due to statement timeout";
+
+// Whether a query was cancelled by the statement timeout of the connection
+fn is_statement_timeout(err: &DieselError) -> bool {
+    matches!(
+        err,
+        DieselError::DatabaseError(_, info)
+            if info.message() == STATEMENT_TIMEOUT_MESSAGE
+    )
+}
+
+impl ApiError {
+    // HTTP status code to report when this error aborts a request
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Diesel(err) if is_statement_timeout(err) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::PoolTimeout
+            | ApiError::Redis(_)
+            | ApiError::DbPool(PoolError::Timeout(_))
+            | ApiError::RpcCircuitOpen
+            | ApiError::Draining => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::InvalidAddress(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidApiKey => StatusCode::UNAUTHORIZED,
+            ApiError::ProgramTooLarge { .. } | ApiError::RepositoryTooLarge { .. } => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            ApiError::InvalidSignature(_)
+            | ApiError::ProgramDenied { .. }
+            | ApiError::ClaimNotProven(_) => StatusCode::FORBIDDEN,
+            ApiError::ProgramClosed(_) | ApiError::UploadNotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::UploadOffsetMismatch { .. }
+            | ApiError::AlreadyClaimed(_)
+            | ApiError::BuildInProgress(_) => StatusCode::CONFLICT,
+            ApiError::LfsFetch(_) | ApiError::PublishedCrate(_) => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // Message shown to users when this error aborts a request
+    pub fn user_message(&self) -> String {
+        if matches!(self, ApiError::Draining) {
+            return self.to_string();
+        }
+        match self.status_code() {
+            StatusCode::SERVICE_UNAVAILABLE => ErrorMessages::Unavailable.to_string(),
+            StatusCode::INTERNAL_SERVER_ERROR => ErrorMessages::DB.to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// JSON error response with the given status and message, without field errors.
+pub fn error_response(
+    status: StatusCode,
+    error: impl Into<String>,
+) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            status: Status::Error,
+            error: error.into(),
+            field_errors: Vec::new(),
+        }),
+    )
+}
+
+/// `error_response` for the handlers which reply with an `ApiResponse`.
+pub fn api_error_response(
+    status: StatusCode,
+    error: impl Into<String>,
+) -> (StatusCode, Json<ApiResponse>) {
+    let (status, Json(error)) = error_response(status, error);
+    (status, Json(error.into()))
+}
+
+// Errors which abort a request reply with their status code and user message
+impl From<ApiError> for (StatusCode, Json<ErrorResponse>) {
+    fn from(err: ApiError) -> Self {
+        error_response(err.status_code(), err.user_message())
+    }
 }
 
 pub enum ErrorMessages {
     Unexpected,
     DB,
+    Unavailable,
 }
 
 impl fmt::Display for ErrorMessages {
@@ -41,7 +184,33 @@ impl fmt::Display for ErrorMessages {
         let message = match self {
             ErrorMessages::Unexpected => "We encountered an unexpected error during the verification process.",
             ErrorMessages::DB => "An unforeseen database error has occurred, preventing the initiation of the build process. Kindly try again after some time.",
+            ErrorMessages::Unavailable => "The service is currently overloaded. Kindly try again after some time.",
         };
         write!(f, "{}", message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::result::DatabaseErrorKind;
+
+    fn database_error(message: &str) -> ApiError {
+        ApiError::Diesel(DieselError::DatabaseError(
+            DatabaseErrorKind::Unknown,
+            Box::new(message.to_string()),
+        ))
+    }
+
+    #[test]
+    fn test_statement_timeout_is_unavailable() {
+        let err = database_error(STATEMENT_TIMEOUT_MESSAGE);
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(err.user_message(), ErrorMessages::Unavailable.to_string());
+
+        assert_eq!(
+            database_error("relation \"verified_programs\" does not exist").status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
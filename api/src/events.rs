@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast;
+
+use crate::models::BuildEvent;
+
+// Events buffered for a subscriber before it starts lagging behind
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Whether the event is a status change after which the build records no more events.
+pub fn is_terminal_event(event: &str) -> bool {
+    event == "completed" || event == "failed"
+}
+
+type Channels = Arc<Mutex<HashMap<String, broadcast::Sender<BuildEvent>>>>;
+
+/// Fans out the events recorded for a build to the clients following it. A channel is
+/// created on the first subscription to a build and dropped once the build terminates
+/// or nobody is listening anymore.
+#[derive(Clone, Default)]
+pub struct BuildEventBus {
+    channels: Channels,
+}
+
+/// A client following the events of a build. The channel of the build is dropped with
+/// its last subscription.
+pub struct Subscription {
+    channels: Channels,
+    build_id: String,
+    pub receiver: broadcast::Receiver<BuildEvent>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut channels = self.channels.lock().expect("event channels poisoned");
+        // The receiver of this subscription is still counted until it is dropped
+        if channels
+            .get(&self.build_id)
+            .is_some_and(|sender| sender.receiver_count() <= 1)
+        {
+            channels.remove(&self.build_id);
+        }
+    }
+}
+
+impl BuildEventBus {
+    pub fn subscribe(&self, build_id: &str) -> Subscription {
+        let receiver = self
+            .channels
+            .lock()
+            .expect("event channels poisoned")
+            .entry(build_id.to_string())
+            .or_insert_with(|| broadcast::channel(EVENT_CHANNEL_CAPACITY).0)
+            .subscribe();
+        Subscription {
+            channels: self.channels.clone(),
+            build_id: build_id.to_string(),
+            receiver,
+        }
+    }
+
+    /// Send an event to the subscribers of its build, if there are any.
+    pub fn publish(&self, event: &BuildEvent) {
+        let mut channels = self.channels.lock().expect("event channels poisoned");
+        let Some(sender) = channels.get(&event.build_id) else {
+            return;
+        };
+        let _ = sender.send(event.clone());
+        if is_terminal_event(&event.event) || sender.receiver_count() == 0 {
+            channels.remove(&event.build_id);
+        }
+    }
+
+    #[cfg(test)]
+    fn channel_count(&self) -> usize {
+        self.channels.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str) -> BuildEvent {
+        BuildEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            build_id: "build".to_string(),
+            event: name.to_string(),
+            detail: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_channel_closes_on_terminal_event() {
+        let bus = BuildEventBus::default();
+        let mut subscription = bus.subscribe("build");
+
+        bus.publish(&event("build_started"));
+        bus.publish(&event("completed"));
+        assert_eq!(bus.channel_count(), 0);
+
+        assert_eq!(
+            subscription.receiver.recv().await.unwrap().event,
+            "build_started"
+        );
+        assert_eq!(
+            subscription.receiver.recv().await.unwrap().event,
+            "completed"
+        );
+        assert!(subscription.receiver.recv().await.is_err());
+    }
+
+    #[test]
+    fn test_abandoned_subscriptions_drop_channel() {
+        let bus = BuildEventBus::default();
+        let first = bus.subscribe("build");
+        let second = bus.subscribe("build");
+
+        drop(first);
+        assert_eq!(bus.channel_count(), 1);
+        drop(second);
+        assert_eq!(bus.channel_count(), 0);
+
+        // Events of builds nobody follows are not buffered
+        bus.publish(&event("build_started"));
+        assert_eq!(bus.channel_count(), 0);
+    }
+}
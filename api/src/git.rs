@@ -0,0 +1,870 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::errors::ApiError;
+use crate::models::RefStrategy;
+use crate::Result;
+
+// Branch of a shallow mirror pointing to the fetched commit
+const SHALLOW_MIRROR_BRANCH: &str = "verify";
+
+// Default cap on the estimated size of repositories which are built (1 GiB)
+const DEFAULT_MAX_REPO_SIZE: u64 = 1024 * 1024 * 1024;
+
+// Time allowed to a repository to answer while its redirects are followed
+const REDIRECT_RESOLUTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Redirects followed at most while resolving a repository URL
+const MAX_REPOSITORY_REDIRECTS: usize = 10;
+
+// Time allowed to the GitHub API to return the latest release of a repository
+const GITHUB_API_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The GitHub API rejects requests without a User-Agent
+const GITHUB_API_USER_AGENT: &str = "solana-verified-programs-api";
+
+/// Temporary file or directory, removed when dropped so it does not outlive a request
+/// which times out or a build which fails half way.
+pub struct TempPath {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl TempPath {
+    /// Path in the temporary directory named after `prefix` and a random id. Nothing is
+    /// created.
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            path: env::temp_dir().join(format!("{}-{}", prefix, uuid::Uuid::new_v4())),
+            keep: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keep the file or directory, handing its removal over to the caller.
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        self.path.clone()
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        let _ = if self.path.is_dir() {
+            std::fs::remove_dir_all(&self.path)
+        } else {
+            std::fs::remove_file(&self.path)
+        };
+    }
+}
+
+// A git ref resolved to the commit that will be built
+#[derive(Debug, Clone)]
+pub struct ResolvedRef {
+    pub name: String,
+    pub commit: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+}
+
+impl RefStrategy {
+    /// Server wide strategy from `DEFAULT_REF_STRATEGY`, falling back to `head`.
+    pub fn from_env() -> Self {
+        env::var("DEFAULT_REF_STRATEGY")
+            .ok()
+            .and_then(|value| serde_json::from_value(serde_json::Value::String(value)).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Normalize a repository URL for comparisons, ignoring case and a trailing `/` or `.git`.
+pub fn normalize_repository(url: &str) -> String {
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .to_ascii_lowercase()
+}
+
+// Run `git ls-remote` against the repository and return its stdout
+async fn ls_remote(repository: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .arg("ls-remote")
+        .args(args)
+        .arg("--")
+        .arg(repository)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git ls-remote failed: {}",
+            String::from_utf8(output.stderr)?
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Parse `git ls-remote --tags` output into (tag, commit) pairs keeping the order of
+/// the output. Annotated tags are resolved to the commit they point to.
+fn parse_tags(output: &str) -> Vec<(String, String)> {
+    let mut tags: Vec<(String, String)> = Vec::new();
+    for line in output.lines() {
+        let Some((sha, reference)) = line.split_once('\t') else {
+            continue;
+        };
+        let Some(tag) = reference.strip_prefix("refs/tags/") else {
+            continue;
+        };
+
+        match tag.strip_suffix("^{}") {
+            Some(peeled) => {
+                if let Some(entry) = tags.iter_mut().find(|(name, _)| name == peeled) {
+                    entry.1 = sha.to_string();
+                } else {
+                    tags.push((peeled.to_string(), sha.to_string()));
+                }
+            }
+            None => {
+                if !tags.iter().any(|(name, _)| name == tag) {
+                    tags.push((tag.to_string(), sha.to_string()));
+                }
+            }
+        }
+    }
+    tags
+}
+
+async fn resolve_head(repository: &str) -> Result<ResolvedRef> {
+    let output = ls_remote(repository, &[]).await?;
+    output
+        .lines()
+        .find_map(|line| line.strip_suffix("\tHEAD"))
+        .map(|sha| ResolvedRef {
+            name: "HEAD".to_string(),
+            commit: sha.to_string(),
+        })
+        .ok_or_else(|| ApiError::Custom("Repository has no HEAD".to_string()))
+}
+
+async fn resolve_tag(repository: &str, tag: Option<&str>) -> Result<ResolvedRef> {
+    let output = ls_remote(repository, &["--tags", "--sort=-v:refname"]).await?;
+    let tags = parse_tags(&output);
+
+    let found = match tag {
+        Some(tag) => tags.into_iter().find(|(name, _)| name == tag),
+        None => tags.into_iter().next(),
+    };
+    found
+        .map(|(name, commit)| ResolvedRef { name, commit })
+        .ok_or_else(|| ApiError::Custom("No matching tag found in the repository".to_string()))
+}
+
+async fn resolve_latest_release(repository: &str) -> Result<ResolvedRef> {
+    let path = repository
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .strip_prefix("https://github.com/")
+        .ok_or_else(|| {
+            ApiError::Custom("latest_release is only supported for GitHub repositories".to_string())
+        })?;
+
+    let response = reqwest::Client::builder()
+        .user_agent(GITHUB_API_USER_AGENT)
+        .timeout(GITHUB_API_TIMEOUT)
+        .build()?
+        .get(format!(
+            "https://api.github.com/repos/{}/releases/latest",
+            path
+        ))
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(ApiError::Custom(format!(
+            "{} has no published release",
+            repository
+        )));
+    }
+    let release: GithubRelease = response.error_for_status()?.json().await?;
+
+    resolve_tag(repository, Some(&release.tag_name)).await
+}
+
+// Follow redirects within the host of the repository only, such as renames on the same
+// forge, and never from https to http
+fn same_host_redirects() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        let Some(original) = attempt.previous().first() else {
+            return attempt.stop();
+        };
+        let url = attempt.url();
+        let same_host = url.host_str() == original.host_str();
+        let downgrade = original.scheme() == "https" && url.scheme() != "https";
+        if same_host && !downgrade && attempt.previous().len() <= MAX_REPOSITORY_REDIRECTS {
+            attempt.follow()
+        } else {
+            attempt.stop()
+        }
+    })
+}
+
+/// Follow the redirects of an HTTP repository URL, such as those GitHub serves for renamed
+/// repositories, and return the URL of the repository they lead to. URLs which do not
+/// redirect, or redirect to another host, are returned unchanged.
+pub async fn resolve_repository_redirect(repository: &str) -> Result<String> {
+    if !repository.starts_with("http://") && !repository.starts_with("https://") {
+        return Ok(repository.to_string());
+    }
+
+    // The smart HTTP endpoint git itself requests first, which redirects like the repository
+    let info_refs = format!("{}/info/refs", repository.trim_end_matches('/'));
+    let response = reqwest::Client::builder()
+        .redirect(same_host_redirects())
+        .timeout(REDIRECT_RESOLUTION_TIMEOUT)
+        .build()?
+        .get(&info_refs)
+        .query(&[("service", "git-upload-pack")])
+        .header("User-Agent", "git/2 (solana-verified-programs-api)")
+        .send()
+        .await?;
+    // A redirect which was not followed leads off the host
+    if response.status().is_redirection() {
+        return Ok(repository.to_string());
+    }
+
+    let mut resolved = response.url().clone();
+    resolved.set_query(None);
+    if resolved.as_str() == info_refs {
+        return Ok(repository.to_string());
+    }
+    let Some(path) = resolved
+        .path()
+        .strip_suffix("/info/refs")
+        .map(str::to_string)
+    else {
+        return Ok(repository.to_string());
+    };
+    resolved.set_path(&path);
+    Ok(resolved.to_string())
+}
+
+/// Resolve a tag of the repository to the commit it points to, failing if it does not exist.
+pub async fn resolve_named_tag(repository: &str, tag: &str) -> Result<ResolvedRef> {
+    resolve_tag(repository, Some(tag)).await
+}
+
+/// Resolve the ref to build according to `strategy` when no commit was specified.
+pub async fn resolve_ref(repository: &str, strategy: RefStrategy) -> Result<ResolvedRef> {
+    match strategy {
+        RefStrategy::Head => resolve_head(repository).await,
+        RefStrategy::LatestTag => resolve_tag(repository, None).await,
+        RefStrategy::LatestRelease => resolve_latest_release(repository).await,
+    }
+}
+
+/// Clone the repository into a new temporary directory and check out `commit`, or the
+/// default branch if none is given. The caller removes the directory when done.
+pub async fn clone_repository(repository: &str, commit: Option<&str>) -> Result<PathBuf> {
+    let clone = TempPath::new("clone");
+    let clone_path = clone.path();
+
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .args(["clone", "--quiet", "--"])
+        .arg(repository)
+        .arg(clone_path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git clone failed: {}",
+            String::from_utf8(output.stderr)?
+        )));
+    }
+
+    if let Some(commit) = commit {
+        let output = Command::new("git")
+            .kill_on_drop(true)
+            .arg("-C")
+            .arg(clone_path)
+            .args(["checkout", "--quiet", "--detach", commit, "--"])
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(ApiError::Custom(format!(
+                "git checkout failed: {}",
+                String::from_utf8(output.stderr)?
+            )));
+        }
+    }
+    Ok(clone.keep())
+}
+
+/// Bare clone of a repository without file contents, whose blobs are fetched as they are
+/// read. A build clones its repository once and shares the clone between the steps reading
+/// its files. The clone is removed when dropped.
+pub struct BareClone {
+    dir: TempPath,
+}
+
+impl BareClone {
+    /// Fetch only `commit` of `repository`, or its default branch if none is given, without
+    /// its history or file contents, into a new temporary directory. The full history is
+    /// cloned instead when the server does not serve the commit directly, such as for
+    /// abbreviated commit ids.
+    pub async fn fetch(repository: &str, commit: Option<&str>) -> Result<Self> {
+        let dir = TempPath::new("bare");
+        match fetch_partial_commit(dir.path(), repository, commit.unwrap_or("HEAD")).await {
+            Ok(()) => Ok(Self { dir }),
+            Err(err) => {
+                tracing::info!(
+                    "Cloning the history of {} after a shallow fetch failed: {}",
+                    repository,
+                    err
+                );
+                Self::fetch_history(repository).await
+            }
+        }
+    }
+
+    /// Clone `repository` into a new temporary directory, fetching only its history and trees.
+    pub async fn fetch_history(repository: &str) -> Result<Self> {
+        let dir = TempPath::new("bare");
+        let output = Command::new("git")
+            .kill_on_drop(true)
+            .args(["clone", "--bare", "--quiet", "--filter=blob:none", "--"])
+            .arg(repository)
+            .arg(dir.path())
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(ApiError::Custom(format!(
+                "git clone failed: {}",
+                String::from_utf8(output.stderr)?
+            )));
+        }
+        Ok(Self { dir })
+    }
+
+    // Run git in the clone and return its output
+    async fn git(&self, args: &[&str]) -> Result<std::process::Output> {
+        Command::new("git")
+            .kill_on_drop(true)
+            .arg("-C")
+            .arg(self.dir.path())
+            .args(args)
+            .output()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Read a file at `commit`, or the default branch if none is given. Returns `None` if
+    /// the file does not exist.
+    pub async fn read_file(&self, commit: Option<&str>, path: &str) -> Result<Option<String>> {
+        let object = format!("{}:{}", commit.unwrap_or("HEAD"), path);
+        let output = self.git(&["show", &object, "--"]).await?;
+        Ok(if output.status.success() {
+            String::from_utf8(output.stdout).ok()
+        } else {
+            None
+        })
+    }
+
+    /// Read several files at `commit`, in the order of `paths`, with `None` for those which
+    /// do not exist.
+    pub async fn read_files(
+        &self,
+        commit: Option<&str>,
+        paths: &[String],
+    ) -> Result<Vec<Option<String>>> {
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            files.push(self.read_file(commit, path).await?);
+        }
+        Ok(files)
+    }
+
+    /// List the files at `commit`, or the default branch if none is given.
+    pub async fn list_files(&self, commit: Option<&str>) -> Result<Vec<String>> {
+        let output = self
+            .git(&[
+                "ls-tree",
+                "-r",
+                "--name-only",
+                commit.unwrap_or("HEAD"),
+                "--",
+            ])
+            .await?;
+        if !output.status.success() {
+            return Err(ApiError::Custom(format!(
+                "git ls-tree failed: {}",
+                String::from_utf8(output.stderr)?
+            )));
+        }
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Commit date of `commit`, or of the head of the default branch if none is given, as a
+    /// unix timestamp.
+    pub async fn commit_timestamp(&self, commit: Option<&str>) -> Result<i64> {
+        let output = self
+            .git(&[
+                "show",
+                "--no-patch",
+                "--format=%ct",
+                commit.unwrap_or("HEAD"),
+                "--",
+            ])
+            .await?;
+        String::from_utf8(output.stdout)?
+            .trim()
+            .parse()
+            .map_err(|_| {
+                ApiError::Custom(format!(
+                    "Failed to read the date of {}: {}",
+                    commit.unwrap_or("HEAD"),
+                    String::from_utf8_lossy(&output.stderr)
+                ))
+            })
+    }
+
+    /// List the commits after `from` up to and including `to`, oldest first. Only commits
+    /// which descend from `from` are returned.
+    pub async fn list_commits(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let range = format!("{}..{}", from, to);
+        let output = self
+            .git(&["rev-list", "--reverse", "--ancestry-path", &range, "--"])
+            .await?;
+        if !output.status.success() {
+            return Err(ApiError::Custom(format!(
+                "git rev-list failed: {}",
+                String::from_utf8(output.stderr)?
+            )));
+        }
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+// Fetch `reference` of the repository without its history or blobs into a new bare
+// repository at `clone_path` whose HEAD points to it. The repository is registered as a
+// promisor remote, so blobs are fetched from it as they are read.
+async fn fetch_partial_commit(clone_path: &Path, repository: &str, reference: &str) -> Result<()> {
+    let branch = format!("refs/heads/{}", SHALLOW_MIRROR_BRANCH);
+    let refspec = format!("{}:{}", reference, branch);
+    let steps: [&[&str]; 3] = [
+        &["remote", "add", "origin", repository],
+        &[
+            "fetch",
+            "--quiet",
+            "--depth",
+            "1",
+            "--filter=blob:none",
+            "origin",
+            &refspec,
+        ],
+        &["symbolic-ref", "HEAD", &branch],
+    ];
+
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .args(["init", "--bare", "--quiet"])
+        .arg(clone_path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git init failed: {}",
+            String::from_utf8(output.stderr)?
+        )));
+    }
+    for args in steps {
+        let output = Command::new("git")
+            .kill_on_drop(true)
+            .arg("-C")
+            .arg(clone_path)
+            .args(args)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(ApiError::Custom(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8(output.stderr)?
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Read a file of the repository at `commit`, or the default branch if none is given.
+/// Returns `None` if the file does not exist. Only the blobs needed are fetched.
+pub async fn read_file(
+    repository: &str,
+    commit: Option<&str>,
+    path: &str,
+) -> Result<Option<String>> {
+    BareClone::fetch(repository, commit)
+        .await?
+        .read_file(commit, path)
+        .await
+}
+
+/// Read several files of the repository at `commit` from a single clone, in the order of
+/// `paths`, with `None` for those which do not exist.
+pub async fn read_files(
+    repository: &str,
+    commit: Option<&str>,
+    paths: &[String],
+) -> Result<Vec<Option<String>>> {
+    BareClone::fetch(repository, commit)
+        .await?
+        .read_files(commit, paths)
+        .await
+}
+
+/// List the files of the repository at `commit`, or the default branch if none is given.
+/// Only the trees are fetched, not the blobs.
+pub async fn list_files(repository: &str, commit: Option<&str>) -> Result<Vec<String>> {
+    BareClone::fetch(repository, commit)
+        .await?
+        .list_files(commit)
+        .await
+}
+
+/// Largest repository built, from `MAX_REPO_SIZE` in bytes.
+pub fn max_repository_size() -> u64 {
+    env::var("MAX_REPO_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_REPO_SIZE)
+}
+
+// Fetch a single commit into a bare repository, without its history
+async fn shallow_fetch(clone_path: &Path, repository: &str, reference: &str) -> Result<()> {
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .arg("-C")
+        .arg(clone_path)
+        .args(["fetch", "--quiet", "--depth", "1", "--"])
+        .arg(repository)
+        .arg(reference)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git fetch failed: {}",
+            String::from_utf8(output.stderr)?
+        )));
+    }
+    Ok(())
+}
+
+/// Parse `git count-objects -v` output into the size in bytes of the packed and loose
+/// objects, which git reports in KiB.
+fn parse_count_objects(output: &str) -> u64 {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .filter(|(key, _)| *key == "size" || *key == "size-pack")
+        .filter_map(|(_, kib)| kib.trim().parse::<u64>().ok())
+        .sum::<u64>()
+        * 1024
+}
+
+/// Estimate the size of the repository at `commit`, or the default branch if none is
+/// given, from the objects a `--depth 1` fetch of that commit downloads. Servers only
+/// serve full commit ids, so the default branch is measured for abbreviated ones.
+pub async fn estimate_repository_size(repository: &str, commit: Option<&str>) -> Result<u64> {
+    let clone = TempPath::new("size");
+    let clone_path = clone.path();
+
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .args(["init", "--bare", "--quiet"])
+        .arg(clone_path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git init failed: {}",
+            String::from_utf8(output.stderr)?
+        )));
+    }
+
+    let mut fetched = shallow_fetch(clone_path, repository, commit.unwrap_or("HEAD")).await;
+    if fetched.is_err() && commit.is_some() {
+        fetched = shallow_fetch(clone_path, repository, "HEAD").await;
+    }
+    let output = match fetched {
+        Ok(()) => Command::new("git")
+            .kill_on_drop(true)
+            .arg("-C")
+            .arg(clone_path)
+            .args(["count-objects", "-v"])
+            .output()
+            .await
+            .map_err(ApiError::from),
+        Err(err) => Err(err),
+    };
+
+    Ok(parse_count_objects(&String::from_utf8(output?.stdout)?))
+}
+
+/// Fail with `RepositoryTooLarge` if the repository at `commit` is estimated to exceed
+/// `MAX_REPO_SIZE`. Repositories which can not be measured are let through, as their
+/// build fails on its own if they can not be fetched.
+pub async fn check_repository_size(repository: &str, commit: Option<&str>) -> Result<()> {
+    let limit = max_repository_size();
+    match estimate_repository_size(repository, commit).await {
+        Ok(size) if size > limit => Err(ApiError::RepositoryTooLarge { size, limit }),
+        Ok(_) => Ok(()),
+        Err(err) => {
+            tracing::warn!("Failed to estimate the size of {}: {}", repository, err);
+            Ok(())
+        }
+    }
+}
+
+/// Fetch only `commit` of the repository, without its history, into a new temporary bare
+/// repository whose HEAD points to it, so it can be cloned in place of the repository.
+/// Fails if the server does not serve the commit directly, such as for abbreviated commit
+/// ids, or if the commit has submodules, whose relative URLs would not resolve against
+/// the mirror. The caller removes the directory when done.
+pub async fn shallow_mirror(repository: &str, commit: &str) -> Result<PathBuf> {
+    let clone = TempPath::new("mirror");
+    fill_shallow_mirror(clone.path(), repository, commit).await?;
+    Ok(clone.keep())
+}
+
+async fn fill_shallow_mirror(clone_path: &Path, repository: &str, commit: &str) -> Result<()> {
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .args(["init", "--bare", "--quiet"])
+        .arg(clone_path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git init failed: {}",
+            String::from_utf8(output.stderr)?
+        )));
+    }
+
+    let branch = format!("refs/heads/{}", SHALLOW_MIRROR_BRANCH);
+    shallow_fetch(clone_path, repository, &format!("{}:{}", commit, branch)).await?;
+
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .arg("-C")
+        .arg(clone_path)
+        .args(["symbolic-ref", "HEAD", &branch])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git symbolic-ref failed: {}",
+            String::from_utf8(output.stderr)?
+        )));
+    }
+
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .arg("-C")
+        .arg(clone_path)
+        .args(["cat-file", "-e"])
+        .arg(format!("{}:.gitmodules", commit))
+        .output()
+        .await?;
+    if output.status.success() {
+        return Err(ApiError::Custom(
+            "The repository has submodules".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Run git in `clone_path` with a separate index, returning its trimmed output
+async fn git_with_index(
+    clone_path: &Path,
+    index: &Path,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Result<String> {
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .arg("-C")
+        .arg(clone_path)
+        .args(args)
+        .env("GIT_INDEX_FILE", index)
+        .envs(envs.iter().copied())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::Custom(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8(output.stderr)?
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Commit `content` as the file at `path` on top of `commit` in the repository at
+/// `clone_path`, such as a shallow mirror, and point its HEAD to the new commit. The commit
+/// has a fixed author and date, so the same file gives the same commit id. Returns its id.
+pub async fn commit_file(
+    clone_path: &Path,
+    commit: &str,
+    path: &str,
+    content: &str,
+) -> Result<String> {
+    let blob_path = TempPath::new("blob");
+    let index_path = TempPath::new("index");
+    let index = index_path.path();
+    tokio::fs::write(blob_path.path(), content).await?;
+
+    let blob_file = blob_path.path().to_string_lossy().into_owned();
+    let cacheinfo = |blob: &str| format!("100644,{},{}", blob, path);
+    let identity = [
+        ("GIT_AUTHOR_NAME", "verify"),
+        ("GIT_AUTHOR_EMAIL", "verify@localhost"),
+        ("GIT_AUTHOR_DATE", "@0 +0000"),
+        ("GIT_COMMITTER_NAME", "verify"),
+        ("GIT_COMMITTER_EMAIL", "verify@localhost"),
+        ("GIT_COMMITTER_DATE", "@0 +0000"),
+    ];
+    let blob = git_with_index(clone_path, index, &["hash-object", "-w", &blob_file], &[]).await?;
+    git_with_index(clone_path, index, &["read-tree", commit], &[]).await?;
+    git_with_index(
+        clone_path,
+        index,
+        &["update-index", "--add", "--cacheinfo", &cacheinfo(&blob)],
+        &[],
+    )
+    .await?;
+    let tree = git_with_index(clone_path, index, &["write-tree"], &[]).await?;
+    let new_commit = git_with_index(
+        clone_path,
+        index,
+        &[
+            "commit-tree",
+            &tree,
+            "-p",
+            commit,
+            "-m",
+            &format!("Add {}", path),
+        ],
+        &identity,
+    )
+    .await?;
+    git_with_index(clone_path, index, &["update-ref", "HEAD", &new_commit], &[]).await?;
+    Ok(new_commit)
+}
+
+/// List the commits after `from` up to and including `to`, oldest first. Only commits
+/// which descend from `from` are returned. The history is fetched without file contents.
+pub async fn list_commits(repository: &str, from: &str, to: &str) -> Result<Vec<String>> {
+    BareClone::fetch_history(repository)
+        .await?
+        .list_commits(from, to)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags_prefers_peeled_commit() {
+        let output = "1111111111111111111111111111111111111111\trefs/tags/v1.1.0\n\
+                      2222222222222222222222222222222222222222\trefs/tags/v1.0.0\n\
+                      3333333333333333333333333333333333333333\trefs/tags/v1.0.0^{}\n";
+        let tags = parse_tags(output);
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].0, "v1.1.0");
+        assert_eq!(tags[0].1, "1111111111111111111111111111111111111111");
+        assert_eq!(tags[1].0, "v1.0.0");
+        assert_eq!(tags[1].1, "3333333333333333333333333333333333333333");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_repository_redirect() {
+        use axum::response::Redirect;
+        use axum::routing::get;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // The same server under another host name
+        let other_host = format!(
+            "http://localhost:{}/new-org/program/info/refs?service=git-upload-pack",
+            addr.port()
+        );
+        let app = axum::Router::new()
+            .route(
+                "/old-org/program/info/refs",
+                get(|| async {
+                    Redirect::permanent("/new-org/program/info/refs?service=git-upload-pack")
+                }),
+            )
+            .route(
+                "/moved-org/program/info/refs",
+                get(move || std::future::ready(Redirect::permanent(&other_host))),
+            )
+            .route("/new-org/program/info/refs", get(|| async { "" }));
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        assert_eq!(
+            resolve_repository_redirect(&format!("http://{}/old-org/program", addr))
+                .await
+                .unwrap(),
+            format!("http://{}/new-org/program", addr)
+        );
+        assert_eq!(
+            resolve_repository_redirect(&format!("http://{}/new-org/program", addr))
+                .await
+                .unwrap(),
+            format!("http://{}/new-org/program", addr)
+        );
+        assert_eq!(
+            resolve_repository_redirect(&format!("http://{}/moved-org/program", addr))
+                .await
+                .unwrap(),
+            format!("http://{}/moved-org/program", addr)
+        );
+        assert_eq!(
+            resolve_repository_redirect("git@github.com:org/program.git")
+                .await
+                .unwrap(),
+            "git@github.com:org/program.git"
+        );
+    }
+
+    #[test]
+    fn test_parse_count_objects() {
+        let output = "count: 2\nsize: 8\nin-pack: 1200\npacks: 1\nsize-pack: 40960\n\
+                      prune-packable: 0\ngarbage: 0\nsize-garbage: 0\n";
+        assert_eq!(parse_count_objects(output), (8 + 40960) * 1024);
+    }
+}
@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+
+use crate::builder::get_on_chain_hash;
+use crate::models::{CircuitStatus, Commitment};
+use crate::Result;
+
+/// Source of the hash of a program as currently deployed on chain.
+#[async_trait]
+pub trait OnChainHashProvider: Send + Sync {
+    /// Hash of the program as of the cluster state at `commitment`.
+    async fn get_on_chain_hash(&self, program_id: &str, commitment: Commitment) -> Result<String>;
+
+    /// State of the circuit breaker guarding the provider, if it has one.
+    fn circuit_status(&self) -> Option<CircuitStatus> {
+        None
+    }
+}
+
+/// Fetches the on-chain hash from the cluster at `RPC_URL` using `solana-verify`.
+pub struct RpcHashProvider;
+
+#[async_trait]
+impl OnChainHashProvider for RpcHashProvider {
+    async fn get_on_chain_hash(&self, program_id: &str, commitment: Commitment) -> Result<String> {
+        get_on_chain_hash(program_id, commitment).await
+    }
+}
+
+// Result of comparing an executable hash against the deployed program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMatch {
+    pub on_chain_hash: String,
+    pub is_verified: bool,
+}
+
+/// Compare the hash of a build with the hash of the deployed program.
+pub async fn match_executable_hash(
+    provider: &dyn OnChainHashProvider,
+    program_id: &str,
+    commitment: Commitment,
+    executable_hash: &str,
+) -> Result<HashMatch> {
+    let on_chain_hash = provider.get_on_chain_hash(program_id, commitment).await?;
+    Ok(HashMatch {
+        is_verified: !executable_hash.is_empty() && on_chain_hash == executable_hash,
+        on_chain_hash,
+    })
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::errors::ApiError;
+
+    /// Serves on-chain hashes from memory so the matching logic can be tested offline.
+    #[derive(Default)]
+    pub struct MockHashProvider {
+        pub hashes: HashMap<String, String>,
+    }
+
+    impl MockHashProvider {
+        pub fn with_hash(mut self, program_id: &str, hash: &str) -> Self {
+            self.hashes.insert(program_id.to_string(), hash.to_string());
+            self
+        }
+    }
+
+    #[async_trait]
+    impl OnChainHashProvider for MockHashProvider {
+        async fn get_on_chain_hash(
+            &self,
+            program_id: &str,
+            _commitment: Commitment,
+        ) -> Result<String> {
+            self.hashes
+                .get(program_id)
+                .cloned()
+                .ok_or_else(|| ApiError::Custom(format!("Program {} not found", program_id)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockHashProvider;
+    use super::*;
+
+    const PROGRAM_ID: &str = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY";
+    const HASH: &str = "5bdb733d10c170fbe08912d258bca0bd15dc52ae4919b7db162f44fa0608516b";
+
+    #[tokio::test]
+    async fn test_matching_hash_is_verified() {
+        let provider = MockHashProvider::default().with_hash(PROGRAM_ID, HASH);
+        let result = match_executable_hash(&provider, PROGRAM_ID, Commitment::Finalized, HASH)
+            .await
+            .unwrap();
+        assert!(result.is_verified);
+        assert_eq!(result.on_chain_hash, HASH);
+    }
+
+    #[tokio::test]
+    async fn test_mismatching_hash_is_not_verified() {
+        let provider = MockHashProvider::default().with_hash(PROGRAM_ID, HASH);
+        let result =
+            match_executable_hash(&provider, PROGRAM_ID, Commitment::Finalized, "deadbeef")
+                .await
+                .unwrap();
+        assert!(!result.is_verified);
+    }
+
+    #[tokio::test]
+    async fn test_empty_executable_hash_is_not_verified() {
+        let provider = MockHashProvider::default().with_hash(PROGRAM_ID, "");
+        let result = match_executable_hash(&provider, PROGRAM_ID, Commitment::Finalized, "")
+            .await
+            .unwrap();
+        assert!(!result.is_verified);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_program_is_an_error() {
+        let provider = MockHashProvider::default();
+        assert!(
+            match_executable_hash(&provider, PROGRAM_ID, Commitment::Finalized, HASH)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+/// Most labels accepted on a verification.
+pub const MAX_LABELS: usize = 16;
+pub const MAX_LABEL_KEY_LENGTH: usize = 63;
+pub const MAX_LABEL_VALUE_LENGTH: usize = 255;
+
+// Keys start with a letter or digit and contain letters, digits and `._-`
+fn is_valid_label_key(key: &str) -> bool {
+    (1..=MAX_LABEL_KEY_LENGTH).contains(&key.len())
+        && key.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+// Values contain letters, digits and `._-:/@+`, so they never need quoting in a filter
+fn is_valid_label_value(value: &str) -> bool {
+    (1..=MAX_LABEL_VALUE_LENGTH).contains(&value.len())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-:/@+".contains(c))
+}
+
+/// Check the labels of a request, describing the first invalid one.
+pub fn validate_labels(labels: &HashMap<String, String>) -> Result<(), String> {
+    if labels.len() > MAX_LABELS {
+        return Err(format!("At most {} labels are accepted", MAX_LABELS));
+    }
+    let mut keys: Vec<&String> = labels.keys().collect();
+    keys.sort();
+    for key in keys {
+        if !is_valid_label_key(key) {
+            return Err(format!(
+                "Label key {:?} must be 1 to {} letters, digits, '.', '_' or '-', starting with a letter or digit",
+                key, MAX_LABEL_KEY_LENGTH
+            ));
+        }
+        if !is_valid_label_value(&labels[key]) {
+            return Err(format!(
+                "Value of label {} must be 1 to {} letters, digits or '._-:/@+'",
+                key, MAX_LABEL_VALUE_LENGTH
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Labels as the JSON object they are stored as, with sorted keys.
+pub fn labels_to_json(labels: &HashMap<String, String>) -> Value {
+    Value::Object(
+        labels
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect(),
+    )
+}
+
+/// Labels stored as a JSON object, ignoring entries which are not strings.
+pub fn labels_from_json(labels: Option<&Value>) -> BTreeMap<String, String> {
+    labels
+        .and_then(Value::as_object)
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parse a `key=value,key=value` label filter.
+pub fn parse_label_filter(filter: &str) -> Result<Vec<(String, String)>, String> {
+    filter
+        .split(',')
+        .map(|entry| {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Label filter {:?} must be key=value", entry))?;
+            if !is_valid_label_key(key) || !is_valid_label_value(value) {
+                return Err(format!("Label filter {:?} is not a valid label", entry));
+            }
+            Ok((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Whether `labels` has every label of the filter.
+pub fn matches_labels(labels: &BTreeMap<String, String>, filter: &[(String, String)]) -> bool {
+    filter
+        .iter()
+        .all(|(key, value)| labels.get(key) == Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_labels() {
+        let labels = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        assert!(validate_labels(&labels(&[("env", "prod"), ("team", "core")])).is_ok());
+        assert!(validate_labels(&labels(&[("-env", "prod")])).is_err());
+        assert!(validate_labels(&labels(&[("env", "prod,dev")])).is_err());
+        assert!(validate_labels(&labels(&[("env", "")])).is_err());
+
+        let stored = labels_to_json(&labels(&[("team", "core"), ("env", "prod")]));
+        assert_eq!(stored.to_string(), r#"{"env":"prod","team":"core"}"#);
+        let filter = parse_label_filter("env=prod").unwrap();
+        assert!(matches_labels(&labels_from_json(Some(&stored)), &filter));
+        assert!(!matches_labels(
+            &labels_from_json(None),
+            &parse_label_filter("env=prod,team=core").unwrap()
+        ));
+        assert!(parse_label_filter("env").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+
+use crate::builder::build_rust_program;
+use crate::db::DbClient;
+use crate::models::{SolanaProgramBuildParams, VerifiedProgram, Warning};
+use crate::Result;
+
+/// Language programs are written in when the request does not name one.
+pub const DEFAULT_LANGUAGE: &str = "rust";
+
+/// Builds the program of a request from its source and compares it to the one on chain,
+/// for the programs written in one language.
+#[async_trait]
+pub trait Builder: Send + Sync {
+    /// Name of the language, as passed in the `language` param.
+    fn language(&self) -> &'static str;
+
+    async fn build(
+        &self,
+        db: &DbClient,
+        payload: SolanaProgramBuildParams,
+        build_id: &str,
+    ) -> Result<(VerifiedProgram, Vec<Warning>)>;
+}
+
+/// Builds Rust programs with cargo, through solana-verify or a Dockerfile of the repository.
+pub struct RustBuilder;
+
+#[async_trait]
+impl Builder for RustBuilder {
+    fn language(&self) -> &'static str {
+        DEFAULT_LANGUAGE
+    }
+
+    async fn build(
+        &self,
+        db: &DbClient,
+        payload: SolanaProgramBuildParams,
+        build_id: &str,
+    ) -> Result<(VerifiedProgram, Vec<Warning>)> {
+        build_rust_program(db, payload, build_id).await
+    }
+}
+
+// Builders of the supported languages. Builders for other languages are registered by
+// adding them here.
+const BUILDERS: &[&dyn Builder] = &[&RustBuilder];
+
+/// Builder registered for a language, if any.
+pub fn find_builder(language: &str) -> Option<&'static dyn Builder> {
+    BUILDERS
+        .iter()
+        .copied()
+        .find(|builder| builder.language() == language)
+}
+
+/// Languages which have a registered builder.
+pub fn supported_languages() -> Vec<&'static str> {
+    BUILDERS.iter().map(|builder| builder.language()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_builder() {
+        assert_eq!(
+            find_builder(DEFAULT_LANGUAGE).map(|builder| builder.language()),
+            Some("rust")
+        );
+        assert!(find_builder("move").is_none());
+        assert!(supported_languages().contains(&DEFAULT_LANGUAGE));
+    }
+}
@@ -0,0 +1,119 @@
+use std::env;
+use std::path::Path;
+
+use tokio::process::Command;
+
+use crate::errors::ApiError;
+use crate::git::BareClone;
+use crate::Result;
+
+// Output of git when a file tracked by LFS could not be fetched during a checkout
+const LFS_FAILURE_MARKERS: [&str; 3] = [
+    "smudge filter lfs failed",
+    "Smudge error",
+    "batch response:",
+];
+
+/// Whether LFS objects are fetched for the repositories which use git LFS, from
+/// `GIT_LFS`. Enabled unless set to `false`.
+pub fn lfs_enabled() -> bool {
+    env::var("GIT_LFS").as_deref() != Ok("false")
+}
+
+// Whether a .gitattributes file tracks any path with the LFS filter
+fn uses_lfs(gitattributes: &str) -> bool {
+    gitattributes
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .any(|line| {
+            line.split_whitespace()
+                .skip(1)
+                .any(|attr| attr == "filter=lfs")
+        })
+}
+
+/// Whether the repository at `commit` stores files with git LFS, as declared by the
+/// .gitattributes at its root.
+pub async fn repository_uses_lfs(clone: &BareClone, commit: Option<&str>) -> Result<bool> {
+    Ok(clone
+        .read_file(commit, ".gitattributes")
+        .await?
+        .is_some_and(|gitattributes| uses_lfs(&gitattributes)))
+}
+
+// LFS server of a repository, as git LFS derives it from the repository URL. Set
+// explicitly so checkouts of local mirrors fetch from the repository they mirror.
+fn lfs_endpoint(repository: &str) -> String {
+    let repository = repository.trim_end_matches('/');
+    format!(
+        "{}.git/info/lfs",
+        repository.strip_suffix(".git").unwrap_or(repository)
+    )
+}
+
+/// Environment making git fetch the LFS objects of `repository` on checkout, with the LFS
+/// filters configured whatever the global git config of the host.
+pub fn lfs_git_env(repository: &str) -> Vec<(String, String)> {
+    let config = [
+        ("lfs.url", lfs_endpoint(repository)),
+        ("filter.lfs.process", "git-lfs filter-process".to_string()),
+        ("filter.lfs.smudge", "git-lfs smudge -- %f".to_string()),
+        ("filter.lfs.required", "true".to_string()),
+    ];
+    let mut envs = vec![("GIT_CONFIG_COUNT".to_string(), config.len().to_string())];
+    for (index, (key, value)) in config.into_iter().enumerate() {
+        envs.push((format!("GIT_CONFIG_KEY_{}", index), key.to_string()));
+        envs.push((format!("GIT_CONFIG_VALUE_{}", index), value));
+    }
+    envs
+}
+
+/// Whether a build failed because LFS objects could not be fetched.
+pub fn is_lfs_failure(output: &str) -> bool {
+    LFS_FAILURE_MARKERS
+        .iter()
+        .any(|marker| output.contains(marker))
+}
+
+/// Fetch the LFS objects of `repository` into the checkout at `checkout_path` and replace
+/// their pointer files with them.
+pub async fn lfs_pull(checkout_path: &Path, repository: &str) -> Result<()> {
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .arg("-C")
+        .arg(checkout_path)
+        .args(["lfs", "pull"])
+        .envs(lfs_git_env(repository))
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::LfsFetch(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uses_lfs() {
+        assert!(uses_lfs(
+            "*.so binary\nassets/*.bin filter=lfs diff=lfs merge=lfs -text\n"
+        ));
+        assert!(!uses_lfs("# *.bin filter=lfs diff=lfs merge=lfs -text\n"));
+        assert!(!uses_lfs("*.rs text eol=lf\n"));
+
+        assert_eq!(
+            lfs_endpoint("https://github.com/Ellipsis-Labs/phoenix-v1.git"),
+            "https://github.com/Ellipsis-Labs/phoenix-v1.git/info/lfs"
+        );
+        assert_eq!(
+            lfs_endpoint("https://github.com/Ellipsis-Labs/phoenix-v1/"),
+            "https://github.com/Ellipsis-Labs/phoenix-v1.git/info/lfs"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Largest Cargo.lock accepted in place of the one in the repository.
+pub const MAX_LOCK_FILE_SIZE: usize = 1024 * 1024;
+
+// Lock file formats written by the cargo releases the build images ship
+const LOCK_FILE_VERSIONS: [u32; 3] = [1, 3, 4];
+
+#[derive(Deserialize)]
+struct LockFile {
+    // Absent from the original format
+    version: Option<u32>,
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+}
+
+/// Check that `content` is a Cargo.lock which cargo can read, returning why it is not.
+pub fn validate_lock_file(content: &str) -> Result<(), String> {
+    if content.len() > MAX_LOCK_FILE_SIZE {
+        return Err(format!(
+            "The lock file must be at most {} bytes",
+            MAX_LOCK_FILE_SIZE
+        ));
+    }
+    let lock_file: LockFile =
+        toml::from_str(content).map_err(|err| format!("Invalid lock file: {}", err.message()))?;
+    if let Some(version) = lock_file.version {
+        if !LOCK_FILE_VERSIONS.contains(&version) {
+            return Err(format!("Unsupported lock file version {}", version));
+        }
+    }
+    if lock_file.package.is_empty() {
+        return Err("The lock file does not lock any package".to_string());
+    }
+    if let Some(package) = lock_file
+        .package
+        .iter()
+        .find(|package| package.name.is_empty() || package.version.is_empty())
+    {
+        return Err(format!(
+            "Locked package {:?} has no name or version",
+            package.name
+        ));
+    }
+    Ok(())
+}
+
+/// Hex encoded sha256 of a lock file, recorded with the build it pins.
+pub fn lock_file_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+/// Path of the lock file in the repository: next to the Cargo.toml the build starts from.
+pub fn lock_file_path(mount_path: Option<&str>) -> String {
+    match mount_path
+        .map(|path| path.trim_matches('/'))
+        .filter(|path| !path.is_empty())
+    {
+        Some(path) => format!("{}/Cargo.lock", path),
+        None => "Cargo.lock".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_lock_file() {
+        let lock_file = r#"
+# This file is automatically @generated by Cargo.
+version = 3
+
+[[package]]
+name = "phoenix-v1"
+version = "0.2.4"
+dependencies = [
+ "borsh",
+]
+"#;
+        assert!(validate_lock_file(lock_file).is_ok());
+        assert!(validate_lock_file("version = 3\n").is_err());
+        assert!(validate_lock_file("[[package]]\nname = ").is_err());
+        assert!(validate_lock_file(&lock_file.replace("version = 3", "version = 9")).is_err());
+    }
+
+    #[test]
+    fn test_lock_file_path() {
+        assert_eq!(lock_file_path(None), "Cargo.lock");
+        assert_eq!(
+            lock_file_path(Some("/programs/phoenix/")),
+            "programs/phoenix/Cargo.lock"
+        );
+    }
+}
@@ -2,16 +2,59 @@ use dotenv::dotenv;
 use routes::create_router;
 use std::env;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 extern crate diesel;
 extern crate tracing;
 
+mod access_log;
+mod anchor;
+mod artifact;
+mod bisect;
+mod bounded;
+mod build_args;
+mod build_script;
 mod builder;
+mod cargo_mirror;
+mod cargo_profile;
+mod certificate;
+mod circuit_breaker;
+mod claim;
 mod db;
+mod declared_id;
+mod denylist;
+mod digests;
+mod dockerfile;
 mod errors;
+mod events;
+mod git;
+mod hash_provider;
+mod labels;
+mod language;
+mod lfs;
+mod lock_file;
+mod metrics;
+mod migrations;
 mod models;
+mod negotiation;
+mod params_hash;
+mod published_crate;
+mod rate_limit;
+mod registry;
+mod reproducibility;
+mod request_timeout;
+mod resolver;
+mod retention;
 mod routes;
+mod rpc;
+mod rustflags;
 mod schema;
+mod status_change;
+mod storage;
+mod tenant;
+mod uploads;
+mod validation;
+mod worker;
 
 pub type Result<T> = std::result::Result<T, errors::ApiError>;
 
@@ -22,7 +65,36 @@ async fn main() {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL not set in .env file");
     let redis_url = env::var("REDIS_URL").expect("REDIS_URL not set in .env file");
 
-    let db_client = db::DbClient::new(&database_url, &redis_url);
+    match migrations::run_pending_migrations(&database_url).await {
+        Ok(applied) if applied.is_empty() => tracing::info!("Database schema is up to date"),
+        Ok(applied) => {
+            for version in applied {
+                tracing::info!("Applied migration {}", version);
+            }
+        }
+        Err(err) => {
+            tracing::error!("Failed to run database migrations: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    let db_client = db::DbClient::new(
+        &database_url,
+        &redis_url,
+        db::PoolConfig::from_env(),
+        Arc::new(circuit_breaker::CircuitBreaker::from_env(
+            hash_provider::RpcHashProvider,
+        )),
+        worker::WorkerPool::from_env(),
+        storage::storage_from_env(),
+    );
+    tracing::info!(
+        "Loaded {} registry programs",
+        registry::registry_programs().len()
+    );
+    registry::spawn_registry_scheduler(db_client.clone());
+    retention::spawn_retention_scheduler(db_client.clone());
+
     let app = create_router(db_client);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
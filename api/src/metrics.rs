@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+
+// Upper bounds in seconds of the request duration histogram buckets
+const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// Route label of requests which matched no route, to keep the label set bounded
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    // Observations per bucket, not cumulative, with the last one counting those above
+    // the largest bound
+    buckets: [u64; DURATION_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Histograms of the request handling time, labeled by route and status code.
+#[derive(Default)]
+pub struct RequestMetrics {
+    histograms: Mutex<BTreeMap<(String, u16), Histogram>>,
+}
+
+impl RequestMetrics {
+    pub fn observe(&self, route: &str, status: u16, elapsed: Duration) {
+        self.histograms
+            .lock()
+            .expect("request metrics poisoned")
+            .entry((route.to_string(), status))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Render the histograms in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let histograms = self
+            .histograms
+            .lock()
+            .expect("request metrics poisoned")
+            .clone();
+
+        let mut output = String::new();
+        output.push_str("# HELP http_request_duration_seconds Time spent handling requests.\n");
+        output.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for ((route, status), histogram) in histograms {
+            let labels = format!("route=\"{}\",status=\"{}\"", route, status);
+            let mut cumulative = 0;
+            for (bound, count) in DURATION_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    output,
+                    "http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                output,
+                "http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, histogram.count
+            );
+            let _ = writeln!(
+                output,
+                "http_request_duration_seconds_sum{{{}}} {}",
+                labels, histogram.sum
+            );
+            let _ = writeln!(
+                output,
+                "http_request_duration_seconds_count{{{}}} {}",
+                labels, histogram.count
+            );
+        }
+        output
+    }
+}
+
+/// Metrics shared by all requests of the process.
+pub fn request_metrics() -> &'static RequestMetrics {
+    static METRICS: OnceLock<RequestMetrics> = OnceLock::new();
+    METRICS.get_or_init(RequestMetrics::default)
+}
+
+/// Measure the time spent handling a request, log it and record it by route and status.
+pub async fn record_request_timing<B>(request: Request<B>, next: Next<B>) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE, |path| path.as_str())
+        .to_string();
+    let method = request.method().clone();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let elapsed = started.elapsed();
+    let status = response.status().as_u16();
+    tracing::info!(
+        route = %route,
+        method = %method,
+        status,
+        elapsed_ms = elapsed.as_millis() as u64,
+        "Handled request"
+    );
+    request_metrics().observe(&route, status, elapsed);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_histogram() {
+        let metrics = RequestMetrics::default();
+        metrics.observe("/status/:address", 200, Duration::from_millis(3));
+        metrics.observe("/status/:address", 200, Duration::from_millis(300));
+        metrics.observe("/status/:address", 200, Duration::from_secs(30));
+
+        let output = metrics.render();
+        let labels = "route=\"/status/:address\",status=\"200\"";
+        assert!(output.contains(&format!(
+            "http_request_duration_seconds_bucket{{{},le=\"0.005\"}} 1\n",
+            labels
+        )));
+        assert!(output.contains(&format!(
+            "http_request_duration_seconds_bucket{{{},le=\"0.5\"}} 2\n",
+            labels
+        )));
+        assert!(output.contains(&format!(
+            "http_request_duration_seconds_bucket{{{},le=\"10\"}} 2\n",
+            labels
+        )));
+        assert!(output.contains(&format!(
+            "http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 3\n",
+            labels
+        )));
+        assert!(output.contains(&format!(
+            "http_request_duration_seconds_count{{{}}} 3\n",
+            labels
+        )));
+    }
+}
@@ -0,0 +1,50 @@
+use std::env;
+
+use diesel::migration::{MigrationSource, Result};
+use diesel::pg::Pg;
+use diesel::sql_types::Text;
+use diesel::{Connection, PgConnection, RunQueryDsl};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+// Same definition diesel uses for its bookkeeping table
+const CREATE_MIGRATIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS __diesel_schema_migrations (
+    version VARCHAR(50) PRIMARY KEY NOT NULL,
+    run_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+)";
+
+/// Record every embedded migration up to and including `baseline` as applied without
+/// running it. Used once for databases whose schema was created by the old init scripts.
+fn mark_applied_until(conn: &mut PgConnection, baseline: &str) -> Result<()> {
+    diesel::sql_query(CREATE_MIGRATIONS_TABLE).execute(conn)?;
+    for migration in MigrationSource::<Pg>::migrations(&MIGRATIONS)? {
+        let version = migration.name().version().to_string();
+        if version.as_str() <= baseline {
+            diesel::sql_query(
+                "INSERT INTO __diesel_schema_migrations (version) VALUES ($1) ON CONFLICT DO NOTHING",
+            )
+            .bind::<Text, _>(&version)
+            .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Bring the schema up to date by applying the pending migrations embedded in the binary.
+/// Returns the versions of the migrations which ran.
+pub async fn run_pending_migrations(database_url: &str) -> Result<Vec<String>> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = PgConnection::establish(&database_url)?;
+
+        if let Ok(baseline) = env::var("MIGRATIONS_BASELINE") {
+            tracing::info!("Marking migrations up to {} as applied", baseline);
+            mark_applied_until(&mut conn, &baseline)?;
+        }
+
+        let applied = conn.run_pending_migrations(MIGRATIONS)?;
+        Ok(applied.iter().map(ToString::to_string).collect())
+    })
+    .await?
+}
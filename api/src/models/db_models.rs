@@ -1,9 +1,16 @@
-use crate::schema::{solana_program_builds, verified_programs};
+use crate::schema::{
+    attestations, build_events, denied_programs, program_claims, program_versions,
+    solana_program_builds, subscriptions, verified_programs,
+};
 use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use super::SolanaProgramBuildParams;
+use super::{Network, SolanaProgramBuildParams, UpdateBuildParams, Warning};
+use crate::build_args::{build_args_to_entries, build_args_to_map};
+use crate::labels::{labels_from_json, labels_to_json};
+use crate::lock_file::lock_file_hash;
+use crate::rustflags::normalize_rustflags;
 
 #[derive(
     Clone, Debug, Serialize, Deserialize, Insertable, Identifiable, Queryable, AsChangeset,
@@ -21,6 +28,49 @@ pub struct SolanaProgramBuild {
     pub bpf_flag: bool,
     pub created_at: NaiveDateTime,
     pub status: String,
+    pub no_default_features: bool,
+    pub workspace_member: Option<String>,
+    pub authority_claimed: bool,
+    pub resolved_ref: Option<String>,
+    pub resolved_commit: Option<String>,
+    pub vendored: bool,
+    pub extra_hashes: Option<Vec<String>>,
+    pub tag: Option<String>,
+    // `KEY=VALUE` docker build args, sorted by key
+    pub docker_build_args: Option<Vec<String>>,
+    pub build_system: String,
+    pub dockerfile_path: Option<String>,
+    pub artifact_path: Option<String>,
+    pub target: String,
+    // Anchor version the program was built with
+    pub anchor_version: Option<String>,
+    pub cargo_profile: String,
+    // `KEY=VALUE` release profile overrides, sorted by key
+    pub profile_overrides: Option<Vec<String>>,
+    // Normalized RUSTFLAGS, in the order they are applied
+    pub rustflags: Option<Vec<String>>,
+    // Canonical name of the cluster the program is deployed to
+    pub network: String,
+    // Requested SOURCE_DATE_EPOCH, the commit date is used when unset
+    pub source_date_epoch: Option<i64>,
+    // Tenant the build is verified for
+    pub tenant_id: String,
+    // Cargo.lock supplied in place of the one in the repository, and its sha256
+    pub lock_file: Option<String>,
+    pub lock_file_hash: Option<String>,
+    // Language the program is written in, naming the builder it was built with
+    pub language: String,
+    // Labels organizing the verification, as a JSON object of strings
+    pub labels: Option<serde_json::Value>,
+    // Upstream repository declared by a build from a fork
+    pub upstream_repo: Option<String>,
+    // Commitment level the deployed program was read at
+    pub commitment: String,
+    // crates.io crate and version built, for builds of a published crate
+    pub crate_name: Option<String>,
+    pub crate_version: Option<String>,
+    // Warnings raised while building, as a JSON array
+    pub warnings: Option<serde_json::Value>,
 }
 
 impl<'a> From<&'a SolanaProgramBuildParams> for SolanaProgramBuild {
@@ -32,12 +82,115 @@ impl<'a> From<&'a SolanaProgramBuildParams> for SolanaProgramBuild {
             commit_hash: params.commit_hash.clone(),
             program_id: params.program_id.clone(),
             lib_name: params.lib_name.clone(),
-            bpf_flag: params.bpf_flag.unwrap_or(false),
+            bpf_flag: params.build_system().uses_bpf(),
             created_at: Utc::now().naive_utc(),
             base_docker_image: params.base_image.clone(),
             mount_path: params.mount_path.clone(),
             cargo_args: params.cargo_args.clone(),
             status: JobStatus::InProgress.into(),
+            no_default_features: params.no_default_features.unwrap_or(false),
+            workspace_member: params.workspace_member.clone(),
+            authority_claimed: false,
+            resolved_ref: None,
+            resolved_commit: None,
+            vendored: params.vendored.unwrap_or(false),
+            extra_hashes: params.extra_hashes.clone(),
+            tag: params.tag.clone(),
+            docker_build_args: params.docker_build_args.as_ref().map(build_args_to_entries),
+            build_system: params.build_system().as_str().to_string(),
+            dockerfile_path: params.dockerfile_path.clone(),
+            artifact_path: params.artifact_path.clone(),
+            target: params.target().as_str().to_string(),
+            anchor_version: params.anchor_version.clone(),
+            cargo_profile: params.cargo_profile().to_string(),
+            profile_overrides: params.profile_overrides.as_ref().map(build_args_to_entries),
+            rustflags: params
+                .rustflags
+                .as_deref()
+                .and_then(|flags| normalize_rustflags(flags).ok()),
+            network: params.network().as_str().to_string(),
+            source_date_epoch: params.source_date_epoch,
+            tenant_id: params.tenant().to_string(),
+            lock_file: params.lock_file_content.clone(),
+            lock_file_hash: params.lock_file_content.as_deref().map(lock_file_hash),
+            language: params.language().to_string(),
+            labels: params.labels.as_ref().map(labels_to_json),
+            upstream_repo: params.upstream_repo.clone(),
+            commitment: params.commitment().as_str().to_string(),
+            crate_name: params.crate_name.clone(),
+            crate_version: params.crate_version.clone(),
+            warnings: None,
+        }
+    }
+}
+
+// Params to submit a build again, such as for the periodic re-verification
+impl SolanaProgramBuild {
+    /// `name@version` of the crates.io crate built, for builds of a published crate.
+    pub fn published_crate(&self) -> Option<String> {
+        self.crate_name
+            .as_ref()
+            .zip(self.crate_version.as_ref())
+            .map(|(name, version)| format!("{}@{}", name, version))
+    }
+
+    /// Warnings stored with the build when it completed.
+    pub fn stored_warnings(&self) -> Vec<Warning> {
+        self.warnings
+            .clone()
+            .and_then(|warnings| serde_json::from_value(warnings).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl From<SolanaProgramBuild> for SolanaProgramBuildParams {
+    fn from(build: SolanaProgramBuild) -> Self {
+        SolanaProgramBuildParams {
+            program_id: build.program_id,
+            repository: build.repository,
+            commit_hash: build.commit_hash,
+            lib_name: build.lib_name,
+            base_image: build.base_docker_image,
+            mount_path: build.mount_path,
+            bpf_flag: Some(build.bpf_flag),
+            cargo_args: build.cargo_args,
+            no_default_features: Some(build.no_default_features),
+            workspace_member: build.workspace_member,
+            signature: None,
+            nonce: None,
+            default_ref_strategy: None,
+            vendored: Some(build.vendored),
+            extra_hashes: build.extra_hashes,
+            tag: build.tag,
+            docker_build_args: build
+                .docker_build_args
+                .as_deref()
+                .map(|entries| build_args_to_map(entries).into_iter().collect()),
+            build_system: serde_json::from_value(serde_json::Value::String(build.build_system))
+                .ok(),
+            dockerfile_path: build.dockerfile_path,
+            artifact_path: build.artifact_path,
+            target: serde_json::from_value(serde_json::Value::String(build.target)).ok(),
+            anchor_version: build.anchor_version,
+            cargo_profile: Some(build.cargo_profile),
+            profile_overrides: build
+                .profile_overrides
+                .as_deref()
+                .map(|entries| build_args_to_map(entries).into_iter().collect()),
+            rustflags: build.rustflags,
+            network: Network::from_alias(&build.network),
+            source_date_epoch: build.source_date_epoch,
+            lock_file_content: build.lock_file,
+            language: Some(build.language),
+            labels: build
+                .labels
+                .as_ref()
+                .map(|labels| labels_from_json(Some(labels)).into_iter().collect()),
+            upstream_repo: build.upstream_repo,
+            commitment: serde_json::from_value(serde_json::Value::String(build.commitment)).ok(),
+            crate_name: build.crate_name,
+            crate_version: build.crate_version,
+            tenant_id: Some(build.tenant_id),
         }
     }
 }
@@ -54,6 +207,127 @@ pub struct VerifiedProgram {
     pub executable_hash: String,
     pub verified_at: NaiveDateTime,
     pub solana_build_id: String,
+    // `algorithm:hex` digests of the executable requested through `extra_hashes`
+    pub extra_digests: Option<Vec<String>>,
+    // Loader owning the program when it was verified, e.g. `loader_v4`
+    pub loader: Option<String>,
+    // Whether the executable hash matched the on-chain hash registry entry, if there is one
+    pub registry_match: Option<bool>,
+    // Whether this verification changed `is_verified` compared to the previous one
+    pub status_changed: bool,
+    // Tenant the program is verified for, each tenant has its own verification
+    pub tenant_id: String,
+    // Program id the source declares with `declare_id!`, if it could be read
+    pub declared_id: Option<String>,
+}
+
+impl VerifiedProgram {
+    /// Whether the program id declared by the source is the verified one, if known.
+    pub fn declared_id_matches(&self) -> Option<bool> {
+        self.declared_id
+            .as_ref()
+            .map(|declared_id| declared_id == &self.program_id)
+    }
+}
+
+// Metadata of a build corrected by an operator, leaving the unset fields unchanged
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = solana_program_builds)]
+pub struct BuildMetadataChanges {
+    pub repository: Option<String>,
+    pub lib_name: Option<String>,
+    pub mount_path: Option<String>,
+}
+
+impl From<&UpdateBuildParams> for BuildMetadataChanges {
+    fn from(params: &UpdateBuildParams) -> Self {
+        Self {
+            repository: params.repository.clone(),
+            lib_name: params.lib_name.clone(),
+            mount_path: params.mount_path.clone(),
+        }
+    }
+}
+
+// Append-only record of a phase or status transition of a build
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Identifiable, Queryable)]
+#[diesel(table_name = build_events, primary_key(id))]
+pub struct BuildEvent {
+    pub id: String,
+    pub build_id: String,
+    pub event: String,
+    pub detail: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// Program an operator refuses to verify or report the status of
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Identifiable, Queryable)]
+#[diesel(table_name = denied_programs, primary_key(program_id))]
+pub struct DeniedProgram {
+    pub program_id: String,
+    pub reason: String,
+    pub created_at: NaiveDateTime,
+}
+
+// Executable hash the upgrade authority of a program vouches for, without source. It is a
+// weaker claim than a verification, which rebuilds the program from its source.
+#[derive(
+    Debug, Clone, Serialize, Deserialize, Insertable, Identifiable, Queryable, AsChangeset,
+)]
+#[diesel(table_name = attestations, primary_key(program_id))]
+pub struct Attestation {
+    pub program_id: String,
+    pub executable_hash: String,
+    // Upgrade authority which signed the attestation
+    pub authority: String,
+    pub signature: String,
+    pub attested_at: NaiveDateTime,
+}
+
+// Attribution of a verification to the owners of the repository or domain which claimed
+// the program. Unlike an authority signature, it names who published the program.
+#[derive(
+    Debug, Clone, Serialize, Deserialize, Insertable, Identifiable, Queryable, AsChangeset,
+)]
+#[diesel(table_name = program_claims, primary_key(tenant_id, program_id))]
+pub struct ProgramClaim {
+    #[serde(skip_serializing)]
+    pub tenant_id: String,
+    pub program_id: String,
+    // `repo` or `dns`
+    pub method: String,
+    // Repository URL or domain whose control was proven
+    pub claimed_by: String,
+    // Claim file or TXT record name the proof was found at
+    pub proof: String,
+    pub claimed_at: NaiveDateTime,
+}
+
+// On-chain hash of a program seen while verifying it, one per version deployed
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable)]
+#[diesel(table_name = program_versions)]
+pub struct ProgramVersion {
+    pub tenant_id: String,
+    pub program_id: String,
+    pub on_chain_hash: String,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    // Build whose executable matched this hash, if one did
+    pub verified_build_id: Option<String>,
+    pub verified_at: Option<NaiveDateTime>,
+}
+
+// Webhook notified when the verification status of a program changes
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Identifiable, Queryable)]
+#[diesel(table_name = subscriptions, primary_key(id))]
+pub struct Subscription {
+    pub id: String,
+    pub program_id: String,
+    pub webhook_url: String,
+    pub created_at: NaiveDateTime,
+    // Client which created the subscription, `tenant:<tenant>` or `ip:<address>`
+    #[serde(skip_serializing)]
+    pub owner: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1,18 +1,683 @@
+use std::collections::HashMap;
+use std::env;
+
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::bounded;
+use crate::cargo_profile::DEFAULT_CARGO_PROFILE;
+use crate::language::DEFAULT_LANGUAGE;
+use crate::published_crate::crate_page_url;
+use crate::tenant::DEFAULT_TENANT;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SolanaProgramBuildParams {
+    // Empty when building a published crate, which fills it with the crate's page
+    #[serde(default)]
     pub repository: String,
     pub program_id: String,
     pub commit_hash: Option<String>,
     pub lib_name: Option<String>,
+    // Deprecated alias of `build_system: cargo-bpf`
     pub bpf_flag: Option<bool>,
     pub base_image: Option<String>,
     pub mount_path: Option<String>,
+    #[serde(default, deserialize_with = "bounded::option_vec")]
     pub cargo_args: Option<Vec<String>>,
+    pub no_default_features: Option<bool>,
+    pub workspace_member: Option<String>,
+    // Base58 signature of `claim_message` by the program upgrade authority
+    pub signature: Option<String>,
+    // Nonce from /challenge the signature was made with
+    pub nonce: Option<String>,
+    pub default_ref_strategy: Option<RefStrategy>,
+    pub vendored: Option<bool>,
+    #[serde(default, deserialize_with = "bounded::option_vec")]
+    pub extra_hashes: Option<Vec<String>>,
+    // Git tag to build, resolved to its commit before the build
+    pub tag: Option<String>,
+    // Allowlisted `--build-arg` values passed to docker
+    #[serde(default, deserialize_with = "bounded::option_map")]
+    pub docker_build_args: Option<HashMap<String, String>>,
+    pub build_system: Option<BuildSystem>,
+    // Dockerfile in the repository to build with instead of solana-verify
+    pub dockerfile_path: Option<String>,
+    // Absolute path of the built executable in the image
+    pub artifact_path: Option<String>,
+    pub target: Option<BuildTarget>,
+    // Anchor release installed in the build image, read from Anchor.toml if omitted
+    pub anchor_version: Option<String>,
+    // Cargo profile the program was deployed with, applied onto the release profile
+    pub cargo_profile: Option<String>,
+    // Release profile settings the deployer overrode, such as `opt-level` or `lto`
+    #[serde(default, deserialize_with = "bounded::option_map")]
+    pub profile_overrides: Option<HashMap<String, String>>,
+    // Allowlisted RUSTFLAGS the program was deployed with, e.g. `-C opt-level=3`
+    #[serde(default, deserialize_with = "bounded::option_vec")]
+    pub rustflags: Option<Vec<String>>,
+    // Cluster the program is deployed to, e.g. `mainnet` or its alias `mainnet-beta`
+    pub network: Option<Network>,
+    // Commitment level the deployed program is read at, `finalized` if omitted
+    pub commitment: Option<Commitment>,
+    // Unix time embedded timestamps are pinned to, the commit date if omitted
+    pub source_date_epoch: Option<i64>,
+    // Cargo.lock built with in place of the one in the repository, if any
+    pub lock_file_content: Option<String>,
+    // Language the program is written in, selecting the builder, `rust` if omitted
+    pub language: Option<String>,
+    // Key/value labels organizing verifications, such as `env=prod`, which do not change
+    // the build
+    #[serde(default, deserialize_with = "bounded::option_map")]
+    pub labels: Option<HashMap<String, String>>,
+    // Repository a fork declares it was forked from, recorded for context only: the build
+    // still uses `repository`
+    pub upstream_repo: Option<String>,
+    // Crate published to crates.io built instead of a git repository, with its version
+    pub crate_name: Option<String>,
+    pub crate_version: Option<String>,
+    // Tenant the build is verified for, from the API key of the request
+    #[serde(skip)]
+    pub tenant_id: Option<String>,
+}
+
+impl SolanaProgramBuildParams {
+    /// Build system of the request, falling back to the deprecated `bpf_flag`.
+    pub fn build_system(&self) -> BuildSystem {
+        self.build_system.unwrap_or(if self.bpf_flag == Some(true) {
+            BuildSystem::CargoBpf
+        } else {
+            BuildSystem::CargoSbf
+        })
+    }
+
+    pub fn target(&self) -> BuildTarget {
+        self.target.unwrap_or_default()
+    }
+
+    pub fn network(&self) -> Network {
+        self.network.unwrap_or_default()
+    }
+
+    pub fn commitment(&self) -> Commitment {
+        self.commitment.unwrap_or_default()
+    }
+
+    pub fn cargo_profile(&self) -> &str {
+        self.cargo_profile
+            .as_deref()
+            .unwrap_or(DEFAULT_CARGO_PROFILE)
+    }
+
+    pub fn language(&self) -> &str {
+        self.language.as_deref().unwrap_or(DEFAULT_LANGUAGE)
+    }
+
+    pub fn tenant(&self) -> &str {
+        self.tenant_id.as_deref().unwrap_or(DEFAULT_TENANT)
+    }
+
+    /// Name and version of the crates.io crate to build, if the request gives both.
+    pub fn published_crate(&self) -> Option<(&str, &str)> {
+        self.crate_name
+            .as_deref()
+            .zip(self.crate_version.as_deref())
+    }
+
+    /// Message the upgrade authority signs to claim the verification, binding a nonce from
+    /// /challenge to the source built. Published crates are named by their crates.io page
+    /// and version.
+    pub fn claim_message(&self, nonce: &str) -> String {
+        let (repository, commit) = match self.published_crate() {
+            Some((name, version)) => (crate_page_url(name, version), version),
+            None => (
+                self.repository.clone(),
+                self.commit_hash.as_deref().unwrap_or_default(),
+            ),
+        };
+        format!(
+            "verify {} {} {} {}",
+            self.program_id, repository, commit, nonce
+        )
+    }
+}
+
+// Target triple the program is compiled for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildTarget {
+    #[default]
+    #[serde(rename = "sbf-solana-solana")]
+    Sbf,
+    #[serde(rename = "sbpfv1-solana-solana")]
+    SbpfV1,
+    #[serde(rename = "sbpfv2-solana-solana")]
+    SbpfV2,
+    #[serde(rename = "sbpfv3-solana-solana")]
+    SbpfV3,
+}
+
+impl BuildTarget {
+    pub const ALL: [BuildTarget; 4] = [
+        BuildTarget::Sbf,
+        BuildTarget::SbpfV1,
+        BuildTarget::SbpfV2,
+        BuildTarget::SbpfV3,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildTarget::Sbf => "sbf-solana-solana",
+            BuildTarget::SbpfV1 => "sbpfv1-solana-solana",
+            BuildTarget::SbpfV2 => "sbpfv2-solana-solana",
+            BuildTarget::SbpfV3 => "sbpfv3-solana-solana",
+        }
+    }
+
+    /// `--arch` value selecting the target in `cargo build-sbf`, `None` for its default.
+    pub fn sbf_arch(&self) -> Option<&'static str> {
+        match self {
+            BuildTarget::Sbf => None,
+            BuildTarget::SbpfV1 => Some("v1"),
+            BuildTarget::SbpfV2 => Some("v2"),
+            BuildTarget::SbpfV3 => Some("v3"),
+        }
+    }
+}
+
+// Cluster the program is deployed to. Builds only run against mainnet, but the names
+// clients use for it vary, so they are all accepted and stored under one name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Network {
+    #[default]
+    #[serde(rename = "mainnet")]
+    Mainnet,
+}
+
+// Names accepted for each network, as in `solana config set --url`
+pub const NETWORK_ALIASES: [(&str, Network); 3] = [
+    ("mainnet", Network::Mainnet),
+    ("mainnet-beta", Network::Mainnet),
+    ("m", Network::Mainnet),
+];
+
+impl Network {
+    pub const ALL: [Network; 1] = [Network::Mainnet];
+
+    /// Canonical name of the network, stored with builds and returned in responses.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+        }
+    }
+
+    /// Resolve a network name or one of its aliases, ignoring case.
+    pub fn from_alias(name: &str) -> Option<Network> {
+        let name = name.trim().to_ascii_lowercase();
+        NETWORK_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, network)| *network)
+    }
+
+    /// Moniker selecting the network in the `--url` option of the solana CLIs.
+    pub fn cli_moniker(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "m",
+        }
+    }
+
+    /// RPC endpoint of the network, `RPC_URL` for mainnet.
+    pub fn rpc_url(&self) -> String {
+        match self {
+            Network::Mainnet => env::var("RPC_URL")
+                .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+        }
+    }
+}
+
+impl TryFrom<String> for Network {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Network::from_alias(&name).ok_or_else(|| {
+            format!(
+                "unknown network {}, expected one of {}",
+                name,
+                NETWORK_ALIASES
+                    .iter()
+                    .map(|(alias, _)| *alias)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+    }
+}
+
+// Commitment level of the cluster state the deployed program is read at. `finalized`
+// lags the cluster, so verifying a program deployed moments ago may need `confirmed`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    #[default]
+    Finalized,
+}
+
+impl Commitment {
+    pub const ALL: [Commitment; 3] = [
+        Commitment::Processed,
+        Commitment::Confirmed,
+        Commitment::Finalized,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+}
+
+// Build command template used to build the program
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuildSystem {
+    #[default]
+    CargoSbf,
+    CargoBpf,
+    Anchor,
+    // Build with the default command of `base_image`, which leaves `lib_name` in
+    // target/deploy
+    Custom,
+}
+
+impl BuildSystem {
+    pub const ALL: [BuildSystem; 4] = [
+        BuildSystem::CargoSbf,
+        BuildSystem::CargoBpf,
+        BuildSystem::Anchor,
+        BuildSystem::Custom,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildSystem::CargoSbf => "cargo-sbf",
+            BuildSystem::CargoBpf => "cargo-bpf",
+            BuildSystem::Anchor => "anchor",
+            BuildSystem::Custom => "custom",
+        }
+    }
+
+    /// Whether the build runs `cargo build-bpf` instead of `cargo build-sbf`.
+    pub fn uses_bpf(&self) -> bool {
+        matches!(self, BuildSystem::CargoBpf | BuildSystem::Anchor)
+    }
+}
+
+// What to build when no commit hash is specified
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefStrategy {
+    #[default]
+    Head,
+    LatestTag,
+    LatestRelease,
+}
+
+// Subset of the GitHub push webhook payload
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GithubPushEvent {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub after: String,
+    #[serde(default)]
+    pub deleted: bool,
+    pub repository: GithubRepository,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GithubRepository {
+    pub html_url: String,
+    pub default_branch: String,
+}
+
+// Body of POST /subscriptions
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubscriptionParams {
+    pub program_id: String,
+    pub webhook_url: String,
+}
+
+// Body of POST /attest. The signature is made by the upgrade authority over the message
+// returned by `attestation_message`, with a nonce from /challenge
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AttestParams {
+    pub program_id: String,
+    pub executable_hash: String,
+    pub signature: String,
+    pub nonce: String,
+}
+
+// Body of POST /claim. Ownership is proven by the repository of the verification or by
+// `domain`, depending on `method`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClaimParams {
+    pub program_id: String,
+    pub method: ClaimMethod,
+    // Domain with the TXT record, required by the `dns` method
+    pub domain: Option<String>,
+}
+
+// How the owners of a program prove they control it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaimMethod {
+    // A claim file listing the program on the default branch of the verified repository
+    Repo,
+    // A TXT record listing the program on a subdomain of `domain`
+    Dns,
+}
+
+impl ClaimMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClaimMethod::Repo => "repo",
+            ClaimMethod::Dns => "dns",
+        }
+    }
+}
+
+// Body of PATCH /builds/:id. Only the metadata listed here can be corrected, other fields
+// such as the hashes or the verification result are collected to be rejected
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateBuildParams {
+    pub repository: Option<String>,
+    pub lib_name: Option<String>,
+    pub mount_path: Option<String>,
+    // Replace the labels of the build, which resubmissions deduplicated onto it cannot
+    #[serde(default, deserialize_with = "bounded::option_map")]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct AutoVerifyParams {
+    pub program_id: String,
+}
+
+// Body of POST /status/batch
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchStatusParams {
+    #[serde(deserialize_with = "bounded::vec")]
+    pub addresses: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct VerificationStatusParams {
     pub address: String,
 }
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BytesEncoding {
+    #[default]
+    Base64,
+    Hex,
+}
+
+// How the deployed program is hashed. `trimmed` is the canonical hash, which other tools
+// may compute over more of the account storing the program
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashMode {
+    // The executable without its trailing zero padding, as `solana-verify` hashes it
+    #[default]
+    Trimmed,
+    // The executable with the zero padding up to the size allocated for it
+    Padded,
+    // The whole account storing the executable, including the loader header
+    Account,
+}
+
+impl HashMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashMode::Trimmed => "trimmed",
+            HashMode::Padded => "padded",
+            HashMode::Account => "account",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct StatusParams {
+    #[serde(default)]
+    pub hash_mode: HashMode,
+}
+
+// Request of the /rpc endpoint, alone or in a batch
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: serde_json::Value,
+    // Requests without an id are notifications, which get no response
+    #[serde(default)]
+    pub id: serde_json::Value,
+}
+
+// Params of the `status` method of /rpc
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct RpcStatusParams {
+    pub address: String,
+    #[serde(default)]
+    pub hash_mode: HashMode,
+}
+
+// Params of the `list` method of /rpc
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct RpcListParams {
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct OnchainBytesParams {
+    #[serde(default)]
+    pub encoding: BytesEncoding,
+}
+
+fn default_quickcheck_edge_size() -> usize {
+    256
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct QuickcheckParams {
+    // Number of bytes taken from each end of the executable for the fingerprint
+    #[serde(default = "default_quickcheck_edge_size")]
+    pub bytes: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ResizeWorkersParams {
+    pub size: usize,
+}
+
+// Body of POST /admin/reverify. Each filter given narrows the verifications whose build is
+// re-verified, at least one is required.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ReverifyParams {
+    // Base image the builds were made with, as it was requested
+    pub base_image: Option<String>,
+    // Only verifications made before this time
+    pub older_than: Option<NaiveDateTime>,
+    pub verified_state: Option<VerifiedState>,
+}
+
+impl ReverifyParams {
+    pub fn has_filter(&self) -> bool {
+        self.base_image.is_some() || self.older_than.is_some() || self.verified_state.is_some()
+    }
+}
+
+// Seconds without activity after which an in-progress build is considered stuck
+pub const DEFAULT_STUCK_AFTER_SECS: u64 = 2 * 60 * 60;
+
+// Query of GET /admin/stuck
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct StuckBuildsParams {
+    // Seconds since the last activity of the build, `DEFAULT_STUCK_AFTER_SECS` if omitted
+    pub older_than: Option<u64>,
+}
+
+// Body of POST /admin/stuck/reset
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ResetStuckParams {
+    pub older_than: Option<u64>,
+    #[serde(default)]
+    pub action: StuckAction,
+}
+
+// What to do with stuck builds
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StuckAction {
+    // Mark them failed, so a resubmission builds again
+    #[default]
+    Fail,
+    // Queue them again on the build workers with their params
+    Requeue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifiedState {
+    Verified,
+    Unverified,
+    Any,
+}
+
+// Body of POST /admin/denylist
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct DenyProgramParams {
+    pub program_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct BisectParams {
+    pub good_commit: String,
+    pub bad_commit: String,
+    #[serde(flatten)]
+    pub build: SolanaProgramBuildParams,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ArtifactVerifyParams {
+    pub program_id: String,
+    // URL of the published executable, such as a release asset
+    pub artifact_url: String,
+    // Params to also build the program from source and compare with the artifact
+    pub source: Option<SolanaProgramBuildParams>,
+}
+
+// Body of POST /reproducibility-check
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ReproducibilityCheckParams {
+    pub repository: String,
+    pub commit_hash: Option<String>,
+    // Directory of the program within the repository
+    pub mount_path: Option<String>,
+    // Image the program would be built in
+    pub base_image: Option<String>,
+}
+
+// Body of POST /uploads
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct UploadParams {
+    pub program_id: String,
+    // Size in bytes of the executable which will be uploaded
+    pub size: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeStyle {
+    #[default]
+    Flat,
+    Plastic,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct BadgeParams {
+    #[serde(default)]
+    pub style: BadgeStyle,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct VerifiedProgramsParams {
+    // Negotiated from the Accept header when not given
+    pub format: Option<ListFormat>,
+    // `key=value,key=value` labels the listed verifications must all have
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ExecutableHashParams {
+    pub hash: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_aliases() {
+        for alias in ["mainnet", "mainnet-beta", "m", " Mainnet-Beta "] {
+            assert_eq!(Network::from_alias(alias), Some(Network::Mainnet));
+        }
+        assert_eq!(Network::from_alias("devnet"), None);
+
+        let network: Network = serde_json::from_value(serde_json::json!("mainnet-beta")).unwrap();
+        assert_eq!(serde_json::to_value(network).unwrap(), "mainnet");
+        assert!(serde_json::from_value::<Network>(serde_json::json!("testnet")).is_err());
+    }
+
+    #[test]
+    fn test_claim_message() {
+        let mut params: SolanaProgramBuildParams = serde_json::from_value(serde_json::json!({
+            "repository": "https://github.com/Ellipsis-Labs/phoenix-v1",
+            "program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+            "commit_hash": "d2c5a3b",
+        }))
+        .unwrap();
+        assert_eq!(
+            params.claim_message("2f1c"),
+            "verify PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY \
+             https://github.com/Ellipsis-Labs/phoenix-v1 d2c5a3b 2f1c"
+        );
+
+        params.repository = String::new();
+        params.commit_hash = None;
+        params.crate_name = Some("phoenix-v1".to_string());
+        params.crate_version = Some("0.2.4".to_string());
+        assert_eq!(
+            params.claim_message("2f1c"),
+            format!(
+                "verify PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY {} 0.2.4 2f1c",
+                crate_page_url("phoenix-v1", "0.2.4")
+            )
+        );
+    }
+}
@@ -1,7 +1,12 @@
+use std::collections::BTreeMap;
+
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
-use super::JobStatus;
+use super::{
+    Attestation, BuildEvent, BytesEncoding, DeniedProgram, HashMode, JobStatus, ProgramClaim,
+    StuckAction,
+};
 
 // Types for API responses
 #[derive(Debug, Serialize, Deserialize)]
@@ -11,6 +16,172 @@ pub struct VerificationResponse {
     pub executable_hash: String,
     pub repo_url: String,
     pub last_verified_at: Option<NaiveDateTime>,
+    pub verified_by_authority: bool,
+    pub record_exists: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_hashes: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rent_exempt: Option<bool>,
+    // Current upgrade authority of the program, omitted when it is immutable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_authority: Option<String>,
+    // Whether the program can no longer be upgraded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub immutable: Option<bool>,
+    // Whether the executable hash matches the on-chain hash registry entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_match: Option<bool>,
+    // Commit the requested tag or ref strategy resolved to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_commit: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub docker_build_args: BTreeMap<String, String>,
+    // Target triple the program was built for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    // Canonical name of the cluster the program was verified against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    // Commitment level the deployed program was read at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+    // Normalized RUSTFLAGS the program was built with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rustflags: Vec<String>,
+    // Whether the latest verification changed whether the program is verified
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub status_changed: bool,
+    // Program id the source declares with `declare_id!`, embedded in the executable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_id: Option<String>,
+    // Whether the declared program id is the one verified against, a mismatch means the
+    // program was deployed to another address than its source expects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_id_matches: Option<bool>,
+    // Labels the verification was submitted with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    // Repository the built fork declares as its upstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_repo: Option<String>,
+    // `name@version` of the crates.io crate built instead of a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_crate: Option<String>,
+    pub warnings: Vec<Warning>,
+}
+
+// Responses for the /status/batch endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchStatusEntry {
+    pub program_id: String,
+    #[serde(flatten)]
+    pub status: StatusResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchStatusResponse {
+    pub statuses: Vec<BatchStatusEntry>,
+}
+
+// Loader which owns a deployed program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgramLoader {
+    BpfLoader,
+    BpfLoaderUpgradeable,
+    LoaderV4,
+}
+
+impl ProgramLoader {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProgramLoader::BpfLoader => "bpf_loader",
+            ProgramLoader::BpfLoaderUpgradeable => "bpf_loader_upgradeable",
+            ProgramLoader::LoaderV4 => "loader_v4",
+        }
+    }
+}
+
+// Non-fatal issues detected while building or checking a program
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    UnpinnedCommit,
+    MutableBaseImage,
+    RpcUnreachable,
+    ExtraHashesUnavailable,
+    ProgramClosed,
+    NoRegistryEntry,
+    OnChainCheckDeferred,
+    BuildScript,
+    DeclaredIdMismatch,
+    ResolverUnspecified,
+}
+
+// How much a program status can be trusted: rebuilt from source and matched, only vouched
+// for by its upgrade authority, or neither
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustLevel {
+    SourceVerified,
+    AuthorityAttested,
+    None,
+}
+
+impl TrustLevel {
+    /// Trust level of a program given whether its source was verified and the hash its
+    /// authority attested, which only counts while it is the deployed executable.
+    pub fn of(is_verified: bool, on_chain_hash: &str, attestation: Option<&Attestation>) -> Self {
+        if is_verified {
+            Self::SourceVerified
+        } else if attestation.is_some_and(|attestation| {
+            !on_chain_hash.is_empty() && attestation.executable_hash == on_chain_hash
+        }) {
+            Self::AuthorityAttested
+        } else {
+            Self::None
+        }
+    }
+}
+
+// How trust in a program was established: by rebuilding it from source, comparing an
+// uploaded or published executable with it, or an attestation of its upgrade authority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationMethod {
+    SourceBuild,
+    BinaryUpload,
+    AuthorityAttestation,
+}
+
+impl VerificationMethod {
+    /// Method which established the trust level of a status, if it is trusted at all.
+    pub fn of(trust_level: TrustLevel) -> Option<Self> {
+        match trust_level {
+            TrustLevel::SourceVerified => Some(Self::SourceBuild),
+            TrustLevel::AuthorityAttested => Some(Self::AuthorityAttestation),
+            TrustLevel::None => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +195,24 @@ pub enum Status {
 pub struct ErrorResponse {
     pub status: Status,
     pub error: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub field_errors: Vec<FieldError>,
+}
+
+// A problem with a single request parameter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +223,85 @@ pub struct StatusResponse {
     pub executable_hash: String,
     pub last_verified_at: Option<NaiveDateTime>,
     pub repo_url: String,
+    pub verified_by_authority: bool,
+    pub record_exists: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_hashes: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rent_exempt: Option<bool>,
+    // Current upgrade authority of the program, omitted when it is immutable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_authority: Option<String>,
+    // Whether the program can no longer be upgraded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub immutable: Option<bool>,
+    // Whether the executable hash matches the on-chain hash registry entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_match: Option<bool>,
+    // Commit the requested tag or ref strategy resolved to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_commit: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub docker_build_args: BTreeMap<String, String>,
+    // Target triple the program was built for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    // Canonical name of the cluster the program was verified against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    // Commitment level the deployed program was read at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+    // Normalized RUSTFLAGS the program was built with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rustflags: Vec<String>,
+    // Whether the latest verification changed whether the program is verified
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub status_changed: bool,
+    // Program id the source declares with `declare_id!`, embedded in the executable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_id: Option<String>,
+    // Whether the declared program id is the one verified against, a mismatch means the
+    // program was deployed to another address than its source expects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_id_matches: Option<bool>,
+    // Mode the deployed program was hashed with for `on_chain_mode_hash`, `on_chain_hash` is
+    // always the canonical `trimmed` hash compared with the executable hash
+    #[serde(default)]
+    pub hash_mode: HashMode,
+    // Hash of the deployed program in `hash_mode`, when it is not `trimmed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_chain_mode_hash: Option<String>,
+    // Labels the verification was submitted with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    // Repository the built fork declares as its upstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_repo: Option<String>,
+    // `name@version` of the crates.io crate built instead of a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_crate: Option<String>,
+    // Whether a build of the program is running, so a fresher result is coming
+    #[serde(default)]
+    pub in_progress: bool,
+    // Ids of the running builds of the program, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub in_progress_builds: Vec<String>,
+    pub trust_level: TrustLevel,
+    // How the trust level was established, omitted when the program is not trusted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_method: Option<VerificationMethod>,
+    // Hash attested by the upgrade authority without source, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+    // Repository or domain owners who claimed the program with /claim, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim: Option<ProgramClaim>,
+    pub warnings: Vec<Warning>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,6 +309,36 @@ pub struct VerifyResponse {
     pub status: JobStatus,
     pub request_id: String,
     pub message: String,
+    // Hash of the params compared to deduplicate builds
+    pub params_hash: String,
+}
+
+// Verification result of the build matched by deduplication
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchedVerification {
+    pub is_verified: bool,
+    pub on_chain_hash: String,
+    pub executable_hash: String,
+    pub verified_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchedBuild {
+    pub id: String,
+    pub created_at: NaiveDateTime,
+    pub status: JobStatus,
+    pub params_hash: String,
+    pub verification: Option<MatchedVerification>,
+}
+
+// Returned with 409 when a previous build has the same params
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicateBuildResponse {
+    pub status: JobStatus,
+    pub request_id: String,
+    pub message: String,
+    pub matched_build_id: String,
+    pub matched_build: MatchedBuild,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,6 +346,7 @@ pub struct VerifyResponse {
 pub enum SuccessResponse {
     Status(StatusResponse),
     Verify(VerifyResponse),
+    Duplicate(DuplicateBuildResponse),
 }
 
 impl From<StatusResponse> for SuccessResponse {
@@ -75,6 +374,12 @@ impl From<VerifyResponse> for ApiResponse {
     }
 }
 
+impl From<DuplicateBuildResponse> for ApiResponse {
+    fn from(value: DuplicateBuildResponse) -> Self {
+        Self::Success(SuccessResponse::Duplicate(value))
+    }
+}
+
 impl From<ErrorResponse> for ApiResponse {
     fn from(value: ErrorResponse) -> Self {
         Self::Error(value)
@@ -89,10 +394,526 @@ pub struct JobVerificationResponse {
     pub on_chain_hash: String,
     pub executable_hash: String,
     pub repo_url: String,
+    pub params_hash: String,
+}
+
+// Responses for the /health endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolStatus {
+    pub max_size: usize,
+    pub size: usize,
+    pub idle: usize,
+    pub active: usize,
+}
+
+// Timeouts applied to database connections and queries
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthConfig {
+    pub db_acquire_timeout_ms: u64,
+    // Zero when statements are not timed out
+    pub db_statement_timeout_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub database: PoolStatus,
+    pub redis: PoolStatus,
+    pub config: HealthConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_circuit: Option<CircuitStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+// State of the circuit breaker around the on-chain hash provider
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CircuitStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    // Seconds until fetches are tried again while the breaker is open
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_in_secs: Option<u64>,
+}
+
+// Values accepted for a request param, `default` being used when it is omitted
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParamChoices {
+    pub values: Vec<String>,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaseImageCapabilities {
+    // Any docker image reference is accepted, but only digest pinned ones are reproducible
+    pub any_image: bool,
+    pub digest_pinning_recommended: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuildArgCapabilities {
+    pub keys: Vec<String>,
+    pub max_value_length: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CargoProfileCapabilities {
+    pub default: String,
+    // Release profile settings which can be set through `profile_overrides`
+    pub override_settings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RustflagsCapabilities {
+    // Options accepted as `-C key=value`, besides `--cfg`
+    pub codegen_options: Vec<String>,
+    pub max_flags: usize,
+}
+
+// Response for the /capabilities endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CapabilitiesResponse {
+    pub languages: ParamChoices,
+    pub build_systems: ParamChoices,
+    pub targets: ParamChoices,
+    pub networks: ParamChoices,
+    pub commitments: ParamChoices,
+    pub base_image: BaseImageCapabilities,
+    pub docker_build_args: BuildArgCapabilities,
+    pub cargo_profile: CargoProfileCapabilities,
+    pub rustflags: RustflagsCapabilities,
+    // Hours after which an identical request builds again, `None` when builds
+    // deduplicate forever
+    pub dedup_window_hours: Option<u64>,
+}
+
+// Responses for the /registry endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistryEntryResponse {
+    pub program_id: String,
+    pub repository: String,
+    pub is_verified: bool,
+    pub last_verified_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistryResponse {
+    pub programs: Vec<RegistryEntryResponse>,
+}
+
+// Responses for the /onchain-bytes endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnchainBytesResponse {
+    pub program_id: String,
+    pub encoding: BytesEncoding,
+    pub size: usize,
+    pub data: String,
+}
+
+// Comparison of a published artifact with a build from source
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SourceComparison {
+    pub build_id: String,
+    pub executable_hash: String,
+    pub artifact_matches_source: bool,
+    pub source_matches_on_chain: bool,
+}
+
+// Responses for the /verify/artifact endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArtifactVerificationResponse {
+    pub program_id: String,
+    pub artifact_url: String,
+    pub artifact_hash: String,
+    pub on_chain_hash: String,
+    pub artifact_matches_on_chain: bool,
+    pub verification_method: VerificationMethod,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceComparison>,
+}
+
+// Responses for the /uploads endpoints
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadResponse {
+    pub id: String,
+    pub program_id: String,
+    pub size: u64,
+    // Number of bytes received, where the next chunk starts
+    pub offset: u64,
+    pub complete: bool,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadVerificationResponse {
+    pub program_id: String,
+    pub upload_id: String,
+    pub artifact_hash: String,
+    pub on_chain_hash: String,
+    pub artifact_matches_on_chain: bool,
+    pub verification_method: VerificationMethod,
+}
+
+// Responses for the /quickcheck endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuickcheckResponse {
+    pub program_id: String,
+    pub program_size: u64,
+    pub edge_bytes: usize,
+    pub fingerprint: String,
+}
+
+// Something in a repository which may keep its builds from being reproducible
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardCode {
+    MissingCargoLock,
+    BuildScript,
+    MutableBaseImage,
+    CompileTimeEnv,
+    BuildTimestamp,
+    GitDependency,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HazardSeverity {
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproducibilityHazard {
+    pub code: HazardCode,
+    pub severity: HazardSeverity,
+    // File the hazard was found in, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub message: String,
+    // What to change to remove the hazard
+    pub action: String,
+}
+
+// Responses for the /reproducibility-check endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReproducibilityReport {
+    pub repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_hash: Option<String>,
+    // 100 when no hazard was found, lower the more severe the hazards are
+    pub score: u8,
+    pub hazards: Vec<ReproducibilityHazard>,
+}
+
+// Responses for the /by-hash endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HashMatchedBuild {
+    pub program_id: String,
+    pub build_id: String,
+    pub repository: String,
+    pub commit: Option<String>,
+    pub status: String,
+    pub is_verified: bool,
+    pub on_chain_hash: String,
+    pub verified_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ByHashResponse {
+    pub executable_hash: String,
+    pub builds: Vec<HashMatchedBuild>,
+}
+
+// Responses for the /attest endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttestationResponse {
+    pub trust_level: TrustLevel,
+    // Whether the attested hash is the one currently deployed
+    pub matches_on_chain: bool,
+    pub attestation: Attestation,
+}
+
+// Responses for the /challenge endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallengeResponse {
+    pub program_id: String,
+    pub nonce: String,
+    pub expires_in: u64,
+}
+
+// Responses for the /builds/:id/events endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuildEventsResponse {
+    pub build_id: String,
+    pub events: Vec<BuildEvent>,
+}
+
+// Responses for the /webhook/github endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookResponse {
+    pub message: String,
+    pub program_ids: Vec<String>,
+}
+
+// Responses for the /admin/denylist endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DenylistResponse {
+    // Programs denied by `DENIED_PROGRAM_IDS`, which can not be removed at runtime
+    pub configured: BTreeMap<String, String>,
+    pub denied: Vec<DeniedProgram>,
+}
+
+// Responses for the /admin/workers endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkerPoolStatus {
+    pub size: usize,
+    pub workers: usize,
+    pub active: usize,
+    pub idle: usize,
+    pub queued: usize,
+    pub per_repo_limit: usize,
+    pub draining: bool,
+}
+
+// Whether the instance takes builds, see GET /ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadyState {
+    Ready,
+    // Drained by an operator, finishing the queued and running builds
+    Draining,
+    // Drained with no build left, the instance can be shut down
+    Drained,
+}
+
+// Responses for the /ready and /admin/drain endpoints
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadyResponse {
+    pub state: ReadyState,
+    pub active: usize,
+    pub queued: usize,
+}
+
+// Responses for the /admin/reverify endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReverifyResponse {
+    // Number of builds queued for re-verification
+    pub enqueued: usize,
+}
+
+// Responses for the /status/:address/timeline endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProgramVersionEntry {
+    pub on_chain_hash: String,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    // Whether a build from source matched this version
+    pub is_verified: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<NaiveDateTime>,
+    // Whether this is the version last seen deployed
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusTimelineResponse {
+    pub program_id: String,
+    // Whether every version seen has been verified
+    pub all_verified: bool,
+    pub versions: Vec<ProgramVersionEntry>,
+}
+
+// Responses for the /rpc endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: serde_json::Value,
+}
+
+impl RpcResponse {
+    pub fn new(id: serde_json::Value, outcome: Result<serde_json::Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".to_string(),
+            result,
+            error,
+            id,
+        }
+    }
+}
+
+// Responses for the /admin/stuck endpoints
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StuckBuild {
+    pub id: String,
+    pub program_id: String,
+    pub repository: String,
+    pub created_at: NaiveDateTime,
+    // Time of the last build event, or of the submission if it has none
+    pub last_activity_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StuckBuildsResponse {
+    pub older_than: u64,
+    pub builds: Vec<StuckBuild>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetStuckResponse {
+    pub action: StuckAction,
+    // Ids of the builds reset
+    pub reset: Vec<String>,
+}
+
+// Responses for the /admin/retention endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRun {
+    pub started_at: NaiveDateTime,
+    // Builds created before this were deleted
+    pub cutoff: NaiveDateTime,
+    pub deleted_builds: usize,
+    pub deleted_events: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetentionResponse {
+    pub enabled: bool,
+    pub max_age_days: Option<u64>,
+    pub interval_secs: u64,
+    pub last_run: Option<RetentionRun>,
+}
+
+// Response of the routes when the client accepts none of their media types
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotAcceptableResponse {
+    pub status: Status,
+    pub error: String,
+    pub supported: Vec<String>,
+}
+
+// Responses for the /version endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionResponse {
+    pub version: String,
+    pub git_commit: String,
+    pub built_at: Option<NaiveDateTime>,
+    pub api_versions: Vec<String>,
+}
+
+// Responses for the /limits endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupLimitStatus {
+    pub group: String,
+    pub limit: u32,
+    pub remaining: u32,
+    pub period_secs: u64,
+    pub reset_in_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LimitsResponse {
+    pub enforced: bool,
+    pub limits: Vec<GroupLimitStatus>,
+}
+
+// Responses for the /admin/selftest endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelftestResponse {
+    pub passed: bool,
+    pub program_id: String,
+    pub build_id: String,
+    pub executable_hash: Option<String>,
+    pub on_chain_hash: Option<String>,
+    // Time taken by the whole verification, from the clone to the hash comparison
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+// Responses for the /status/:address/certificate endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateToolchain {
+    pub base_image: Option<String>,
+    pub bpf: bool,
+    pub build_system: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor_version: Option<String>,
+    pub cargo_profile: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile_overrides: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rustflags: Vec<String>,
+    pub lib_name: Option<String>,
+    pub mount_path: Option<String>,
+    pub workspace_member: Option<String>,
+    pub no_default_features: bool,
+    pub vendored: bool,
+    pub cargo_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateSignature {
+    pub algorithm: String,
+    pub public_key: String,
+    pub signature: String,
+}
+
+// Self-contained record of a verification which can be archived independently of the API.
+// Fields are serialized in declaration order so the document is deterministic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationCertificate {
+    pub version: u32,
+    pub program_id: String,
+    pub repository: String,
+    pub commit: Option<String>,
+    pub executable_hash: String,
+    pub on_chain_hash: String,
+    pub is_verified: bool,
+    pub toolchain: CertificateToolchain,
+    pub build_id: String,
+    pub submitted_at: NaiveDateTime,
+    pub verified_at: NaiveDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<CertificateSignature>,
 }
 
 // Responses for the /verified_programs endpoint
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VerifiedProgramListResponse {
     pub verified_programs: Vec<String>,
+    // Labels of the listed programs which have any, by program id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, BTreeMap<String, String>>,
 }
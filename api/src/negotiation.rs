@@ -0,0 +1,176 @@
+use axum::extract::MatchedPath;
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::models::{NotAcceptableResponse, Status};
+
+pub const JSON_MEDIA_TYPE: &str = "application/json";
+pub const CSV_MEDIA_TYPE: &str = "text/csv";
+const SVG_MEDIA_TYPE: &str = "image/svg+xml";
+const TEXT_MEDIA_TYPE: &str = "text/plain";
+const BINARY_MEDIA_TYPE: &str = "application/octet-stream";
+const EVENT_STREAM_MEDIA_TYPE: &str = "text/event-stream";
+// Accepted in place of JSON, for pretty-printed JSON
+const PRETTY_JSON_MEDIA_TYPE: &str = "application/json+pretty";
+
+// Media types of the routes which do not respond with JSON, most preferred first
+const ROUTE_MEDIA_TYPES: [(&str, &[&str]); 6] = [
+    ("/verified-programs", &[JSON_MEDIA_TYPE, CSV_MEDIA_TYPE]),
+    ("/badge/:address", &[SVG_MEDIA_TYPE]),
+    ("/metrics", &[TEXT_MEDIA_TYPE]),
+    ("/builds/:id/logs", &[TEXT_MEDIA_TYPE]),
+    ("/builds/:id/artifact", &[BINARY_MEDIA_TYPE]),
+    ("/verify/:id/events", &[EVENT_STREAM_MEDIA_TYPE]),
+];
+
+/// Media types a route can respond with, most preferred first.
+pub fn supported_media_types(route: &str) -> &'static [&'static str] {
+    ROUTE_MEDIA_TYPES
+        .iter()
+        .find(|(path, _)| *path == route)
+        .map_or(&[JSON_MEDIA_TYPE], |(_, media_types)| *media_types)
+}
+
+// Media ranges of an Accept header with their quality, ignoring other parameters
+fn parse_accept(accept: &str) -> Vec<(String, f32)> {
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_range = parts.next().filter(|range| !range.is_empty())?;
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_range.to_ascii_lowercase(), quality))
+        })
+        .collect()
+}
+
+// How specifically a media range matches a media type, if it does at all
+fn match_specificity(range: &str, media_type: &str) -> Option<u8> {
+    let (media_kind, _) = media_type.split_once('/')?;
+    if range == media_type || (media_type == JSON_MEDIA_TYPE && range == PRETTY_JSON_MEDIA_TYPE) {
+        Some(2)
+    } else if range.strip_suffix("/*") == Some(media_kind) {
+        Some(1)
+    } else if range == "*/*" {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+// Quality the client gives to a media type, from its most specific matching range
+fn quality(ranges: &[(String, f32)], media_type: &str) -> f32 {
+    ranges
+        .iter()
+        .filter_map(|(range, quality)| {
+            match_specificity(range, media_type).map(|specificity| (specificity, *quality))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, quality)| quality)
+}
+
+/// The supported media type the client prefers according to its Accept headers, or the
+/// first one without any. `None` when the client accepts none of them.
+pub fn preferred_media_type(
+    headers: &HeaderMap,
+    supported: &[&'static str],
+) -> Option<&'static str> {
+    let accept = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let ranges = parse_accept(&accept);
+    if ranges.is_empty() {
+        return supported.first().copied();
+    }
+
+    let mut preferred = None;
+    let mut preferred_quality = 0.0;
+    for media_type in supported {
+        let quality = quality(&ranges, media_type);
+        if quality > preferred_quality {
+            preferred = Some(*media_type);
+            preferred_quality = quality;
+        }
+    }
+    preferred
+}
+
+/// Respond with 406 and the supported media types when the Accept headers of the client
+/// rule out every media type the route can respond with.
+pub async fn negotiate_content<B>(request: Request<B>, next: Next<B>) -> Response {
+    let Some(route) = request.extensions().get::<MatchedPath>() else {
+        return next.run(request).await;
+    };
+    let supported = supported_media_types(route.as_str());
+    if request.method() == Method::OPTIONS
+        || preferred_media_type(request.headers(), supported).is_some()
+    {
+        return next.run(request).await;
+    }
+
+    (
+        StatusCode::NOT_ACCEPTABLE,
+        Json(NotAcceptableResponse {
+            status: Status::Error,
+            error: format!(
+                "None of the accepted media types can be served, supported: {}",
+                supported.join(", ")
+            ),
+            supported: supported
+                .iter()
+                .map(|media_type| media_type.to_string())
+                .collect(),
+        }),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_preferred_media_type() {
+        let list = supported_media_types("/verified-programs");
+        assert_eq!(
+            preferred_media_type(&HeaderMap::new(), list),
+            Some(JSON_MEDIA_TYPE)
+        );
+        assert_eq!(
+            preferred_media_type(&accept("*/*"), list),
+            Some(JSON_MEDIA_TYPE)
+        );
+        assert_eq!(
+            preferred_media_type(&accept("text/csv, application/json;q=0.5"), list),
+            Some(CSV_MEDIA_TYPE)
+        );
+        assert_eq!(
+            preferred_media_type(&accept("text/*"), list),
+            Some(CSV_MEDIA_TYPE)
+        );
+        assert_eq!(preferred_media_type(&accept("application/xml"), list), None);
+        assert_eq!(
+            preferred_media_type(&accept("application/json;q=0, */*"), list),
+            Some(CSV_MEDIA_TYPE)
+        );
+        assert_eq!(
+            preferred_media_type(&accept(PRETTY_JSON_MEDIA_TYPE), &[JSON_MEDIA_TYPE]),
+            Some(JSON_MEDIA_TYPE)
+        );
+    }
+}
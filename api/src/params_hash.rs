@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::models::SolanaProgramBuild;
+
+// Params compared to deduplicate builds, as canonical JSON with sorted keys. Every key is
+// always present, with defaults applied and null for the unset optional params, so the
+// same build is described by the same bytes however it was requested.
+fn canonical_params(build: &SolanaProgramBuild) -> String {
+    let params: BTreeMap<&str, Value> = BTreeMap::from([
+        ("anchor_version", json!(build.anchor_version)),
+        ("artifact_path", json!(build.artifact_path)),
+        ("base_image", json!(build.base_docker_image)),
+        ("build_system", json!(build.build_system)),
+        ("cargo_args", json!(build.cargo_args)),
+        ("cargo_profile", json!(build.cargo_profile)),
+        ("commit_hash", json!(build.commit_hash)),
+        ("crate_name", json!(build.crate_name)),
+        ("crate_version", json!(build.crate_version)),
+        ("docker_build_args", json!(build.docker_build_args)),
+        ("dockerfile_path", json!(build.dockerfile_path)),
+        ("language", json!(build.language)),
+        ("lib_name", json!(build.lib_name)),
+        ("lock_file_hash", json!(build.lock_file_hash)),
+        ("mount_path", json!(build.mount_path)),
+        ("network", json!(build.network)),
+        ("no_default_features", json!(build.no_default_features)),
+        ("profile_overrides", json!(build.profile_overrides)),
+        ("program_id", json!(build.program_id)),
+        ("repository", json!(build.repository)),
+        ("rustflags", json!(build.rustflags)),
+        ("source_date_epoch", json!(build.source_date_epoch)),
+        ("tag", json!(build.tag)),
+        ("target", json!(build.target)),
+        ("vendored", json!(build.vendored)),
+        ("workspace_member", json!(build.workspace_member)),
+    ]);
+    serde_json::to_string(&params).unwrap_or_default()
+}
+
+/// Hex encoded sha256 of the canonical params of a build. Two requests are deduplicated
+/// exactly when their params hash is the same.
+pub fn params_hash(build: &SolanaProgramBuild) -> String {
+    hex::encode(Sha256::digest(canonical_params(build).as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SolanaProgramBuildParams;
+
+    fn params() -> SolanaProgramBuildParams {
+        serde_json::from_value(json!({
+            "repository": "https://github.com/Ellipsis-Labs/phoenix-v1",
+            "program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+            "commit_hash": "0d2a2ba",
+            "lib_name": "phoenix",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_canonical_params() {
+        let build = SolanaProgramBuild::from(&params());
+        assert_eq!(
+            canonical_params(&build),
+            "{\"anchor_version\":null,\"artifact_path\":null,\"base_image\":null,\
+             \"build_system\":\"cargo-sbf\",\"cargo_args\":null,\"cargo_profile\":\"release\",\
+             \"commit_hash\":\"0d2a2ba\",\"crate_name\":null,\"crate_version\":null,\
+             \"docker_build_args\":null,\"dockerfile_path\":null,\
+             \"language\":\"rust\",\"lib_name\":\"phoenix\",\"lock_file_hash\":null,\"mount_path\":null,\"network\":\"mainnet\",\
+             \"no_default_features\":false,\
+             \"profile_overrides\":null,\"program_id\":\"PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY\",\
+             \"repository\":\"https://github.com/Ellipsis-Labs/phoenix-v1\",\"rustflags\":null,\
+             \"source_date_epoch\":null,\"tag\":null,\"target\":\"sbf-solana-solana\",\"vendored\":false,\
+             \"workspace_member\":null}"
+        );
+    }
+
+    #[test]
+    fn test_params_hash_applies_defaults() {
+        let mut explicit = params();
+        explicit.vendored = Some(false);
+        explicit.cargo_profile = Some("release".to_string());
+        explicit.signature = Some("ignored".to_string());
+        assert_eq!(
+            params_hash(&SolanaProgramBuild::from(&params())),
+            params_hash(&SolanaProgramBuild::from(&explicit))
+        );
+
+        let mut other = params();
+        other.rustflags = Some(vec!["-C opt-level=3".to_string()]);
+        assert_ne!(
+            params_hash(&SolanaProgramBuild::from(&params())),
+            params_hash(&SolanaProgramBuild::from(&other))
+        );
+    }
+}
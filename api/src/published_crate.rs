@@ -0,0 +1,265 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+
+use crate::errors::ApiError;
+use crate::git::max_repository_size;
+use crate::Result;
+
+// Sparse index of crates.io, listing the checksum of every published version
+const INDEX_URL: &str = "https://index.crates.io";
+// Host serving the `.crate` tarballs of crates.io
+const DOWNLOAD_URL: &str = "https://static.crates.io/crates";
+
+// Identity and date of the commit made of a published crate, so the same tarball always
+// gives the same commit id
+const COMMIT_IDENTITY: [(&str, &str); 6] = [
+    ("GIT_AUTHOR_NAME", "verify"),
+    ("GIT_AUTHOR_EMAIL", "verify@localhost"),
+    ("GIT_AUTHOR_DATE", "@0 +0000"),
+    ("GIT_COMMITTER_NAME", "verify"),
+    ("GIT_COMMITTER_EMAIL", "verify@localhost"),
+    ("GIT_COMMITTER_DATE", "@0 +0000"),
+];
+
+// A version of a crate, as listed in its sparse index file
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+    vers: String,
+    cksum: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// Source of a program published to crates.io, checked out as a local git repository.
+#[derive(Debug)]
+pub struct PublishedCrate {
+    /// Directory of the repository, to remove once built.
+    pub path: PathBuf,
+    /// `file://` URL of the repository, which builds clone like any other.
+    pub repository: String,
+    /// Id of the single commit holding the crate source.
+    pub commit: String,
+    /// sha256 of the `.crate` tarball, as listed in the crates.io index.
+    pub checksum: String,
+    pub yanked: bool,
+}
+
+/// Page of a crate version on crates.io, stored as the repository of builds from it.
+pub fn crate_page_url(name: &str, version: &str) -> String {
+    format!("https://crates.io/crates/{}/{}", name, version)
+}
+
+// Path of the index file of a crate, as laid out by cargo registries
+fn index_path(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+// Find `version` in the lines of a sparse index file
+fn find_version(index: &str, version: &str) -> Option<IndexEntry> {
+    index
+        .lines()
+        .filter_map(|line| serde_json::from_str::<IndexEntry>(line).ok())
+        .find(|entry| entry.vers == version)
+}
+
+async fn index_entry(name: &str, version: &str) -> Result<IndexEntry> {
+    let index = reqwest::Client::new()
+        .get(format!("{}/{}", INDEX_URL, index_path(name)))
+        .header("User-Agent", "solana-verified-programs-api")
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|_| ApiError::PublishedCrate(format!("{} is not published on crates.io", name)))?
+        .text()
+        .await?;
+    find_version(&index, version).ok_or_else(|| {
+        ApiError::PublishedCrate(format!(
+            "{} {} is not published on crates.io",
+            name, version
+        ))
+    })
+}
+
+// Download a `.crate` tarball, failing if it exceeds `MAX_REPO_SIZE` bytes
+async fn download_crate(name: &str, version: &str) -> Result<Vec<u8>> {
+    let max_size = max_repository_size();
+
+    let mut response = reqwest::Client::new()
+        .get(format!(
+            "{}/{}/{}-{}.crate",
+            DOWNLOAD_URL, name, name, version
+        ))
+        .header("User-Agent", "solana-verified-programs-api")
+        .send()
+        .await?
+        .error_for_status()?;
+    if let Some(size) = response.content_length() {
+        if size > max_size {
+            return Err(ApiError::RepositoryTooLarge {
+                size,
+                limit: max_size,
+            });
+        }
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > max_size {
+            return Err(ApiError::RepositoryTooLarge {
+                size: bytes.len() as u64,
+                limit: max_size,
+            });
+        }
+    }
+    Ok(bytes)
+}
+
+async fn run(dir: &Path, program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .kill_on_drop(true)
+        .current_dir(dir)
+        .args(args)
+        .envs(COMMIT_IDENTITY)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::PublishedCrate(format!(
+            "{} {} failed: {}",
+            program,
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(())
+}
+
+async fn rev_parse_head(dir: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .kill_on_drop(true)
+        .current_dir(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ApiError::PublishedCrate(format!(
+            "git rev-parse failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+// Extract the tarball into `dir` and commit its content, ignored files included, as the
+// only commit of a new repository. Returns the directory of the repository.
+async fn commit_tarball(dir: &Path, tarball: &Path, name: &str, version: &str) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await?;
+    let tarball = tarball.to_string_lossy().into_owned();
+    run(dir, "tar", &["-xzf", &tarball, "--no-same-owner"]).await?;
+
+    // Crate tarballs hold a single `{name}-{version}` directory
+    let source = dir.join(format!("{}-{}", name, version));
+    if !tokio::fs::try_exists(&source).await.unwrap_or(false) {
+        return Err(ApiError::PublishedCrate(format!(
+            "{}-{}.crate does not contain {}-{}/",
+            name, version, name, version
+        )));
+    }
+    run(&source, "git", &["init", "--quiet"]).await?;
+    run(&source, "git", &["add", "--all", "--force"]).await?;
+    run(
+        &source,
+        "git",
+        &[
+            "-c",
+            "commit.gpgsign=false",
+            "commit",
+            "--quiet",
+            "--no-verify",
+            "-m",
+            &format!("{} {}", name, version),
+        ],
+    )
+    .await?;
+    Ok(source)
+}
+
+/// Download version `version` of the crate `name` from crates.io, check it against the
+/// checksum in the crates.io index and commit its source to a local repository.
+pub async fn checkout_published_crate(name: &str, version: &str) -> Result<PublishedCrate> {
+    let entry = index_entry(name, version).await?;
+    let tarball = download_crate(name, version).await?;
+
+    let checksum = hex::encode(Sha256::digest(&tarball));
+    if !checksum.eq_ignore_ascii_case(&entry.cksum) {
+        return Err(ApiError::PublishedCrate(format!(
+            "{}-{}.crate has checksum {} but the crates.io index lists {}",
+            name, version, checksum, entry.cksum
+        )));
+    }
+
+    let id = uuid::Uuid::new_v4();
+    let tarball_path = env::temp_dir().join(format!("crate-{}.crate", id));
+    let path = env::temp_dir().join(format!("crate-{}", id));
+    tokio::fs::write(&tarball_path, &tarball).await?;
+    let result = commit_tarball(&path, &tarball_path, name, version).await;
+    let _ = tokio::fs::remove_file(&tarball_path).await;
+
+    let source = match result {
+        Ok(source) => source,
+        Err(err) => {
+            let _ = tokio::fs::remove_dir_all(&path).await;
+            return Err(err);
+        }
+    };
+    let commit = match rev_parse_head(&source).await {
+        Ok(commit) => commit,
+        Err(err) => {
+            let _ = tokio::fs::remove_dir_all(&path).await;
+            return Err(err);
+        }
+    };
+    Ok(PublishedCrate {
+        repository: format!("file://{}", source.display()),
+        path,
+        commit,
+        checksum,
+        yanked: entry.yanked,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_path() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("ab"), "2/ab");
+        assert_eq!(index_path("abc"), "3/a/abc");
+        assert_eq!(index_path("Anchor-Lang"), "an/ch/anchor-lang");
+    }
+
+    #[test]
+    fn test_find_version() {
+        let index = concat!(
+            r#"{"name":"phoenix-v1","vers":"0.2.3","deps":[],"cksum":"aa","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"phoenix-v1","vers":"0.2.4","deps":[],"cksum":"bb","features":{},"yanked":true}"#,
+        );
+        let entry = find_version(index, "0.2.4").unwrap();
+        assert_eq!(entry.cksum, "bb");
+        assert!(entry.yanked);
+        assert!(find_version(index, "0.3.0").is_none());
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, HeaderValue, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use governor::clock::DefaultClock;
+use governor::middleware::StateInformationMiddleware;
+use governor::state::keyed::DefaultKeyedStateStore;
+use governor::{Quota, RateLimiter};
+use tower_governor::key_extractor::{KeyExtractor, SmartIpKeyExtractor};
+
+use crate::access_log::RateLimitReported;
+
+// Headers reported by the per-IP limiter, the same ones the enforcing limiter sets
+const LIMIT_HEADER: &str = "x-ratelimit-limit";
+const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
+// Clients whose observed state is kept before the fully replenished ones are dropped
+const MAX_OBSERVED_CLIENTS: usize = 10_000;
+
+/// Configuration of the per-IP rate limiter.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    /// Whether requests over the limit are rejected. When `false` the limiter only reports
+    /// how throttled a client is and logs the requests it would have rejected.
+    pub enforce: bool,
+}
+
+impl RateLimitConfig {
+    /// Read the config from `RATE_LIMIT_ENFORCE`, enforcing unless it is `false`.
+    pub fn from_env() -> Self {
+        Self {
+            enforce: env::var("RATE_LIMIT_ENFORCE").as_deref() != Ok("false"),
+        }
+    }
+}
+
+type IpRateLimiter =
+    RateLimiter<IpAddr, DefaultKeyedStateStore<IpAddr>, DefaultClock, StateInformationMiddleware>;
+
+/// Per-IP limiter which never rejects requests, used when the limits are not enforced.
+pub struct ReportOnlyLimiter {
+    limiter: IpRateLimiter,
+    burst_size: u32,
+}
+
+impl ReportOnlyLimiter {
+    /// Replenish one request every `period_secs` seconds up to `burst_size`, like the
+    /// enforcing limiter built with the same values.
+    pub fn new(period_secs: u64, burst_size: u32) -> Arc<Self> {
+        let quota = Quota::with_period(Duration::from_secs(period_secs))
+            .expect("rate limit period must not be zero")
+            .allow_burst(NonZeroU32::new(burst_size).expect("burst size must not be zero"));
+        Arc::new(Self {
+            limiter: RateLimiter::keyed(quota).with_middleware::<StateInformationMiddleware>(),
+            burst_size,
+        })
+    }
+}
+
+/// Report the remaining requests of the client in the rate limit headers and log the
+/// requests which would have been rejected, without rejecting them.
+pub async fn report_rate_limit<B>(
+    State(limiter): State<Arc<ReportOnlyLimiter>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Ok(ip) = SmartIpKeyExtractor.extract(&request) else {
+        return next.run(request).await;
+    };
+    let (remaining, would_reject) = match limiter.limiter.check_key(&ip) {
+        Ok(state) => (state.remaining_burst_capacity(), false),
+        Err(_) => {
+            tracing::warn!(
+                "Rate limit would have rejected {} {} from {}",
+                request.method(),
+                request.uri().path(),
+                ip
+            );
+            (0, true)
+        }
+    };
+
+    let mut response = next.run(request).await;
+    if would_reject {
+        response.extensions_mut().insert(RateLimitReported);
+    }
+    let headers = response.headers_mut();
+    headers.insert(LIMIT_HEADER, HeaderValue::from(limiter.burst_size));
+    headers.insert(REMAINING_HEADER, HeaderValue::from(remaining));
+    response
+}
+
+/// Per-IP limit of a group of routes, which share their limiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupLimit {
+    pub group: &'static str,
+    pub period_secs: u64,
+    pub burst_size: u32,
+}
+
+impl GroupLimit {
+    /// Requests left to a client which had `remaining` left `elapsed` ago, as one is
+    /// replenished every period, and the seconds until it has its full burst again.
+    pub fn estimate(&self, remaining: u32, elapsed: Duration) -> (u32, u64) {
+        let period = self.period_secs.max(1);
+        let elapsed = elapsed.as_secs();
+        let replenished = (elapsed / period).min(u64::from(self.burst_size)) as u32;
+        let remaining = remaining.saturating_add(replenished).min(self.burst_size);
+        let missing = u64::from(self.burst_size - remaining);
+        let reset_in_secs = if missing == 0 {
+            0
+        } else {
+            missing * period - elapsed % period
+        };
+        (remaining, reset_in_secs)
+    }
+}
+
+// Remaining requests last reported to a client by the limiter of a group
+#[derive(Debug, Clone, Copy)]
+struct Observation {
+    remaining: u32,
+    at: Instant,
+}
+
+fn groups() -> &'static Mutex<Vec<GroupLimit>> {
+    static GROUPS: OnceLock<Mutex<Vec<GroupLimit>>> = OnceLock::new();
+    GROUPS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn observations() -> &'static Mutex<HashMap<(&'static str, IpAddr), Observation>> {
+    static OBSERVATIONS: OnceLock<Mutex<HashMap<(&'static str, IpAddr), Observation>>> =
+        OnceLock::new();
+    OBSERVATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Register the limit of a group of routes so it is reported by `/limits`.
+pub fn register_group(limit: GroupLimit) {
+    if let Ok(mut groups) = groups().lock() {
+        if !groups.iter().any(|group| group.group == limit.group) {
+            groups.push(limit);
+        }
+    }
+}
+
+/// Remember the remaining requests the limiter of a group reported to the client, from
+/// the rate limit headers it sets, so `/limits` can tell the client without a request
+/// to the group.
+pub async fn observe_rate_limit<B>(
+    State(limit): State<GroupLimit>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let ip = SmartIpKeyExtractor.extract(&request).ok();
+    let response = next.run(request).await;
+    let Some(ip) = ip else {
+        return response;
+    };
+
+    let remaining = if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        Some(0)
+    } else {
+        response
+            .headers()
+            .get(REMAINING_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+    };
+    if let (Some(remaining), Ok(mut observations)) = (remaining, observations().lock()) {
+        if observations.len() >= MAX_OBSERVED_CLIENTS {
+            let groups = groups()
+                .lock()
+                .map(|groups| groups.clone())
+                .unwrap_or_default();
+            observations.retain(|(group, _), observation| {
+                groups.iter().any(|limit| {
+                    limit.group == *group
+                        && limit
+                            .estimate(observation.remaining, observation.at.elapsed())
+                            .0
+                            < limit.burst_size
+                })
+            });
+        }
+        observations.insert(
+            (limit.group, ip),
+            Observation {
+                remaining,
+                at: Instant::now(),
+            },
+        );
+    }
+    response
+}
+
+/// Per-IP allowance of the `verify` calls of `/rpc`, one every 30 seconds like `/verify`.
+/// `/rpc` is served with the status limits, so each call in a request or batch is charged
+/// here before it submits a build.
+pub const RPC_VERIFY_LIMIT: GroupLimit = GroupLimit {
+    group: "rpc_verify",
+    period_secs: 30,
+    burst_size: 1,
+};
+
+/// Per-IP allowance of the `status` calls of `/rpc`, like the status limits. Each call
+/// reads the deployed program, so a batch is charged per call rather than once.
+pub const RPC_STATUS_LIMIT: GroupLimit = GroupLimit {
+    group: "rpc_status",
+    period_secs: 1,
+    burst_size: 100,
+};
+
+fn keyed_limiter(limit: GroupLimit) -> IpRateLimiter {
+    let quota = Quota::with_period(Duration::from_secs(limit.period_secs))
+        .expect("rate limit period must not be zero")
+        .allow_burst(NonZeroU32::new(limit.burst_size).expect("burst size must not be zero"));
+    RateLimiter::keyed(quota).with_middleware::<StateInformationMiddleware>()
+}
+
+fn rpc_verify_limiter() -> &'static IpRateLimiter {
+    static LIMITER: OnceLock<IpRateLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| keyed_limiter(RPC_VERIFY_LIMIT))
+}
+
+fn rpc_status_limiter() -> &'static IpRateLimiter {
+    static LIMITER: OnceLock<IpRateLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| keyed_limiter(RPC_STATUS_LIMIT))
+}
+
+/// Client IP of a request from its forwarding headers or its connection, as the per-IP
+/// limiters key it.
+pub fn client_ip(
+    headers: &HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+) -> Option<IpAddr> {
+    let mut request = Request::new(());
+    *request.headers_mut() = headers.clone();
+    if let Some(connect_info) = connect_info {
+        request.extensions_mut().insert(connect_info);
+    }
+    SmartIpKeyExtractor.extract(&request).ok()
+}
+
+// Spend one call of the `limit` allowance of `ip`, observed for /limits
+fn check_rpc_call(
+    limiter: &IpRateLimiter,
+    limit: GroupLimit,
+    ip: IpAddr,
+    config: RateLimitConfig,
+) -> bool {
+    let (remaining, allowed) = match limiter.check_key(&ip) {
+        Ok(state) => (state.remaining_burst_capacity(), true),
+        Err(_) => (0, false),
+    };
+    if let Ok(mut observations) = observations().lock() {
+        observations.insert(
+            (limit.group, ip),
+            Observation {
+                remaining,
+                at: Instant::now(),
+            },
+        );
+    }
+    if !allowed && !config.enforce {
+        tracing::warn!(
+            "Rate limit would have rejected an /rpc {} call from {}",
+            limit.group,
+            ip
+        );
+        return true;
+    }
+    allowed
+}
+
+/// Spend one `verify` call of the `/rpc` allowance of `ip`. Returns whether the call may
+/// proceed, which it always may when the limits are not enforced.
+pub fn check_rpc_verify(ip: IpAddr, config: RateLimitConfig) -> bool {
+    check_rpc_call(rpc_verify_limiter(), RPC_VERIFY_LIMIT, ip, config)
+}
+
+/// Spend one `status` call of the `/rpc` allowance of `ip`, like `check_rpc_verify`.
+pub fn check_rpc_status(ip: IpAddr, config: RateLimitConfig) -> bool {
+    check_rpc_call(rpc_status_limiter(), RPC_STATUS_LIMIT, ip, config)
+}
+
+/// Current limits of a client in every group, estimated from what the limiters last
+/// reported to it. Groups it has not used yet have their full burst left.
+pub fn client_limits(ip: IpAddr) -> Vec<(GroupLimit, u32, u64)> {
+    let groups = groups()
+        .lock()
+        .map(|groups| groups.clone())
+        .unwrap_or_default();
+    let observations = observations().lock();
+    groups
+        .into_iter()
+        .map(|limit| {
+            let observation = observations
+                .as_ref()
+                .ok()
+                .and_then(|observations| observations.get(&(limit.group, ip)).copied());
+            let (remaining, reset_in_secs) = match observation {
+                Some(observation) => {
+                    limit.estimate(observation.remaining, observation.at.elapsed())
+                }
+                None => (limit.burst_size, 0),
+            };
+            (limit, remaining, reset_in_secs)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_report_only_does_not_reject() {
+        let app =
+            Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(middleware::from_fn_with_state(
+                    ReportOnlyLimiter::new(60, 2),
+                    report_rate_limit,
+                ));
+
+        for expected_remaining in ["1", "0", "0"] {
+            let request = Request::builder()
+                .uri("/")
+                .header("x-forwarded-for", "203.0.113.7")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[LIMIT_HEADER], "2");
+            assert_eq!(response.headers()[REMAINING_HEADER], expected_remaining);
+        }
+    }
+
+    #[test]
+    fn test_estimate_replenishes() {
+        let limit = GroupLimit {
+            group: "verify",
+            period_secs: 30,
+            burst_size: 2,
+        };
+        assert_eq!(limit.estimate(0, Duration::from_secs(0)), (0, 60));
+        assert_eq!(limit.estimate(0, Duration::from_secs(45)), (1, 15));
+        assert_eq!(limit.estimate(0, Duration::from_secs(600)), (2, 0));
+        assert_eq!(limit.estimate(2, Duration::from_secs(5)), (2, 0));
+    }
+}
@@ -0,0 +1,114 @@
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::time::{interval_at, Instant};
+
+use crate::builder::verify_build;
+use crate::db::DbClient;
+use crate::git::normalize_repository;
+use crate::models::{JobStatus, SolanaProgramBuild, SolanaProgramBuildParams};
+use crate::Result;
+
+// Trusted programs which are pinned at compile time and re-verified periodically
+static REGISTRY_JSON: &str = include_str!("../registry.json");
+static REGISTRY: OnceLock<Vec<SolanaProgramBuildParams>> = OnceLock::new();
+
+// Re-verify the registry once a day unless configured otherwise
+const DEFAULT_REGISTRY_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+/// Returns the build params of all programs pinned in `registry.json`.
+pub fn registry_programs() -> &'static [SolanaProgramBuildParams] {
+    REGISTRY.get_or_init(|| {
+        serde_json::from_str(REGISTRY_JSON).expect("registry.json contains invalid build params")
+    })
+}
+
+/// Find the registry programs built from `repository`, ignoring a trailing `/` or `.git`.
+pub fn registry_programs_for_repo(repository: &str) -> Vec<&'static SolanaProgramBuildParams> {
+    let repository = normalize_repository(repository);
+    registry_programs()
+        .iter()
+        .filter(|params| normalize_repository(&params.repository) == repository)
+        .collect()
+}
+
+/// Verify a program in the background.
+pub fn enqueue_verification(db: DbClient, params: SolanaProgramBuildParams) {
+    let repository = params.repository.clone();
+    db.workers.clone().spawn_for_repo(&repository, async move {
+        if let Err(err) = verify_registry_program(&db, &params).await {
+            tracing::error!("Failed to verify program {}: {:?}", params.program_id, err);
+        }
+    });
+}
+
+/// Spawn a background task which periodically enqueues a verification for every
+/// program in the registry. The interval is read from `REGISTRY_INTERVAL_SECS`.
+pub fn spawn_registry_scheduler(db: DbClient) {
+    let period = env::var("REGISTRY_INTERVAL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(DEFAULT_REGISTRY_INTERVAL_SECS));
+
+    tokio::spawn(async move {
+        let mut interval = interval_at(Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            if db.workers.ensure_accepting_builds().is_err() {
+                tracing::info!("Skipping the registry programs while draining");
+                continue;
+            }
+            tracing::info!("Verifying {} registry programs", registry_programs().len());
+            for params in registry_programs() {
+                if let Err(err) = verify_registry_program(&db, params).await {
+                    tracing::error!(
+                        "Failed to verify registry program {}: {:?}",
+                        params.program_id,
+                        err
+                    );
+                }
+            }
+        }
+    });
+}
+
+// Verify a single registry program, reusing its existing build record if there is one
+async fn verify_registry_program(db: &DbClient, params: &SolanaProgramBuildParams) -> Result<()> {
+    db.workers.ensure_accepting_builds()?;
+    let build = match db.check_for_dupliate(params).await {
+        Ok(build) => {
+            if matches!(JobStatus::from(build.status.clone()), JobStatus::InProgress) {
+                tracing::info!(
+                    "Registry program {} is already being verified",
+                    build.program_id
+                );
+                return Ok(());
+            }
+            db.update_build_status(&build.id, JobStatus::InProgress.into())
+                .await?;
+            build
+        }
+        Err(_) => {
+            let build = SolanaProgramBuild::from(params);
+            db.insert_build_params(&build).await?;
+            build
+        }
+    };
+
+    match verify_build(db, params.clone(), &build.id).await {
+        Ok((res, warnings)) => {
+            db.insert_or_update_verified_build(&res).await?;
+            db.set_build_warnings(&build.id, &warnings).await?;
+            db.update_build_status(&build.id, JobStatus::Completed.into())
+                .await?;
+        }
+        Err(err) => {
+            db.update_build_status(&build.id, JobStatus::Failed.into())
+                .await?;
+            return Err(err);
+        }
+    }
+    Ok(())
+}
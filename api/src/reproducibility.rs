@@ -0,0 +1,326 @@
+use std::collections::BTreeSet;
+
+use crate::build_script::find_build_scripts;
+use crate::git::{list_files, read_files};
+use crate::models::{
+    HazardCode, HazardSeverity, ReproducibilityCheckParams, ReproducibilityHazard,
+    ReproducibilityReport,
+};
+use crate::Result;
+
+// Most manifests and source files read from the repository
+const MAX_MANIFESTS: usize = 64;
+const MAX_SOURCE_FILES: usize = 100;
+
+// Crates which embed the time or environment of the build into the executable
+const BUILD_INFO_CRATES: [&str; 5] = ["vergen", "built", "build-time", "compile-time", "shadow-rs"];
+
+// Variables cargo sets to the same value on every machine
+const DETERMINISTIC_ENV_PREFIXES: [&str; 2] = ["CARGO_PKG_", "CARGO_CRATE_NAME"];
+
+fn hazard(
+    code: HazardCode,
+    severity: HazardSeverity,
+    path: Option<&str>,
+    message: String,
+    action: &str,
+) -> ReproducibilityHazard {
+    ReproducibilityHazard {
+        code,
+        severity,
+        path: path.map(ToOwned::to_owned),
+        message,
+        action: action.to_string(),
+    }
+}
+
+// Files of the repository within `mount_path`, or all of them if none is given
+fn files_within(files: &[String], mount_path: Option<&str>) -> Vec<String> {
+    let prefix = mount_path
+        .map(|path| path.trim_matches('/'))
+        .filter(|path| !path.is_empty())
+        .map(|path| format!("{}/", path))
+        .unwrap_or_default();
+    files
+        .iter()
+        .filter(|file| file.starts_with(&prefix))
+        .cloned()
+        .collect()
+}
+
+// Dependencies declared by a manifest, as their name and specification: either the value
+// of an inline entry, or the body of a `[dependencies.name]` table
+fn dependency_entries(manifest: &str) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    let mut in_dependencies = false;
+    let mut table: Option<(String, String)> = None;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            entries.extend(table.take());
+            let header = line.trim_matches(|c| c == '[' || c == ']').trim();
+            in_dependencies = header.ends_with("dependencies") || header.starts_with("patch.");
+            if let Some((_, name)) = header.rsplit_once("dependencies.") {
+                table = Some((name.trim_matches('"').to_string(), String::new()));
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((_, body)) = &mut table {
+            body.push_str(line);
+            body.push('\n');
+        } else if in_dependencies {
+            if let Some((name, spec)) = line.split_once('=') {
+                entries.push((
+                    name.trim().trim_matches('"').to_string(),
+                    spec.trim().to_string(),
+                ));
+            }
+        }
+    }
+    entries.extend(table);
+    entries
+}
+
+// Whether a dependency specification sets `key`
+fn has_key(spec: &str, key: &str) -> bool {
+    spec.split(|c| matches!(c, ',' | '{' | '}' | '\n'))
+        .filter_map(|part| part.split_once('='))
+        .any(|(name, _)| name.trim() == key)
+}
+
+fn manifest_hazards(path: &str, manifest: &str) -> Vec<ReproducibilityHazard> {
+    let mut hazards = Vec::new();
+    for (name, spec) in dependency_entries(manifest) {
+        if has_key(&spec, "git") && !has_key(&spec, "rev") {
+            hazards.push(hazard(
+                HazardCode::GitDependency,
+                HazardSeverity::High,
+                Some(path),
+                format!(
+                    "Dependency {} is fetched from a git branch or tag, which may point to other code on the next build",
+                    name
+                ),
+                "Pin the git dependency to a commit with `rev`, or depend on a published version",
+            ));
+        }
+        if BUILD_INFO_CRATES.contains(&name.as_str()) {
+            hazards.push(hazard(
+                HazardCode::BuildTimestamp,
+                HazardSeverity::High,
+                Some(path),
+                format!(
+                    "Dependency {} embeds build information such as the time of the build",
+                    name
+                ),
+                "Remove the build information from the program, or derive it from SOURCE_DATE_EPOCH",
+            ));
+        }
+    }
+    hazards
+}
+
+// Environment variables read at compile time with `env!` or `option_env!`, other than
+// those cargo sets the same way everywhere
+fn compile_time_env(source: &str) -> BTreeSet<String> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .flat_map(|line| line.split("env!(").skip(1))
+        .filter_map(|rest| {
+            let (name, _) = rest.trim_start().strip_prefix('"')?.split_once('"')?;
+            Some(name.to_string())
+        })
+        .filter(|name| {
+            !DETERMINISTIC_ENV_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        })
+        .collect()
+}
+
+// Deducted from the score for each kind of hazard found
+fn severity_penalty(severity: HazardSeverity) -> u8 {
+    match severity {
+        HazardSeverity::Medium => 15,
+        HazardSeverity::High => 30,
+    }
+}
+
+/// Score of a repository with the given hazards, out of 100. Each kind of hazard only
+/// counts once, however many files it was found in.
+pub fn reproducibility_score(hazards: &[ReproducibilityHazard]) -> u8 {
+    let kinds: BTreeSet<(HazardCode, HazardSeverity)> = hazards
+        .iter()
+        .map(|hazard| (hazard.code, hazard.severity))
+        .collect();
+    kinds.into_iter().fold(100, |score, (_, severity)| {
+        score.saturating_sub(severity_penalty(severity))
+    })
+}
+
+/// Look for what may keep a repository from building reproducibly, without building it.
+pub async fn check_reproducibility(
+    params: &ReproducibilityCheckParams,
+) -> Result<ReproducibilityReport> {
+    let commit = params.commit_hash.as_deref();
+    let files = list_files(&params.repository, commit).await?;
+    let within = files_within(&files, params.mount_path.as_deref());
+    let mut hazards = Vec::new();
+
+    // The lock file may be next to the program or at the root of its workspace
+    if !files
+        .iter()
+        .any(|file| file == "Cargo.lock" || file.ends_with("/Cargo.lock"))
+    {
+        hazards.push(hazard(
+            HazardCode::MissingCargoLock,
+            HazardSeverity::High,
+            None,
+            "No Cargo.lock is committed, so dependencies resolve to their latest versions when built".to_string(),
+            "Commit the Cargo.lock of the workspace",
+        ));
+    }
+
+    for script in find_build_scripts(&within) {
+        hazards.push(hazard(
+            HazardCode::BuildScript,
+            HazardSeverity::Medium,
+            Some(&script),
+            "The build script runs arbitrary code during the build, which may depend on the machine or the network".to_string(),
+            "Remove the build script, or make sure it only reads files of the repository",
+        ));
+    }
+
+    if let Some(base_image) = &params.base_image {
+        if !base_image.contains("@sha256:") {
+            hazards.push(hazard(
+                HazardCode::MutableBaseImage,
+                HazardSeverity::Medium,
+                None,
+                format!(
+                    "Base image {} is not pinned to a digest and may change over time",
+                    base_image
+                ),
+                "Reference the base image by digest, as image@sha256:<digest>",
+            ));
+        }
+    }
+
+    let manifests: Vec<String> = within
+        .iter()
+        .filter(|file| file.ends_with("Cargo.toml"))
+        .take(MAX_MANIFESTS)
+        .cloned()
+        .collect();
+    let sources: Vec<String> = within
+        .iter()
+        .filter(|file| file.ends_with(".rs") && !file.ends_with("build.rs"))
+        .filter(|file| {
+            !file
+                .split('/')
+                .any(|dir| matches!(dir, "tests" | "benches" | "examples" | "target"))
+        })
+        .take(MAX_SOURCE_FILES)
+        .cloned()
+        .collect();
+    let paths: Vec<String> = manifests.iter().chain(&sources).cloned().collect();
+    let contents = read_files(&params.repository, commit, &paths).await?;
+
+    for (path, content) in paths.iter().zip(contents) {
+        let Some(content) = content else {
+            continue;
+        };
+        if path.ends_with("Cargo.toml") {
+            hazards.extend(manifest_hazards(path, &content));
+            continue;
+        }
+        let variables = compile_time_env(&content);
+        if !variables.is_empty() {
+            hazards.push(hazard(
+                HazardCode::CompileTimeEnv,
+                HazardSeverity::Medium,
+                Some(path),
+                format!(
+                    "Reads {} at compile time, which embeds the environment of the build into the executable",
+                    variables.into_iter().collect::<Vec<_>>().join(", ")
+                ),
+                "Replace the environment variables with constants, or make sure they are set the same way in every build",
+            ));
+        }
+    }
+
+    hazards.sort_by(|a, b| b.severity.cmp(&a.severity));
+    Ok(ReproducibilityReport {
+        repository: params.repository.clone(),
+        commit_hash: params.commit_hash.clone(),
+        score: reproducibility_score(&hazards),
+        hazards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_hazards() {
+        let manifest = r#"
+[package]
+name = "phoenix"
+
+[dependencies]
+anchor-lang = "0.29.0"
+spl-token = { git = "https://github.com/solana-labs/solana-program-library", branch = "master" }
+pinned = { git = "https://github.com/ellipsis-labs/pinned", rev = "3742e55" }
+
+[build-dependencies]
+vergen = "8"
+
+[dependencies.sokoban]
+git = "https://github.com/Ellipsis-Labs/sokoban"
+tag = "v0.3.0"
+"#;
+        let hazards: Vec<(HazardCode, String)> = manifest_hazards("Cargo.toml", manifest)
+            .into_iter()
+            .map(|hazard| (hazard.code, hazard.message))
+            .collect();
+        assert_eq!(hazards.len(), 3);
+        assert_eq!(hazards[0].0, HazardCode::GitDependency);
+        assert!(hazards[0].1.contains("spl-token"));
+        assert_eq!(hazards[1].0, HazardCode::BuildTimestamp);
+        assert!(hazards[2].1.contains("sokoban"));
+    }
+
+    #[test]
+    fn test_compile_time_env() {
+        let source = r#"
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const COMMIT: Option<&str> = option_env!("GIT_COMMIT");
+// const BUILT: &str = env!("BUILD_TIME");
+"#;
+        assert_eq!(
+            compile_time_env(source).into_iter().collect::<Vec<_>>(),
+            vec!["GIT_COMMIT"]
+        );
+    }
+
+    #[test]
+    fn test_reproducibility_score() {
+        let build_script = |path: &str| {
+            hazard(
+                HazardCode::BuildScript,
+                HazardSeverity::Medium,
+                Some(path),
+                String::new(),
+                "",
+            )
+        };
+        assert_eq!(reproducibility_score(&[]), 100);
+        assert_eq!(
+            reproducibility_score(&[build_script("a/build.rs"), build_script("b/build.rs")]),
+            85
+        );
+    }
+}
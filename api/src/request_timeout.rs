@@ -0,0 +1,125 @@
+use std::env;
+use std::time::Duration;
+
+use axum::extract::{MatchedPath, State};
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::models::{ErrorResponse, Status};
+
+// Defaults for `REQUEST_TIMEOUT_SECS` and `LONG_REQUEST_TIMEOUT_SECS`
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_LONG_REQUEST_TIMEOUT_SECS: u64 = 30 * 60;
+
+// Routes which build a program or fetch its repository before responding, and get the long
+// timeout
+const LONG_RUNNING_ROUTES: [&str; 10] = [
+    "/verify_sync",
+    "/verify/artifact",
+    "/admin/selftest",
+    "/verify",
+    "/verify/bisect",
+    "/verify/auto",
+    "/reproducibility-check",
+    "/builds/:id/retry",
+    "/rpc",
+    "/claim",
+];
+
+/// How long a request may take before it is aborted with 504.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeouts {
+    pub default: Duration,
+    // For the routes which build a program or fetch its repository before responding
+    pub long: Duration,
+}
+
+fn env_secs(key: &str, default: u64) -> Duration {
+    Duration::from_secs(
+        env::var(key)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(default),
+    )
+}
+
+impl RequestTimeouts {
+    /// Read the timeouts from `REQUEST_TIMEOUT_SECS` and `LONG_REQUEST_TIMEOUT_SECS`.
+    pub fn from_env() -> Self {
+        Self {
+            default: env_secs("REQUEST_TIMEOUT_SECS", DEFAULT_REQUEST_TIMEOUT_SECS),
+            long: env_secs(
+                "LONG_REQUEST_TIMEOUT_SECS",
+                DEFAULT_LONG_REQUEST_TIMEOUT_SECS,
+            ),
+        }
+    }
+
+    /// Timeout of the requests to a route.
+    pub fn of_route(&self, route: Option<&str>) -> Duration {
+        match route {
+            Some(route) if LONG_RUNNING_ROUTES.contains(&route) => self.long,
+            _ => self.default,
+        }
+    }
+}
+
+/// Abort requests which are not answered within the timeout of their route with 504, so
+/// slow handlers cannot hold connections forever. Only producing the response is timed,
+/// streamed bodies such as server-sent events are not cut.
+pub async fn enforce_request_timeout<B>(
+    State(timeouts): State<RequestTimeouts>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let timeout = timeouts.of_route(route.as_deref());
+
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!(
+                "Request to {} timed out after {}s",
+                route.as_deref().unwrap_or("unmatched route"),
+                timeout.as_secs()
+            );
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                Json(ErrorResponse {
+                    status: Status::Error,
+                    error: format!("Request timed out after {}s", timeout.as_secs()),
+                    field_errors: Vec::new(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_timeouts() {
+        let timeouts = RequestTimeouts {
+            default: Duration::from_secs(30),
+            long: Duration::from_secs(1800),
+        };
+        assert_eq!(timeouts.of_route(Some("/verify_sync")), timeouts.long);
+        for route in ["/builds/:id/retry", "/verify/auto", "/rpc", "/claim"] {
+            assert_eq!(timeouts.of_route(Some(route)), timeouts.long);
+        }
+        assert_eq!(
+            timeouts.of_route(Some("/status/:address")),
+            timeouts.default
+        );
+        assert_eq!(timeouts.of_route(None), timeouts.default);
+    }
+}
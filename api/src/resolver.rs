@@ -0,0 +1,124 @@
+use crate::errors::ApiError;
+use crate::git::BareClone;
+use crate::Result;
+
+/// Version of cargo's feature resolver a project builds with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureResolver {
+    pub version: String,
+    // Whether Cargo.toml sets `resolver`, rather than cargo picking the default
+    pub declared: bool,
+    // Edition the default was derived from, `None` for a virtual workspace
+    pub edition: Option<String>,
+}
+
+impl FeatureResolver {
+    /// Description of the resolver for the build events.
+    pub fn describe(&self) -> String {
+        match (self.declared, &self.edition) {
+            (true, _) => format!("{} (declared)", self.version),
+            (false, Some(edition)) => format!("{} (default of edition {})", self.version, edition),
+            (false, None) => format!("{} (default of virtual workspaces)", self.version),
+        }
+    }
+}
+
+// Resolver cargo picks for a package of an edition when none is declared
+fn edition_resolver(edition: &str) -> &'static str {
+    match edition {
+        "2015" | "2018" => "1",
+        "2021" => "2",
+        _ => "3",
+    }
+}
+
+// Resolver of a Cargo.toml: the one declared for the workspace or the package, or else
+// the default of the edition of the package. Virtual workspaces default to resolver 1
+// whatever the edition of their members.
+fn parse_feature_resolver(cargo_toml: &str) -> Result<FeatureResolver> {
+    let manifest: toml::Table = toml::from_str(cargo_toml)
+        .map_err(|err| ApiError::Build(format!("Failed to parse Cargo.toml: {}", err)))?;
+    let workspace = manifest.get("workspace");
+    let package = manifest.get("package");
+
+    let declared = workspace
+        .and_then(|workspace| workspace.get("resolver"))
+        .or_else(|| package.and_then(|package| package.get("resolver")))
+        .and_then(toml::Value::as_str);
+    if let Some(version) = declared {
+        return Ok(FeatureResolver {
+            version: version.to_string(),
+            declared: true,
+            edition: None,
+        });
+    }
+
+    let Some(package) = package else {
+        return Ok(FeatureResolver {
+            version: "1".to_string(),
+            declared: false,
+            edition: None,
+        });
+    };
+    // `edition.workspace = true` inherits the edition of the workspace
+    let edition = match package.get("edition") {
+        Some(toml::Value::String(edition)) => Some(edition.as_str()),
+        Some(toml::Value::Table(_)) => workspace
+            .and_then(|workspace| workspace.get("package"))
+            .and_then(|package| package.get("edition"))
+            .and_then(toml::Value::as_str),
+        _ => None,
+    }
+    .unwrap_or("2015");
+    Ok(FeatureResolver {
+        version: edition_resolver(edition).to_string(),
+        declared: false,
+        edition: Some(edition.to_string()),
+    })
+}
+
+/// Read the feature resolver of the project at `commit` from its Cargo.toml, found at the
+/// root of the repository or of `mount_path`.
+pub async fn read_feature_resolver(
+    clone: &BareClone,
+    commit: Option<&str>,
+    mount_path: Option<&str>,
+) -> Result<FeatureResolver> {
+    let path = match mount_path.map(|path| path.trim_matches('/')) {
+        Some(mount_path) if !mount_path.is_empty() => format!("{}/Cargo.toml", mount_path),
+        _ => "Cargo.toml".to_string(),
+    };
+    let cargo_toml = clone
+        .read_file(commit, &path)
+        .await?
+        .ok_or_else(|| ApiError::Build(format!("{} not found in the repository", path)))?;
+    parse_feature_resolver(&cargo_toml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_feature_resolver() {
+        let declared =
+            parse_feature_resolver("[workspace]\nmembers = [\"programs/*\"]\nresolver = \"2\"\n")
+                .unwrap();
+        assert_eq!(declared.describe(), "2 (declared)");
+
+        let virtual_workspace =
+            parse_feature_resolver("[workspace]\nmembers = [\"programs/*\"]\n").unwrap();
+        assert_eq!(virtual_workspace.version, "1");
+        assert!(!virtual_workspace.declared);
+
+        let package =
+            parse_feature_resolver("[package]\nname = \"phoenix\"\nedition = \"2021\"\n").unwrap();
+        assert_eq!(package.describe(), "2 (default of edition 2021)");
+
+        let inherited = parse_feature_resolver(
+            "[workspace.package]\nedition = \"2018\"\n\n[package]\nname = \"phoenix\"\nedition.workspace = true\n",
+        )
+        .unwrap();
+        assert_eq!(inherited.version, "1");
+    }
+}
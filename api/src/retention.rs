@@ -0,0 +1,119 @@
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::{interval_at, Instant};
+
+use crate::db::DbClient;
+use crate::models::RetentionRun;
+use crate::Result;
+
+// Apply the retention policy once a day unless configured otherwise
+const DEFAULT_RETENTION_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+// Builds deleted per query, so a first run on a large database does not hold long locks
+const RETENTION_BATCH_SIZE: i64 = 1000;
+
+// Outcome of the last time the policy was applied, reported by /admin/retention
+static LAST_RUN: Mutex<Option<RetentionRun>> = Mutex::new(None);
+
+/// How long build records are kept, from `RETENTION_DAYS` and `RETENTION_INTERVAL_SECS`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    // Builds older than this are deleted, none are when unset
+    pub max_age_days: Option<u64>,
+    pub interval: Duration,
+}
+
+// A missing, invalid or zero `RETENTION_DAYS` keeps builds forever
+fn parse_max_age_days(value: Option<&str>) -> Option<u64> {
+    value
+        .and_then(|value| value.parse().ok())
+        .filter(|days| *days > 0)
+}
+
+impl RetentionPolicy {
+    pub fn from_env() -> Self {
+        Self {
+            max_age_days: parse_max_age_days(env::var("RETENTION_DAYS").ok().as_deref()),
+            interval: Duration::from_secs(
+                env::var("RETENTION_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(DEFAULT_RETENTION_INTERVAL_SECS),
+            ),
+        }
+    }
+}
+
+/// Outcome of the last run of the retention policy, if it ran since the server started.
+pub fn last_run() -> Option<RetentionRun> {
+    LAST_RUN.lock().map(|run| run.clone()).unwrap_or_default()
+}
+
+/// Delete the builds older than `max_age_days` in batches, keeping the latest build of each
+/// program and the builds of the current verifications.
+pub async fn apply_retention(db: &DbClient, max_age_days: u64) -> Result<RetentionRun> {
+    let started_at = chrono::Utc::now().naive_utc();
+    let cutoff = started_at - chrono::Duration::days(max_age_days as i64);
+
+    let mut run = RetentionRun {
+        started_at,
+        cutoff,
+        deleted_builds: 0,
+        deleted_events: 0,
+    };
+    loop {
+        let (builds, events) = db
+            .delete_expired_builds(cutoff, RETENTION_BATCH_SIZE)
+            .await?;
+        run.deleted_builds += builds;
+        run.deleted_events += events;
+        if builds < RETENTION_BATCH_SIZE as usize {
+            break;
+        }
+    }
+
+    if let Ok(mut last_run) = LAST_RUN.lock() {
+        *last_run = Some(run.clone());
+    }
+    Ok(run)
+}
+
+/// Spawn a background task which periodically applies the retention policy, unless no
+/// `RETENTION_DAYS` is configured.
+pub fn spawn_retention_scheduler(db: DbClient) {
+    let policy = RetentionPolicy::from_env();
+    let Some(max_age_days) = policy.max_age_days else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut interval = interval_at(Instant::now() + policy.interval, policy.interval);
+        loop {
+            interval.tick().await;
+            match apply_retention(&db, max_age_days).await {
+                Ok(run) => tracing::info!(
+                    "Deleted {} builds and {} build events older than {}",
+                    run.deleted_builds,
+                    run.deleted_events,
+                    run.cutoff
+                ),
+                Err(err) => tracing::error!("Failed to apply the retention policy: {}", err),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_max_age_days() {
+        assert_eq!(parse_max_age_days(Some("90")), Some(90));
+        assert_eq!(parse_max_age_days(Some("0")), None);
+        assert_eq!(parse_max_age_days(Some("-1")), None);
+        assert_eq!(parse_max_age_days(None), None);
+    }
+}
@@ -1,30 +1,110 @@
+mod admin;
+mod attest;
+mod badge;
+mod build_events;
+mod build_output;
+mod build_retry;
+mod by_hash;
+mod capabilities;
+mod certificate;
+mod challenge;
+mod claim;
+mod health;
 mod job;
+mod limits;
+mod metrics;
+mod onchain_bytes;
+mod quickcheck;
+mod registry;
+mod reproducibility;
+mod rpc;
 mod status;
+mod subscriptions;
+mod timeline;
+mod uploads;
 mod verified_programs;
+mod verify_artifact;
 mod verify_async;
+mod verify_auto;
+mod verify_bisect;
+mod verify_events;
 mod verify_sync;
+mod version;
+mod webhook;
+use crate::access_log::{audit_access, log_access};
 use crate::db::DbClient;
+use crate::metrics::record_request_timing;
+use crate::negotiation::negotiate_content;
+use crate::rate_limit::{
+    observe_rate_limit, register_group, report_rate_limit, GroupLimit, RateLimitConfig,
+    ReportOnlyLimiter, RPC_STATUS_LIMIT, RPC_VERIFY_LIMIT,
+};
+use crate::request_timeout::{enforce_request_timeout, RequestTimeouts};
 use crate::routes::{
-    job::get_job_status, status::verify_status, verified_programs::get_verified_programs_list,
-    verify_async::verify_async, verify_sync::verify_sync,
+    admin::{
+        allow_program, deny_program, drain, get_denylist, get_retention, get_stuck_builds,
+        get_workers, reset_stuck_builds, resize_workers, reverify, run_selftest, update_build,
+    },
+    attest::attest_program,
+    badge::get_badge,
+    build_events::get_build_events,
+    build_output::{get_build_artifact, get_build_logs},
+    build_retry::retry_build,
+    by_hash::get_builds_by_hash,
+    capabilities::get_capabilities,
+    certificate::get_certificate,
+    challenge::get_challenge,
+    claim::claim_program,
+    health::{get_health, get_ready},
+    job::get_job_status,
+    limits::get_limits,
+    metrics::get_metrics,
+    onchain_bytes::get_onchain_bytes,
+    quickcheck::get_quickcheck,
+    registry::get_registry,
+    reproducibility::reproducibility_check,
+    rpc::rpc,
+    status::{get_status_batch, verify_status},
+    subscriptions::{create_subscription, delete_subscription},
+    timeline::get_status_timeline,
+    uploads::{append_upload_chunk, create_upload, get_upload, verify_upload},
+    verified_programs::get_verified_programs_list,
+    verify_artifact::verify_artifact,
+    verify_async::verify_async,
+    verify_auto::verify_auto,
+    verify_bisect::verify_bisect,
+    verify_events::stream_build_events,
+    verify_sync::verify_sync,
+    version::get_version,
+    webhook::github_webhook,
 };
+use crate::tenant::API_KEY_HEADER;
 use axum::{
+    body::{boxed, Full},
     error_handling::HandleErrorLayer,
-    http::{Method, StatusCode},
-    routing::{get, post},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post},
     BoxError, Json, Router,
 };
 use serde_json::{json, Value};
 use std::sync::OnceLock;
 use std::time::Duration;
-use tower::{buffer::BufferLayer, limit::RateLimitLayer, ServiceBuilder};
+use tower::{
+    buffer::BufferLayer,
+    limit::RateLimitLayer,
+    util::{Either, MapResponseLayer},
+    ServiceBuilder,
+};
 use tower_governor::{
     governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor, GovernorLayer,
 };
 use tower_http::{
     compression::CompressionLayer,
     cors::{Any, CorsLayer},
-    trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer},
+    sensitive_headers::SetSensitiveRequestHeadersLayer,
+    trace::{DefaultMakeSpan, DefaultOnRequest, TraceLayer},
 };
 use tracing::Level;
 
@@ -45,104 +125,821 @@ pub fn create_router(db: DbClient) -> Router {
             .layer(RateLimitLayer::new(req_per_sec, Duration::from_secs(1)))
     };
 
-    let rate_limit_per_ip = |timeout: u64, limit: u32| {
-        let config = Box::new(
-            GovernorConfigBuilder::default()
-                .per_second(timeout)
-                .burst_size(limit)
-                .use_headers()
-                .key_extractor(SmartIpKeyExtractor)
-                .finish()
-                .unwrap(),
-        );
+    // In report-only mode the limits are only reported and logged, to tune them against
+    // real traffic before enforcing them. What the limiter of each group reports to a
+    // client is observed so /limits can tell it without spending its allowance.
+    let rate_limit_config = RateLimitConfig::from_env();
+    register_group(RPC_VERIFY_LIMIT);
+    register_group(RPC_STATUS_LIMIT);
+    let rate_limit_per_ip = |group: &'static str, timeout: u64, limit: u32| {
+        let group_limit = GroupLimit {
+            group,
+            period_secs: timeout,
+            burst_size: limit,
+        };
+        register_group(group_limit);
+        let limiter = if rate_limit_config.enforce {
+            let config = Box::new(
+                GovernorConfigBuilder::default()
+                    .per_second(timeout)
+                    .burst_size(limit)
+                    .use_headers()
+                    .key_extractor(SmartIpKeyExtractor)
+                    .finish()
+                    .unwrap(),
+            );
+            // Boxes the body like the report-only limiter, so both arms are the same service
+            Either::A(
+                ServiceBuilder::new()
+                    .layer(MapResponseLayer::new(IntoResponse::into_response))
+                    .layer(GovernorLayer {
+                        config: Box::leak(config),
+                    }),
+            )
+        } else {
+            Either::B(middleware::from_fn_with_state(
+                ReportOnlyLimiter::new(timeout, limit),
+                report_rate_limit,
+            ))
+        };
 
         ServiceBuilder::new()
+            .layer(middleware::from_fn_with_state(
+                group_limit,
+                observe_rate_limit,
+            ))
             .layer(error_handler())
-            .layer(GovernorLayer {
-                config: Box::leak(config),
-            })
+            .layer(limiter)
     };
 
-    let cors = |method: Method| {
-        ServiceBuilder::new().layer(CorsLayer::new().allow_methods(method).allow_origin(Any))
+    let cors = |methods: &[Method]| {
+        ServiceBuilder::new().layer(
+            CorsLayer::new()
+                .allow_methods(methods.to_vec())
+                .allow_origin(Any),
+        )
     };
 
     let trace_layer = TraceLayer::new_for_http()
         .make_span_with(DefaultMakeSpan::new().include_headers(true))
         .on_request(DefaultOnRequest::new().level(Level::INFO))
-        .on_response(DefaultOnResponse::new().level(Level::INFO));
+        .on_response(log_access);
+
+    // Uploads are sent in many small requests, so they get their own limits, applied to
+    // their own router as `layer` wraps every route added before it
+    let uploads = Router::new()
+        .route("/uploads", post(create_upload))
+        .route("/uploads/:id", get(get_upload).patch(append_upload_chunk))
+        .route("/uploads/:id/verify", post(verify_upload))
+        .layer(
+            global_rate_limit(100)
+                .layer(rate_limit_per_ip("uploads", 1, 20))
+                .layer(cors(&[Method::GET, Method::POST, Method::PATCH]))
+                .layer(compression_layer())
+                .layer(middleware::from_fn(pretty_json))
+                .layer(middleware::from_fn(cache_control)),
+        );
+
+    // GitHub sends every delivery from a few shared addresses, and each one is signed, so
+    // the webhook is not held to the build limits of /verify
+    let webhooks = Router::new()
+        .route("/webhook/github", post(github_webhook))
+        .layer(
+            global_rate_limit(10)
+                .layer(rate_limit_per_ip("webhook", 1, 50))
+                .layer(cors(&[Method::POST]))
+                .layer(compression_layer())
+                .layer(middleware::from_fn(pretty_json))
+                .layer(middleware::from_fn(cache_control)),
+        );
+
+    // Requests proven by the upgrade authority or the owners of a program do not start
+    // builds, so they are not held to the build limits of /verify either
+    let owners = Router::new()
+        .route("/attest", post(attest_program))
+        .route("/claim", post(claim_program))
+        .layer(
+            global_rate_limit(10)
+                .layer(rate_limit_per_ip("owners", 2, 10))
+                .layer(cors(&[Method::POST]))
+                .layer(compression_layer())
+                .layer(middleware::from_fn(pretty_json))
+                .layer(middleware::from_fn(cache_control)),
+        );
 
     Router::new()
         .route("/", get(|| async { index() }))
         .route("/verify", post(verify_async))
         .route("/verify_sync", post(verify_sync))
+        .route("/verify/auto", post(verify_auto))
+        .route("/verify/bisect", post(verify_bisect))
+        .route("/verify/artifact", post(verify_artifact))
+        .route("/reproducibility-check", post(reproducibility_check))
+        .route("/builds/:id/retry", post(retry_build))
+        .route("/subscriptions", post(create_subscription))
+        .route("/subscriptions/:id", delete(delete_subscription))
         .layer(
             global_rate_limit(1)
-                .layer(rate_limit_per_ip(30, 1))
-                .layer(cors(Method::POST))
-                .layer(CompressionLayer::new().zstd(true)),
+                .layer(rate_limit_per_ip("verify", 30, 1))
+                .layer(cors(&[Method::POST, Method::DELETE]))
+                .layer(compression_layer())
+                .layer(middleware::from_fn(pretty_json))
+                .layer(middleware::from_fn(cache_control)),
         )
         .route("/status/:address", get(verify_status))
+        .route("/status/batch", post(get_status_batch))
+        // Its verify and status calls are also charged to the `rpc_verify` and `rpc_status`
+        // limits one by one
+        .route("/rpc", post(rpc))
         .layer(
             global_rate_limit(10000)
-                .layer(rate_limit_per_ip(1, 100))
-                .layer(cors(Method::GET))
-                .layer(CompressionLayer::new().zstd(true)),
+                .layer(rate_limit_per_ip("status", 1, 100))
+                .layer(cors(&[Method::GET, Method::POST]))
+                .layer(compression_layer())
+                .layer(middleware::from_fn(pretty_json))
+                .layer(middleware::from_fn(cache_control)),
         )
         .route("/job/:job_id", get(get_job_status))
         .layer(
             global_rate_limit(10000)
-                .layer(rate_limit_per_ip(1, 100))
-                .layer(cors(Method::GET))
-                .layer(CompressionLayer::new().zstd(true)),
+                .layer(rate_limit_per_ip("jobs", 1, 100))
+                .layer(cors(&[Method::GET]))
+                .layer(compression_layer())
+                .layer(middleware::from_fn(pretty_json))
+                .layer(middleware::from_fn(cache_control)),
         )
         .route("/verified-programs", get(get_verified_programs_list))
+        .route("/health", get(get_health))
+        .route("/registry", get(get_registry))
+        .route("/onchain-bytes/:address", get(get_onchain_bytes))
+        .route("/quickcheck/:address", get(get_quickcheck))
+        .route("/challenge/:program_id", get(get_challenge))
+        .route("/builds/:id/events", get(get_build_events))
+        .route("/badge/:address", get(get_badge))
+        .route("/by-hash/:hash", get(get_builds_by_hash))
+        .route("/status/:address/certificate", get(get_certificate))
+        .route("/status/:address/timeline", get(get_status_timeline))
+        .route("/admin/workers", get(get_workers).post(resize_workers))
+        .route("/admin/denylist", get(get_denylist).post(deny_program))
+        .route("/admin/denylist/:program_id", delete(allow_program))
+        .route("/admin/retention", get(get_retention))
+        .route("/admin/selftest", get(run_selftest))
+        .route("/admin/drain", post(drain))
+        .route("/admin/reverify", post(reverify))
+        .route("/admin/stuck", get(get_stuck_builds))
+        .route("/admin/stuck/reset", post(reset_stuck_builds))
+        .route("/builds/:id", patch(update_build))
+        .route("/verify/:id/events", get(stream_build_events))
+        .route("/builds/:id/logs", get(get_build_logs))
+        .route("/builds/:id/artifact", get(get_build_artifact))
+        .route("/capabilities", get(get_capabilities))
+        .route("/metrics", get(get_metrics))
+        .route("/limits", get(get_limits))
         .layer(
             global_rate_limit(10000)
-                .layer(rate_limit_per_ip(1, 100))
-                .layer(cors(Method::GET))
-                .layer(CompressionLayer::new().zstd(true)),
+                .layer(rate_limit_per_ip("default", 1, 100))
+                .layer(cors(&[Method::GET]))
+                .layer(compression_layer())
+                .layer(middleware::from_fn(pretty_json))
+                .layer(middleware::from_fn(cache_control)),
         )
+        // Merged after the layers above, so only these routes have their own limits
+        .merge(uploads)
+        .merge(webhooks)
+        .merge(owners)
+        // Added after the route groups so they are not rate limited
+        .route("/version", get(get_version))
+        .route("/ready", get(get_ready))
+        .layer(middleware::from_fn_with_state(
+            RequestTimeouts::from_env(),
+            enforce_request_timeout,
+        ))
+        .layer(middleware::from_fn(negotiate_content))
+        .layer(middleware::from_fn(record_request_timing))
+        .layer(middleware::from_fn(audit_access))
         .layer(trace_layer)
+        // Credentials are logged as `Sensitive` in the request headers of the trace span
+        .layer(SetSensitiveRequestHeadersLayer::new([
+            header::AUTHORIZATION,
+            HeaderName::from_static(API_KEY_HEADER),
+        ]))
         .with_state(db)
 }
 
+// Negotiate the best encoding supported by the client from Accept-Encoding
+fn compression_layer() -> CompressionLayer {
+    CompressionLayer::new().gzip(true).br(true).zstd(true)
+}
+
+// Cache-Control values of the cache policies
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=86400, immutable";
+const SHORT_CACHE_CONTROL: &str = "public, max-age=10";
+const NO_CACHE_CONTROL: &str = "no-cache";
+const NO_STORE_CACHE_CONTROL: &str = "no-store";
+
+/// How long clients and CDNs may cache a response. Handlers attach it as a response
+/// extension; responses without one are revalidated on every request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CachePolicy {
+    // Results which no longer change, such as a verified status. Programs are re-verified
+    // every 24 hours, which bounds how long a stale result can be served.
+    Immutable,
+    // Results which are expected to change soon, such as an unverified or in-progress status
+    Short,
+}
+
+// Cache-Control header for a response with the given status and policy
+fn cache_control_value(status: StatusCode, policy: Option<CachePolicy>) -> &'static str {
+    if !status.is_success() {
+        return NO_STORE_CACHE_CONTROL;
+    }
+    match policy {
+        Some(CachePolicy::Immutable) => IMMUTABLE_CACHE_CONTROL,
+        Some(CachePolicy::Short) => SHORT_CACHE_CONTROL,
+        None => NO_CACHE_CONTROL,
+    }
+}
+
+/// Set the Cache-Control header from the `CachePolicy` of the response, unless the
+/// handler already set one.
+async fn cache_control<B>(request: Request<B>, next: Next<B>) -> Response {
+    let mut response = next.run(request).await;
+    if !response.headers().contains_key(header::CACHE_CONTROL) {
+        let value = cache_control_value(
+            response.status(),
+            response.extensions().get::<CachePolicy>().copied(),
+        );
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static(value));
+    }
+    // Responses are scoped to the tenant of the API key, so caches must not share them
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static(API_KEY_HEADER));
+    response
+}
+
+// Media type which can be sent in the Accept header to request pretty-printed JSON
+const PRETTY_JSON_MEDIA_TYPE: &str = "application/json+pretty";
+
+// Whether the client asked for pretty-printed JSON with `?pretty=true` or the Accept header
+fn wants_pretty_json(query: Option<&str>, headers: &HeaderMap) -> bool {
+    let pretty_query = query
+        .unwrap_or_default()
+        .split('&')
+        .any(|pair| pair == "pretty" || pair == "pretty=true");
+    let pretty_accept = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains(PRETTY_JSON_MEDIA_TYPE));
+    pretty_query || pretty_accept
+}
+
+/// Re-serialize JSON responses with `serde_json::to_vec_pretty` when the client asked for
+/// pretty output. Responses stay compact by default.
+async fn pretty_json<B>(request: Request<B>, next: Next<B>) -> Response {
+    let pretty = wants_pretty_json(request.uri().query(), request.headers());
+    let response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !pretty || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = hyper::body::to_bytes(body).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let body = serde_json::from_slice::<Value>(&bytes)
+        .and_then(|value| serde_json::to_vec_pretty(&value))
+        .unwrap_or_else(|_| bytes.to_vec());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, boxed(Full::from(body)))
+}
+
 static INDEX_JSON: OnceLock<Value> = OnceLock::new();
 
 fn index() -> Json<Value> {
-    let value = INDEX_JSON.get_or_init(||
-        json!({
-            "endpoints": [
-                {
-                    "path": "/verify",
-                    "method": "POST",
-                    "description": "Verify a program",
-                    "params" : {
-                        "repo": "Git repository URL",
-                        "program_id": "Program ID of the program in mainnet",
-                        "commit": "(Optional) Commit hash of the repository. If not specified, the latest commit will be used.",
-                        "lib_name": "(Optional) If the repository contains multiple programs, specify the name of the library name of the program to build and verify.",
-                        "bpf_flag": "(Optional)  If the program requires cargo build-bpf (instead of cargo build-sbf), as for an Anchor program, set this flag.",
-                        "base_image": "(Optional) Base docker image to use for building the program.",
-                        "mount_path": "(Optional) Mount path for the repository.",
-                        "cargo_args": "(Optional) Cargo args to pass to the build command. It should be Vector of strings."
-                    },
-                },
-                {
-                    "path": "/status/:address",
-                    "method": "GET",
-                    "description": "Check the verification status of a program by its address",
-                    "params": {
-                        "address": "Address of the mainnet program to check the verification status"
-                    }
+    let value = INDEX_JSON.get_or_init(|| {
+        // One `json!` per endpoint, a single literal exceeds the macro recursion limit
+        let endpoints = vec![
+            json!({
+                "path": "/verify",
+                "method": "POST",
+                "description": "Verify a program. Responds with 409 when a previous build has the same params, including matched_build_id and the matched build's creation time, status and verification result. Requests are the same build when their params_hash, also returned when a build starts, is the same.",
+                "params" : {
+                    "repo": "Git repository URL",
+                    "program_id": "Program ID of the program in mainnet",
+                    "commit": "(Optional) Commit hash of the repository. If not specified, the latest commit will be used.",
+                    "lib_name": "(Optional) If the repository contains multiple programs, specify the name of the library name of the program to build and verify.",
+                    "bpf_flag": "(Optional, deprecated) Alias of build_system cargo-bpf.",
+                    "build_system": "(Optional) Build command template: cargo-sbf (default), cargo-bpf, anchor (built with cargo build-bpf) or custom (the default command of base_image, leaving lib_name in target/deploy; both are then required). The build system used is recorded with the build.",
+                    "base_image": "(Optional) Base docker image to use for building the program.",
+                    "mount_path": "(Optional) Mount path for the repository.",
+                    "cargo_args": "(Optional) Cargo args to pass to the build command. It should be Vector of strings. At most 32 args and 1024 characters in total by default, without shell metacharacters.",
+                    "no_default_features": "(Optional) Build with --no-default-features. Features that should still be enabled can be passed through cargo_args, e.g. [\"--features\", \"mainnet\"].",
+                    "workspace_member": "(Optional) Package name of the workspace member to build. The build runs from the workspace root with cargo -p so the shared Cargo.lock is used.",
+                    "signature": "(Optional) Base58 signature by the program upgrade authority of \"verify <program_id> <repo> <commit> <nonce>\", with a nonce from /challenge/:program_id. Requires commit. Marks the verification as claimed by the authority.",
+                    "nonce": "(Optional) Nonce from /challenge/:program_id the signature was made with. Required with signature.",
+                    "default_ref_strategy": "(Optional) What to build when no commit is specified: head (default), latest_tag or latest_release. The resolved ref and commit are recorded with the build.",
+                    "vendored": "(Optional) Build offline against the vendored registry mounted into the container of the custom build system, which it requires. Fails if a dependency is not vendored.",
+                    "extra_hashes": "(Optional) Additional digests of the executable to return alongside the canonical sha256 hash: sha256, sha512 or blake3. The verification decision always uses the canonical hash.",
+                    "tag": "(Optional) Git tag to build instead of a commit, e.g. v1.2.3. The tag must exist in the repository and the commit it points to is returned as resolved_commit.",
+                    "docker_build_args": "(Optional) Map of docker --build-arg values for the Dockerfile build. Requires dockerfile_path. Allowed keys are IMAGE_VARIANT, RUST_VERSION, SOLANA_VERSION and ANCHOR_VERSION. The args are returned as docker_build_args.",
+                    "dockerfile_path": "(Optional) Path of a Dockerfile in the repository to build with instead of solana-verify, e.g. Dockerfile. Requires artifact_path.",
+                    "artifact_path": "(Optional) Absolute path of the built .so in the image built from dockerfile_path, e.g. /build/target/deploy/program.so.",
+                    "target": "(Optional) Target triple to build for: sbf-solana-solana (default), sbpfv1-solana-solana, sbpfv2-solana-solana or sbpfv3-solana-solana. Only supported with the cargo-sbf build system. The target is returned as target.",
+                    "anchor_version": "(Optional) Anchor release to build with, e.g. 0.29.0. Requires the anchor build system and selects its build image, so it cannot be combined with base_image. Read from the [toolchain] section of Anchor.toml when omitted. The build fails if there is no such Anchor release.",
+                    "cargo_profile": "(Optional) Cargo profile the program was deployed with, release by default. The integer and boolean opt-level, lto, codegen-units, overflow-checks, debug-assertions and incremental settings of the profile in the Cargo.toml are applied onto the release profile.",
+                    "profile_overrides": "(Optional) Map of release profile settings overridden when the program was deployed, e.g. {\"opt-level\": \"3\", \"lto\": \"true\"}. Only opt-level (0 to 3), lto, codegen-units, overflow-checks, debug-assertions and incremental are accepted.",
+                    "rustflags": "(Optional) List of RUSTFLAGS the program was deployed with, e.g. [\"-C opt-level=3\", \"--cfg feature_gate\"]. Requires the custom build system, whose container gets them as RUSTFLAGS. Only --cfg and the -C options listed by /capabilities are accepted. The normalized flags are returned as rustflags.",
+                    "network": "(Optional) Cluster the program is deployed to: mainnet (default), or its aliases mainnet-beta and m. Stored and returned as mainnet; other clusters are rejected.",
+                    "commitment": "(Optional) Commitment level the deployed program is read at: processed, confirmed or finalized (default). Use confirmed to verify a program deployed moments ago. Stored and returned in the status.",
+                    "source_date_epoch": "(Optional) Unix timestamp SOURCE_DATE_EPOCH is set to for Dockerfile and custom builds, pinning embedded timestamps. Defaults to the date of the commit built, after resolving tag or the default branch.",
+                    "lock_file_content": "(Optional) Content of the Cargo.lock to build with, up to 1 MiB, for repositories which did not commit the one the program was deployed with. It is written at the root of mount_path, replacing any committed one, and its sha256 is recorded as lock_file_hash.",
+                    "language": "(Optional) Language the program is written in, selecting the builder it is built with: rust (default). The languages with a builder are listed by /capabilities.",
+                    "labels": "(Optional) Map of up to 16 labels organizing the verification, e.g. {\"env\": \"prod\", \"team\": \"core\"}. Keys are 1 to 63 letters, digits, '.', '_' or '-', values 1 to 255 letters, digits or '._-:/@+'. Labels are returned in the status and do not change the build.",
+                    "upstream_repo": "(Optional) https URL of the repository a fork in repository was forked from. Recorded and returned in the status for context only: the build uses repository and the params hash is unchanged.",
+                    "crate_name": "(Optional) Name of a crate published to crates.io to build instead of a git repository, with crate_version. repository, commit and tag must then be omitted: the .crate tarball is checked against the crates.io index checksum and built as is. The build is stored under https://crates.io/crates/<name>/<version> and returned as published_crate.",
+                    "crate_version": "(Optional) Exact version of crate_name to build, e.g. 0.2.4."
                 },
-                {
-                    "path": "/verified-programs",
-                    "method": "GET",
-                    "description": "Get the list of verified programs"
-                }
-            ]
-        })
-    );
+            }),
+            json!({
+                "path": "/verify/auto",
+                "method": "POST",
+                "description": "Re-verify a program using the repository and params of its previous build or the registry",
+                "params": {
+                    "program_id": "Program ID of the program in mainnet"
+                }
+            }),
+            json!({
+                "path": "/verify/bisect",
+                "method": "POST",
+                "description": "Find the commit a program was deployed from by binary searching the commits between a known-bad and a known-good commit. Accepts the /verify params except commit. The matching commit and the number of builds are recorded in /builds/:id/events.",
+                "params": {
+                    "bad_commit": "Commit whose build does not match the on-chain program, e.g. one from before the deployment",
+                    "good_commit": "Descendant of bad_commit whose build matches the on-chain program"
+                }
+            }),
+            json!({
+                "path": "/verify/artifact",
+                "method": "POST",
+                "description": "Compare a published executable, such as a release asset, with the deployed program without building it. With source, the program is also built from source, waiting for the build, to check that the artifact is reproducible.",
+                "params": {
+                    "program_id": "Program ID of the program in mainnet",
+                    "artifact_url": "https URL of the published .so",
+                    "source": "(Optional) /verify params of the same program to build from source and compare with the artifact"
+                }
+            }),
+            json!({
+                "path": "/reproducibility-check",
+                "method": "POST",
+                "description": "Check a repository for what may keep its builds from being reproducible, such as a missing Cargo.lock, build scripts or git dependencies, without building it. Returns a score out of 100 and the hazards found, each with an action to fix it.",
+                "params": {
+                    "repository": "Git repository URL",
+                    "commit_hash": "(Optional) Commit to check, the head of the default branch if not given",
+                    "mount_path": "(Optional) Directory of the program within the repository",
+                    "base_image": "(Optional) Image the program would be built in"
+                }
+            }),
+            json!({
+                "path": "/uploads",
+                "method": "POST",
+                "description": "Start a resumable upload of an executable to compare with the deployed program. Send the file in chunks of up to 1 MiB with PATCH /uploads/:id, then compare it with POST /uploads/:id/verify. Uploads expire after 24 hours.",
+                "params": {
+                    "program_id": "Program ID of the program in mainnet",
+                    "size": "Size of the executable in bytes"
+                }
+            }),
+            json!({
+                "path": "/uploads/:id",
+                "method": "PATCH",
+                "description": "Append the chunk in the body to an upload. The Upload-Offset header must be the offset the upload is at, returned by GET /uploads/:id after a dropped connection.",
+                "params": {
+                    "id": "Upload ID returned by POST /uploads"
+                }
+            }),
+            json!({
+                "path": "/uploads/:id/verify",
+                "method": "POST",
+                "description": "Compare a completed upload with the deployed program",
+                "params": {
+                    "id": "Upload ID returned by POST /uploads"
+                }
+            }),
+            json!({
+                "path": "/webhook/github",
+                "method": "POST",
+                "description": "GitHub push webhook. Re-verifies the registry programs built from the pushed repository when its default branch changes. Requests must be signed with X-Hub-Signature-256."
+            }),
+            json!({
+                "path": "/builds/:id/retry",
+                "method": "POST",
+                "description": "Submit a finished build again with some of its params changed, e.g. only base_image. The body is a partial /verify params object merged onto the params of the build, where null clears a param. program_id cannot be changed. Responds like /verify with the id of the new build.",
+                "params": {
+                    "id": "Build ID returned as request_id by /verify"
+                }
+            }),
+            json!({
+                "path": "/attest",
+                "method": "POST",
+                "description": "Attest the executable hash of a program whose source is not published, signed by its upgrade authority. While that hash is deployed, /status reports trust_level authority_attested, a weaker claim than source_verified, and is_verified stays false.",
+                "params": {
+                    "program_id": "Program ID of the program in mainnet",
+                    "executable_hash": "Hex encoded sha256 executable hash the authority vouches for",
+                    "signature": "Base58 signature by the upgrade authority of \"attest <program_id> <executable_hash> <nonce>\", with a nonce from /challenge/:program_id",
+                    "nonce": "Nonce from /challenge/:program_id the signature was made with"
+                }
+            }),
+            json!({
+                "path": "/claim",
+                "method": "POST",
+                "description": "Claim a verified program for its publisher by proving control of its repository or of a domain. /status then returns the claim, whose claimed_by is the repository URL or the domain. Unlike signature, it attributes the program to people rather than to its upgrade authority key.",
+                "params": {
+                    "program_id": "Program ID of a verified program",
+                    "method": "repo: the default branch of the verified repository has a .solana-verify-claim file listing the program ID on its own line. dns: _solana-verify.<domain> has the TXT record solana-verify-claim=<program_id>.",
+                    "domain": "(Optional) Domain claiming the program, required by the dns method, e.g. ellipsislabs.xyz"
+                }
+            }),
+            json!({
+                "path": "/subscriptions",
+                "method": "POST",
+                "description": "Subscribe a webhook to a program. It is sent a JSON event whenever the program gets a new build or its verification status changes. Responds with the subscription, whose id is needed to delete it.",
+                "params": {
+                    "program_id": "Program ID of the program in mainnet",
+                    "webhook_url": "https URL the events are posted to"
+                }
+            }),
+            json!({
+                "path": "/subscriptions/:id",
+                "method": "DELETE",
+                "description": "Delete a subscription",
+                "params": {
+                    "id": "Subscription ID returned by POST /subscriptions"
+                }
+            }),
+            json!({
+                "path": "/rpc",
+                "method": "POST",
+                "description": "JSON-RPC 2.0 interface to /verify, /status/:address and /verified-programs, accepting a single request or a batch of up to 100",
+                "params": {
+                    "jsonrpc": "2.0",
+                    "method": "verify (params of /verify), status ({\"address\": ..., \"hash_mode\": ...}) or list ({\"label\": ...})",
+                    "params": "Params of the method, as an object",
+                    "id": "(Optional) Id echoed in the response. Requests without an id are notifications and get no response."
+                }
+            }),
+            json!({
+                "path": "/status/:address",
+                "method": "GET",
+                "description": "Check the verification status of a program by its address",
+                "params": {
+                    "address": "Address of the mainnet program to check the verification status",
+                    "hash_mode": "Optional: how the deployed program is also hashed for on_chain_mode_hash, `padded` (with its zero padding) or `account` (the whole account storing it). Defaults to `trimmed`, the canonical hash"
+                }
+            }),
+            json!({
+                "path": "/status/batch",
+                "method": "POST",
+                "description": "Get the status of up to 100 programs in one call, as of their last verification. The programs are not checked on-chain, so use /status/:address for a fresh result.",
+                "params": {
+                    "addresses": "List of mainnet program addresses"
+                }
+            }),
+            json!({
+                "path": "/status/:address/certificate",
+                "method": "GET",
+                "description": "Get a self-contained certificate of the latest verification of a program with the repository, commit, hashes, toolchain and timestamps. Signed with the server ed25519 key when one is configured.",
+                "params": {
+                    "address": "Address of the mainnet program"
+                }
+            }),
+            json!({
+                "path": "/status/:address/timeline",
+                "method": "GET",
+                "description": "Get every on-chain hash seen for a program across its upgrades, oldest first, with whether a build from source matched it and which version is current.",
+                "params": {
+                    "address": "Address of the mainnet program"
+                }
+            }),
+            json!({
+                "path": "/uploads/:id",
+                "method": "GET",
+                "description": "Get the size of an upload and the offset to resume it from",
+                "params": {
+                    "id": "Upload ID returned by POST /uploads"
+                }
+            }),
+            json!({
+                "path": "/verified-programs",
+                "method": "GET",
+                "description": "Get the list of verified programs",
+                "params": {
+                    "format": "(Optional) json (default unless Accept prefers text/csv) for the list of program ids, or csv for a spreadsheet of their verifications: program_id, repository, commit, is_verified, on_chain_hash, executable_hash and verified_at",
+                    "label": "(Optional) Only list the programs whose verification has all the given labels, as key=value pairs separated by commas, e.g. env=prod,team=core"
+                }
+            }),
+            json!({
+                "path": "/by-hash/:hash",
+                "method": "GET",
+                "description": "List the programs whose latest verification, of the tenant of the x-api-key header, built the given executable hash, with their build id, repository, commit, build status and verification result",
+                "params": {
+                    "hash": "Hex encoded sha256 executable hash, as reported by solana-verify"
+                }
+            }),
+            json!({
+                "path": "/capabilities",
+                "method": "GET",
+                "description": "List the build systems, targets, networks, base images and docker build args accepted by /verify, with their defaults"
+            }),
+            json!({
+                "path": "/ready",
+                "method": "GET",
+                "description": "Check whether the instance accepts builds: 200 with state ready, or 503 with state draining or drained and the active and queued builds after POST /admin/drain. Not rate limited."
+            }),
+            json!({
+                "path": "/version",
+                "method": "GET",
+                "description": "Get the crate version, git commit and build time of the running server, and the API versions it serves. Not rate limited."
+            }),
+            json!({
+                "path": "/health",
+                "method": "GET",
+                "description": "Get the connection pool usage of the service, the database timeouts and the state of the RPC circuit breaker"
+            }),
+            json!({
+                "path": "/metrics",
+                "method": "GET",
+                "description": "Get the request duration histograms by route and status code in the Prometheus text format"
+            }),
+            json!({
+                "path": "/limits",
+                "method": "GET",
+                "description": "Get the rate limit of every group of routes for the client IP: the limit, the remaining requests and the seconds until the allowance is fully replenished"
+            }),
+            json!({
+                "path": "/registry",
+                "method": "GET",
+                "description": "Get the list of trusted programs which are verified periodically and their latest status"
+            }),
+            json!({
+                "path": "/onchain-bytes/:address",
+                "method": "GET",
+                "description": "Get the raw bytes of the deployed program",
+                "params": {
+                    "address": "Address of the mainnet program",
+                    "encoding": "(Optional) Encoding of the returned bytes, either base64 (default) or hex"
+                }
+            }),
+            json!({
+                "path": "/quickcheck/:address",
+                "method": "GET",
+                "description": "Get the size of the deployed program and a fingerprint of its first and last bytes, to cheaply detect changes without a full hash or build",
+                "params": {
+                    "address": "Address of the mainnet program",
+                    "bytes": "(Optional) Number of bytes taken from each end of the program, between 1 and 4096 (default 256)"
+                }
+            }),
+            json!({
+                "path": "/challenge/:program_id",
+                "method": "GET",
+                "description": "Get a single use nonce for the program upgrade authority to sign for /verify or /attest. Each call issues a new nonce without invalidating the pending ones.",
+                "params": {
+                    "program_id": "Program ID of the program in mainnet"
+                }
+            }),
+            json!({
+                "path": "/builds/:id/events",
+                "method": "GET",
+                "description": "Get the timeline of status transitions and phases of a build",
+                "params": {
+                    "id": "Build ID returned as request_id by /verify"
+                }
+            }),
+            json!({
+                "path": "/verify/:id/events",
+                "method": "GET",
+                "description": "Stream the events of a build as Server-Sent Events. Recorded events are replayed first and the stream closes once the build completes or fails.",
+                "params": {
+                    "id": "Build ID returned as request_id by /verify"
+                }
+            }),
+            json!({
+                "path": "/builds/:id/logs",
+                "method": "GET",
+                "description": "Get the output of the solana-verify build as plain text",
+                "params": {
+                    "id": "Build ID returned as request_id by /verify"
+                }
+            }),
+            json!({
+                "path": "/builds/:id/artifact",
+                "method": "GET",
+                "description": "Download the executable extracted from the image of a build with dockerfile_path",
+                "params": {
+                    "id": "Build ID returned as request_id by /verify"
+                }
+            }),
+            json!({
+                "path": "/badge/:address.svg",
+                "method": "GET",
+                "description": "Get an SVG badge showing whether a program is verified, for embedding in READMEs",
+                "params": {
+                    "address": "Address of the mainnet program",
+                    "style": "(Optional) Badge style, either flat (default) or plastic"
+                }
+            }),
+            json!({
+                "path": "/admin/workers",
+                "method": "GET",
+                "description": "Get the number of active and idle build workers, the queue depth and the per-repository build limit set by BUILD_CONCURRENCY_PER_REPO. Requires Authorization: Bearer <ADMIN_TOKEN>."
+            }),
+            json!({
+                "path": "/admin/workers",
+                "method": "POST",
+                "description": "Resize the build worker pool without a restart. Requires Authorization: Bearer <ADMIN_TOKEN>.",
+                "params": {
+                    "size": "Number of build workers, between 1 and 64"
+                }
+            }),
+            json!({
+                "path": "/admin/denylist",
+                "method": "GET",
+                "description": "List the programs denied by DENIED_PROGRAM_IDS and through the admin endpoints. Requires Authorization: Bearer <ADMIN_TOKEN>."
+            }),
+            json!({
+                "path": "/admin/denylist",
+                "method": "POST",
+                "description": "Deny a program: /verify, /status, its badge and certificate refuse it with a 403 giving the reason. Requires Authorization: Bearer <ADMIN_TOKEN>.",
+                "params": {
+                    "program_id": "Program ID to deny",
+                    "reason": "Reason returned to clients, e.g. a known scam"
+                }
+            }),
+            json!({
+                "path": "/admin/denylist/:program_id",
+                "method": "DELETE",
+                "description": "Allow a program denied through POST /admin/denylist again. Requires Authorization: Bearer <ADMIN_TOKEN>.",
+                "params": {
+                    "program_id": "Program ID to allow"
+                }
+            }),
+            json!({
+                "path": "/builds/:id",
+                "method": "PATCH",
+                "description": "Correct the metadata of a build without verifying it again. The body may only contain repository, lib_name, mount_path and labels; the hashes, verification result and other params cannot be changed. Responds with the updated build. Requires Authorization: Bearer <ADMIN_TOKEN>.",
+                "params": {
+                    "id": "Build ID returned as request_id by /verify"
+                }
+            }),
+            json!({
+                "path": "/admin/retention",
+                "method": "GET",
+                "description": "Get the retention policy of build records, RETENTION_DAYS and RETENTION_INTERVAL_SECS, and how many builds and build events its last run deleted. Requires Authorization: Bearer <ADMIN_TOKEN>."
+            }),
+            json!({
+                "path": "/admin/selftest",
+                "method": "GET",
+                "description": "Verify the program in SELFTEST_PARAMS, or the first registry program, end to end and report whether it matched and how long it took. Responds with 500 when it did not match. Requires Authorization: Bearer <ADMIN_TOKEN>."
+            }),
+            json!({
+                "path": "/admin/drain",
+                "method": "POST",
+                "description": "Stop accepting new builds and finish the queued and running ones, so the instance can be shut down once GET /ready reports drained. Responds with 202 and the active and queued builds. Requires Authorization: Bearer <ADMIN_TOKEN>."
+            }),
+            json!({
+                "path": "/admin/reverify",
+                "method": "POST",
+                "description": "Queue the builds of the verifications matching the filters for re-verification, of all tenants, and respond with 202 and the number enqueued. Requires Authorization: Bearer <ADMIN_TOKEN>.",
+                "params": {
+                    "base_image": "(Optional) Only builds made with this base image, as it was requested",
+                    "older_than": "(Optional) Only verifications made before this UTC time, e.g. 2024-06-01T00:00:00",
+                    "verified_state": "(Optional) verified, unverified or any. At least one filter is required."
+                }
+            }),
+            json!({
+                "path": "/admin/stuck",
+                "method": "GET",
+                "description": "List the builds of all tenants left in progress without a build event for longer than a threshold. Requires Authorization: Bearer <ADMIN_TOKEN>.",
+                "params": {
+                    "older_than": "(Optional) Seconds without activity, 7200 by default"
+                }
+            }),
+            json!({
+                "path": "/admin/stuck/reset",
+                "method": "POST",
+                "description": "Mark the stuck builds failed, or queue them again, and respond with the ids reset. Requires Authorization: Bearer <ADMIN_TOKEN>.",
+                "params": {
+                    "older_than": "(Optional) Seconds without activity, 7200 by default",
+                    "action": "(Optional) fail (default) to mark them failed, or requeue to queue them on the build workers with their params"
+                }
+            }),
+        ];
+        json!({ "endpoints": endpoints })
+    });
     Json(value.clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    async fn negotiated_encoding(accept_encoding: &str) -> Option<String> {
+        let app = Router::new()
+            .route("/", get(|| async { index() }))
+            .layer(compression_layer());
+        let request = Request::builder()
+            .uri("/")
+            .header(header::ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        response
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_compression_negotiation() {
+        assert_eq!(negotiated_encoding("gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(negotiated_encoding("br").await.as_deref(), Some("br"));
+        assert_eq!(negotiated_encoding("zstd").await.as_deref(), Some("zstd"));
+        assert_eq!(
+            negotiated_encoding("gzip;q=0.5, br;q=1.0").await.as_deref(),
+            Some("br")
+        );
+        assert_eq!(negotiated_encoding("identity").await, None);
+    }
+
+    async fn cache_control_header(policy: Option<CachePolicy>, status: StatusCode) -> String {
+        let handler = move || async move {
+            let mut response = (status, "{}").into_response();
+            if let Some(policy) = policy {
+                response.extensions_mut().insert(policy);
+            }
+            response
+        };
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(middleware::from_fn(cache_control));
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        response.headers()[header::CACHE_CONTROL]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_cache_control() {
+        assert_eq!(
+            cache_control_header(Some(CachePolicy::Immutable), StatusCode::OK).await,
+            IMMUTABLE_CACHE_CONTROL
+        );
+        assert_eq!(
+            cache_control_header(Some(CachePolicy::Short), StatusCode::OK).await,
+            SHORT_CACHE_CONTROL
+        );
+        assert_eq!(
+            cache_control_header(None, StatusCode::OK).await,
+            NO_CACHE_CONTROL
+        );
+        assert_eq!(
+            cache_control_header(Some(CachePolicy::Immutable), StatusCode::NOT_FOUND).await,
+            NO_STORE_CACHE_CONTROL
+        );
+    }
+
+    async fn index_body(uri: &str, accept: &str) -> String {
+        let app = Router::new()
+            .route("/", get(|| async { index() }))
+            .layer(middleware::from_fn(pretty_json));
+        let request = Request::builder()
+            .uri(uri)
+            .header(header::ACCEPT, accept)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pretty_json_toggle() {
+        assert!(!index_body("/", "application/json").await.contains('\n'));
+        assert!(index_body("/?pretty=true", "application/json")
+            .await
+            .contains("\n  \"endpoints\""));
+        assert!(index_body("/", PRETTY_JSON_MEDIA_TYPE).await.contains('\n'));
+        assert!(!index_body("/?pretty=false", "*/*").await.contains('\n'));
+    }
+}
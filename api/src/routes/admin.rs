@@ -0,0 +1,470 @@
+use std::env;
+use std::time::Instant;
+
+use crate::builder::{is_valid_address, verify_build};
+use crate::db::DbClient;
+use crate::denylist::configured_denials;
+use crate::errors::{error_response, ApiError};
+use crate::models::{
+    BuildMetadataChanges, DeniedProgram, DenyProgramParams, DenylistResponse, ErrorResponse,
+    JobStatus, ReadyResponse, ResetStuckParams, ResetStuckResponse, ResizeWorkersParams,
+    RetentionResponse, ReverifyParams, ReverifyResponse, SelftestResponse, SolanaProgramBuild,
+    SolanaProgramBuildParams, Status, StuckAction, StuckBuild, StuckBuildsParams,
+    StuckBuildsResponse, UpdateBuildParams, WorkerPoolStatus, DEFAULT_STUCK_AFTER_SECS,
+};
+use crate::registry::registry_programs;
+use crate::retention::{last_run, RetentionPolicy};
+use crate::validation::validate_build_update;
+use crate::worker::MAX_BUILD_WORKERS;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::Json;
+
+type AdminError = (StatusCode, Json<ErrorResponse>);
+
+// Compare two byte strings without returning early on the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Check the `Authorization: Bearer <token>` header against `ADMIN_TOKEN`.
+/// Admin endpoints are disabled when the token is not configured.
+fn authorize(headers: &HeaderMap) -> Result<(), AdminError> {
+    let Some(admin_token) = env::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+    else {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Admin endpoints are not configured",
+        ));
+    };
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !constant_time_eq(token.as_bytes(), admin_token.as_bytes()) {
+        return Err(error_response(
+            StatusCode::UNAUTHORIZED,
+            "Invalid admin token",
+        ));
+    }
+    Ok(())
+}
+
+// Route handler for GET /admin/workers which reports the build worker pool usage
+pub(crate) async fn get_workers(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+) -> Result<Json<WorkerPoolStatus>, AdminError> {
+    authorize(&headers)?;
+    Ok(Json(db.workers.status()))
+}
+
+// Route handler for POST /admin/workers which resizes the build worker pool
+pub(crate) async fn resize_workers(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(payload): Json<ResizeWorkersParams>,
+) -> Result<Json<WorkerPoolStatus>, AdminError> {
+    authorize(&headers)?;
+
+    if payload.size == 0 || payload.size > MAX_BUILD_WORKERS {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Worker pool size must be between 1 and {}",
+                MAX_BUILD_WORKERS
+            ),
+        ));
+    }
+
+    tracing::info!("Resizing build worker pool to {}", payload.size);
+    db.workers.resize(payload.size);
+    Ok(Json(db.workers.status()))
+}
+
+// Route handler for POST /admin/drain which stops accepting new builds, so the instance can
+// be shut down once the queued and running ones finish, as GET /ready then reports
+pub(crate) async fn drain(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<ReadyResponse>), AdminError> {
+    authorize(&headers)?;
+
+    tracing::info!("Draining the build queue, new builds are refused");
+    db.workers.drain();
+    Ok((StatusCode::ACCEPTED, Json(db.workers.readiness())))
+}
+
+// Route handler for POST /admin/reverify which queues the builds of the verifications
+// matching the filters for re-verification, such as those made with an outdated base image
+pub(crate) async fn reverify(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(payload): Json<ReverifyParams>,
+) -> Result<(StatusCode, Json<ReverifyResponse>), AdminError> {
+    authorize(&headers)?;
+
+    if !payload.has_filter() {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "At least one of base_image, older_than or verified_state is required",
+        ));
+    }
+    db.workers
+        .ensure_accepting_builds()
+        .map_err(|err| error_response(err.status_code(), &err.user_message()))?;
+
+    let builds = db
+        .get_builds_to_reverify(&payload)
+        .await
+        .map_err(database_error)?;
+    let enqueued = builds.len();
+    for build in builds {
+        let _ = db
+            .update_build_status(&build.id, JobStatus::InProgress.into())
+            .await;
+        db.record_event(&build.id, "reverification_queued", Some("admin"))
+            .await;
+        db.clone().reverify_program(build);
+    }
+
+    tracing::info!("Queued {} builds for re-verification", enqueued);
+    Ok((StatusCode::ACCEPTED, Json(ReverifyResponse { enqueued })))
+}
+
+// Time before which a build without activity is stuck, `older_than` seconds ago
+fn stuck_cutoff(older_than: Option<u64>) -> (u64, chrono::NaiveDateTime) {
+    let older_than = older_than.unwrap_or(DEFAULT_STUCK_AFTER_SECS);
+    let cutoff = chrono::Utc::now().naive_utc()
+        - chrono::Duration::seconds(older_than.min(i64::MAX as u64) as i64);
+    (older_than, cutoff)
+}
+
+// Route handler for GET /admin/stuck which lists the builds left in progress without
+// activity for longer than `older_than` seconds, such as after a crash of their worker
+pub(crate) async fn get_stuck_builds(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Query(StuckBuildsParams { older_than }): Query<StuckBuildsParams>,
+) -> Result<Json<StuckBuildsResponse>, AdminError> {
+    authorize(&headers)?;
+
+    let (older_than, cutoff) = stuck_cutoff(older_than);
+    let builds = db
+        .get_stuck_builds(cutoff)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(|(build, last_activity_at)| StuckBuild {
+            id: build.id,
+            program_id: build.program_id,
+            repository: build.repository,
+            created_at: build.created_at,
+            last_activity_at,
+        })
+        .collect();
+    Ok(Json(StuckBuildsResponse { older_than, builds }))
+}
+
+// Route handler for POST /admin/stuck/reset which marks the stuck builds failed, or queues
+// them again on the build workers
+pub(crate) async fn reset_stuck_builds(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(ResetStuckParams { older_than, action }): Json<ResetStuckParams>,
+) -> Result<Json<ResetStuckResponse>, AdminError> {
+    authorize(&headers)?;
+
+    if action == StuckAction::Requeue {
+        db.workers
+            .ensure_accepting_builds()
+            .map_err(|err| error_response(err.status_code(), &err.user_message()))?;
+    }
+    let (older_than, cutoff) = stuck_cutoff(older_than);
+    let builds = db.get_stuck_builds(cutoff).await.map_err(database_error)?;
+
+    let mut reset = Vec::new();
+    for (build, _) in builds {
+        let detail = format!("no activity for over {} seconds", older_than);
+        match action {
+            StuckAction::Fail => {
+                db.record_event(&build.id, "stuck_build_failed", Some(&detail))
+                    .await;
+                db.update_build_status(&build.id, JobStatus::Failed.into())
+                    .await
+                    .map_err(database_error)?;
+                reset.push(build.id);
+            }
+            StuckAction::Requeue => {
+                db.record_event(&build.id, "stuck_build_requeued", Some(&detail))
+                    .await;
+                reset.push(build.id.clone());
+                db.clone().reverify_program(build);
+            }
+        }
+    }
+
+    tracing::info!("Reset {} stuck builds ({:?})", reset.len(), action);
+    Ok(Json(ResetStuckResponse { action, reset }))
+}
+
+// Route handler for GET /admin/retention which reports the retention policy of build
+// records and the outcome of its last run
+pub(crate) async fn get_retention(
+    headers: HeaderMap,
+) -> Result<Json<RetentionResponse>, AdminError> {
+    authorize(&headers)?;
+
+    let policy = RetentionPolicy::from_env();
+    Ok(Json(RetentionResponse {
+        enabled: policy.max_age_days.is_some(),
+        max_age_days: policy.max_age_days,
+        interval_secs: policy.interval.as_secs(),
+        last_run: last_run(),
+    }))
+}
+
+// Route handler for PATCH /builds/:id which corrects the metadata of a build, such as a
+// mistyped repository URL, keeping its hashes and verification result as they are
+pub(crate) async fn update_build(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Path(build_id): Path<String>,
+    Json(payload): Json<UpdateBuildParams>,
+) -> Result<Json<SolanaProgramBuild>, AdminError> {
+    authorize(&headers)?;
+
+    if let Err(field_errors) = validate_build_update(&payload) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Invalid build update".to_string(),
+                field_errors,
+            }),
+        ));
+    }
+    let changes = BuildMetadataChanges::from(&payload);
+    let changed_fields = [
+        ("repository", &changes.repository),
+        ("lib_name", &changes.lib_name),
+        ("mount_path", &changes.mount_path),
+    ]
+    .into_iter()
+    .filter_map(|(field, value)| value.as_ref().map(|value| format!("{}={}", field, value)))
+    .chain(payload.labels.as_ref().map(|labels| {
+        format!(
+            "labels={}",
+            serde_json::to_string(labels).unwrap_or_default()
+        )
+    }))
+    .collect::<Vec<_>>();
+    if changed_fields.is_empty() {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "No field to update was given",
+        ));
+    }
+
+    // Diesel rejects an empty changeset, which is what a request changing only labels gives
+    let mut updated = 0;
+    if changes.repository.is_some() || changes.lib_name.is_some() || changes.mount_path.is_some() {
+        updated = db
+            .update_build_metadata(&build_id, &changes)
+            .await
+            .map_err(database_error)?;
+    }
+    if let Some(labels) = &payload.labels {
+        updated = db
+            .set_labels(&build_id, labels)
+            .await
+            .map_err(database_error)?;
+    }
+    if updated == 0 {
+        return Err(error_response(StatusCode::NOT_FOUND, "Build not found"));
+    }
+    tracing::info!("Updated build {}: {}", build_id, changed_fields.join(" "));
+    db.record_event(
+        &build_id,
+        "metadata_updated",
+        Some(&changed_fields.join(" ")),
+    )
+    .await;
+
+    let build = db.get_job(&build_id).await.map_err(database_error)?;
+    Ok(Json(build))
+}
+
+// Program verified by the self-test: `SELFTEST_PARAMS` as /verify params, or the first
+// registry program
+fn selftest_params() -> Option<SolanaProgramBuildParams> {
+    match env::var("SELFTEST_PARAMS") {
+        Ok(value) => serde_json::from_str(&value)
+            .map_err(|err| tracing::warn!("Invalid SELFTEST_PARAMS: {}", err))
+            .ok(),
+        Err(_) => registry_programs().first().cloned(),
+    }
+}
+
+// Route handler for GET /admin/selftest which verifies a known reproducible program end to
+// end, cloning, building and comparing it with the deployed program, and reports whether
+// it matched. Responds with 500 if it did not.
+pub(crate) async fn run_selftest(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<SelftestResponse>), AdminError> {
+    authorize(&headers)?;
+
+    let Some(params) = selftest_params() else {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No self-test program is configured, set SELFTEST_PARAMS",
+        ));
+    };
+
+    let started = Instant::now();
+    let build = SolanaProgramBuild::from(&params);
+    if let Err(err) = db.insert_build_params(&build).await {
+        tracing::error!("Self-test failed to insert its build: {}", err);
+        return Err(error_response(err.status_code(), &err.user_message()));
+    }
+
+    let result = verify_build(&db, params, &build.id).await;
+    let job_status = match &result {
+        Ok((verified_build, _)) if verified_build.is_verified => JobStatus::Completed,
+        _ => JobStatus::Failed,
+    };
+    let _ = db.update_build_status(&build.id, job_status.into()).await;
+
+    let mut response = SelftestResponse {
+        passed: false,
+        program_id: build.program_id,
+        build_id: build.id,
+        executable_hash: None,
+        on_chain_hash: None,
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: None,
+    };
+    match result {
+        Ok((verified_build, _)) => {
+            response.passed = verified_build.is_verified;
+            if !verified_build.is_verified {
+                response.error = Some("The build does not match the deployed program".to_string());
+            }
+            response.executable_hash = Some(verified_build.executable_hash);
+            response.on_chain_hash = Some(verified_build.on_chain_hash);
+        }
+        Err(err) => response.error = Some(err.to_string()),
+    }
+
+    if response.passed {
+        tracing::info!("Self-test passed in {} ms", response.duration_ms);
+        Ok((StatusCode::OK, Json(response)))
+    } else {
+        tracing::error!("Self-test failed: {:?}", response.error);
+        Ok((StatusCode::INTERNAL_SERVER_ERROR, Json(response)))
+    }
+}
+
+fn database_error(err: ApiError) -> AdminError {
+    tracing::error!("Error getting data from database: {}", err);
+    error_response(err.status_code(), &err.user_message())
+}
+
+async fn denylist(db: &DbClient) -> Result<Json<DenylistResponse>, AdminError> {
+    Ok(Json(DenylistResponse {
+        configured: configured_denials()
+            .iter()
+            .map(|(program_id, reason)| (program_id.clone(), reason.clone()))
+            .collect(),
+        denied: db.get_denied_programs().await.map_err(database_error)?,
+    }))
+}
+
+// Route handler for GET /admin/denylist which lists the denied programs
+pub(crate) async fn get_denylist(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+) -> Result<Json<DenylistResponse>, AdminError> {
+    authorize(&headers)?;
+    denylist(&db).await
+}
+
+// Route handler for POST /admin/denylist which denies a program, or changes the reason it
+// is denied
+pub(crate) async fn deny_program(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(payload): Json<DenyProgramParams>,
+) -> Result<Json<DenylistResponse>, AdminError> {
+    authorize(&headers)?;
+
+    if !is_valid_address(&payload.program_id) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid program address: {}", payload.program_id),
+        ));
+    }
+    if payload.reason.trim().is_empty() {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "A reason is required to deny a program",
+        ));
+    }
+
+    tracing::info!("Denying program {}: {}", payload.program_id, payload.reason);
+    db.insert_denied_program(&DeniedProgram {
+        program_id: payload.program_id,
+        reason: payload.reason.trim().to_string(),
+        created_at: chrono::Utc::now().naive_utc(),
+    })
+    .await
+    .map_err(database_error)?;
+    denylist(&db).await
+}
+
+// Route handler for DELETE /admin/denylist/:program_id which allows a denied program again
+pub(crate) async fn allow_program(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Path(program_id): Path<String>,
+) -> Result<Json<DenylistResponse>, AdminError> {
+    authorize(&headers)?;
+
+    if configured_denials().contains_key(&program_id) {
+        return Err(error_response(
+            StatusCode::CONFLICT,
+            "Program is denied by DENIED_PROGRAM_IDS and can only be allowed by changing it",
+        ));
+    }
+    let deleted = db
+        .delete_denied_program(&program_id)
+        .await
+        .map_err(database_error)?;
+    if deleted == 0 {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            &format!("Program {} is not denied", program_id),
+        ));
+    }
+
+    tracing::info!("Allowing program {} again", program_id);
+    denylist(&db).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secrets"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::errors::{error_response, ApiError};
+use crate::hash_provider::OnChainHashProvider;
+use crate::models::{
+    AttestParams, Attestation, AttestationResponse, Commitment, ErrorResponse, Status, TrustLevel,
+};
+use crate::rpc::verify_authority_signature;
+use crate::validation::validate_attest_params;
+use axum::extract::State;
+use axum::{http::StatusCode, Json};
+
+// Message signed by the upgrade authority, binding the attested hash to the program and
+// to a single use challenge
+fn attestation_message(program_id: &str, executable_hash: &str, nonce: &str) -> String {
+    format!("attest {} {} {}", program_id, executable_hash, nonce)
+}
+
+// Route handler for POST /attest which records the executable hash the upgrade authority
+// of a program vouches for, for programs whose source is not published. The program gets
+// the authority_attested trust level while that hash is deployed, never is_verified.
+pub(crate) async fn attest_program(
+    State(db): State<DbClient>,
+    Json(payload): Json<AttestParams>,
+) -> Result<(StatusCode, Json<AttestationResponse>), (StatusCode, Json<ErrorResponse>)> {
+    if let Err(field_errors) = validate_attest_params(&payload) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Invalid attestation params".to_string(),
+                field_errors,
+            }),
+        ));
+    }
+    let executable_hash = payload.executable_hash.to_ascii_lowercase();
+
+    let api_error = |err: ApiError| {
+        tracing::warn!("Rejected attestation of {}: {}", payload.program_id, err);
+        error_response(err.status_code(), err.user_message())
+    };
+    check_not_denied(&db, &payload.program_id)
+        .await
+        .map_err(api_error)?;
+
+    let issued = db
+        .take_challenge(&payload.program_id, &payload.nonce)
+        .await
+        .map_err(api_error)?;
+    if !issued {
+        return Err(error_response(
+            StatusCode::FORBIDDEN,
+            "No pending challenge with this nonce for this program".to_string(),
+        ));
+    }
+    let message = attestation_message(&payload.program_id, &executable_hash, &payload.nonce);
+    let authority = verify_authority_signature(&payload.program_id, &message, &payload.signature)
+        .await
+        .map_err(api_error)?;
+
+    let attestation = Attestation {
+        program_id: payload.program_id.clone(),
+        executable_hash,
+        authority,
+        signature: payload.signature.clone(),
+        attested_at: chrono::Utc::now().naive_utc(),
+    };
+    db.upsert_attestation(&attestation).await.map_err(|err| {
+        tracing::error!("Error inserting attestation: {}", err);
+        error_response(err.status_code(), err.user_message())
+    })?;
+
+    let on_chain_hash = db
+        .hash_provider
+        .get_on_chain_hash(&attestation.program_id, Commitment::default())
+        .await
+        .unwrap_or_else(|err| {
+            tracing::warn!(
+                "Failed to get the on-chain hash of {}: {}",
+                attestation.program_id,
+                err
+            );
+            String::new()
+        });
+    let matches_on_chain = on_chain_hash == attestation.executable_hash;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(AttestationResponse {
+            trust_level: TrustLevel::of(false, &on_chain_hash, Some(&attestation)),
+            matches_on_chain,
+            attestation,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attestation_message() {
+        assert_eq!(
+            attestation_message(
+                "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "2f1c"
+            ),
+            "attest PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY \
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 2f1c"
+        );
+    }
+}
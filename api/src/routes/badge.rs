@@ -0,0 +1,91 @@
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::errors::ApiError;
+use crate::models::{BadgeParams, BadgeStyle, VerificationStatusParams};
+use crate::tenant::DEFAULT_TENANT;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+
+const BADGE_LABEL: &str = "solana verify";
+// Badges are cached for as long as the on-chain hash of a program is cached
+const BADGE_CACHE_CONTROL: &str = "public, max-age=60";
+
+// Rough width of a string in the 11px Verdana used by shields.io badges
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+/// Render a shields.io style badge with a grey label and a colored message.
+fn render_badge(label: &str, message: &str, color: &str, style: BadgeStyle) -> String {
+    let label_width = text_width(label);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+    let (radius, gradient, gradient_fill) = match style {
+        BadgeStyle::Flat => (3, "", ""),
+        BadgeStyle::Plastic => (
+            4,
+            r##"<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#fff" stop-opacity=".7"/><stop offset=".1" stop-color="#aaa" stop-opacity=".1"/><stop offset=".9" stop-opacity=".3"/><stop offset="1" stop-opacity=".5"/></linearGradient>"##,
+            r#"<rect width="100%" height="20" fill="url(#s)"/>"#,
+        ),
+    };
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}"><title>{label}: {message}</title>{gradient}<clipPath id="r"><rect width="{width}" height="20" rx="{radius}" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>{gradient_fill}</g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="14">{label}</text><text x="{message_x}" y="14">{message}</text></g></svg>"##,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+// Route handler for GET /badge/:address.svg which returns the verification status of a
+// program as an SVG badge
+pub(crate) async fn get_badge(
+    State(db): State<DbClient>,
+    Path(VerificationStatusParams { address }): Path<VerificationStatusParams>,
+    Query(BadgeParams { style }): Query<BadgeParams>,
+) -> impl IntoResponse {
+    let address = address.trim_end_matches(".svg").to_string();
+
+    let denied = check_not_denied(&db, &address).await;
+    let (message, color) = match denied {
+        Err(ApiError::ProgramDenied { .. }) => ("denied", "#e05d44"),
+        _ => match db
+            .check_is_verified(DEFAULT_TENANT.to_string(), address)
+            .await
+        {
+            Ok(result) if result.is_verified => ("verified", "#4c1"),
+            Ok(result) if result.record_exists => ("not verified", "#e05d44"),
+            Ok(_) => ("unknown", "#9f9f9f"),
+            Err(err) => {
+                tracing::error!("Error getting data from database: {}", err);
+                ("unknown", "#9f9f9f")
+            }
+        },
+    };
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, BADGE_CACHE_CONTROL),
+        ],
+        render_badge(BADGE_LABEL, message, color, style),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_badge() {
+        let flat = render_badge("solana verify", "verified", "#4c1", BadgeStyle::Flat);
+        assert!(flat.starts_with("<svg"));
+        assert!(flat.contains(r##"fill="#4c1""##));
+        assert!(flat.contains("<text x=\"50\" y=\"14\">solana verify</text>"));
+        assert!(!flat.contains("linearGradient"));
+
+        let plastic = render_badge("solana verify", "verified", "#4c1", BadgeStyle::Plastic);
+        assert!(plastic.contains("linearGradient"));
+    }
+}
@@ -0,0 +1,33 @@
+use crate::db::DbClient;
+use crate::models::{BuildEventsResponse, ErrorResponse, Status};
+use axum::extract::{Path, State};
+use axum::{http::StatusCode, Json};
+
+// Route handler for GET /builds/:id/events which returns the timeline of a build
+pub(crate) async fn get_build_events(
+    State(db): State<DbClient>,
+    Path(build_id): Path<String>,
+) -> Result<Json<BuildEventsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match db.get_build_events(&build_id).await {
+        Ok(events) if events.is_empty() => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: format!("No events found for build {}", build_id),
+                field_errors: Vec::new(),
+            }),
+        )),
+        Ok(events) => Ok(Json(BuildEventsResponse { build_id, events })),
+        Err(err) => {
+            tracing::error!("Error getting data from database: {}", err);
+            Err((
+                err.status_code(),
+                Json(ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }),
+            ))
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::db::DbClient;
+use crate::models::{ErrorResponse, Status};
+use crate::routes::CachePolicy;
+use crate::storage::{artifact_key, log_key};
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::{Extension, Json};
+
+type OutputResponse = (
+    Extension<CachePolicy>,
+    [(header::HeaderName, &'static str); 1],
+    Vec<u8>,
+);
+
+// Read a stored build output, which never changes once the build has written it
+async fn build_output(
+    db: &DbClient,
+    key: &str,
+    content_type: &'static str,
+    not_found: String,
+) -> Result<OutputResponse, (StatusCode, Json<ErrorResponse>)> {
+    match db.storage.get(key).await {
+        Ok(Some(data)) => Ok((
+            Extension(CachePolicy::Immutable),
+            [(header::CONTENT_TYPE, content_type)],
+            data,
+        )),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: not_found,
+                field_errors: Vec::new(),
+            }),
+        )),
+        Err(err) => {
+            tracing::error!("Error reading {} from storage: {}", key, err);
+            Err((
+                err.status_code(),
+                Json(ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }),
+            ))
+        }
+    }
+}
+
+// Route handler for GET /builds/:id/logs which returns the output of the build command
+pub(crate) async fn get_build_logs(
+    State(db): State<DbClient>,
+    Path(build_id): Path<String>,
+) -> Result<OutputResponse, (StatusCode, Json<ErrorResponse>)> {
+    build_output(
+        &db,
+        &log_key(&build_id),
+        "text/plain; charset=utf-8",
+        format!("No logs found for build {}", build_id),
+    )
+    .await
+}
+
+// Route handler for GET /builds/:id/artifact which returns the executable built by the build
+pub(crate) async fn get_build_artifact(
+    State(db): State<DbClient>,
+    Path(build_id): Path<String>,
+) -> Result<OutputResponse, (StatusCode, Json<ErrorResponse>)> {
+    build_output(
+        &db,
+        &artifact_key(&build_id),
+        "application/octet-stream",
+        format!("No artifact found for build {}", build_id),
+    )
+    .await
+}
@@ -0,0 +1,141 @@
+use crate::db::DbClient;
+use crate::errors::{api_error_response, ApiError};
+use crate::models::{ApiResponse, JobStatus, SolanaProgramBuildParams};
+use crate::routes::verify_async::start_verification;
+use crate::tenant::tenant_of;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Json};
+use serde_json::{Map, Value};
+
+// Overlay the fields of `overrides` onto the params of the original build. A null field
+// clears the original value, and the build stays in the tenant of the original.
+fn merge_params(
+    original: SolanaProgramBuildParams,
+    overrides: Map<String, Value>,
+) -> Result<SolanaProgramBuildParams, String> {
+    if overrides.contains_key("program_id") {
+        return Err("program_id cannot be changed, submit a new verification instead".to_string());
+    }
+
+    // The tenant is not serialized, so carry it over the round trip
+    let tenant_id = original.tenant_id.clone();
+    let mut params = serde_json::to_value(original).map_err(|err| err.to_string())?;
+    if let Value::Object(fields) = &mut params {
+        fields.extend(overrides);
+    }
+    let mut params: SolanaProgramBuildParams = serde_json::from_value(params)
+        .map_err(|err| format!("Invalid params override: {}", err))?;
+    params.tenant_id = tenant_id;
+    Ok(params)
+}
+
+// Route handler for POST /builds/:id/retry which submits a finished build again with some
+// of its params overridden, returning the id of the new build
+pub(crate) async fn retry_build(
+    State(db): State<DbClient>,
+    Path(build_id): Path<String>,
+    headers: HeaderMap,
+    Json(overrides): Json<Map<String, Value>>,
+) -> (StatusCode, Json<ApiResponse>) {
+    let tenant = match tenant_of(&headers) {
+        Ok(tenant) => tenant,
+        Err(err) => return api_error_response(err.status_code(), err.user_message()),
+    };
+    // Builds of other tenants are reported as missing rather than forbidden
+    let build = match db.get_job(&build_id).await {
+        Ok(build) if build.tenant_id == tenant => build,
+        Ok(_) | Err(ApiError::Diesel(diesel::result::Error::NotFound)) => {
+            return api_error_response(
+                StatusCode::NOT_FOUND,
+                format!("No build found with id {}", build_id),
+            )
+        }
+        Err(err) => {
+            tracing::error!("Error getting build {}: {}", build_id, err);
+            return api_error_response(err.status_code(), err.user_message());
+        }
+    };
+
+    if matches!(JobStatus::from(build.status.clone()), JobStatus::InProgress) {
+        return api_error_response(
+            StatusCode::CONFLICT,
+            format!("Build {} is still in progress", build_id),
+        );
+    }
+
+    let overridden: Vec<String> = overrides.keys().cloned().collect();
+    let mut payload = match merge_params(build.into(), overrides) {
+        Ok(payload) => payload,
+        Err(error) => return api_error_response(StatusCode::BAD_REQUEST, error),
+    };
+    payload.tenant_id = Some(tenant);
+    tracing::info!(
+        "Retrying build {} with overridden params: {}",
+        build_id,
+        overridden.join(", ")
+    );
+
+    start_verification(db, payload).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn original() -> SolanaProgramBuildParams {
+        serde_json::from_value(json!({
+            "repository": "https://github.com/Ellipsis-Labs/phoenix-v1",
+            "program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+            "commit_hash": "d2c5a3b",
+            "base_image": "solanafoundation/solana-verifiable-build:1.16.0",
+            "lib_name": "phoenix",
+        }))
+        .unwrap()
+    }
+
+    fn overrides(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(fields) => fields,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_merge_params() {
+        let params = merge_params(
+            original(),
+            overrides(json!({
+                "base_image": "solanafoundation/solana-verifiable-build:1.18.26",
+                "lib_name": null,
+            })),
+        )
+        .unwrap();
+        assert_eq!(
+            params.base_image.as_deref(),
+            Some("solanafoundation/solana-verifiable-build:1.18.26")
+        );
+        assert_eq!(params.lib_name, None);
+        assert_eq!(params.commit_hash.as_deref(), Some("d2c5a3b"));
+        assert_eq!(params.program_id, original().program_id);
+    }
+
+    #[test]
+    fn test_merge_params_keeps_tenant() {
+        let mut tenant_build = original();
+        tenant_build.tenant_id = Some("acme".to_string());
+        let params = merge_params(
+            tenant_build,
+            overrides(json!({ "base_image": "solanafoundation/solana-verifiable-build:1.18.26" })),
+        )
+        .unwrap();
+        assert_eq!(params.tenant(), "acme");
+    }
+
+    #[test]
+    fn test_merge_params_rejects_invalid_overrides() {
+        assert!(merge_params(original(), overrides(json!({ "program_id": "x" }))).is_err());
+        assert!(merge_params(original(), overrides(json!({ "cargo_args": "--offline" }))).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::db::DbClient;
+use crate::errors::{error_response, ApiError};
+use crate::models::{ByHashResponse, ErrorResponse, ExecutableHashParams, HashMatchedBuild};
+use crate::routes::CachePolicy;
+use crate::tenant::tenant_of;
+use crate::validation::is_valid_executable_hash;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Extension, Json};
+
+// Route handler for GET /by-hash/:hash which lists the programs whose latest build
+// produced the executable hash, to find programs sharing the same code or confirm a
+// locally computed hash was verified
+pub(crate) async fn get_builds_by_hash(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Path(ExecutableHashParams { hash }): Path<ExecutableHashParams>,
+) -> Result<(Extension<CachePolicy>, Json<ByHashResponse>), (StatusCode, Json<ErrorResponse>)> {
+    if !is_valid_executable_hash(&hash) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid executable hash: {}", hash),
+        ));
+    }
+    let hash = hash.to_ascii_lowercase();
+
+    let verifications = match tenant_of(&headers) {
+        Ok(tenant) => {
+            db.get_verifications_by_executable_hash(&tenant, &hash)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+    let verifications = verifications.map_err(|err| {
+        if !matches!(err, ApiError::InvalidApiKey) {
+            tracing::error!("Error getting data from database: {}", err);
+        }
+        error_response(err.status_code(), err.user_message())
+    })?;
+
+    let builds = verifications
+        .into_iter()
+        .map(|(verification, build)| HashMatchedBuild {
+            program_id: verification.program_id,
+            build_id: build.id,
+            repository: build.repository,
+            commit: build.commit_hash.or(build.resolved_commit),
+            status: build.status,
+            is_verified: verification.is_verified,
+            on_chain_hash: verification.on_chain_hash,
+            verified_at: verification.verified_at,
+        })
+        .collect();
+
+    Ok((
+        Extension(CachePolicy::Short),
+        Json(ByHashResponse {
+            executable_hash: hash,
+            builds,
+        }),
+    ))
+}
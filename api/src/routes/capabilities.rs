@@ -0,0 +1,75 @@
+use crate::build_args::{ALLOWED_BUILD_ARGS, MAX_BUILD_ARG_VALUE_LENGTH};
+use crate::cargo_profile::{DEFAULT_CARGO_PROFILE, PROFILE_SETTINGS};
+use crate::db::dedup_window_hours;
+use crate::language::{supported_languages, DEFAULT_LANGUAGE};
+use crate::models::{
+    BaseImageCapabilities, BuildArgCapabilities, BuildSystem, BuildTarget, CapabilitiesResponse,
+    CargoProfileCapabilities, Commitment, Network, ParamChoices, RustflagsCapabilities,
+};
+use crate::routes::CachePolicy;
+use crate::rustflags::{ALLOWED_CODEGEN_OPTIONS, MAX_RUSTFLAGS};
+use axum::{Extension, Json};
+
+// Route handler for GET /capabilities which lists the values accepted by /verify, taken
+// from the same definitions the request validation uses
+pub(crate) async fn get_capabilities() -> (Extension<CachePolicy>, Json<CapabilitiesResponse>) {
+    let capabilities = CapabilitiesResponse {
+        languages: ParamChoices {
+            values: supported_languages()
+                .into_iter()
+                .map(ToString::to_string)
+                .collect(),
+            default: Some(DEFAULT_LANGUAGE.to_string()),
+        },
+        build_systems: ParamChoices {
+            values: BuildSystem::ALL
+                .iter()
+                .map(|build_system| build_system.as_str().to_string())
+                .collect(),
+            default: Some(BuildSystem::default().as_str().to_string()),
+        },
+        targets: ParamChoices {
+            values: BuildTarget::ALL
+                .iter()
+                .map(|target| target.as_str().to_string())
+                .collect(),
+            default: Some(BuildTarget::default().as_str().to_string()),
+        },
+        networks: ParamChoices {
+            values: Network::ALL
+                .iter()
+                .map(|network| network.as_str().to_string())
+                .collect(),
+            default: Some(Network::default().as_str().to_string()),
+        },
+        commitments: ParamChoices {
+            values: Commitment::ALL
+                .iter()
+                .map(|commitment| commitment.as_str().to_string())
+                .collect(),
+            default: Some(Commitment::default().as_str().to_string()),
+        },
+        base_image: BaseImageCapabilities {
+            any_image: true,
+            digest_pinning_recommended: true,
+        },
+        docker_build_args: BuildArgCapabilities {
+            keys: ALLOWED_BUILD_ARGS.iter().map(ToString::to_string).collect(),
+            max_value_length: MAX_BUILD_ARG_VALUE_LENGTH,
+        },
+        cargo_profile: CargoProfileCapabilities {
+            default: DEFAULT_CARGO_PROFILE.to_string(),
+            override_settings: PROFILE_SETTINGS.iter().map(ToString::to_string).collect(),
+        },
+        rustflags: RustflagsCapabilities {
+            codegen_options: ALLOWED_CODEGEN_OPTIONS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            max_flags: MAX_RUSTFLAGS,
+        },
+        dedup_window_hours: dedup_window_hours(),
+    };
+    // Cached briefly so a deployment changing them is picked up quickly
+    (Extension(CachePolicy::Short), Json(capabilities))
+}
@@ -0,0 +1,47 @@
+use crate::certificate::{build_certificate, sign_certificate, signing_key_from_env};
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::errors::{error_response, ApiError};
+use crate::models::{ErrorResponse, VerificationCertificate, VerificationStatusParams};
+use crate::tenant::tenant_of;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Json};
+
+fn api_error(err: ApiError) -> (StatusCode, Json<ErrorResponse>) {
+    tracing::error!("Failed to issue certificate: {}", err);
+    err.into()
+}
+
+// Route handler for GET /status/:address/certificate which returns a portable record of
+// the latest verification of a program
+pub(crate) async fn get_certificate(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Path(VerificationStatusParams { address }): Path<VerificationStatusParams>,
+) -> Result<Json<VerificationCertificate>, (StatusCode, Json<ErrorResponse>)> {
+    let tenant = tenant_of(&headers).map_err(api_error)?;
+    check_not_denied(&db, &address).await.map_err(api_error)?;
+
+    let verified_build = match db.get_verified_build(&tenant, &address).await {
+        Ok(verified_build) => verified_build,
+        Err(ApiError::Diesel(diesel::result::Error::NotFound)) => {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                format!("No verification found for program {}", address),
+            ))
+        }
+        Err(err) => return Err(api_error(err)),
+    };
+    let build = db
+        .get_job(&verified_build.solana_build_id)
+        .await
+        .map_err(api_error)?;
+
+    let certificate = build_certificate(verified_build, build);
+    let certificate = match signing_key_from_env().map_err(api_error)? {
+        Some(key) => sign_certificate(certificate, &key).map_err(api_error)?,
+        None => certificate,
+    };
+    Ok(Json(certificate))
+}
@@ -0,0 +1,42 @@
+use crate::builder::is_valid_address;
+use crate::db::{DbClient, CHALLENGE_TTL_SECS};
+use crate::models::{ChallengeResponse, ErrorResponse, Status};
+use axum::extract::{Path, State};
+use axum::{http::StatusCode, Json};
+
+// Route handler for GET /challenge/:program_id which issues a nonce to be signed by the
+// program upgrade authority and submitted with POST /verify
+pub(crate) async fn get_challenge(
+    State(db): State<DbClient>,
+    Path(program_id): Path<String>,
+) -> Result<Json<ChallengeResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if !is_valid_address(&program_id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: format!("Invalid program address: {}", program_id),
+                field_errors: Vec::new(),
+            }),
+        ));
+    }
+
+    let nonce = uuid::Uuid::new_v4().to_string();
+    if let Err(err) = db.set_challenge(&program_id, &nonce).await {
+        tracing::error!("Failed to store challenge: {}", err);
+        return Err((
+            err.status_code(),
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: err.user_message(),
+                field_errors: Vec::new(),
+            }),
+        ));
+    }
+
+    Ok(Json(ChallengeResponse {
+        program_id,
+        nonce,
+        expires_in: CHALLENGE_TTL_SECS,
+    }))
+}
@@ -0,0 +1,117 @@
+use crate::claim::{check_dns_claim, check_repository_claim, is_claim_still_proven};
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::errors::{error_response, ApiError};
+use crate::models::{ClaimMethod, ClaimParams, ErrorResponse, ProgramClaim, Status};
+use crate::tenant::tenant_of;
+use crate::validation::validate_claim_params;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Json};
+
+// Route handler for POST /claim which attributes the verification of a program to the
+// owners of its repository or of a domain, once they prove they control it
+pub(crate) async fn claim_program(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(payload): Json<ClaimParams>,
+) -> Result<(StatusCode, Json<ProgramClaim>), (StatusCode, Json<ErrorResponse>)> {
+    if let Err(field_errors) = validate_claim_params(&payload) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Invalid claim params".to_string(),
+                field_errors,
+            }),
+        ));
+    }
+
+    let api_error = |err: ApiError| {
+        tracing::warn!("Rejected claim of {}: {}", payload.program_id, err);
+        error_response(err.status_code(), err.user_message())
+    };
+    let tenant = tenant_of(&headers).map_err(api_error)?;
+    check_not_denied(&db, &payload.program_id)
+        .await
+        .map_err(api_error)?;
+
+    // Only verified programs can be claimed, as the claim attributes their verification
+    let verification = match db.get_verified_build(&tenant, &payload.program_id).await {
+        Ok(verification) => verification,
+        Err(ApiError::Diesel(diesel::result::Error::NotFound)) => {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                "No verification record found for this program".to_string(),
+            ))
+        }
+        Err(err) => return Err(api_error(err)),
+    };
+    if !verification.is_verified {
+        return Err(error_response(
+            StatusCode::CONFLICT,
+            "Only verified programs can be claimed".to_string(),
+        ));
+    }
+
+    let build = db
+        .get_job(&verification.solana_build_id)
+        .await
+        .map_err(api_error)?;
+    let repository = build.repository.clone();
+    let (claimed_by, proof) = match (payload.method, &payload.domain) {
+        (ClaimMethod::Dns, Some(domain)) => {
+            let domain = domain.to_ascii_lowercase();
+            let proof = check_dns_claim(&domain, &payload.program_id, &build.repository)
+                .await
+                .map_err(api_error)?;
+            (domain, proof)
+        }
+        _ => {
+            let proof = check_repository_claim(&build.repository, &payload.program_id)
+                .await
+                .map_err(api_error)?;
+            (build.repository, proof)
+        }
+    };
+
+    let claim = ProgramClaim {
+        tenant_id: tenant,
+        program_id: payload.program_id.clone(),
+        method: payload.method.as_str().to_string(),
+        claimed_by,
+        proof,
+        claimed_at: chrono::Utc::now().naive_utc(),
+    };
+    // A claim which the current verification no longer proves, such as one made while the
+    // program was verified from a fork, gives way to a claim proven against it
+    let stale = match db.get_claim(&claim.tenant_id, &claim.program_id).await {
+        Ok(Some(existing))
+            if existing.method != claim.method || existing.claimed_by != claim.claimed_by =>
+        {
+            !is_claim_still_proven(&existing, &repository)
+                .await
+                .map_err(api_error)?
+        }
+        Ok(_) => false,
+        Err(err) => return Err(api_error(err)),
+    };
+    let stored = if stale {
+        tracing::info!(
+            "Replacing the stale claim of {} by {}",
+            claim.program_id,
+            claim.claimed_by
+        );
+        db.replace_claim(&claim).await
+    } else {
+        db.upsert_claim(&claim).await
+    };
+    stored.map_err(|err| {
+        if !matches!(err, ApiError::AlreadyClaimed(_)) {
+            tracing::error!("Error inserting claim: {}", err);
+        }
+        error_response(err.status_code(), err.user_message())
+    })?;
+
+    Ok((StatusCode::CREATED, Json(claim)))
+}
@@ -0,0 +1,30 @@
+use crate::db::DbClient;
+use crate::models::{HealthConfig, HealthResponse, ReadyResponse, ReadyState};
+use axum::{extract::State, http::StatusCode, Json};
+
+// Route handler for GET /health which reports the connection pool usage, the database
+// timeouts and the state of the RPC circuit breaker
+pub(crate) async fn get_health(State(db): State<DbClient>) -> Json<HealthResponse> {
+    let (database, redis) = db.pool_status();
+    Json(HealthResponse {
+        database,
+        redis,
+        config: HealthConfig {
+            db_acquire_timeout_ms: db.acquire_timeout.as_millis() as u64,
+            db_statement_timeout_ms: db.statement_timeout.as_millis() as u64,
+        },
+        rpc_circuit: db.hash_provider.circuit_status(),
+    })
+}
+
+// Route handler for GET /ready which load balancers poll. Responds 503 once the instance
+// is drained through POST /admin/drain, telling whether builds are still running.
+pub(crate) async fn get_ready(State(db): State<DbClient>) -> (StatusCode, Json<ReadyResponse>) {
+    let readiness = db.workers.readiness();
+    let status = if readiness.state == ReadyState::Ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
@@ -1,27 +1,42 @@
 use crate::db::DbClient;
 use crate::models::{JobStatus, JobVerificationResponse};
+use crate::params_hash::params_hash;
+use crate::routes::CachePolicy;
 use axum::extract::{Path, State};
-use axum::Json;
+use axum::{Extension, Json};
 
 // Route handler for GET /jobs/:job_id which checks the status of a job
 pub(crate) async fn get_job_status(
     State(db): State<DbClient>,
     Path(job_id): Path<String>,
-) -> Json<JobVerificationResponse> {
-    let status = db.get_job(&job_id).await;
+) -> (Extension<CachePolicy>, Json<JobVerificationResponse>) {
+    // Job results follow the latest verification of the program, so they are never final
+    (
+        Extension(CachePolicy::Short),
+        job_status(&db, &job_id).await,
+    )
+}
+
+async fn job_status(db: &DbClient, job_id: &str) -> Json<JobVerificationResponse> {
+    let status = db.get_job(job_id).await;
     match status {
-        Ok(res) => match res.status.into() {
+        Ok(res) => match res.status.clone().into() {
             JobStatus::Completed => {
-                let verify_build_data = db.get_verified_build(&res.program_id).await;
+                let verify_build_data =
+                    db.get_verified_build(&res.tenant_id, &res.program_id).await;
                 match verify_build_data {
                     Ok(verified_build) => Json(JobVerificationResponse {
                         status: JobStatus::Completed.into(),
                         message: "Job completed".to_string(),
                         on_chain_hash: verified_build.on_chain_hash,
                         executable_hash: verified_build.executable_hash,
-                        repo_url: res.commit_hash.map_or(res.repository.clone(), |hash| {
-                            format!("{}/commit/{}", res.repository, hash)
-                        }),
+                        repo_url: res
+                            .commit_hash
+                            .as_ref()
+                            .map_or(res.repository.clone(), |hash| {
+                                format!("{}/commit/{}", res.repository, hash)
+                            }),
+                        params_hash: params_hash(&res),
                     }),
                     Err(err) => {
                         tracing::error!("Error getting data from database: {}", err);
@@ -31,6 +46,7 @@ pub(crate) async fn get_job_status(
                             on_chain_hash: "".to_string(),
                             executable_hash: "".to_string(),
                             repo_url: "".to_string(),
+                            params_hash: params_hash(&res),
                         })
                     }
                 }
@@ -41,6 +57,7 @@ pub(crate) async fn get_job_status(
                 on_chain_hash: "".to_string(),
                 executable_hash: "".to_string(),
                 repo_url: "".to_string(),
+                params_hash: params_hash(&res),
             }),
             JobStatus::InProgress => Json(JobVerificationResponse {
                 status: JobStatus::InProgress.into(),
@@ -48,6 +65,7 @@ pub(crate) async fn get_job_status(
                 on_chain_hash: "".to_string(),
                 executable_hash: "".to_string(),
                 repo_url: "".to_string(),
+                params_hash: params_hash(&res),
             }),
         },
         Err(err) => {
@@ -58,6 +76,7 @@ pub(crate) async fn get_job_status(
                 on_chain_hash: "".to_string(),
                 executable_hash: "".to_string(),
                 repo_url: "".to_string(),
+                params_hash: "".to_string(),
             })
         }
     }
@@ -0,0 +1,40 @@
+use crate::models::{ErrorResponse, GroupLimitStatus, LimitsResponse, Status};
+use crate::rate_limit::{client_limits, RateLimitConfig};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Json;
+use tower_governor::key_extractor::{KeyExtractor, SmartIpKeyExtractor};
+
+// Route handler for GET /limits which reports the remaining requests of the client in
+// every group of routes and when its allowance is replenished, so it can back off before
+// being rejected
+pub(crate) async fn get_limits(
+    request: Request<Body>,
+) -> Result<Json<LimitsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let ip = SmartIpKeyExtractor.extract(&request).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Unable to determine the client IP address".to_string(),
+                field_errors: Vec::new(),
+            }),
+        )
+    })?;
+
+    let limits = client_limits(ip)
+        .into_iter()
+        .map(|(limit, remaining, reset_in_secs)| GroupLimitStatus {
+            group: limit.group.to_string(),
+            limit: limit.burst_size,
+            remaining,
+            period_secs: limit.period_secs,
+            reset_in_secs,
+        })
+        .collect();
+
+    Ok(Json(LimitsResponse {
+        enforced: RateLimitConfig::from_env().enforce,
+        limits,
+    }))
+}
@@ -0,0 +1,14 @@
+use crate::metrics::request_metrics;
+use axum::http::header;
+
+// Content type of the Prometheus text exposition format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+// Route handler for GET /metrics which exposes the request duration histograms by route
+// and status code
+pub(crate) async fn get_metrics() -> ([(header::HeaderName, &'static str); 1], String) {
+    (
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        request_metrics().render(),
+    )
+}
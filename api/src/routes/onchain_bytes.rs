@@ -0,0 +1,50 @@
+use std::env;
+
+use crate::builder::get_on_chain_bytes;
+use crate::models::{
+    BytesEncoding, ErrorResponse, OnchainBytesParams, OnchainBytesResponse, Status,
+    VerificationStatusParams,
+};
+use axum::extract::{Path, Query};
+use axum::{http::StatusCode, Json};
+use base64::Engine;
+
+// Default cap on the size of returned programs (10 MiB)
+const DEFAULT_MAX_PROGRAM_SIZE: u64 = 10 * 1024 * 1024;
+
+// Route handler for GET /onchain-bytes/:address which returns the deployed program bytes
+pub(crate) async fn get_onchain_bytes(
+    Path(VerificationStatusParams { address }): Path<VerificationStatusParams>,
+    Query(OnchainBytesParams { encoding }): Query<OnchainBytesParams>,
+) -> Result<Json<OnchainBytesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let max_size = env::var("ONCHAIN_BYTES_MAX_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_PROGRAM_SIZE);
+
+    let bytes = get_on_chain_bytes(&address, max_size)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to get on-chain bytes for {}: {}", address, err);
+            (
+                err.status_code(),
+                Json(ErrorResponse {
+                    status: Status::Error,
+                    error: err.to_string(),
+                    field_errors: Vec::new(),
+                }),
+            )
+        })?;
+
+    let data = match encoding {
+        BytesEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(&bytes),
+        BytesEncoding::Hex => hex::encode(&bytes),
+    };
+
+    Ok(Json(OnchainBytesResponse {
+        program_id: address,
+        encoding,
+        size: bytes.len(),
+        data,
+    }))
+}
@@ -0,0 +1,48 @@
+use crate::builder::is_valid_address;
+use crate::digests::edge_fingerprint;
+use crate::errors::error_response;
+use crate::models::{
+    ErrorResponse, QuickcheckParams, QuickcheckResponse, VerificationStatusParams,
+};
+use crate::routes::CachePolicy;
+use crate::rpc::get_program_edges;
+use axum::extract::{Path, Query};
+use axum::{http::StatusCode, Extension, Json};
+
+// Upper bound of the number of bytes taken from each end of the executable
+const MAX_EDGE_BYTES: usize = 4096;
+
+// Route handler for GET /quickcheck/:address which returns the size of the deployed
+// executable and a fingerprint of its first and last bytes, without a full hash or build
+pub(crate) async fn get_quickcheck(
+    Path(VerificationStatusParams { address }): Path<VerificationStatusParams>,
+    Query(QuickcheckParams { bytes }): Query<QuickcheckParams>,
+) -> Result<(Extension<CachePolicy>, Json<QuickcheckResponse>), (StatusCode, Json<ErrorResponse>)> {
+    if !is_valid_address(&address) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid program address: {}", address),
+        ));
+    }
+    if bytes == 0 || bytes > MAX_EDGE_BYTES {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("bytes must be between 1 and {}", MAX_EDGE_BYTES),
+        ));
+    }
+
+    let edges = get_program_edges(&address, bytes).await.map_err(|err| {
+        tracing::error!("Failed to quickcheck {}: {}", address, err);
+        error_response(err.status_code(), err.to_string())
+    })?;
+
+    Ok((
+        Extension(CachePolicy::Short),
+        Json(QuickcheckResponse {
+            program_id: address,
+            program_size: edges.program_size,
+            edge_bytes: bytes,
+            fingerprint: edge_fingerprint(edges.program_size, &edges.head, &edges.tail),
+        }),
+    ))
+}
@@ -0,0 +1,27 @@
+use crate::db::DbClient;
+use crate::models::{RegistryEntryResponse, RegistryResponse};
+use crate::registry::registry_programs;
+use crate::tenant::DEFAULT_TENANT;
+use axum::{extract::State, Json};
+
+// Route handler for GET /registry which lists the pinned programs and their latest status
+pub(crate) async fn get_registry(State(db): State<DbClient>) -> Json<RegistryResponse> {
+    let mut programs = Vec::with_capacity(registry_programs().len());
+
+    for params in registry_programs() {
+        let verified_build = db
+            .get_verified_build(DEFAULT_TENANT, &params.program_id)
+            .await
+            .ok();
+        programs.push(RegistryEntryResponse {
+            program_id: params.program_id.clone(),
+            repository: params.repository.clone(),
+            is_verified: verified_build
+                .as_ref()
+                .is_some_and(|build| build.is_verified),
+            last_verified_at: verified_build.map(|build| build.verified_at),
+        });
+    }
+
+    Json(RegistryResponse { programs })
+}
@@ -0,0 +1,34 @@
+use crate::models::{ErrorResponse, ReproducibilityCheckParams, ReproducibilityReport, Status};
+use crate::reproducibility::check_reproducibility;
+use crate::validation::validate_reproducibility_params;
+use axum::{http::StatusCode, Json};
+
+// Route handler for POST /reproducibility-check which reports what may keep a repository
+// from building reproducibly, before it is submitted for verification
+pub(crate) async fn reproducibility_check(
+    Json(payload): Json<ReproducibilityCheckParams>,
+) -> Result<Json<ReproducibilityReport>, (StatusCode, Json<ErrorResponse>)> {
+    if let Err(field_errors) = validate_reproducibility_params(&payload) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Invalid reproducibility check parameters".to_string(),
+                field_errors,
+            }),
+        ));
+    }
+
+    let report = check_reproducibility(&payload).await.map_err(|err| {
+        tracing::warn!("Failed to check {}: {}", payload.repository, err);
+        (
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Failed to read the repository".to_string(),
+                field_errors: Vec::new(),
+            }),
+        )
+    })?;
+    Ok(Json(report))
+}
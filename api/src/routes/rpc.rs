@@ -0,0 +1,251 @@
+use std::net::{IpAddr, SocketAddr};
+
+use crate::db::DbClient;
+use crate::models::{
+    ListFormat, RpcError, RpcListParams, RpcRequest, RpcResponse, RpcStatusParams,
+    SolanaProgramBuildParams, StatusParams, VerificationStatusParams, VerifiedProgramsParams,
+};
+use crate::rate_limit::{
+    check_rpc_status, check_rpc_verify, client_ip, GroupLimit, RateLimitConfig, RPC_STATUS_LIMIT,
+    RPC_VERIFY_LIMIT,
+};
+use crate::routes::status::verify_status;
+use crate::routes::verified_programs::get_verified_programs_list;
+use crate::routes::verify_async::verify_async;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+// The REST handler of the method responded with an error status
+const HANDLER_ERROR: i64 = -32000;
+
+// Most requests accepted in a batch, as for /status/batch
+const MAX_RPC_BATCH_SIZE: usize = 100;
+
+// Methods of /rpc, each dispatched to the REST handler of the same name
+const RPC_METHODS: &[&str] = &["verify", "status", "list"];
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // Methods without required params can be called without any
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+// Result of a call from the response of its REST handler: the JSON body of a successful
+// response, or an error carrying the HTTP status and body of a failed one
+async fn response_result(response: Response) -> Result<Value, RpcError> {
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|_| RpcError::new(INTERNAL_ERROR, "Failed to read the response"))?;
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    if status.is_success() {
+        return Ok(body);
+    }
+
+    let message = body
+        .get("error")
+        .or_else(|| body.get("message"))
+        .and_then(Value::as_str)
+        .or(status.canonical_reason())
+        .unwrap_or("Request failed")
+        .to_string();
+    Err(RpcError {
+        code: HANDLER_ERROR,
+        message,
+        data: Some(json!({ "http_status": status.as_u16(), "response": body })),
+    })
+}
+
+// Error of a call over the `rpc_verify` or `rpc_status` allowance of the client, shaped like
+// the 429 the per-IP limiters respond with
+fn limit_error(method: &str, limit: GroupLimit) -> RpcError {
+    RpcError {
+        code: HANDLER_ERROR,
+        message: format!(
+            "Too many {} calls, the allowance of {} is replenished one call every {} seconds",
+            method, limit.burst_size, limit.period_secs
+        ),
+        data: Some(json!({ "http_status": StatusCode::TOO_MANY_REQUESTS.as_u16() })),
+    }
+}
+
+async fn dispatch(
+    db: &DbClient,
+    headers: &HeaderMap,
+    ip: Option<IpAddr>,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    let response = match method {
+        "verify" => {
+            let params: SolanaProgramBuildParams = parse_params(params)?;
+            // Each call submits a build, so each is charged like a request to /verify
+            let config = RateLimitConfig::from_env();
+            match ip {
+                Some(ip) if check_rpc_verify(ip, config) => {}
+                None if !config.enforce => {}
+                _ => return Err(limit_error("verify", RPC_VERIFY_LIMIT)),
+            }
+            verify_async(State(db.clone()), headers.clone(), Json(params))
+                .await
+                .into_response()
+        }
+        "status" => {
+            let RpcStatusParams { address, hash_mode } = parse_params(params)?;
+            // Each call reads the deployed program, so each is charged to `rpc_status`
+            let config = RateLimitConfig::from_env();
+            match ip {
+                Some(ip) if check_rpc_status(ip, config) => {}
+                None if !config.enforce => {}
+                _ => return Err(limit_error("status", RPC_STATUS_LIMIT)),
+            }
+            verify_status(
+                State(db.clone()),
+                headers.clone(),
+                Path(VerificationStatusParams { address }),
+                Query(StatusParams { hash_mode }),
+            )
+            .await
+            .into_response()
+        }
+        "list" => {
+            let RpcListParams { label } = parse_params(params)?;
+            get_verified_programs_list(
+                State(db.clone()),
+                headers.clone(),
+                Query(VerifiedProgramsParams {
+                    format: Some(ListFormat::Json),
+                    label,
+                }),
+            )
+            .await
+            .into_response()
+        }
+        _ => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!(
+                    "Unknown method {}, expected one of: {}",
+                    method,
+                    RPC_METHODS.join(", ")
+                ),
+            ))
+        }
+    };
+    response_result(response).await
+}
+
+// Response to one request of the body, `None` for notifications
+async fn handle_request(
+    db: &DbClient,
+    headers: &HeaderMap,
+    ip: Option<IpAddr>,
+    request: Value,
+) -> Option<RpcResponse> {
+    let is_notification = request.get("id").is_none();
+    let request = match serde_json::from_value::<RpcRequest>(request) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        _ => {
+            return Some(RpcResponse::new(
+                Value::Null,
+                Err(RpcError::new(
+                    INVALID_REQUEST,
+                    "Invalid JSON-RPC 2.0 request",
+                )),
+            ))
+        }
+    };
+
+    let outcome = dispatch(db, headers, ip, &request.method, request.params).await;
+    (!is_notification).then(|| RpcResponse::new(request.id, outcome))
+}
+
+// Route handler for POST /rpc which serves the verify, status and list endpoints as
+// JSON-RPC 2.0 methods, alone or in batches, for clients standardized on JSON-RPC
+pub(crate) async fn rpc(
+    State(db): State<DbClient>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let ip = client_ip(&headers, connect_info);
+    let body = match serde_json::from_slice::<Value>(&body) {
+        Ok(body) => body,
+        Err(err) => {
+            return Json(RpcResponse::new(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, err.to_string())),
+            ))
+            .into_response()
+        }
+    };
+
+    let Value::Array(requests) = body else {
+        return match handle_request(&db, &headers, ip, body).await {
+            Some(response) => Json(response).into_response(),
+            None => StatusCode::NO_CONTENT.into_response(),
+        };
+    };
+    if requests.is_empty() || requests.len() > MAX_RPC_BATCH_SIZE {
+        return Json(RpcResponse::new(
+            Value::Null,
+            Err(RpcError::new(
+                INVALID_REQUEST,
+                format!(
+                    "A batch must contain between 1 and {} requests",
+                    MAX_RPC_BATCH_SIZE
+                ),
+            )),
+        ))
+        .into_response();
+    }
+
+    let responses: Vec<RpcResponse> = futures::future::join_all(
+        requests
+            .into_iter()
+            .map(|request| handle_request(&db, &headers, ip, request)),
+    )
+    .await
+    .into_iter()
+    .flatten()
+    .collect();
+    if responses.is_empty() {
+        return StatusCode::NO_CONTENT.into_response();
+    }
+    Json(responses).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_response_result() {
+        let success = Json(json!({ "is_verified": true })).into_response();
+        assert_eq!(
+            response_result(success).await.unwrap(),
+            json!({ "is_verified": true })
+        );
+
+        let failure = (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "status": "error", "error": "Program not found" })),
+        )
+            .into_response();
+        let error = response_result(failure).await.unwrap_err();
+        assert_eq!(error.code, HANDLER_ERROR);
+        assert_eq!(error.message, "Program not found");
+        assert_eq!(error.data.unwrap()["http_status"], 404);
+    }
+}
@@ -1,38 +1,363 @@
+use crate::builder::{get_on_chain_hash_in_mode, is_valid_address};
 use crate::db::DbClient;
-use crate::models::{ApiResponse, ErrorResponse, Status, StatusResponse, VerificationStatusParams};
-use axum::extract::{Path, State};
-use axum::Json;
+use crate::denylist::{check_not_denied, configured_denials};
+use crate::errors::ApiError;
+use crate::models::{
+    ApiResponse, Attestation, BatchStatusEntry, BatchStatusParams, BatchStatusResponse,
+    ErrorResponse, FieldError, HashMode, ProgramClaim, Status, StatusParams, StatusResponse,
+    TrustLevel, VerificationMethod, VerificationResponse, VerificationStatusParams, Warning,
+    WarningCode,
+};
+use crate::routes::CachePolicy;
+use crate::tenant::tenant_of;
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Extension, Json};
+
+// Status of a program from its verification, running builds and attestation
+fn status_response(
+    mut result: VerificationResponse,
+    in_progress_builds: Vec<String>,
+    attestation: Option<Attestation>,
+    claim: Option<ProgramClaim>,
+) -> StatusResponse {
+    if let (Some(false), Some(declared_id)) = (result.declared_id_matches, &result.declared_id) {
+        result.warnings.push(Warning::new(
+            WarningCode::DeclaredIdMismatch,
+            format!(
+                "The source declares program id {} with declare_id!, not this one. The program may have been deployed to an unexpected address.",
+                declared_id
+            ),
+        ));
+    }
+    let trust_level = TrustLevel::of(
+        result.is_verified,
+        &result.on_chain_hash,
+        attestation.as_ref(),
+    );
+    StatusResponse {
+        is_verified: result.is_verified,
+        message: if result.is_verified {
+            "On chain program verified".to_string()
+        } else if trust_level == TrustLevel::AuthorityAttested {
+            "On chain program attested by its upgrade authority, not verified from source"
+                .to_string()
+        } else if result
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning.code, WarningCode::ProgramClosed))
+        {
+            "On chain program is closed".to_string()
+        } else if result.record_exists {
+            "On chain program not verified".to_string()
+        } else {
+            "No verification record found for this program".to_string()
+        },
+        on_chain_hash: result.on_chain_hash,
+        last_verified_at: result.last_verified_at,
+        executable_hash: result.executable_hash,
+        repo_url: result.repo_url,
+        verified_by_authority: result.verified_by_authority,
+        record_exists: result.record_exists,
+        extra_hashes: result.extra_hashes,
+        loader: result.loader,
+        registry_match: result.registry_match,
+        program_size: result.program_size,
+        rent_exempt: result.rent_exempt,
+        upgrade_authority: result.upgrade_authority,
+        immutable: result.immutable,
+        resolved_commit: result.resolved_commit,
+        docker_build_args: result.docker_build_args,
+        target: result.target,
+        network: result.network,
+        commitment: result.commitment,
+        rustflags: result.rustflags,
+        status_changed: result.status_changed,
+        declared_id: result.declared_id,
+        declared_id_matches: result.declared_id_matches,
+        hash_mode: HashMode::Trimmed,
+        on_chain_mode_hash: None,
+        labels: result.labels,
+        upstream_repo: result.upstream_repo,
+        published_crate: result.published_crate,
+        in_progress: !in_progress_builds.is_empty(),
+        in_progress_builds,
+        trust_level,
+        verification_method: VerificationMethod::of(trust_level),
+        attestation,
+        claim,
+        warnings: result.warnings,
+    }
+}
+
+// Add the hash of the deployed program in `hash_mode` to a status, the canonical hash
+// being the `trimmed` one already compared with the executable hash
+async fn with_hash_mode(
+    mut status: StatusResponse,
+    hash_mode: HashMode,
+    address: &str,
+) -> StatusResponse {
+    status.hash_mode = hash_mode;
+    if hash_mode == HashMode::Trimmed {
+        return status;
+    }
+    match get_on_chain_hash_in_mode(address, hash_mode).await {
+        Ok(hash) => status.on_chain_mode_hash = Some(hash),
+        Err(err) => {
+            tracing::warn!(
+                "Failed to hash {} in {} mode: {}",
+                address,
+                hash_mode.as_str(),
+                err
+            );
+            status.warnings.push(Warning::new(
+                WarningCode::RpcUnreachable,
+                format!(
+                    "Failed to fetch the deployed program to hash it in {} mode.",
+                    hash_mode.as_str()
+                ),
+            ));
+        }
+    }
+    status
+}
 
 //  Route handler for GET /status/:address which checks if the program is verified or not
 pub(crate) async fn verify_status(
     State(db): State<DbClient>,
+    headers: HeaderMap,
     Path(VerificationStatusParams { address }): Path<VerificationStatusParams>,
-) -> Json<ApiResponse> {
-    match db.check_is_verified(address).await {
-        Ok(result) => Json(
-            StatusResponse {
-                is_verified: result.is_verified,
-                message: if result.is_verified {
-                    "On chain program verified".to_string()
-                } else {
-                    "On chain program not verified".to_string()
-                },
-                on_chain_hash: result.on_chain_hash,
-                last_verified_at: result.last_verified_at,
-                executable_hash: result.executable_hash,
-                repo_url: result.repo_url,
+    Query(StatusParams { hash_mode }): Query<StatusParams>,
+) -> (StatusCode, Extension<CachePolicy>, Json<ApiResponse>) {
+    let checked = match tenant_of(&headers) {
+        Ok(tenant) => check_not_denied(&db, &address).await.map(|_| tenant),
+        Err(err) => Err(err),
+    };
+    let tenant = match checked {
+        Ok(tenant) => tenant,
+        Err(err) => {
+            if !matches!(
+                err,
+                ApiError::ProgramDenied { .. } | ApiError::InvalidApiKey
+            ) {
+                tracing::error!("Error getting data from database: {}", err);
             }
-            .into(),
+            return (
+                err.status_code(),
+                Extension(CachePolicy::Short),
+                Json(
+                    ErrorResponse {
+                        status: Status::Error,
+                        error: err.user_message(),
+                        field_errors: Vec::new(),
+                    }
+                    .into(),
+                ),
+            );
+        }
+    };
+
+    // Running builds are looked up on every request, as the cached result cannot tell
+    let in_progress_builds = db
+        .get_in_progress_builds(&tenant, &address)
+        .await
+        .unwrap_or_else(|err| {
+            tracing::warn!(
+                "Failed to get the in-progress builds of {}: {}",
+                address,
+                err
+            );
+            Vec::new()
+        });
+
+    let attestation = db.get_attestation(&address).await.unwrap_or_else(|err| {
+        tracing::warn!("Failed to get the attestation of {}: {}", address, err);
+        None
+    });
+
+    let claim = db.get_claim(&tenant, &address).await.unwrap_or_else(|err| {
+        tracing::warn!("Failed to get the claim of {}: {}", address, err);
+        None
+    });
+
+    match db.check_is_verified(tenant, address.clone()).await {
+        Ok(result) => (
+            // 404 when nothing is known about the program, so clients can tell it apart
+            // from a program whose build did not match
+            if result.record_exists || attestation.is_some() {
+                StatusCode::OK
+            } else {
+                StatusCode::NOT_FOUND
+            },
+            // A verified result only changes when the program is upgraded or re-verified
+            Extension(if result.is_verified && in_progress_builds.is_empty() {
+                CachePolicy::Immutable
+            } else {
+                CachePolicy::Short
+            }),
+            Json(
+                with_hash_mode(
+                    status_response(result, in_progress_builds, attestation, claim),
+                    hash_mode,
+                    &address,
+                )
+                .await
+                .into(),
+            ),
         ),
         Err(err) => {
             tracing::error!("Error getting data from database: {}", err);
-            Json(
-                ErrorResponse {
-                    status: Status::Error,
-                    error: "An unexpected database error occurred.".to_string(),
-                }
-                .into(),
+            (
+                err.status_code(),
+                Extension(CachePolicy::Short),
+                Json(
+                    ErrorResponse {
+                        status: Status::Error,
+                        error: "An unexpected database error occurred.".to_string(),
+                        field_errors: Vec::new(),
+                    }
+                    .into(),
+                ),
+            )
+        }
+    }
+}
+
+// Most programs whose status can be requested at once
+const MAX_BATCH_STATUS_SIZE: usize = 100;
+
+// Route handler for POST /status/batch which returns the stored status of several programs
+// at once, without checking them on-chain
+pub(crate) async fn get_status_batch(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(BatchStatusParams { addresses }): Json<BatchStatusParams>,
+) -> Result<(Extension<CachePolicy>, Json<BatchStatusResponse>), (StatusCode, Json<ErrorResponse>)>
+{
+    let tenant = tenant_of(&headers).map_err(|err| {
+        (
+            err.status_code(),
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: err.user_message(),
+                field_errors: Vec::new(),
+            }),
+        )
+    })?;
+
+    let field_errors: Vec<FieldError> = addresses
+        .iter()
+        .enumerate()
+        .filter(|(_, address)| !is_valid_address(address))
+        .map(|(index, _)| {
+            FieldError::new(
+                &format!("addresses[{}]", index),
+                "Address must be a base58 encoded public key",
             )
+        })
+        .collect();
+    let error = if addresses.is_empty() || addresses.len() > MAX_BATCH_STATUS_SIZE {
+        Some(format!(
+            "Between 1 and {} addresses can be requested at once",
+            MAX_BATCH_STATUS_SIZE
+        ))
+    } else if !field_errors.is_empty() {
+        Some("Invalid addresses".to_string())
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error,
+                field_errors,
+            }),
+        ));
+    }
+
+    // Each program is returned once, in the order it was first requested
+    let mut unique = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        if !unique.contains(&address) {
+            unique.push(address);
         }
     }
+
+    let database_error = |err: ApiError| {
+        tracing::error!("Error getting data from database: {}", err);
+        (
+            err.status_code(),
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "An unexpected database error occurred.".to_string(),
+                field_errors: Vec::new(),
+            }),
+        )
+    };
+
+    let runtime_denials = db.get_denied_programs().await.map_err(database_error)?;
+    let denied: Vec<FieldError> = unique
+        .iter()
+        .filter_map(|address| {
+            let reason = configured_denials().get(address).or_else(|| {
+                runtime_denials
+                    .iter()
+                    .find(|denied| &denied.program_id == address)
+                    .map(|denied| &denied.reason)
+            })?;
+            Some(FieldError::new(
+                "addresses",
+                format!("Program {} is denied: {}", address, reason),
+            ))
+        })
+        .collect();
+    if !denied.is_empty() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Some of the programs are denied".to_string(),
+                field_errors: denied,
+            }),
+        ));
+    }
+
+    let mut statuses = db
+        .get_statuses(&tenant, &unique)
+        .await
+        .map_err(database_error)?;
+    let mut in_progress = db
+        .get_in_progress_builds_of(&tenant, &unique)
+        .await
+        .map_err(database_error)?;
+    let mut attestations = db.get_attestations(&unique).await.map_err(database_error)?;
+    let mut claims = db
+        .get_claims(&tenant, &unique)
+        .await
+        .map_err(database_error)?;
+
+    let statuses = unique
+        .into_iter()
+        .filter_map(|address| {
+            let result = statuses.remove(&address)?;
+            let in_progress_builds = in_progress.remove(&address).unwrap_or_default();
+            let status = status_response(
+                result,
+                in_progress_builds,
+                attestations.remove(&address),
+                claims.remove(&address),
+            );
+            Some(BatchStatusEntry {
+                program_id: address,
+                status,
+            })
+        })
+        .collect();
+
+    Ok((
+        Extension(CachePolicy::Short),
+        Json(BatchStatusResponse { statuses }),
+    ))
 }
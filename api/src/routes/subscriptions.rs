@@ -0,0 +1,103 @@
+use std::net::SocketAddr;
+
+use crate::db::DbClient;
+use crate::errors::error_response;
+use crate::models::{ErrorResponse, FieldError, Status, Subscription, SubscriptionParams};
+use crate::rate_limit::client_ip;
+use crate::status_change::is_public_webhook;
+use crate::tenant::{tenant_of, API_KEY_HEADER};
+use crate::validation::validate_subscription_params;
+use axum::extract::{ConnectInfo, Path, State};
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Json};
+
+// Upper bound of the number of subscriptions a client, an API key or an IP, may create
+const MAX_SUBSCRIPTIONS_PER_CLIENT: i64 = 20;
+
+// Route handler for POST /subscriptions which registers a webhook notified whenever a
+// program gets a new build or its verification status changes
+pub(crate) async fn create_subscription(
+    State(db): State<DbClient>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(payload): Json<SubscriptionParams>,
+) -> Result<(StatusCode, Json<Subscription>), (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = validate_subscription_params(&payload);
+    if validation.is_ok() && !is_public_webhook(&payload.webhook_url).await {
+        validation = Err(vec![FieldError::new(
+            "webhook_url",
+            "Webhook URL must resolve to a public address, not a private, loopback or link-local one",
+        )]);
+    }
+    if let Err(field_errors) = validation {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Invalid subscription params".to_string(),
+                field_errors,
+            }),
+        ));
+    }
+
+    // Clients with an API key are counted by its tenant, others by their IP
+    let owner = if headers.contains_key(API_KEY_HEADER) {
+        let tenant = tenant_of(&headers)
+            .map_err(|err| error_response(err.status_code(), err.user_message()))?;
+        format!("tenant:{}", tenant)
+    } else {
+        match client_ip(&headers, connect_info) {
+            Some(ip) => format!("ip:{}", ip),
+            None => "ip:unknown".to_string(),
+        }
+    };
+    let existing = db
+        .count_subscriptions_of_owner(&owner)
+        .await
+        .map_err(|err| {
+            tracing::error!("Error counting the subscriptions of {}: {}", owner, err);
+            error_response(err.status_code(), err.user_message())
+        })?;
+    if existing >= MAX_SUBSCRIPTIONS_PER_CLIENT {
+        return Err(error_response(
+            StatusCode::CONFLICT,
+            format!(
+                "At most {} subscriptions can be created per client, delete one first",
+                MAX_SUBSCRIPTIONS_PER_CLIENT
+            ),
+        ));
+    }
+
+    let subscription = Subscription {
+        id: uuid::Uuid::new_v4().to_string(),
+        program_id: payload.program_id,
+        webhook_url: payload.webhook_url,
+        created_at: chrono::Utc::now().naive_utc(),
+        owner,
+    };
+    db.insert_subscription(&subscription).await.map_err(|err| {
+        tracing::error!("Error inserting subscription: {}", err);
+        error_response(err.status_code(), err.user_message())
+    })?;
+
+    Ok((StatusCode::CREATED, Json(subscription)))
+}
+
+// Route handler for DELETE /subscriptions/:id which removes a subscription. Its id,
+// only known to whoever created it, is the credential to remove it.
+pub(crate) async fn delete_subscription(
+    State(db): State<DbClient>,
+    Path(subscription_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    match db.delete_subscription(&subscription_id).await {
+        Ok(0) => Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("No subscription found with id {}", subscription_id),
+        )),
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(err) => {
+            tracing::error!("Error deleting subscription {}: {}", subscription_id, err);
+            Err(error_response(err.status_code(), err.user_message()))
+        }
+    }
+}
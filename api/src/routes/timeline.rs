@@ -0,0 +1,60 @@
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::errors::{error_response, ApiError};
+use crate::models::{
+    ErrorResponse, ProgramVersionEntry, StatusTimelineResponse, VerificationStatusParams,
+};
+use crate::tenant::tenant_of;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Json};
+
+fn api_error(err: ApiError) -> (StatusCode, Json<ErrorResponse>) {
+    tracing::error!("Failed to get status timeline: {}", err);
+    err.into()
+}
+
+// Route handler for GET /status/:address/timeline which returns the verification status of
+// each on-chain hash seen for a program across its upgrades
+pub(crate) async fn get_status_timeline(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Path(VerificationStatusParams { address }): Path<VerificationStatusParams>,
+) -> Result<Json<StatusTimelineResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let tenant = tenant_of(&headers).map_err(api_error)?;
+    check_not_denied(&db, &address).await.map_err(api_error)?;
+
+    let versions = db
+        .get_program_versions(&tenant, &address)
+        .await
+        .map_err(api_error)?;
+    if versions.is_empty() {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("No version seen for program {}", address),
+        ));
+    }
+    let current_hash = match db.get_verified_build(&tenant, &address).await {
+        Ok(verified_build) => Some(verified_build.on_chain_hash),
+        Err(ApiError::Diesel(diesel::result::Error::NotFound)) => None,
+        Err(err) => return Err(api_error(err)),
+    };
+
+    let versions: Vec<ProgramVersionEntry> = versions
+        .into_iter()
+        .map(|version| ProgramVersionEntry {
+            current: current_hash.as_ref() == Some(&version.on_chain_hash),
+            is_verified: version.verified_build_id.is_some(),
+            on_chain_hash: version.on_chain_hash,
+            first_seen_at: version.first_seen_at,
+            last_seen_at: version.last_seen_at,
+            build_id: version.verified_build_id,
+            verified_at: version.verified_at,
+        })
+        .collect();
+    Ok(Json(StatusTimelineResponse {
+        program_id: address,
+        all_verified: versions.iter().all(|version| version.is_verified),
+        versions,
+    }))
+}
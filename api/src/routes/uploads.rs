@@ -0,0 +1,148 @@
+use crate::artifact::max_artifact_size;
+use crate::builder::{executable_hash, is_valid_address};
+use crate::db::DbClient;
+use crate::errors::{error_response, ApiError};
+use crate::models::{
+    Commitment, ErrorResponse, UploadParams, UploadResponse, UploadVerificationResponse,
+    VerificationMethod,
+};
+use crate::uploads::{Upload, UploadStore};
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::{http::StatusCode, Json};
+
+// Header carrying the offset a chunk starts at, as in the tus protocol
+const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+
+// Largest chunk accepted in a single request (1 MiB)
+const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+fn upload_error(err: ApiError) -> (StatusCode, Json<ErrorResponse>) {
+    if err.status_code() == StatusCode::INTERNAL_SERVER_ERROR {
+        tracing::error!("Upload failed: {}", err);
+    }
+    error_response(err.status_code(), err.user_message())
+}
+
+fn upload_response(upload: Upload) -> Json<UploadResponse> {
+    Json(UploadResponse {
+        complete: upload.is_complete(),
+        expires_at: upload.expires_at(),
+        id: upload.id,
+        program_id: upload.program_id,
+        size: upload.size,
+        offset: upload.offset,
+    })
+}
+
+// Route handler for POST /uploads which starts an upload of an executable in chunks
+pub(crate) async fn create_upload(
+    Json(UploadParams { program_id, size }): Json<UploadParams>,
+) -> Result<(StatusCode, Json<UploadResponse>), (StatusCode, Json<ErrorResponse>)> {
+    if !is_valid_address(&program_id) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid program address: {}", program_id),
+        ));
+    }
+    let max_size = max_artifact_size();
+    if size == 0 || size > max_size {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("size must be between 1 and {} bytes", max_size),
+        ));
+    }
+
+    let upload = UploadStore::from_env()
+        .create(&program_id, size)
+        .await
+        .map_err(upload_error)?;
+    Ok((StatusCode::CREATED, upload_response(upload)))
+}
+
+// Route handler for GET /uploads/:id which returns the offset to resume an upload from
+pub(crate) async fn get_upload(
+    Path(id): Path<String>,
+) -> Result<Json<UploadResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let upload = UploadStore::from_env()
+        .get(&id)
+        .await
+        .map_err(upload_error)?;
+    Ok(upload_response(upload))
+}
+
+// Route handler for PATCH /uploads/:id which appends the chunk in the body at the offset
+// given in the Upload-Offset header
+pub(crate) async fn append_upload_chunk(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    chunk: Bytes,
+) -> Result<Json<UploadResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(offset) = headers
+        .get(UPLOAD_OFFSET_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+    else {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "The Upload-Offset header is required".to_string(),
+        ));
+    };
+    if chunk.len() > MAX_CHUNK_SIZE {
+        return Err(error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Chunks may be at most {} bytes", MAX_CHUNK_SIZE),
+        ));
+    }
+
+    let upload = UploadStore::from_env()
+        .append(&id, offset, &chunk)
+        .await
+        .map_err(upload_error)?;
+    Ok(upload_response(upload))
+}
+
+// Route handler for POST /uploads/:id/verify which compares a completed upload with the
+// deployed program
+pub(crate) async fn verify_upload(
+    State(db): State<DbClient>,
+    Path(id): Path<String>,
+) -> Result<Json<UploadVerificationResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let store = UploadStore::from_env();
+    let upload = store.get(&id).await.map_err(upload_error)?;
+    if !upload.is_complete() {
+        return Err(error_response(
+            StatusCode::CONFLICT,
+            format!(
+                "Upload {} is incomplete: {} of {} bytes received",
+                id, upload.offset, upload.size
+            ),
+        ));
+    }
+    let artifact = store.read(&upload).await.map_err(upload_error)?;
+    // Hashed like the deployed program, without the trailing zero padding
+    let artifact_hash = executable_hash(&artifact);
+
+    let on_chain_hash = db
+        .hash_provider
+        .get_on_chain_hash(&upload.program_id, Commitment::default())
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "Failed to get the on-chain hash of {}: {}",
+                upload.program_id,
+                err
+            );
+            error_response(err.status_code(), err.user_message())
+        })?;
+
+    Ok(Json(UploadVerificationResponse {
+        program_id: upload.program_id,
+        upload_id: upload.id,
+        artifact_matches_on_chain: artifact_hash == on_chain_hash,
+        verification_method: VerificationMethod::BinaryUpload,
+        artifact_hash,
+        on_chain_hash,
+    }))
+}
@@ -1,21 +1,169 @@
+use std::convert::Infallible;
+
 use crate::db::DbClient;
-use crate::models::VerifiedProgramListResponse;
-use axum::{extract::State, http::StatusCode, Json};
+use crate::errors::ApiError;
+use crate::labels::{labels_from_json, matches_labels, parse_label_filter};
+use crate::models::{
+    ErrorResponse, FieldError, ListFormat, SolanaProgramBuild, Status, VerifiedProgram,
+    VerifiedProgramListResponse, VerifiedProgramsParams,
+};
+use crate::negotiation::{preferred_media_type, CSV_MEDIA_TYPE, JSON_MEDIA_TYPE};
+use crate::tenant::tenant_of;
+use axum::body::StreamBody;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use axum::{http::StatusCode, Json};
+
+const CSV_HEADER: &str =
+    "program_id,repository,commit,is_verified,on_chain_hash,executable_hash,verified_at\r\n";
+
+// Quote a CSV field if it contains a separator, a quote or a line break, doubling quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
 
+fn csv_row(verified_build: &VerifiedProgram, build: &SolanaProgramBuild) -> String {
+    let commit = build
+        .commit_hash
+        .as_deref()
+        .or(build.resolved_commit.as_deref())
+        .unwrap_or_default();
+    let fields = [
+        verified_build.program_id.as_str(),
+        build.repository.as_str(),
+        commit,
+        if verified_build.is_verified {
+            "true"
+        } else {
+            "false"
+        },
+        verified_build.on_chain_hash.as_str(),
+        verified_build.executable_hash.as_str(),
+        &verified_build.verified_at.to_string(),
+    ]
+    .map(csv_field);
+    format!("{}\r\n", fields.join(","))
+}
+
+// Route handler for GET /verified-programs which lists the verified programs, as JSON
+// program ids or with `?format=csv` or `Accept: text/csv` as a CSV of their verifications
 pub(crate) async fn get_verified_programs_list(
     State(db): State<DbClient>,
-) -> (StatusCode, Json<VerifiedProgramListResponse>) {
-    let verified_programs = db.get_verified_programs().await.unwrap();
+    headers: HeaderMap,
+    Query(VerifiedProgramsParams { format, label }): Query<VerifiedProgramsParams>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let label_filter = match label.as_deref().map(parse_label_filter).transpose() {
+        Ok(filter) => filter.unwrap_or_default(),
+        Err(error) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    status: Status::Error,
+                    error: "Invalid label filter".to_string(),
+                    field_errors: vec![FieldError::new("label", error)],
+                }),
+            ));
+        }
+    };
+    let format = format.unwrap_or_else(|| {
+        match preferred_media_type(&headers, &[JSON_MEDIA_TYPE, CSV_MEDIA_TYPE]) {
+            Some(CSV_MEDIA_TYPE) => ListFormat::Csv,
+            _ => ListFormat::Json,
+        }
+    });
+
+    let verified_programs = match tenant_of(&headers) {
+        Ok(tenant) => db.get_verified_programs(&tenant).await,
+        Err(err) => Err(err),
+    };
+    let verified_programs = verified_programs.map_err(|err| {
+        if !matches!(err, ApiError::InvalidApiKey) {
+            tracing::error!("Error getting data from database: {}", err);
+        }
+        (
+            err.status_code(),
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: err.user_message(),
+                field_errors: Vec::new(),
+            }),
+        )
+    })?;
+
+    let verified_programs = verified_programs
+        .into_iter()
+        .filter(|(_, build)| {
+            matches_labels(&labels_from_json(build.labels.as_ref()), &label_filter)
+        })
+        .collect::<Vec<_>>();
+
+    if format == ListFormat::Csv {
+        let rows = std::iter::once(CSV_HEADER.to_string()).chain(
+            verified_programs
+                .into_iter()
+                .map(|(verified_build, build)| csv_row(&verified_build, &build)),
+        );
+        return Ok((
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (header::VARY, "accept"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"verified-programs.csv\"",
+                ),
+            ],
+            StreamBody::new(futures::stream::iter(rows.map(Ok::<_, Infallible>))),
+        )
+            .into_response());
+    }
 
     // get all program ids from the verified_programs
     let programs_list = verified_programs
         .iter()
-        .map(|program| program.program_id.clone())
+        .map(|(program, _)| program.program_id.clone())
         .collect::<Vec<String>>();
 
+    let labels = verified_programs
+        .iter()
+        .map(|(program, build)| {
+            (
+                program.program_id.clone(),
+                labels_from_json(build.labels.as_ref()),
+            )
+        })
+        .filter(|(_, labels)| !labels.is_empty())
+        .collect();
+
     let response_data = VerifiedProgramListResponse {
         verified_programs: programs_list,
+        labels,
     };
 
-    (StatusCode::OK, Json(response_data))
+    Ok((
+        StatusCode::OK,
+        [(header::VARY, "accept")],
+        Json(response_data),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(
+            csv_field("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"),
+            "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"
+        );
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
 }
@@ -0,0 +1,176 @@
+use crate::artifact::download_artifact;
+use crate::builder::{executable_hash, verify_build};
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::errors::{error_response, ApiError};
+use crate::git::{check_repository_size, resolve_repository_redirect};
+use crate::models::{
+    ArtifactVerificationResponse, ArtifactVerifyParams, Commitment, ErrorResponse, JobStatus,
+    SolanaProgramBuild, SolanaProgramBuildParams, SourceComparison, Status, VerificationMethod,
+};
+use crate::published_crate::crate_page_url;
+use crate::tenant::tenant_of;
+use crate::validation::validate_artifact_params;
+use crate::Result;
+use axum::http::HeaderMap;
+use axum::{extract::State, http::StatusCode, Json};
+use tokio::sync::oneshot;
+
+// Build the program from source on the worker pool and wait for the build, returning its
+// id and the hash of the executable. A completed build with the same params is reused.
+async fn build_from_source(
+    db: &DbClient,
+    mut params: SolanaProgramBuildParams,
+) -> Result<(String, String)> {
+    check_not_denied(db, &params.program_id).await?;
+    let authority_claimed = db.check_authority_claim(&params).await?;
+
+    // Stored and deduplicated like the builds of /verify
+    if let Some(page) = params
+        .published_crate()
+        .map(|(name, version)| crate_page_url(name, version))
+    {
+        params.repository = page;
+    } else if let Ok(canonical) = resolve_repository_redirect(&params.repository).await {
+        params.repository = canonical;
+    }
+
+    if let Ok(previous) = db.check_for_dupliate(&params).await {
+        if authority_claimed {
+            let _ = db.set_authority_claimed(&previous.id).await;
+        }
+        match previous.status.clone().into() {
+            JobStatus::Completed => {
+                let verified_build = db
+                    .get_verified_build(&previous.tenant_id, &previous.program_id)
+                    .await?;
+                // Only reuse the hash if the verification is still the one of that build
+                if verified_build.solana_build_id == previous.id {
+                    return Ok((previous.id, verified_build.executable_hash));
+                }
+            }
+            JobStatus::InProgress => return Err(ApiError::BuildInProgress(previous.id)),
+            JobStatus::Failed => {}
+        }
+    }
+
+    if params.published_crate().is_none() {
+        check_repository_size(&params.repository, params.commit_hash.as_deref()).await?;
+    }
+    let mut build = SolanaProgramBuild::from(&params);
+    build.authority_claimed = authority_claimed;
+    db.insert_build_params(&build).await?;
+
+    // The job records the final status of the build even if this request is dropped
+    let (sender, receiver) = oneshot::channel();
+    let job_db = db.clone();
+    let build_id = build.id.clone();
+    let repository = params.repository.clone();
+    db.workers.clone().spawn_for_repo(&repository, async move {
+        let result = match verify_build(&job_db, params, &build_id).await {
+            Ok((verified_build, warnings)) => {
+                let _ = job_db
+                    .insert_or_update_verified_build(&verified_build)
+                    .await;
+                let _ = job_db.set_build_warnings(&build_id, &warnings).await;
+                let _ = job_db
+                    .update_build_status(&build_id, JobStatus::Completed.into())
+                    .await;
+                Ok(verified_build.executable_hash)
+            }
+            Err(err) => {
+                let _ = job_db
+                    .update_build_status(&build_id, JobStatus::Failed.into())
+                    .await;
+                Err(err)
+            }
+        };
+        let _ = sender.send(result);
+    });
+
+    let executable_hash = receiver
+        .await
+        .map_err(|_| ApiError::Build("The build stopped without a result".to_string()))??;
+    Ok((build.id, executable_hash))
+}
+
+// Route handler for POST /verify/artifact which compares a published executable with the
+// deployed program and, if source params are given, with a build from source
+pub(crate) async fn verify_artifact(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(payload): Json<ArtifactVerifyParams>,
+) -> std::result::Result<Json<ArtifactVerificationResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let tenant =
+        tenant_of(&headers).map_err(|err| error_response(err.status_code(), err.user_message()))?;
+    if let Err(field_errors) = validate_artifact_params(&payload) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                status: Status::Error,
+                error: "Invalid artifact verification parameters".to_string(),
+                field_errors,
+            }),
+        ));
+    }
+
+    let artifact = download_artifact(&payload.artifact_url)
+        .await
+        .map_err(|err| {
+            tracing::warn!("Failed to download {}: {}", payload.artifact_url, err);
+            let status = match err {
+                ApiError::ProgramTooLarge { .. } => err.status_code(),
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            error_response(status, format!("Failed to download the artifact: {}", err))
+        })?;
+    // Hashed like the deployed program, without the trailing zero padding
+    let artifact_hash = executable_hash(&artifact);
+
+    let on_chain_hash = db
+        .hash_provider
+        .get_on_chain_hash(&payload.program_id, Commitment::default())
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "Failed to get the on-chain hash of {}: {}",
+                payload.program_id,
+                err
+            );
+            error_response(err.status_code(), err.user_message())
+        })?;
+
+    let source = match payload.source {
+        Some(mut params) => {
+            params.tenant_id = Some(tenant);
+            let (build_id, source_hash) = build_from_source(&db, params).await.map_err(|err| {
+                tracing::error!(
+                    "Failed to build {} from source: {}",
+                    payload.program_id,
+                    err
+                );
+                error_response(
+                    err.status_code(),
+                    format!("Failed to build from source: {}", err),
+                )
+            })?;
+            Some(SourceComparison {
+                build_id,
+                artifact_matches_source: source_hash == artifact_hash,
+                source_matches_on_chain: source_hash == on_chain_hash,
+                executable_hash: source_hash,
+            })
+        }
+        None => None,
+    };
+
+    Ok(Json(ArtifactVerificationResponse {
+        program_id: payload.program_id,
+        artifact_url: payload.artifact_url,
+        artifact_matches_on_chain: artifact_hash == on_chain_hash,
+        verification_method: VerificationMethod::BinaryUpload,
+        artifact_hash,
+        on_chain_hash,
+        source,
+    }))
+}
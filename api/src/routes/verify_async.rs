@@ -1,52 +1,181 @@
 use crate::builder::verify_build;
 use crate::db::DbClient;
+use crate::denylist::check_not_denied;
 use crate::errors::ErrorMessages;
+use crate::git::{check_repository_size, resolve_repository_redirect};
 use crate::models::{
-    ApiResponse, ErrorResponse, JobStatus, SolanaProgramBuild, SolanaProgramBuildParams, Status,
-    VerifyResponse,
+    ApiResponse, DuplicateBuildResponse, ErrorResponse, JobStatus, MatchedBuild,
+    MatchedVerification, SolanaProgramBuild, SolanaProgramBuildParams, Status, VerifyResponse,
 };
+use crate::params_hash::params_hash;
+use crate::published_crate::crate_page_url;
+use crate::tenant::tenant_of;
+use crate::validation::validate_params;
+use axum::http::HeaderMap;
 use axum::{extract::State, http::StatusCode, Json};
 
+// 409 response describing the previous build which matched the request
+fn duplicate_response(
+    build: SolanaProgramBuild,
+    status: JobStatus,
+    verification: Option<MatchedVerification>,
+    message: &str,
+) -> (StatusCode, Json<ApiResponse>) {
+    (
+        StatusCode::CONFLICT,
+        Json(
+            DuplicateBuildResponse {
+                status,
+                request_id: build.id.clone(),
+                message: message.to_string(),
+                matched_build_id: build.id.clone(),
+                matched_build: MatchedBuild {
+                    params_hash: params_hash(&build),
+                    id: build.id,
+                    created_at: build.created_at,
+                    status: build.status.into(),
+                    verification,
+                },
+            }
+            .into(),
+        ),
+    )
+}
+
 // Route handler for POST /verify which creates a new process to verify the program
 pub(crate) async fn verify_async(
     State(db): State<DbClient>,
-    Json(payload): Json<SolanaProgramBuildParams>,
+    headers: HeaderMap,
+    Json(mut payload): Json<SolanaProgramBuildParams>,
+) -> (StatusCode, Json<ApiResponse>) {
+    match tenant_of(&headers) {
+        Ok(tenant) => payload.tenant_id = Some(tenant),
+        Err(err) => {
+            return (
+                err.status_code(),
+                Json(
+                    ErrorResponse {
+                        status: Status::Error,
+                        error: err.user_message(),
+                        field_errors: Vec::new(),
+                    }
+                    .into(),
+                ),
+            )
+        }
+    }
+    start_verification(db, payload).await
+}
+
+// Validate the params and start a build in the background, unless a previous build with
+// the same params completed or is still running
+pub(crate) async fn start_verification(
+    db: DbClient,
+    mut payload: SolanaProgramBuildParams,
 ) -> (StatusCode, Json<ApiResponse>) {
-    let verify_build_data = SolanaProgramBuild::from(&payload);
+    if let Err(field_errors) = validate_params(&payload) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: "Invalid build parameters".to_string(),
+                    field_errors,
+                }
+                .into(),
+            ),
+        );
+    }
+
+    if let Err(err) = check_not_denied(&db, &payload.program_id).await {
+        tracing::warn!("Refused to verify {}: {}", payload.program_id, err);
+        return (
+            err.status_code(),
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }
+                .into(),
+            ),
+        );
+    }
+
+    // Check the optional upgrade authority signature before doing any work
+    let authority_claimed = match db.check_authority_claim(&payload).await {
+        Ok(claimed) => claimed,
+        Err(err) => {
+            tracing::error!(
+                "Rejected authority claim for {}: {}",
+                payload.program_id,
+                err
+            );
+            return (
+                err.status_code(),
+                Json(
+                    ErrorResponse {
+                        status: Status::Error,
+                        error: err.user_message(),
+                        field_errors: Vec::new(),
+                    }
+                    .into(),
+                ),
+            );
+        }
+    };
+
+    // Store and deduplicate builds of published crates under their crates.io page, and
+    // builds of renamed repositories under the URL they redirect to
+    if let Some(page) = payload
+        .published_crate()
+        .map(|(name, version)| crate_page_url(name, version))
+    {
+        payload.repository = page;
+    } else if let Ok(canonical) = resolve_repository_redirect(&payload.repository).await {
+        payload.repository = canonical;
+    }
+
+    let mut verify_build_data = SolanaProgramBuild::from(&payload);
+    verify_build_data.authority_claimed = authority_claimed;
     let uuid = verify_build_data.id.clone();
+    let build_params_hash = params_hash(&verify_build_data);
 
     // Check if the build was already processed
     let is_duplicate = db.check_for_dupliate(&payload).await;
 
     if let Ok(respose) = is_duplicate {
-        match respose.status.into() {
+        if authority_claimed {
+            let _ = db.set_authority_claimed(&respose.id).await;
+        }
+
+        match respose.status.clone().into() {
             JobStatus::Completed => {
-                // Get the verified build from the database
-                let verified_build = db.get_verified_build(&respose.program_id).await.unwrap();
-                return (
-                    StatusCode::OK,
-                    Json(
-                        VerifyResponse {
-                            status: JobStatus::Completed,
-                            request_id: verified_build.solana_build_id,
-                            message: "Verification already completed.".to_string(),
-                        }
-                        .into(),
-                    ),
+                // Include the verification of the matched build so clients can reuse it
+                let verification = db
+                    .get_verified_build(&respose.tenant_id, &respose.program_id)
+                    .await
+                    .ok()
+                    .map(|verified_build| MatchedVerification {
+                        is_verified: verified_build.is_verified,
+                        on_chain_hash: verified_build.on_chain_hash,
+                        executable_hash: verified_build.executable_hash,
+                        verified_at: verified_build.verified_at,
+                    });
+                return duplicate_response(
+                    respose,
+                    JobStatus::Completed,
+                    verification,
+                    "Verification already completed.",
                 );
             }
             JobStatus::InProgress => {
                 // Return ID to user to check status
-                return (
-                    StatusCode::OK,
-                    Json(
-                        VerifyResponse {
-                            status: JobStatus::InProgress,
-                            request_id: respose.id,
-                            message: "Build verification already in progress".to_string(),
-                        }
-                        .into(),
-                    ),
+                return duplicate_response(
+                    respose,
+                    JobStatus::InProgress,
+                    None,
+                    "Build verification already in progress",
                 );
             }
             JobStatus::Failed => {
@@ -56,15 +185,37 @@ pub(crate) async fn verify_async(
         }
     }
 
+    // Reject giant repositories before spending disk and build time on them. Published
+    // crates are held to the same limit as they are downloaded.
+    let size_check = match payload.published_crate() {
+        Some(_) => Ok(()),
+        None => check_repository_size(&payload.repository, payload.commit_hash.as_deref()).await,
+    };
+    if let Err(err) = size_check {
+        tracing::warn!("Refused to build {}: {}", payload.repository, err);
+        return (
+            err.status_code(),
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }
+                .into(),
+            ),
+        );
+    }
+
     // insert into database
     if let Err(e) = db.insert_build_params(&verify_build_data).await {
         tracing::error!("Error inserting into database: {:?}", e);
         return (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status_code(),
             Json(
                 ErrorResponse {
                     status: Status::Error,
-                    error: ErrorMessages::DB.to_string(),
+                    error: e.user_message(),
+                    field_errors: Vec::new(),
                 }
                 .into(),
             ),
@@ -74,10 +225,14 @@ pub(crate) async fn verify_async(
     tracing::info!("Inserted into database");
 
     //run task in background
-    tokio::spawn(async move {
-        match verify_build(payload, &verify_build_data.id).await {
-            Ok(res) => {
+    let repository = payload.repository.clone();
+    db.workers.clone().spawn_for_repo(&repository, async move {
+        match verify_build(&db, payload, &verify_build_data.id).await {
+            Ok((res, warnings)) => {
                 let _ = db.insert_or_update_verified_build(&res).await;
+                let _ = db
+                    .set_build_warnings(&verify_build_data.id, &warnings)
+                    .await;
                 let _ = db
                     .update_build_status(&verify_build_data.id, JobStatus::Completed.into())
                     .await;
@@ -99,6 +254,7 @@ pub(crate) async fn verify_async(
                 status: JobStatus::InProgress,
                 request_id: uuid,
                 message: "Build verification started".to_string(),
+                params_hash: build_params_hash,
             }
             .into(),
         ),
@@ -0,0 +1,101 @@
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::errors::{api_error_response, ApiError};
+use crate::models::{ApiResponse, AutoVerifyParams, JobStatus, SolanaProgramBuild, VerifyResponse};
+use crate::params_hash::params_hash;
+use crate::registry::registry_programs;
+use crate::tenant::tenant_of;
+use axum::http::HeaderMap;
+use axum::{extract::State, http::StatusCode, Json};
+
+// Route handler for POST /verify/auto which re-verifies a program using the repository
+// from its previous build or from the registry
+pub(crate) async fn verify_auto(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(AutoVerifyParams { program_id }): Json<AutoVerifyParams>,
+) -> (StatusCode, Json<ApiResponse>) {
+    let tenant = match tenant_of(&headers) {
+        Ok(tenant) => tenant,
+        Err(err) => return api_error_response(err.status_code(), err.user_message()),
+    };
+    if let Err(err) = check_not_denied(&db, &program_id).await {
+        tracing::warn!("Refused to verify {}: {}", program_id, err);
+        return api_error_response(err.status_code(), err.user_message());
+    }
+
+    let build = match db.get_build_params(&tenant, &program_id).await {
+        Ok(build) => {
+            if matches!(JobStatus::from(build.status.clone()), JobStatus::InProgress) {
+                return (
+                    StatusCode::OK,
+                    Json(
+                        VerifyResponse {
+                            status: JobStatus::InProgress,
+                            params_hash: params_hash(&build),
+                            request_id: build.id,
+                            message: "Build verification already in progress".to_string(),
+                        }
+                        .into(),
+                    ),
+                );
+            }
+            if let Err(err) = db.workers.ensure_accepting_builds() {
+                return api_error_response(err.status_code(), err.user_message());
+            }
+            if let Err(err) = db
+                .update_build_status(&build.id, JobStatus::InProgress.into())
+                .await
+            {
+                tracing::error!("Error updating build status: {}", err);
+                return api_error_response(err.status_code(), err.user_message());
+            }
+            build
+        }
+        Err(ApiError::Diesel(diesel::result::Error::NotFound)) => {
+            let Some(params) = registry_programs()
+                .iter()
+                .find(|params| params.program_id == program_id)
+            else {
+                return api_error_response(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "No known repository for program {}. Submit it with /verify first.",
+                        program_id
+                    ),
+                );
+            };
+
+            let build = SolanaProgramBuild {
+                tenant_id: tenant,
+                ..SolanaProgramBuild::from(params)
+            };
+            if let Err(err) = db.insert_build_params(&build).await {
+                tracing::error!("Error inserting into database: {:?}", err);
+                return api_error_response(err.status_code(), err.user_message());
+            }
+            build
+        }
+        Err(err) => {
+            tracing::error!("Error getting data from database: {}", err);
+            return api_error_response(err.status_code(), err.user_message());
+        }
+    };
+
+    let request_id = build.id.clone();
+    let build_params_hash = params_hash(&build);
+    db.reverify_program(build);
+
+    (
+        StatusCode::OK,
+        Json(
+            VerifyResponse {
+                status: JobStatus::InProgress,
+                request_id,
+                message: "Build verification started".to_string(),
+                params_hash: build_params_hash,
+            }
+            .into(),
+        ),
+    )
+}
@@ -0,0 +1,160 @@
+use crate::bisect::bisect_build;
+use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::git::check_repository_size;
+use crate::models::{
+    ApiResponse, BisectParams, ErrorResponse, JobStatus, SolanaProgramBuild, Status, VerifyResponse,
+};
+use crate::params_hash::params_hash;
+use crate::tenant::tenant_of;
+use crate::validation::validate_bisect_params;
+use axum::http::HeaderMap;
+use axum::{extract::State, http::StatusCode, Json};
+
+// Route handler for POST /verify/bisect which searches the commits between a known-bad
+// and a known-good commit for the one that was deployed
+pub(crate) async fn verify_bisect(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    Json(mut payload): Json<BisectParams>,
+) -> (StatusCode, Json<ApiResponse>) {
+    match tenant_of(&headers) {
+        Ok(tenant) => payload.build.tenant_id = Some(tenant),
+        Err(err) => {
+            return (
+                err.status_code(),
+                Json(
+                    ErrorResponse {
+                        status: Status::Error,
+                        error: err.user_message(),
+                        field_errors: Vec::new(),
+                    }
+                    .into(),
+                ),
+            )
+        }
+    }
+
+    if let Err(field_errors) = validate_bisect_params(&payload) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: "Invalid bisect parameters".to_string(),
+                    field_errors,
+                }
+                .into(),
+            ),
+        );
+    }
+
+    if let Err(err) = check_not_denied(&db, &payload.build.program_id).await {
+        tracing::warn!("Refused to bisect {}: {}", payload.build.program_id, err);
+        return (
+            err.status_code(),
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }
+                .into(),
+            ),
+        );
+    }
+
+    if let Err(err) =
+        check_repository_size(&payload.build.repository, Some(&payload.bad_commit)).await
+    {
+        tracing::warn!("Refused to bisect {}: {}", payload.build.repository, err);
+        return (
+            err.status_code(),
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }
+                .into(),
+            ),
+        );
+    }
+
+    let build = SolanaProgramBuild::from(&payload.build);
+    if let Err(e) = db.insert_build_params(&build).await {
+        tracing::error!("Error inserting into database: {:?}", e);
+        return (
+            e.status_code(),
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: e.user_message(),
+                    field_errors: Vec::new(),
+                }
+                .into(),
+            ),
+        );
+    }
+
+    let build_params_hash = params_hash(&build);
+    let build_id = build.id;
+    let request_id = build_id.clone();
+
+    //run task in background
+    let repository = payload.build.repository.clone();
+    db.workers.clone().spawn_for_repo(&repository, async move {
+        let BisectParams {
+            good_commit,
+            bad_commit,
+            build: params,
+        } = payload;
+
+        match bisect_build(&db, params, &build_id, &bad_commit, &good_commit).await {
+            Ok(result) => {
+                tracing::info!(
+                    "Bisect matched {} after {} builds",
+                    result.commit,
+                    result.builds
+                );
+                let _ = db
+                    .insert_or_update_verified_build(&result.verified_build)
+                    .await;
+                let _ = db
+                    .update_resolved_ref(&build_id, "bisect", &result.commit)
+                    .await;
+                db.record_event(
+                    &build_id,
+                    "bisect_finished",
+                    Some(&format!(
+                        "{} matched after {} builds",
+                        result.commit, result.builds
+                    )),
+                )
+                .await;
+                let _ = db
+                    .update_build_status(&build_id, JobStatus::Completed.into())
+                    .await;
+            }
+            Err(err) => {
+                let _ = db
+                    .update_build_status(&build_id, JobStatus::Failed.into())
+                    .await;
+                tracing::error!("Error bisecting build: {:?}", err);
+            }
+        }
+    });
+
+    (
+        StatusCode::OK,
+        Json(
+            VerifyResponse {
+                status: JobStatus::InProgress,
+                request_id,
+                message: "Bisect started. The matching commit and the number of builds are reported in the build events.".to_string(),
+                params_hash: build_params_hash,
+            }
+            .into(),
+        ),
+    )
+}
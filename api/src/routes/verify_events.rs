@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use crate::db::DbClient;
+use crate::errors::ApiError;
+use crate::events::{is_terminal_event, Subscription};
+use crate::models::{BuildEvent, ErrorResponse, JobStatus};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::Json;
+use futures::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+type EventsError = (StatusCode, Json<ErrorResponse>);
+
+fn api_error(err: ApiError) -> EventsError {
+    tracing::error!("Error getting data from database: {}", err);
+    err.into()
+}
+
+fn sse_event(event: &BuildEvent) -> Result<Event, axum::Error> {
+    Event::default()
+        .id(&event.id)
+        .event(&event.event)
+        .json_data(event)
+        .map_err(axum::Error::new)
+}
+
+// Follow the live events of a build until it terminates, skipping the ones already replayed
+fn live_events(
+    subscription: Subscription,
+    replayed: HashSet<String>,
+) -> impl Stream<Item = BuildEvent> {
+    stream::unfold(Some((subscription, replayed)), |state| async move {
+        let (mut subscription, replayed) = state?;
+        loop {
+            match subscription.receiver.recv().await {
+                Ok(event) if replayed.contains(&event.id) => continue,
+                Ok(event) => {
+                    let next =
+                        (!is_terminal_event(&event.event)).then_some((subscription, replayed));
+                    return Some((event, next));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event stream lagged behind by {} events", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+// Route handler for GET /verify/:id/events which streams the events of a build as
+// Server-Sent Events until it completes or fails
+pub(crate) async fn stream_build_events(
+    State(db): State<DbClient>,
+    Path(build_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, EventsError> {
+    // Subscribe before reading the recorded events so none are missed in between
+    let subscription = db.events.subscribe(&build_id);
+    let build = db.get_job(&build_id).await.map_err(api_error)?;
+    let recorded = db.get_build_events(&build_id).await.map_err(api_error)?;
+
+    let finished = !matches!(JobStatus::from(build.status), JobStatus::InProgress)
+        || recorded.iter().any(|event| is_terminal_event(&event.event));
+    let live = if finished {
+        None
+    } else {
+        let replayed = recorded.iter().map(|event| event.id.clone()).collect();
+        Some(live_events(subscription, replayed))
+    };
+
+    let events = stream::iter(recorded)
+        .chain(stream::iter(live).flatten())
+        .map(|event| sse_event(&event));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -1,25 +1,126 @@
+use std::collections::BTreeMap;
+
+use crate::build_args::build_args_to_map;
 use crate::builder::verify_build;
 use crate::db::DbClient;
+use crate::denylist::check_not_denied;
+use crate::digests::digests_to_map;
 use crate::errors::ErrorMessages;
+use crate::git::{check_repository_size, resolve_repository_redirect};
+use crate::labels::labels_from_json;
 use crate::models::{
-    ApiResponse, ErrorResponse, JobStatus, SolanaProgramBuild, SolanaProgramBuildParams, Status,
-    StatusResponse,
+    ApiResponse, ErrorResponse, HashMode, JobStatus, SolanaProgramBuild, SolanaProgramBuildParams,
+    Status, StatusResponse, TrustLevel, VerificationMethod,
 };
+use crate::published_crate::crate_page_url;
+use crate::tenant::tenant_of;
+use crate::validation::validate_params;
+use axum::http::HeaderMap;
 use axum::{extract::State, http::StatusCode, Json};
 
 pub(crate) async fn verify_sync(
     State(db): State<DbClient>,
-    Json(payload): Json<SolanaProgramBuildParams>,
+    headers: HeaderMap,
+    Json(mut payload): Json<SolanaProgramBuildParams>,
 ) -> (StatusCode, Json<ApiResponse>) {
-    let verify_build_data = SolanaProgramBuild::from(&payload);
+    match tenant_of(&headers) {
+        Ok(tenant) => payload.tenant_id = Some(tenant),
+        Err(err) => {
+            return (
+                err.status_code(),
+                Json(
+                    ErrorResponse {
+                        status: Status::Error,
+                        error: err.user_message(),
+                        field_errors: Vec::new(),
+                    }
+                    .into(),
+                ),
+            )
+        }
+    }
+
+    if let Err(field_errors) = validate_params(&payload) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: "Invalid build parameters".to_string(),
+                    field_errors,
+                }
+                .into(),
+            ),
+        );
+    }
+
+    if let Err(err) = check_not_denied(&db, &payload.program_id).await {
+        tracing::warn!("Refused to verify {}: {}", payload.program_id, err);
+        return (
+            err.status_code(),
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }
+                .into(),
+            ),
+        );
+    }
+
+    // Check the optional upgrade authority signature before doing any work
+    let authority_claimed = match db.check_authority_claim(&payload).await {
+        Ok(claimed) => claimed,
+        Err(err) => {
+            tracing::error!(
+                "Rejected authority claim for {}: {}",
+                payload.program_id,
+                err
+            );
+            return (
+                err.status_code(),
+                Json(
+                    ErrorResponse {
+                        status: Status::Error,
+                        error: err.user_message(),
+                        field_errors: Vec::new(),
+                    }
+                    .into(),
+                ),
+            );
+        }
+    };
+
+    // Store and deduplicate builds of published crates under their crates.io page, and
+    // builds of renamed repositories under the URL they redirect to
+    if let Some(page) = payload
+        .published_crate()
+        .map(|(name, version)| crate_page_url(name, version))
+    {
+        payload.repository = page;
+    } else if let Ok(canonical) = resolve_repository_redirect(&payload.repository).await {
+        payload.repository = canonical;
+    }
+
+    let mut verify_build_data = SolanaProgramBuild::from(&payload);
+    verify_build_data.authority_claimed = authority_claimed;
 
     // First check if the program is already verified
     let is_duplicate = db.check_for_dupliate(&payload).await;
 
     if let Ok(res) = is_duplicate {
-        match res.status.into() {
+        if authority_claimed {
+            let _ = db.set_authority_claimed(&res.id).await;
+        }
+
+        match res.status.clone().into() {
             JobStatus::Completed => {
-                let verified_build = db.get_verified_build(&res.program_id).await.unwrap();
+                let verified_build = db
+                    .get_verified_build(&res.tenant_id, &res.program_id)
+                    .await
+                    .unwrap();
+                let declared_id_matches = verified_build.declared_id_matches();
                 return (
                     StatusCode::CONFLICT,
                     Json(
@@ -38,6 +139,43 @@ pub(crate) async fn verify_sync(
                                     format!("{}/commit/{}", verify_build_data.repository, hash)
                                 }),
                             last_verified_at: Some(verified_build.verified_at),
+                            verified_by_authority: verified_build.is_verified
+                                && (res.authority_claimed || authority_claimed),
+                            record_exists: true,
+                            extra_hashes: digests_to_map(verified_build.extra_digests),
+                            loader: verified_build.loader,
+                            registry_match: verified_build.registry_match,
+                            program_size: None,
+                            rent_exempt: None,
+                            upgrade_authority: None,
+                            immutable: None,
+                            resolved_commit: res.resolved_commit.clone(),
+                            docker_build_args: verify_build_data
+                                .docker_build_args
+                                .as_deref()
+                                .map(build_args_to_map)
+                                .unwrap_or_default(),
+                            target: Some(verify_build_data.target.clone()),
+                            network: Some(verify_build_data.network.clone()),
+                            commitment: Some(verify_build_data.commitment.clone()),
+                            rustflags: verify_build_data.rustflags.clone().unwrap_or_default(),
+                            status_changed: verified_build.status_changed,
+                            declared_id: verified_build.declared_id,
+                            declared_id_matches,
+                            hash_mode: HashMode::Trimmed,
+                            on_chain_mode_hash: None,
+                            labels: labels_from_json(res.labels.as_ref()),
+                            upstream_repo: res.upstream_repo.clone(),
+                            published_crate: res.published_crate(),
+                            in_progress: false,
+                            in_progress_builds: Vec::new(),
+                            trust_level: TrustLevel::of(verified_build.is_verified, "", None),
+                            verification_method: verified_build
+                                .is_verified
+                                .then_some(VerificationMethod::SourceBuild),
+                            attestation: None,
+                            claim: None,
+                            warnings: res.stored_warnings(),
                         }
                         .into(),
                     ),
@@ -58,6 +196,40 @@ pub(crate) async fn verify_sync(
                                     format!("{}/commit/{}", verify_build_data.repository, hash)
                                 }),
                             last_verified_at: None,
+                            verified_by_authority: false,
+                            record_exists: true,
+                            extra_hashes: BTreeMap::new(),
+                            loader: None,
+                            registry_match: None,
+                            program_size: None,
+                            rent_exempt: None,
+                            upgrade_authority: None,
+                            immutable: None,
+                            resolved_commit: res.resolved_commit.clone(),
+                            docker_build_args: verify_build_data
+                                .docker_build_args
+                                .as_deref()
+                                .map(build_args_to_map)
+                                .unwrap_or_default(),
+                            target: Some(verify_build_data.target.clone()),
+                            network: Some(verify_build_data.network.clone()),
+                            commitment: Some(verify_build_data.commitment.clone()),
+                            rustflags: verify_build_data.rustflags.clone().unwrap_or_default(),
+                            status_changed: false,
+                            declared_id: None,
+                            declared_id_matches: None,
+                            hash_mode: HashMode::Trimmed,
+                            on_chain_mode_hash: None,
+                            labels: labels_from_json(res.labels.as_ref()),
+                            upstream_repo: res.upstream_repo.clone(),
+                            published_crate: res.published_crate(),
+                            in_progress: true,
+                            in_progress_builds: vec![res.id.clone()],
+                            trust_level: TrustLevel::of(false, "", None),
+                            verification_method: None,
+                            attestation: None,
+                            claim: None,
+                            warnings: res.stored_warnings(),
                         }
                         .into(),
                     ),
@@ -70,15 +242,37 @@ pub(crate) async fn verify_sync(
         }
     }
 
+    // Reject giant repositories before spending disk and build time on them. Published
+    // crates are held to the same limit as they are downloaded.
+    let size_check = match payload.published_crate() {
+        Some(_) => Ok(()),
+        None => check_repository_size(&payload.repository, payload.commit_hash.as_deref()).await,
+    };
+    if let Err(err) = size_check {
+        tracing::warn!("Refused to build {}: {}", payload.repository, err);
+        return (
+            err.status_code(),
+            Json(
+                ErrorResponse {
+                    status: Status::Error,
+                    error: err.user_message(),
+                    field_errors: Vec::new(),
+                }
+                .into(),
+            ),
+        );
+    }
+
     // insert into database
     if let Err(e) = db.insert_build_params(&verify_build_data).await {
         tracing::error!("Error inserting into database: {:?}", e);
         return (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status_code(),
             Json(
                 ErrorResponse {
                     status: Status::Error,
-                    error: ErrorMessages::DB.to_string(),
+                    error: e.user_message(),
+                    field_errors: Vec::new(),
                 }
                 .into(),
             ),
@@ -88,12 +282,25 @@ pub(crate) async fn verify_sync(
     tracing::info!("Inserted into database");
 
     // run task and wait for it to finish
-    match verify_build(payload, &verify_build_data.id).await {
-        Ok(res) => {
-            let _ = db.insert_or_update_verified_build(&res).await;
+    match verify_build(&db, payload, &verify_build_data.id).await {
+        Ok((res, warnings)) => {
+            let resolved_commit = db
+                .get_job(&verify_build_data.id)
+                .await
+                .ok()
+                .and_then(|build| build.resolved_commit);
+            let status_changed = db
+                .insert_or_update_verified_build(&res)
+                .await
+                .unwrap_or(false);
+            let _ = db
+                .set_build_warnings(&verify_build_data.id, &warnings)
+                .await;
             let _ = db
                 .update_build_status(&verify_build_data.id, JobStatus::Completed.into())
                 .await;
+            let declared_id_matches = res.declared_id_matches();
+            let published_crate = verify_build_data.published_crate();
             (
                 StatusCode::OK,
                 Json(
@@ -112,6 +319,42 @@ pub(crate) async fn verify_sync(
                             .map_or(verify_build_data.repository.clone(), |hash| {
                                 format!("{}/commit/{}", verify_build_data.repository, hash)
                             }),
+                        verified_by_authority: res.is_verified && authority_claimed,
+                        record_exists: true,
+                        extra_hashes: digests_to_map(res.extra_digests),
+                        loader: res.loader,
+                        registry_match: res.registry_match,
+                        program_size: None,
+                        rent_exempt: None,
+                        upgrade_authority: None,
+                        immutable: None,
+                        resolved_commit,
+                        docker_build_args: verify_build_data
+                            .docker_build_args
+                            .as_deref()
+                            .map(build_args_to_map)
+                            .unwrap_or_default(),
+                        target: Some(verify_build_data.target.clone()),
+                        network: Some(verify_build_data.network.clone()),
+                        commitment: Some(verify_build_data.commitment.clone()),
+                        rustflags: verify_build_data.rustflags.clone().unwrap_or_default(),
+                        status_changed,
+                        declared_id: res.declared_id,
+                        declared_id_matches,
+                        hash_mode: HashMode::Trimmed,
+                        on_chain_mode_hash: None,
+                        labels: labels_from_json(verify_build_data.labels.as_ref()),
+                        upstream_repo: verify_build_data.upstream_repo.clone(),
+                        published_crate,
+                        in_progress: false,
+                        in_progress_builds: Vec::new(),
+                        trust_level: TrustLevel::of(res.is_verified, "", None),
+                        verification_method: res
+                            .is_verified
+                            .then_some(VerificationMethod::SourceBuild),
+                        attestation: None,
+                        claim: None,
+                        warnings,
                     }
                     .into(),
                 ),
@@ -128,6 +371,7 @@ pub(crate) async fn verify_sync(
                     ErrorResponse {
                         status: Status::Error,
                         error: ErrorMessages::Unexpected.to_string(),
+                        field_errors: Vec::new(),
                     }
                     .into(),
                 ),
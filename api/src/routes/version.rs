@@ -0,0 +1,23 @@
+use crate::models::VersionResponse;
+use axum::Json;
+
+// Versions of the API served, the current one last
+const API_VERSIONS: [&str; 1] = ["1"];
+
+// Route handler for GET /version which reports the version, commit and build time of the
+// running server, to tell which build a deployment runs
+pub(crate) async fn get_version() -> Json<VersionResponse> {
+    let built_at = env!("BUILD_TIMESTAMP")
+        .parse()
+        .ok()
+        .and_then(|timestamp| chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0));
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("BUILD_GIT_COMMIT").to_string(),
+        built_at,
+        api_versions: API_VERSIONS
+            .iter()
+            .map(|version| version.to_string())
+            .collect(),
+    })
+}
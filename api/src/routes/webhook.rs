@@ -0,0 +1,135 @@
+use std::env;
+
+use crate::db::DbClient;
+use crate::errors::error_response;
+use crate::models::{ErrorResponse, GithubPushEvent, WebhookResponse};
+use crate::registry::{enqueue_verification, registry_programs_for_repo};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+const EVENT_HEADER: &str = "X-GitHub-Event";
+
+/// Check the `sha256=<hex>` HMAC of the body against the webhook secret in constant time.
+fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex_signature| hex::decode(hex_signature).ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+// Route handler for POST /webhook/github which re-verifies the registry programs built
+// from a repository whenever its default branch is pushed to
+pub(crate) async fn github_webhook(
+    State(db): State<DbClient>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<WebhookResponse>), (StatusCode, Json<ErrorResponse>)> {
+    // Anyone can sign a body with an empty secret, so it counts as not configured
+    let Some(secret) = env::var("GITHUB_WEBHOOK_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+    else {
+        tracing::error!("GITHUB_WEBHOOK_SECRET is not set, rejecting webhook");
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "GitHub webhooks are not configured",
+        ));
+    };
+
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !verify_signature(&secret, &body, signature) {
+        return Err(error_response(
+            StatusCode::UNAUTHORIZED,
+            "Invalid webhook signature",
+        ));
+    }
+
+    let event = headers
+        .get(EVENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if event != "push" {
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(WebhookResponse {
+                message: format!("Ignoring {} event", event),
+                program_ids: Vec::new(),
+            }),
+        ));
+    }
+
+    let push: GithubPushEvent = serde_json::from_slice(&body)
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid push event payload"))?;
+
+    // Only pushes to the default branch trigger a verification
+    if push.git_ref != format!("refs/heads/{}", push.repository.default_branch) || push.deleted {
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(WebhookResponse {
+                message: format!("Ignoring push to {}", push.git_ref),
+                program_ids: Vec::new(),
+            }),
+        ));
+    }
+
+    let mut program_ids = Vec::new();
+    for registered in registry_programs_for_repo(&push.repository.html_url) {
+        let mut params = registered.clone();
+        params.commit_hash = Some(push.after.clone());
+        program_ids.push(params.program_id.clone());
+        enqueue_verification(db.clone(), params);
+    }
+
+    tracing::info!(
+        "Push to {} at {} queued {} verifications",
+        push.repository.html_url,
+        push.after,
+        program_ids.len()
+    );
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(WebhookResponse {
+            message: if program_ids.is_empty() {
+                "No registered programs for this repository".to_string()
+            } else {
+                "Verification queued".to_string()
+            },
+            program_ids,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature() {
+        // Example from the GitHub webhook documentation
+        let secret = "It's a Secret to Everybody";
+        let body = b"Hello, World!";
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+        assert!(verify_signature(secret, body, signature));
+        assert!(!verify_signature("wrong secret", body, signature));
+        assert!(!verify_signature(secret, b"Hello, World", signature));
+        assert!(!verify_signature(secret, body, "sha1=deadbeef"));
+    }
+}
@@ -0,0 +1,538 @@
+use std::env;
+
+use base64::Engine;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::errors::ApiError;
+use crate::models::{Commitment, Network, ProgramLoader};
+use crate::Result;
+
+// Size of the UpgradeableLoaderState::ProgramData header preceding the program bytes:
+// enum tag (4) + slot (8) + Option<Pubkey> tag (1) + upgrade authority (32)
+const PROGRAM_DATA_METADATA_SIZE: usize = 45;
+// Size of the LoaderV4State header preceding the program bytes:
+// slot (8) + authority or next version (32) + status (8)
+const LOADER_V4_METADATA_SIZE: usize = 48;
+// LoaderV4Status::Finalized, the program can no longer be upgraded
+const LOADER_V4_STATUS_FINALIZED: u64 = 2;
+
+// Seed prefix of the hash registry entry of a program
+const HASH_REGISTRY_SEED: &[u8] = b"program_hash";
+// Size of a hash registry entry: discriminator (8) + program id (32) + executable hash (32)
+const HASH_REGISTRY_ENTRY_SIZE: usize = 72;
+
+const BPF_LOADER_UPGRADEABLE_ID: &str = "BPFLoaderUpgradeab1e11111111111111111111111";
+const LOADER_V4_ID: &str = "LoaderV411111111111111111111111111111111111";
+const BPF_LOADER_IDS: [&str; 2] = [
+    "BPFLoader2111111111111111111111111111111111",
+    "BPFLoader1111111111111111111111111111111111",
+];
+
+struct Account {
+    owner: String,
+    lamports: u64,
+    // Full size of the account data, which may be larger than `data` when sliced
+    space: usize,
+    data: Vec<u8>,
+}
+
+/// Size of a deployed executable, whether the account storing it is rent-exempt and
+/// who can upgrade it.
+#[derive(Debug, Clone)]
+pub struct ProgramAccountInfo {
+    pub program_size: u64,
+    pub rent_exempt: bool,
+    // `None` if the program is immutable
+    pub upgrade_authority: Option<String>,
+}
+
+fn rpc_url() -> String {
+    Network::default().rpc_url()
+}
+
+// Send a JSON-RPC request to the cluster at `RPC_URL` and return its result
+async fn rpc_request(method: &str, params: Value) -> Result<Value> {
+    let mut response: Value = reqwest::Client::new()
+        .post(rpc_url())
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if let Some(error) = response.get("error") {
+        return Err(ApiError::Custom(format!("RPC error: {}", error)));
+    }
+    Ok(response["result"].take())
+}
+
+/// Fetch an account with `getAccountInfo`, optionally limited to the first `length`
+/// bytes of its data. Returns `None` if the account does not exist.
+async fn get_account(address: &str, length: Option<usize>) -> Result<Option<Account>> {
+    get_account_at(address, 0, length, Commitment::default()).await
+}
+
+/// Fetch an account with `getAccountInfo`, optionally limited to `length` bytes of its
+/// data starting at `offset`. Returns `None` if the account does not exist.
+async fn get_account_slice(
+    address: &str,
+    offset: usize,
+    length: Option<usize>,
+) -> Result<Option<Account>> {
+    get_account_at(address, offset, length, Commitment::default()).await
+}
+
+// Fetch a slice of an account as of the cluster state at `commitment`
+async fn get_account_at(
+    address: &str,
+    offset: usize,
+    length: Option<usize>,
+    commitment: Commitment,
+) -> Result<Option<Account>> {
+    let mut config = json!({ "encoding": "base64", "commitment": commitment.as_str() });
+    if let Some(length) = length {
+        config["dataSlice"] = json!({ "offset": offset, "length": length });
+    }
+
+    let result = rpc_request("getAccountInfo", json!([address, config])).await?;
+    let value = &result["value"];
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    let malformed = || ApiError::Custom("Malformed getAccountInfo response".to_string());
+    let owner = value["owner"].as_str().ok_or_else(malformed)?.to_string();
+    let lamports = value["lamports"].as_u64().ok_or_else(malformed)?;
+    let data = value["data"][0].as_str().ok_or_else(malformed)?;
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|err| ApiError::Custom(format!("Failed to decode account data: {}", err)))?;
+    let space = value["space"]
+        .as_u64()
+        .map_or(data.len(), |space| space as usize);
+    Ok(Some(Account {
+        owner,
+        lamports,
+        space,
+        data,
+    }))
+}
+
+async fn get_minimum_balance_for_rent_exemption(space: usize) -> Result<u64> {
+    rpc_request("getMinimumBalanceForRentExemption", json!([space]))
+        .await?
+        .as_u64()
+        .ok_or_else(|| {
+            ApiError::Custom("Malformed getMinimumBalanceForRentExemption response".to_string())
+        })
+}
+
+async fn get_program_account(program_id: &str, length: Option<usize>) -> Result<Account> {
+    get_account(program_id, length)
+        .await?
+        .ok_or_else(|| ApiError::ProgramClosed(program_id.to_string()))
+}
+
+fn loader_from_owner(owner: &str) -> Option<ProgramLoader> {
+    match owner {
+        BPF_LOADER_UPGRADEABLE_ID => Some(ProgramLoader::BpfLoaderUpgradeable),
+        LOADER_V4_ID => Some(ProgramLoader::LoaderV4),
+        owner if BPF_LOADER_IDS.contains(&owner) => Some(ProgramLoader::BpfLoader),
+        _ => None,
+    }
+}
+
+/// Detect the loader which owns a deployed program.
+pub async fn get_program_loader(program_id: &str) -> Result<ProgramLoader> {
+    let program_account = get_program_account(program_id, Some(0)).await?;
+    loader_from_owner(&program_account.owner).ok_or_else(|| {
+        ApiError::Custom(format!(
+            "Program {} is owned by unsupported loader {}",
+            program_id, program_account.owner
+        ))
+    })
+}
+
+/// Returns the size of the deployed executable and whether the account storing it,
+/// the program data account for upgradeable programs, holds enough lamports to be rent-exempt.
+pub async fn get_program_account_info(program_id: &str) -> Result<ProgramAccountInfo> {
+    let program_account = get_program_account(program_id, Some(LOADER_V4_METADATA_SIZE)).await?;
+
+    let (storage, metadata_size, upgrade_authority) =
+        match loader_from_owner(&program_account.owner) {
+            Some(ProgramLoader::BpfLoaderUpgradeable) => {
+                if program_account.data.len() < 36 || program_account.data[..4] != [2, 0, 0, 0] {
+                    return Err(ApiError::Custom(format!(
+                        "Invalid program account for {}",
+                        program_id
+                    )));
+                }
+                let program_data_address = bs58::encode(&program_account.data[4..36]).into_string();
+                let program_data =
+                    get_account(&program_data_address, Some(PROGRAM_DATA_METADATA_SIZE))
+                        .await?
+                        .ok_or_else(|| ApiError::ProgramClosed(program_id.to_string()))?;
+                let upgrade_authority =
+                    program_data_upgrade_authority(program_id, &program_data.data)?;
+                (program_data, PROGRAM_DATA_METADATA_SIZE, upgrade_authority)
+            }
+            Some(ProgramLoader::LoaderV4) => {
+                let (header, _) = parse_loader_v4_account(&program_account.data)?;
+                let upgrade_authority = loader_v4_upgrade_authority(header);
+                (program_account, LOADER_V4_METADATA_SIZE, upgrade_authority)
+            }
+            // Programs of the non-upgradeable loaders can never be changed
+            Some(ProgramLoader::BpfLoader) => (program_account, 0, None),
+            None => {
+                return Err(ApiError::Custom(format!(
+                    "Program {} is owned by unsupported loader {}",
+                    program_id, program_account.owner
+                )))
+            }
+        };
+
+    let minimum_balance = get_minimum_balance_for_rent_exemption(storage.space).await?;
+    Ok(ProgramAccountInfo {
+        program_size: storage.space.saturating_sub(metadata_size) as u64,
+        rent_exempt: storage.lamports >= minimum_balance,
+        upgrade_authority,
+    })
+}
+
+/// Size of a deployed executable with its first and last bytes.
+#[derive(Debug, Clone)]
+pub struct ProgramEdges {
+    pub program_size: u64,
+    pub head: Vec<u8>,
+    pub tail: Vec<u8>,
+}
+
+// Address of the account storing the executable of a program, the program data account for
+// upgradeable programs, and the size of the loader header preceding the executable in it
+fn program_storage_address(program_id: &str, program_account: &Account) -> Result<(String, usize)> {
+    match loader_from_owner(&program_account.owner) {
+        Some(ProgramLoader::BpfLoaderUpgradeable) => {
+            if program_account.data.len() < 36 || program_account.data[..4] != [2, 0, 0, 0] {
+                return Err(ApiError::Custom(format!(
+                    "Invalid program account for {}",
+                    program_id
+                )));
+            }
+            Ok((
+                bs58::encode(&program_account.data[4..36]).into_string(),
+                PROGRAM_DATA_METADATA_SIZE,
+            ))
+        }
+        Some(ProgramLoader::LoaderV4) => Ok((program_id.to_string(), LOADER_V4_METADATA_SIZE)),
+        Some(ProgramLoader::BpfLoader) => Ok((program_id.to_string(), 0)),
+        None => Err(ApiError::Custom(format!(
+            "Program {} is owned by unsupported loader {}",
+            program_id, program_account.owner
+        ))),
+    }
+}
+
+/// Returns the size of the deployed executable and up to `edge_size` of its first and
+/// last bytes, fetched as slices of the account storing it rather than the whole program.
+pub async fn get_program_edges(program_id: &str, edge_size: usize) -> Result<ProgramEdges> {
+    let program_account = get_program_account(program_id, Some(LOADER_V4_METADATA_SIZE)).await?;
+    let (storage_address, metadata_size) = program_storage_address(program_id, &program_account)?;
+
+    let fetch = |offset: usize, length: usize| {
+        let storage_address = storage_address.clone();
+        async move {
+            get_account_slice(&storage_address, offset, Some(length))
+                .await?
+                .ok_or_else(|| ApiError::ProgramClosed(program_id.to_string()))
+        }
+    };
+
+    let head = fetch(metadata_size, edge_size).await?;
+    let program_size = head.space.saturating_sub(metadata_size);
+    if program_size == 0 {
+        return Err(ApiError::ProgramClosed(program_id.to_string()));
+    }
+    let tail_size = edge_size.min(program_size);
+    let tail = fetch(head.space - tail_size, tail_size).await?;
+
+    Ok(ProgramEdges {
+        program_size: program_size as u64,
+        head: head.data,
+        tail: tail.data,
+    })
+}
+
+/// Returns the whole account storing the executable of a program, including the loader
+/// header and the zero padding after the executable, along with the size of the header.
+pub async fn get_program_storage(program_id: &str) -> Result<(Vec<u8>, usize)> {
+    get_program_storage_at(program_id, Commitment::default()).await
+}
+
+/// Same as `get_program_storage`, reading the accounts as of the cluster state at
+/// `commitment`.
+pub async fn get_program_storage_at(
+    program_id: &str,
+    commitment: Commitment,
+) -> Result<(Vec<u8>, usize)> {
+    let program_account = get_account_at(program_id, 0, Some(LOADER_V4_METADATA_SIZE), commitment)
+        .await?
+        .ok_or_else(|| ApiError::ProgramClosed(program_id.to_string()))?;
+    let (storage_address, metadata_size) = program_storage_address(program_id, &program_account)?;
+    let storage = get_account_at(&storage_address, 0, None, commitment)
+        .await?
+        .ok_or_else(|| ApiError::ProgramClosed(program_id.to_string()))?;
+    if storage.data.len() <= metadata_size {
+        return Err(ApiError::ProgramClosed(program_id.to_string()));
+    }
+    Ok((storage.data, metadata_size))
+}
+
+// Split a loader-v4 program account into its header and the executable bytes
+fn parse_loader_v4_account(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    if data.len() < LOADER_V4_METADATA_SIZE {
+        return Err(ApiError::Custom(
+            "Invalid loader-v4 program account".to_string(),
+        ));
+    }
+    Ok(data.split_at(LOADER_V4_METADATA_SIZE))
+}
+
+/// Returns the executable bytes of a program deployed with loader-v4, which are
+/// stored in the program account itself after the `LoaderV4State` header.
+pub async fn get_loader_v4_executable(program_id: &str) -> Result<Vec<u8>> {
+    let program_account = get_program_account(program_id, None).await?;
+    if program_account.owner != LOADER_V4_ID {
+        return Err(ApiError::Custom(format!(
+            "Program {} is not owned by loader-v4",
+            program_id
+        )));
+    }
+    let (_, executable) = parse_loader_v4_account(&program_account.data)?;
+    if executable.is_empty() {
+        return Err(ApiError::ProgramClosed(program_id.to_string()));
+    }
+    Ok(executable.to_vec())
+}
+
+/// Returns the upgrade authority of a program deployed with the upgradeable BPF loader
+/// or loader-v4, or `None` if the program is immutable.
+pub async fn get_upgrade_authority(program_id: &str) -> Result<Option<String>> {
+    let program_account = get_program_account(program_id, None).await?;
+
+    if program_account.owner == LOADER_V4_ID {
+        let (header, _) = parse_loader_v4_account(&program_account.data)?;
+        return Ok(loader_v4_upgrade_authority(header));
+    }
+
+    // UpgradeableLoaderState::Program { programdata_address }
+    let program_account = program_account.data;
+    if program_account.len() < 36 || program_account[..4] != [2, 0, 0, 0] {
+        return Err(ApiError::Custom(format!(
+            "Program {} is not owned by the upgradeable loader",
+            program_id
+        )));
+    }
+    let program_data_address = bs58::encode(&program_account[4..36]).into_string();
+
+    let program_data = get_account(&program_data_address, Some(PROGRAM_DATA_METADATA_SIZE))
+        .await?
+        .ok_or_else(|| ApiError::ProgramClosed(program_id.to_string()))?
+        .data;
+    program_data_upgrade_authority(program_id, &program_data)
+}
+
+// Upgrade authority stored in the `LoaderV4State` header, `None` once finalized
+fn loader_v4_upgrade_authority(header: &[u8]) -> Option<String> {
+    let status = u64::from_le_bytes(header[40..48].try_into().expect("8 byte slice"));
+    if status == LOADER_V4_STATUS_FINALIZED {
+        return None;
+    }
+    Some(bs58::encode(&header[8..40]).into_string())
+}
+
+// Upgrade authority stored in the `UpgradeableLoaderState::ProgramData` header
+fn program_data_upgrade_authority(program_id: &str, program_data: &[u8]) -> Result<Option<String>> {
+    if program_data.len() < PROGRAM_DATA_METADATA_SIZE || program_data[..4] != [3, 0, 0, 0] {
+        return Err(ApiError::Custom(format!(
+            "Invalid program data account for {}",
+            program_id
+        )));
+    }
+
+    match program_data[12] {
+        0 => Ok(None),
+        _ => Ok(Some(bs58::encode(&program_data[13..45]).into_string())),
+    }
+}
+
+/// Check that `signature` is a valid signature of `message` by the program's upgrade
+/// authority, returning the authority.
+pub async fn verify_authority_signature(
+    program_id: &str,
+    message: &str,
+    signature: &str,
+) -> Result<String> {
+    let authority = get_upgrade_authority(program_id).await?.ok_or_else(|| {
+        ApiError::InvalidSignature("Program has no upgrade authority".to_string())
+    })?;
+
+    let authority_bytes: [u8; 32] = bs58::decode(&authority)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ApiError::Custom("Invalid upgrade authority".to_string()))?;
+    let signature_bytes: [u8; 64] = bs58::decode(signature)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ApiError::InvalidSignature("Malformed signature".to_string()))?;
+
+    let verifying_key = VerifyingKey::from_bytes(&authority_bytes)
+        .map_err(|_| ApiError::Custom("Invalid upgrade authority".to_string()))?;
+    verifying_key
+        .verify_strict(message.as_bytes(), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| {
+            ApiError::InvalidSignature(
+                "Signature does not match the program upgrade authority".to_string(),
+            )
+        })?;
+    Ok(authority)
+}
+
+// Decode a base58 public key
+fn decode_pubkey(address: &str) -> Result<[u8; 32]> {
+    bs58::decode(address)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ApiError::Custom(format!("Invalid public key {}", address)))
+}
+
+/// Derive a program address the same way as `Pubkey::find_program_address`, returning the
+/// first address off the ed25519 curve starting from bump 255.
+fn find_program_address(seeds: &[&[u8]], program_id: &[u8; 32]) -> Option<([u8; 32], u8)> {
+    (0..=u8::MAX).rev().find_map(|bump| {
+        let mut hasher = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update([bump]);
+        hasher.update(program_id);
+        hasher.update(b"ProgramDerivedAddress");
+        let address: [u8; 32] = hasher.finalize().into();
+        CompressedEdwardsY(address)
+            .decompress()
+            .is_none()
+            .then_some((address, bump))
+    })
+}
+
+/// Program owning the on-chain hash registry, configured with `HASH_REGISTRY_PROGRAM_ID`.
+pub fn hash_registry_program_id() -> Option<String> {
+    env::var("HASH_REGISTRY_PROGRAM_ID")
+        .ok()
+        .filter(|program_id| !program_id.is_empty())
+}
+
+/// Read the expected executable hash of `program_id` from its entry in the hash registry,
+/// the PDA with seeds `["program_hash", program_id]`. Returns `None` if the program has no entry.
+pub async fn get_registry_hash(
+    registry_program_id: &str,
+    program_id: &str,
+) -> Result<Option<String>> {
+    let registry = decode_pubkey(registry_program_id)?;
+    let program = decode_pubkey(program_id)?;
+    let (entry_address, _) = find_program_address(&[HASH_REGISTRY_SEED, &program], &registry)
+        .ok_or_else(|| ApiError::Custom("Failed to derive the registry entry".to_string()))?;
+    let entry_address = bs58::encode(entry_address).into_string();
+
+    let Some(entry) = get_account(&entry_address, Some(HASH_REGISTRY_ENTRY_SIZE)).await? else {
+        return Ok(None);
+    };
+    if entry.owner != registry_program_id
+        || entry.data.len() < HASH_REGISTRY_ENTRY_SIZE
+        || entry.data[8..40] != program
+    {
+        return Err(ApiError::Custom(format!(
+            "Invalid hash registry entry {}",
+            entry_address
+        )));
+    }
+    Ok(Some(hex::encode(&entry.data[40..72])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_ID: &str = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY";
+
+    #[test]
+    fn test_program_data_upgrade_authority() {
+        let authority = [7u8; 32];
+        let mut program_data = vec![3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        program_data.extend_from_slice(&authority);
+        assert_eq!(
+            program_data_upgrade_authority(PROGRAM_ID, &program_data).unwrap(),
+            Some(bs58::encode(authority).into_string())
+        );
+
+        // Immutable programs have no authority
+        program_data[12] = 0;
+        assert_eq!(
+            program_data_upgrade_authority(PROGRAM_ID, &program_data).unwrap(),
+            None
+        );
+
+        assert!(program_data_upgrade_authority(PROGRAM_ID, &program_data[..20]).is_err());
+    }
+
+    #[test]
+    fn test_find_program_address() {
+        let registry = decode_pubkey("verifycLy8mB96wd9wqq3WDXQwM4oU6r42Th37Db9fC").unwrap();
+        let program = decode_pubkey("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY").unwrap();
+        let (address, bump) =
+            find_program_address(&[HASH_REGISTRY_SEED, &program], &registry).unwrap();
+        assert_eq!(
+            bs58::encode(address).into_string(),
+            "Ff5JdbEm4wDCqdLyCZ3yW8uMb1bptutv8fJhQXQwCxBg"
+        );
+        assert_eq!(bump, 254);
+    }
+
+    #[test]
+    fn test_loader_from_owner() {
+        assert_eq!(
+            loader_from_owner(BPF_LOADER_UPGRADEABLE_ID),
+            Some(ProgramLoader::BpfLoaderUpgradeable)
+        );
+        assert_eq!(
+            loader_from_owner(LOADER_V4_ID),
+            Some(ProgramLoader::LoaderV4)
+        );
+        assert_eq!(
+            loader_from_owner("BPFLoader2111111111111111111111111111111111"),
+            Some(ProgramLoader::BpfLoader)
+        );
+        assert_eq!(loader_from_owner("11111111111111111111111111111111"), None);
+    }
+
+    #[test]
+    fn test_parse_loader_v4_account() {
+        let mut data = vec![0u8; LOADER_V4_METADATA_SIZE];
+        data.extend_from_slice(b"\x7fELF");
+        let (header, executable) = parse_loader_v4_account(&data).unwrap();
+        assert_eq!(header.len(), LOADER_V4_METADATA_SIZE);
+        assert_eq!(executable, b"\x7fELF");
+
+        assert!(parse_loader_v4_account(&[0u8; 16]).is_err());
+    }
+}
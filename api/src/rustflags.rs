@@ -0,0 +1,182 @@
+// Codegen options which can be passed with `-C`. They change the generated code but not
+// what the build can read or run.
+pub const ALLOWED_CODEGEN_OPTIONS: [&str; 10] = [
+    "opt-level",
+    "codegen-units",
+    "overflow-checks",
+    "debug-assertions",
+    "debuginfo",
+    "lto",
+    "embed-bitcode",
+    "strip",
+    "target-feature",
+    "link-arg",
+];
+
+// Codegen options known to break reproducibility or to let a build run or read anything
+// outside the repository, with the reason they are rejected
+const REJECTED_CODEGEN_OPTIONS: [(&str, &str); 8] = [
+    ("linker", "runs an executable chosen by the request"),
+    ("linker-flavor", "changes the linker the build runs"),
+    (
+        "link-args",
+        "is split on spaces, use link-arg once per argument",
+    ),
+    ("incremental", "makes the output depend on previous builds"),
+    ("profile-generate", "instruments the program"),
+    ("profile-use", "reads a profile from outside the repository"),
+    ("instrument-coverage", "instruments the program"),
+    ("metadata", "changes the symbol names of every crate"),
+];
+
+// Linker arguments which read files, such as linker scripts
+const REJECTED_LINK_ARG_PREFIXES: [&str; 5] =
+    ["-T", "--script", "--version-script", "--dynamic-list", "@"];
+
+// Most flags accepted in a request
+pub const MAX_RUSTFLAGS: usize = 16;
+
+// Longest value of a single flag
+const MAX_RUSTFLAG_VALUE_LENGTH: usize = 64;
+
+// Values are limited to characters found in codegen option values, so a flag cannot
+// reference a path or carry another flag
+fn is_valid_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_RUSTFLAG_VALUE_LENGTH
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_=,+".contains(c))
+}
+
+fn is_valid_cfg_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Check a requested flag and turn it into the single token form used in `RUSTFLAGS`,
+/// `-Ckey=value` or `--cfg=name`, so that equal flags are always stored the same way.
+pub fn normalize_rustflag(flag: &str) -> Result<String, String> {
+    let flag = flag.trim();
+
+    if let Some(rest) = flag.strip_prefix("--cfg") {
+        let name = rest
+            .strip_prefix(|c: char| c == '=' || c == ' ')
+            .unwrap_or_default()
+            .trim_start();
+        if !is_valid_cfg_name(name) {
+            return Err(format!("--cfg takes an identifier: {}", flag));
+        }
+        return Ok(format!("--cfg={}", name));
+    }
+
+    if flag.starts_with("-Z") {
+        return Err(format!(
+            "Unstable -Z options are not reproducible across toolchains: {}",
+            flag
+        ));
+    }
+
+    let option = if let Some(rest) = flag.strip_prefix("--codegen") {
+        rest.strip_prefix(|c: char| c == '=' || c == ' ')
+    } else {
+        flag.strip_prefix("-C")
+    };
+    let Some(option) = option.map(str::trim_start) else {
+        return Err(format!(
+            "Only -C codegen options and --cfg are accepted: {}",
+            flag
+        ));
+    };
+    let Some((key, value)) = option.split_once('=') else {
+        return Err(format!(
+            "Codegen options must be given as -C key=value: {}",
+            flag
+        ));
+    };
+
+    if let Some((_, reason)) = REJECTED_CODEGEN_OPTIONS
+        .iter()
+        .find(|(rejected, _)| *rejected == key)
+    {
+        return Err(format!("-C {} is not accepted as it {}", key, reason));
+    }
+    if !ALLOWED_CODEGEN_OPTIONS.contains(&key) {
+        return Err(format!(
+            "Codegen option {} is not accepted, allowed options are {}",
+            key,
+            ALLOWED_CODEGEN_OPTIONS.join(", ")
+        ));
+    }
+    if !is_valid_value(value) {
+        return Err(format!(
+            "Invalid value for -C {}: values may only contain alphanumerics and '-_=,+'",
+            key
+        ));
+    }
+    if key == "link-arg"
+        && REJECTED_LINK_ARG_PREFIXES
+            .iter()
+            .any(|prefix| value.starts_with(prefix))
+    {
+        return Err(format!("Linker argument {} reads files", value));
+    }
+
+    Ok(format!("-C{}={}", key, value))
+}
+
+/// Normalize all the requested flags, keeping their order since later flags override
+/// earlier ones.
+pub fn normalize_rustflags(flags: &[String]) -> Result<Vec<String>, String> {
+    flags.iter().map(|flag| normalize_rustflag(flag)).collect()
+}
+
+/// Value of `RUSTFLAGS` for normalized flags, which never contain spaces.
+pub fn rustflags_env(flags: &[String]) -> String {
+    flags.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_rustflags() {
+        let flags = normalize_rustflags(&[
+            "-C opt-level=3".to_string(),
+            "-Clink-arg=--icf=all".to_string(),
+            "--codegen=overflow-checks=on".to_string(),
+            "--cfg feature_gate".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            flags,
+            vec![
+                "-Copt-level=3",
+                "-Clink-arg=--icf=all",
+                "-Coverflow-checks=on",
+                "--cfg=feature_gate",
+            ]
+        );
+        assert_eq!(
+            rustflags_env(&flags),
+            "-Copt-level=3 -Clink-arg=--icf=all -Coverflow-checks=on --cfg=feature_gate"
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsafe_rustflags() {
+        assert!(normalize_rustflag("-C linker=/tmp/evil").is_err());
+        assert!(normalize_rustflag("-C incremental=target").is_err());
+        assert!(normalize_rustflag("-C link-arg=-T../../etc/passwd").is_err());
+        assert!(normalize_rustflag("-C link-arg=--script=layout").is_err());
+        assert!(normalize_rustflag("-Zshare-generics").is_err());
+        assert!(normalize_rustflag("-C opt-level=3 -C linker=cc").is_err());
+        assert!(normalize_rustflag("-C target-cpu=native").is_err());
+        assert!(normalize_rustflag("-C lto").is_err());
+        assert!(normalize_rustflag("--cfg a=\"b\"").is_err());
+        assert!(normalize_rustflag("--remap-path-prefix=/a=/b").is_err());
+    }
+}
@@ -11,6 +11,35 @@ diesel::table! {
         bpf_flag -> Bool,
         created_at -> Timestamp,
         status -> Varchar,
+        no_default_features -> Bool,
+        workspace_member -> Nullable<Varchar>,
+        authority_claimed -> Bool,
+        resolved_ref -> Nullable<Varchar>,
+        resolved_commit -> Nullable<Varchar>,
+        vendored -> Bool,
+        extra_hashes -> Nullable<Array<Text>>,
+        tag -> Nullable<Varchar>,
+        docker_build_args -> Nullable<Array<Text>>,
+        build_system -> Varchar,
+        dockerfile_path -> Nullable<Varchar>,
+        artifact_path -> Nullable<Varchar>,
+        target -> Varchar,
+        anchor_version -> Nullable<Varchar>,
+        cargo_profile -> Varchar,
+        profile_overrides -> Nullable<Array<Text>>,
+        rustflags -> Nullable<Array<Text>>,
+        network -> Varchar,
+        source_date_epoch -> Nullable<Int8>,
+        tenant_id -> Varchar,
+        lock_file -> Nullable<Text>,
+        lock_file_hash -> Nullable<Varchar>,
+        language -> Varchar,
+        labels -> Nullable<Jsonb>,
+        upstream_repo -> Nullable<Varchar>,
+        commitment -> Varchar,
+        crate_name -> Nullable<Varchar>,
+        crate_version -> Nullable<Varchar>,
+        warnings -> Nullable<Jsonb>,
     }
 }
 
@@ -23,9 +52,86 @@ diesel::table! {
         executable_hash -> Varchar,
         verified_at -> Timestamp,
         solana_build_id -> Varchar,
+        extra_digests -> Nullable<Array<Text>>,
+        loader -> Nullable<Varchar>,
+        registry_match -> Nullable<Bool>,
+        status_changed -> Bool,
+        tenant_id -> Varchar,
+        declared_id -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    build_events (id) {
+        id -> Varchar,
+        build_id -> Varchar,
+        event -> Varchar,
+        detail -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    denied_programs (program_id) {
+        program_id -> Varchar,
+        reason -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    subscriptions (id) {
+        id -> Varchar,
+        program_id -> Varchar,
+        webhook_url -> Varchar,
+        created_at -> Timestamp,
+        owner -> Varchar,
+    }
+}
+
+diesel::table! {
+    attestations (program_id) {
+        program_id -> Varchar,
+        executable_hash -> Varchar,
+        authority -> Varchar,
+        signature -> Varchar,
+        attested_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    program_claims (tenant_id, program_id) {
+        tenant_id -> Varchar,
+        program_id -> Varchar,
+        method -> Varchar,
+        claimed_by -> Varchar,
+        proof -> Varchar,
+        claimed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    program_versions (tenant_id, program_id, on_chain_hash) {
+        tenant_id -> Varchar,
+        program_id -> Varchar,
+        on_chain_hash -> Varchar,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        verified_build_id -> Nullable<Varchar>,
+        verified_at -> Nullable<Timestamp>,
     }
 }
 
 diesel::joinable!(verified_programs -> solana_program_builds (solana_build_id));
+diesel::joinable!(build_events -> solana_program_builds (build_id));
 
-diesel::allow_tables_to_appear_in_same_query!(solana_program_builds, verified_programs,);
+diesel::allow_tables_to_appear_in_same_query!(
+    attestations,
+    build_events,
+    denied_programs,
+    program_claims,
+    program_versions,
+    solana_program_builds,
+    subscriptions,
+    verified_programs,
+);
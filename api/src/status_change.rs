@@ -0,0 +1,289 @@
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::db::DbClient;
+use crate::models::VerifiedProgram;
+use crate::tenant::DEFAULT_TENANT;
+
+// Time allowed to each subscriber webhook to respond
+const SUBSCRIPTION_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Time allowed to `STATUS_WEBHOOK_URL` to respond
+const STATUS_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Payload posted to `STATUS_WEBHOOK_URL` when a verified program stops matching its build
+#[derive(Debug, Serialize)]
+struct VerificationLostEvent {
+    event: &'static str,
+    program_id: String,
+    build_id: String,
+    on_chain_hash: String,
+    executable_hash: String,
+}
+
+// Payload posted to the webhook of each subscription of a program
+#[derive(Debug, Serialize)]
+struct SubscriptionEvent {
+    event: &'static str,
+    subscription_id: String,
+    program_id: String,
+    build_id: String,
+    is_verified: bool,
+    previous_is_verified: Option<bool>,
+    on_chain_hash: String,
+    executable_hash: String,
+}
+
+// Whether an address is reachable from the internet, rather than private, loopback,
+// link-local or otherwise reserved to the network of the server
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            // 100.64.0.0/10 is shared by carrier-grade NATs
+            let shared = first == 100 && (64..128).contains(&second);
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || shared)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            // fc00::/7 are unique local addresses and fe80::/10 link-local ones
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+// The host of `url` and the addresses it resolves to, if they are all public. Hosts which
+// do not resolve are not public.
+async fn public_webhook_addresses(url: &str) -> Option<(String, Vec<SocketAddr>)> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let (host, port) = (url.host_str()?, url.port_or_known_default()?);
+    // IPv6 hosts are bracketed in URLs
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await.ok()?.collect();
+    (!addresses.is_empty() && addresses.iter().all(|address| is_public_ip(address.ip())))
+        .then(|| (host.to_string(), addresses))
+}
+
+/// Whether every address the host of `url` resolves to is public, so webhooks cannot make
+/// the server post to its own network. Hosts which do not resolve are not public.
+pub async fn is_public_webhook(url: &str) -> bool {
+    public_webhook_addresses(url).await.is_some()
+}
+
+// Client posting to `url` only if its host resolves to public addresses. The request is
+// pinned to the addresses checked, so the host cannot be pointed elsewhere in between, and
+// redirects are not followed, as they could lead anywhere.
+async fn webhook_client(url: &str) -> Option<reqwest::Client> {
+    let (host, addresses) = public_webhook_addresses(url).await?;
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&host, &addresses)
+        .build()
+        .ok()
+}
+
+/// Whether a verification result changes `is_verified` compared to the previous one.
+pub fn status_changed(previous: Option<&VerifiedProgram>, is_verified: bool) -> bool {
+    previous.is_some_and(|previous| previous.is_verified != is_verified)
+}
+
+/// Whether a previously verified program no longer matches its build.
+pub fn verification_lost(previous: Option<&VerifiedProgram>, is_verified: bool) -> bool {
+    !is_verified && previous.is_some_and(|previous| previous.is_verified)
+}
+
+/// Flag a program which was verified but no longer matches its build, usually after an
+/// upgrade: record a `verification_lost` event on the build and post it to
+/// `STATUS_WEBHOOK_URL` if set and public. The webhook is called in the background so that
+/// a slow endpoint never delays a build.
+pub async fn report_verification_lost(db: &DbClient, verification: &VerifiedProgram) {
+    tracing::warn!(
+        "Program {} is no longer verified: on-chain hash {} does not match {}",
+        verification.program_id,
+        verification.on_chain_hash,
+        verification.executable_hash
+    );
+    db.record_event(
+        &verification.solana_build_id,
+        "verification_lost",
+        Some(&verification.on_chain_hash),
+    )
+    .await;
+
+    let Ok(webhook_url) = env::var("STATUS_WEBHOOK_URL") else {
+        return;
+    };
+    let event = VerificationLostEvent {
+        event: "verification_lost",
+        program_id: verification.program_id.clone(),
+        build_id: verification.solana_build_id.clone(),
+        on_chain_hash: verification.on_chain_hash.clone(),
+        executable_hash: verification.executable_hash.clone(),
+    };
+    tokio::spawn(async move {
+        let Some(client) = webhook_client(&webhook_url).await else {
+            tracing::error!(
+                "Failed to send the status webhook for {}: {} does not resolve to a public address",
+                event.program_id,
+                webhook_url
+            );
+            return;
+        };
+        let result = client
+            .post(&webhook_url)
+            .timeout(STATUS_WEBHOOK_TIMEOUT)
+            .json(&event)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(err) = result {
+            tracing::error!(
+                "Failed to send the status webhook for {}: {}",
+                event.program_id,
+                err
+            );
+        }
+    });
+}
+
+/// Post `event` with the new verification of a program to the webhooks subscribed to it.
+/// Webhooks are called in the background so that slow subscribers never delay a build.
+/// Subscriptions are public, so only the verifications of the default tenant are posted.
+pub async fn notify_subscribers(
+    db: &DbClient,
+    event: &'static str,
+    previous: Option<&VerifiedProgram>,
+    verification: &VerifiedProgram,
+) {
+    if verification.tenant_id != DEFAULT_TENANT {
+        return;
+    }
+    let subscriptions = match db.get_subscriptions(&verification.program_id).await {
+        Ok(subscriptions) => subscriptions,
+        Err(err) => {
+            tracing::error!(
+                "Failed to get the subscriptions of {}: {}",
+                verification.program_id,
+                err
+            );
+            return;
+        }
+    };
+
+    for subscription in subscriptions {
+        let payload = SubscriptionEvent {
+            event,
+            subscription_id: subscription.id,
+            program_id: verification.program_id.clone(),
+            build_id: verification.solana_build_id.clone(),
+            is_verified: verification.is_verified,
+            previous_is_verified: previous.map(|previous| previous.is_verified),
+            on_chain_hash: verification.on_chain_hash.clone(),
+            executable_hash: verification.executable_hash.clone(),
+        };
+        let webhook_url = subscription.webhook_url;
+        tokio::spawn(async move {
+            // The host may have been pointed elsewhere since the subscription was created
+            let Some(client) = webhook_client(&webhook_url).await else {
+                tracing::warn!(
+                    "Skipped subscription {} of {}: {} does not resolve to a public address",
+                    payload.subscription_id,
+                    payload.program_id,
+                    webhook_url
+                );
+                return;
+            };
+            let result = client
+                .post(&webhook_url)
+                .timeout(SUBSCRIPTION_WEBHOOK_TIMEOUT)
+                .json(&payload)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(err) = result {
+                tracing::warn!(
+                    "Failed to notify subscription {} of {}: {}",
+                    payload.subscription_id,
+                    payload.program_id,
+                    err
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verification(is_verified: bool) -> VerifiedProgram {
+        VerifiedProgram {
+            id: "1".to_string(),
+            program_id: "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY".to_string(),
+            is_verified,
+            on_chain_hash: "5bdb733d".to_string(),
+            executable_hash: "5bdb733d".to_string(),
+            verified_at: chrono::Utc::now().naive_utc(),
+            solana_build_id: "build".to_string(),
+            extra_digests: None,
+            loader: None,
+            registry_match: None,
+            status_changed: false,
+            tenant_id: DEFAULT_TENANT.to_string(),
+            declared_id: None,
+        }
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.8",
+            "172.16.4.2",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        let verified = verification(true);
+        let unverified = verification(false);
+
+        assert!(verification_lost(Some(&verified), false));
+        assert!(status_changed(Some(&verified), false));
+
+        assert!(!verification_lost(Some(&unverified), false));
+        assert!(!verification_lost(None, false));
+        assert!(!status_changed(None, false));
+
+        assert!(!verification_lost(Some(&unverified), true));
+        assert!(status_changed(Some(&unverified), true));
+    }
+}
@@ -0,0 +1,268 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+use crate::errors::ApiError;
+use crate::Result;
+
+// Directory of the disk backend when `STORAGE_DIR` is not set
+const DEFAULT_STORAGE_DIR: &str = "/var/lib/verified-programs/storage";
+const DEFAULT_S3_REGION: &str = "us-east-1";
+
+/// Key of the build output of a build.
+pub fn log_key(build_id: &str) -> String {
+    format!("builds/{}/build.log", build_id)
+}
+
+/// Key of the executable produced by a build.
+pub fn artifact_key(build_id: &str) -> String {
+    format!("builds/{}/program.so", build_id)
+}
+
+/// Where build logs and artifacts are kept, so any instance can serve the output of a
+/// build done by another one.
+#[async_trait]
+pub trait BuildStorage: Send + Sync {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()>;
+    /// Returns `None` if nothing is stored under the key.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+}
+
+/// Select the backend with `STORAGE_BACKEND`: `disk` (default) or `s3`.
+pub fn storage_from_env() -> Arc<dyn BuildStorage> {
+    match env::var("STORAGE_BACKEND").as_deref() {
+        Ok("s3") => Arc::new(S3Storage::from_env()),
+        _ => Arc::new(DiskStorage::new(
+            env::var("STORAGE_DIR").unwrap_or_else(|_| DEFAULT_STORAGE_DIR.to_string()),
+        )),
+    }
+}
+
+/// Stores objects as files under a local directory.
+pub struct DiskStorage {
+    root: PathBuf,
+}
+
+impl DiskStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Keys are relative paths which may not leave the storage directory
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ApiError::Custom(format!("Invalid storage key {}", key)));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl BuildStorage for DiskStorage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, data).await.map_err(Into::into)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Stores objects in a bucket of an S3-compatible service, addressed path-style so it
+/// also works with MinIO, R2 and similar services.
+pub struct S3Storage {
+    client: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Key used to sign AWS Signature Version 4 requests made on `date`
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+// Percent-encode a URI path, keeping the `/` separators
+fn uri_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+impl S3Storage {
+    /// Configure the bucket from `S3_BUCKET`, `S3_REGION`, `S3_ENDPOINT`, `S3_ACCESS_KEY_ID`
+    /// and `S3_SECRET_ACCESS_KEY`.
+    pub fn from_env() -> Self {
+        let region = env::var("S3_REGION").unwrap_or_else(|_| DEFAULT_S3_REGION.to_string());
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: env::var("S3_ENDPOINT")
+                .unwrap_or_else(|_| format!("https://s3.{}.amazonaws.com", region)),
+            bucket: env::var("S3_BUCKET").expect("S3_BUCKET not set in .env file"),
+            region,
+            access_key_id: env::var("S3_ACCESS_KEY_ID")
+                .expect("S3_ACCESS_KEY_ID not set in .env file"),
+            secret_access_key: env::var("S3_SECRET_ACCESS_KEY")
+                .expect("S3_SECRET_ACCESS_KEY not set in .env file"),
+        }
+    }
+
+    // Build a request signed with AWS Signature Version 4
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::RequestBuilder> {
+        let path = uri_encode_path(&format!("/{}/{}", self.bucket, key));
+        let url = Url::parse(&format!("{}{}", self.endpoint.trim_end_matches('/'), path))
+            .map_err(|err| ApiError::Custom(format!("Invalid S3 endpoint: {}", err)))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => return Err(ApiError::Custom("Invalid S3 endpoint".to_string())),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature = hex::encode(hmac_sha256(
+            &signing_key(&self.secret_access_key, &date, &self.region, "s3"),
+            &string_to_sign,
+        ));
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key_id, scope, signed_headers, signature
+                ),
+            )
+            .body(body))
+    }
+}
+
+#[async_trait]
+impl BuildStorage for S3Storage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let response = self
+            .signed_request(reqwest::Method::PUT, key, data)?
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ApiError::Custom(format!(
+                "S3 upload of {} failed with {}",
+                key,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .signed_request(reqwest::Method::GET, key, Vec::new())?
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+            status => Err(ApiError::Custom(format!(
+                "S3 download of {} failed with {}",
+                key, status
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20150830",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disk_storage() {
+        let root = env::temp_dir().join(format!("storage-{}", uuid::Uuid::new_v4()));
+        let storage = DiskStorage::new(&root);
+
+        storage
+            .put(&log_key("build"), b"log".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get(&log_key("build")).await.unwrap(),
+            Some(b"log".to_vec())
+        );
+        assert_eq!(storage.get(&artifact_key("build")).await.unwrap(), None);
+        assert!(storage.get("../etc/passwd").await.is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
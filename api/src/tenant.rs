@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::OnceLock;
+
+use axum::http::HeaderMap;
+
+use crate::errors::ApiError;
+use crate::Result;
+
+/// Tenant of requests without an API key, and of the badges, certificates and
+/// subscriptions, which are public.
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Header clients of a tenant authenticate with
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// Parse `api_key=tenant` entries separated by commas
+fn parse_tenant_keys(value: &str) -> HashMap<String, String> {
+    value
+        .split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(key, tenant)| (key.trim().to_string(), tenant.trim().to_string()))
+        .filter(|(key, tenant)| !key.is_empty() && !tenant.is_empty())
+        .collect()
+}
+
+// Tenants of the API keys configured in `TENANT_API_KEYS`
+fn tenant_keys() -> &'static HashMap<String, String> {
+    static KEYS: OnceLock<HashMap<String, String>> = OnceLock::new();
+    KEYS.get_or_init(|| parse_tenant_keys(&env::var("TENANT_API_KEYS").unwrap_or_default()))
+}
+
+/// Tenant whose verifications a request reads and writes, from its `x-api-key` header.
+/// Requests without one use the default tenant, and unknown keys fail with `InvalidApiKey`.
+pub fn tenant_of(headers: &HeaderMap) -> Result<String> {
+    let Some(key) = headers.get(API_KEY_HEADER) else {
+        return Ok(DEFAULT_TENANT.to_string());
+    };
+    key.to_str()
+        .ok()
+        .and_then(|key| tenant_keys().get(key))
+        .cloned()
+        .ok_or(ApiError::InvalidApiKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tenant_keys() {
+        let keys = parse_tenant_keys("k3y-a=acme, k3y-b = globex,missing,=nokey,,");
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys["k3y-a"], "acme");
+        assert_eq!(keys["k3y-b"], "globex");
+    }
+}
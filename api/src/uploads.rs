@@ -0,0 +1,231 @@
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::errors::ApiError;
+use crate::Result;
+
+// Time an upload is kept after it was started, whether or not it was completed
+pub const UPLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Metadata of an upload, stored next to its data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UploadMetadata {
+    program_id: String,
+    size: u64,
+    created_at: NaiveDateTime,
+}
+
+/// An executable uploaded in chunks, which can be resumed from `offset`.
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub id: String,
+    pub program_id: String,
+    pub size: u64,
+    pub offset: u64,
+    pub created_at: NaiveDateTime,
+}
+
+impl Upload {
+    pub fn is_complete(&self) -> bool {
+        self.offset == self.size
+    }
+
+    pub fn expires_at(&self) -> NaiveDateTime {
+        self.created_at + chrono::Duration::from_std(UPLOAD_TTL).unwrap_or_default()
+    }
+}
+
+// Chunks are appended one at a time so two requests can not both write at the same offset
+fn append_lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+/// Uploads in progress, kept on disk under `UPLOAD_DIR`.
+pub struct UploadStore {
+    dir: PathBuf,
+}
+
+impl UploadStore {
+    /// Store uploads in `UPLOAD_DIR`, or a directory under the system temp dir.
+    pub fn from_env() -> Self {
+        Self {
+            dir: env::var("UPLOAD_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| env::temp_dir().join("verified-programs-uploads")),
+        }
+    }
+
+    fn metadata_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.part", id))
+    }
+
+    /// Start an upload of `size` bytes for a program.
+    pub async fn create(&self, program_id: &str, size: u64) -> Result<Upload> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        self.remove_expired().await;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let metadata = UploadMetadata {
+            program_id: program_id.to_string(),
+            size,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+        tokio::fs::write(self.data_path(&id), b"").await?;
+        tokio::fs::write(
+            self.metadata_path(&id),
+            serde_json::to_vec(&metadata).map_err(|err| ApiError::Custom(err.to_string()))?,
+        )
+        .await?;
+
+        Ok(Upload {
+            id,
+            program_id: metadata.program_id,
+            size,
+            offset: 0,
+            created_at: metadata.created_at,
+        })
+    }
+
+    /// Get an upload, removing it if it expired.
+    pub async fn get(&self, id: &str) -> Result<Upload> {
+        // Ids are uuids, which also keeps them from escaping the upload directory
+        if uuid::Uuid::parse_str(id).is_err() {
+            return Err(ApiError::UploadNotFound(id.to_string()));
+        }
+
+        let metadata = match tokio::fs::read(self.metadata_path(id)).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ApiError::UploadNotFound(id.to_string()))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let metadata: UploadMetadata = serde_json::from_slice(&metadata)
+            .map_err(|err| ApiError::Custom(format!("Corrupted upload {}: {}", id, err)))?;
+        let offset = tokio::fs::metadata(self.data_path(id)).await?.len();
+
+        let upload = Upload {
+            id: id.to_string(),
+            program_id: metadata.program_id,
+            size: metadata.size,
+            offset,
+            created_at: metadata.created_at,
+        };
+        if upload.expires_at() < chrono::Utc::now().naive_utc() {
+            self.remove(id).await;
+            return Err(ApiError::UploadNotFound(id.to_string()));
+        }
+        Ok(upload)
+    }
+
+    /// Append a chunk at `offset`, which must be where the upload stopped.
+    pub async fn append(&self, id: &str, offset: u64, chunk: &[u8]) -> Result<Upload> {
+        let _guard = append_lock().lock().await;
+
+        let mut upload = self.get(id).await?;
+        if offset != upload.offset {
+            return Err(ApiError::UploadOffsetMismatch {
+                expected: upload.offset,
+                received: offset,
+            });
+        }
+        let end = upload.offset + chunk.len() as u64;
+        if end > upload.size {
+            return Err(ApiError::ProgramTooLarge {
+                size: end,
+                limit: upload.size,
+            });
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(self.data_path(id))
+            .await?;
+        file.write_all(chunk).await?;
+        file.flush().await?;
+
+        upload.offset = end;
+        Ok(upload)
+    }
+
+    /// Read the executable of a completed upload.
+    pub async fn read(&self, upload: &Upload) -> Result<Vec<u8>> {
+        Ok(tokio::fs::read(self.data_path(&upload.id)).await?)
+    }
+
+    // Remove the abandoned uploads, which are otherwise only removed when requested again
+    async fn remove_expired(&self) {
+        let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    // Expired uploads are removed by `get`
+                    let _ = self.get(id).await;
+                }
+            }
+        }
+    }
+
+    // Failures are logged, as a leftover upload is only a waste of disk space
+    async fn remove(&self, id: &str) {
+        for path in [self.metadata_path(id), self.data_path(id)] {
+            if let Err(err) = tokio::fs::remove_file(&path).await {
+                tracing::warn!("Failed to remove {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resume_upload() {
+        let store = UploadStore {
+            dir: env::temp_dir().join(format!("uploads-test-{}", uuid::Uuid::new_v4())),
+        };
+        let upload = store
+            .create("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY", 6)
+            .await
+            .unwrap();
+
+        store.append(&upload.id, 0, b"abc").await.unwrap();
+        assert!(matches!(
+            store.append(&upload.id, 0, b"abc").await,
+            Err(ApiError::UploadOffsetMismatch {
+                expected: 3,
+                received: 0
+            })
+        ));
+        assert!(store.append(&upload.id, 3, b"defg").await.is_err());
+
+        let upload = store.append(&upload.id, 3, b"def").await.unwrap();
+        assert!(upload.is_complete());
+        assert_eq!(store.read(&upload).await.unwrap(), b"abcdef");
+
+        assert!(matches!(
+            store.get("../../etc/passwd").await,
+            Err(ApiError::UploadNotFound(_))
+        ));
+        let _ = tokio::fs::remove_dir_all(&store.dir).await;
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+use crate::errors::ApiError;
+use crate::git::normalize_repository;
+use crate::models::{ReadyResponse, ReadyState, WorkerPoolStatus};
+use crate::Result;
+
+// Number of build workers started when `BUILD_WORKERS` is not set
+pub const DEFAULT_BUILD_WORKERS: usize = 4;
+// Upper bound accepted when resizing the pool
+pub const MAX_BUILD_WORKERS: usize = 64;
+// Concurrent builds of a single repository when `BUILD_CONCURRENCY_PER_REPO` is not set
+pub const DEFAULT_BUILD_CONCURRENCY_PER_REPO: usize = 2;
+
+type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct QueuedJob {
+    // Normalized repository the job builds, if it is subject to the per-repo limit
+    repository: Option<String>,
+    job: Job,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<QueuedJob>,
+    active_per_repo: HashMap<String, usize>,
+}
+
+impl Queue {
+    // Take the oldest job whose repository is below the per-repo limit, deferring the others
+    fn pop_runnable(&mut self, per_repo_limit: usize) -> Option<QueuedJob> {
+        let position = self
+            .jobs
+            .iter()
+            .position(|queued| match &queued.repository {
+                Some(repository) => {
+                    self.active_per_repo.get(repository).copied().unwrap_or(0) < per_repo_limit
+                }
+                None => true,
+            })?;
+        let queued = self.jobs.remove(position)?;
+        if let Some(repository) = &queued.repository {
+            *self.active_per_repo.entry(repository.clone()).or_default() += 1;
+        }
+        Some(queued)
+    }
+
+    fn finish(&mut self, repository: &str) {
+        if let Some(active) = self.active_per_repo.get_mut(repository) {
+            *active -= 1;
+            if *active == 0 {
+                self.active_per_repo.remove(repository);
+            }
+        }
+    }
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    notify: Notify,
+    target: AtomicUsize,
+    workers: AtomicUsize,
+    active: AtomicUsize,
+    per_repo_limit: usize,
+    // Set once an operator drains the pool, new builds are then refused
+    draining: AtomicBool,
+}
+
+/// A pool of background workers which run queued verification jobs. The number
+/// of workers can be changed at runtime with [`WorkerPool::resize`].
+#[derive(Clone)]
+pub struct WorkerPool {
+    shared: Arc<Shared>,
+}
+
+impl WorkerPool {
+    /// Create a pool and start `size` workers, running at most `per_repo_limit` jobs
+    /// of the same repository at once. Must be called inside a Tokio runtime.
+    pub fn new(size: usize, per_repo_limit: usize) -> Self {
+        let pool = Self {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(Queue::default()),
+                notify: Notify::new(),
+                target: AtomicUsize::new(0),
+                workers: AtomicUsize::new(0),
+                active: AtomicUsize::new(0),
+                per_repo_limit: per_repo_limit.max(1),
+                draining: AtomicBool::new(false),
+            }),
+        };
+        pool.resize(size);
+        pool
+    }
+
+    /// Create a pool sized from `BUILD_WORKERS` and `BUILD_CONCURRENCY_PER_REPO`,
+    /// falling back to the defaults.
+    pub fn from_env() -> Self {
+        fn parse_env(key: &str) -> Option<usize> {
+            env::var(key).ok().and_then(|value| value.parse().ok())
+        }
+
+        let size = parse_env("BUILD_WORKERS")
+            .unwrap_or(DEFAULT_BUILD_WORKERS)
+            .clamp(1, MAX_BUILD_WORKERS);
+        let per_repo_limit =
+            parse_env("BUILD_CONCURRENCY_PER_REPO").unwrap_or(DEFAULT_BUILD_CONCURRENCY_PER_REPO);
+        Self::new(size, per_repo_limit)
+    }
+
+    fn enqueue(&self, repository: Option<String>, job: Job) {
+        self.shared
+            .queue
+            .lock()
+            .expect("worker queue poisoned")
+            .jobs
+            .push_back(QueuedJob { repository, job });
+        self.shared.notify.notify_one();
+    }
+
+    /// Queue a job to be run by the next idle worker.
+    pub fn spawn<F>(&self, job: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.enqueue(None, Box::pin(job));
+    }
+
+    /// Queue a build of `repository`. Builds of a repository which already has the
+    /// maximum number of active builds are deferred so other repositories are not starved.
+    pub fn spawn_for_repo<F>(&self, repository: &str, job: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.enqueue(Some(normalize_repository(repository)), Box::pin(job));
+    }
+
+    /// Change the number of workers. New workers are started immediately, while
+    /// surplus workers retire once they finish their current job.
+    pub fn resize(&self, size: usize) {
+        self.shared.target.store(size, Ordering::SeqCst);
+
+        while self
+            .shared
+            .workers
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |workers| {
+                (workers < size).then_some(workers + 1)
+            })
+            .is_ok()
+        {
+            tokio::spawn(run_worker(self.shared.clone()));
+        }
+
+        // Wake idle workers so the surplus can retire
+        self.shared.notify.notify_waiters();
+    }
+
+    /// Stop accepting new builds so the instance can be shut down once the queued and
+    /// running ones finish. Draining cannot be undone, the instance is meant to be replaced.
+    pub fn drain(&self) {
+        self.shared.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Fail with `Draining` once the pool was drained, so no new build is started.
+    pub fn ensure_accepting_builds(&self) -> Result<()> {
+        if self.shared.draining.load(Ordering::SeqCst) {
+            return Err(ApiError::Draining);
+        }
+        Ok(())
+    }
+
+    /// Whether the instance accepts builds, is draining them or can be shut down.
+    pub fn readiness(&self) -> ReadyResponse {
+        let status = self.status();
+        let state = if !status.draining {
+            ReadyState::Ready
+        } else if status.active == 0 && status.queued == 0 {
+            ReadyState::Drained
+        } else {
+            ReadyState::Draining
+        };
+        ReadyResponse {
+            state,
+            active: status.active,
+            queued: status.queued,
+        }
+    }
+
+    pub fn status(&self) -> WorkerPoolStatus {
+        let workers = self.shared.workers.load(Ordering::SeqCst);
+        let active = self.shared.active.load(Ordering::SeqCst);
+        WorkerPoolStatus {
+            size: self.shared.target.load(Ordering::SeqCst),
+            workers,
+            active,
+            idle: workers.saturating_sub(active),
+            queued: self
+                .shared
+                .queue
+                .lock()
+                .expect("worker queue poisoned")
+                .jobs
+                .len(),
+            per_repo_limit: self.shared.per_repo_limit,
+            draining: self.shared.draining.load(Ordering::SeqCst),
+        }
+    }
+}
+
+// Retire the calling worker if the pool has more workers than its target size
+fn try_retire(shared: &Shared) -> bool {
+    shared
+        .workers
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |workers| {
+            (workers > shared.target.load(Ordering::SeqCst)).then(|| workers - 1)
+        })
+        .is_ok()
+}
+
+async fn run_worker(shared: Arc<Shared>) {
+    loop {
+        if try_retire(&shared) {
+            return;
+        }
+
+        let queued = shared
+            .queue
+            .lock()
+            .expect("worker queue poisoned")
+            .pop_runnable(shared.per_repo_limit);
+        match queued {
+            Some(QueuedJob { repository, job }) => {
+                shared.active.fetch_add(1, Ordering::SeqCst);
+                // Run in its own task so a panicking job neither kills the worker nor skips
+                // the bookkeeping below
+                if let Err(err) = tokio::spawn(job).await {
+                    tracing::error!("Build job failed: {}", err);
+                }
+                shared.active.fetch_sub(1, Ordering::SeqCst);
+
+                if let Some(repository) = repository {
+                    shared
+                        .queue
+                        .lock()
+                        .expect("worker queue poisoned")
+                        .finish(&repository);
+                    // A deferred build of the repository may be runnable now
+                    shared.notify.notify_one();
+                }
+            }
+            None => shared.notify.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn test_resize_pool() {
+        let pool = WorkerPool::new(2, 1);
+        settle().await;
+        assert_eq!(pool.status().workers, 2);
+        assert_eq!(pool.status().idle, 2);
+
+        pool.resize(5);
+        settle().await;
+        assert_eq!(pool.status().workers, 5);
+
+        pool.resize(1);
+        settle().await;
+        assert_eq!(pool.status().workers, 1);
+        assert_eq!(pool.status().size, 1);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_queued() {
+        let pool = WorkerPool::new(1, 1);
+        let (release, blocked) = oneshot::channel::<()>();
+        let (done, finished) = oneshot::channel();
+
+        pool.spawn(async move {
+            let _ = blocked.await;
+        });
+        pool.spawn(async move {
+            let _ = done.send(());
+        });
+        settle().await;
+
+        let status = pool.status();
+        assert_eq!(status.active, 1);
+        assert_eq!(status.queued, 1);
+
+        release.send(()).unwrap();
+        finished.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_per_repo_limit_defers_builds() {
+        let pool = WorkerPool::new(3, 1);
+        let (release, blocked) = oneshot::channel::<()>();
+        let (done, finished) = oneshot::channel();
+
+        pool.spawn_for_repo("https://github.com/a/b", async move {
+            let _ = blocked.await;
+        });
+        pool.spawn_for_repo("https://github.com/a/b.git", async {});
+        pool.spawn_for_repo("https://github.com/c/d", async move {
+            let _ = done.send(());
+        });
+
+        // The other repository is not held up by the deferred build
+        finished.await.unwrap();
+        settle().await;
+        let status = pool.status();
+        assert_eq!(status.active, 1);
+        assert_eq!(status.queued, 1);
+
+        release.send(()).unwrap();
+        settle().await;
+        assert_eq!(pool.status().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_keeps_worker() {
+        let pool = WorkerPool::new(1, 1);
+        let (done, finished) = oneshot::channel();
+
+        pool.spawn_for_repo("https://github.com/a/b", async { panic!("build panicked") });
+        pool.spawn_for_repo("https://github.com/a/b", async move {
+            let _ = done.send(());
+        });
+
+        finished.await.unwrap();
+        settle().await;
+        let status = pool.status();
+        assert_eq!((status.workers, status.active, status.queued), (1, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let pool = WorkerPool::new(1, 1);
+        let (release, blocked) = oneshot::channel::<()>();
+        pool.spawn(async move {
+            let _ = blocked.await;
+        });
+        pool.spawn(async {});
+        settle().await;
+        assert_eq!(pool.readiness().state, ReadyState::Ready);
+        assert!(pool.ensure_accepting_builds().is_ok());
+
+        pool.drain();
+        assert!(matches!(
+            pool.ensure_accepting_builds(),
+            Err(ApiError::Draining)
+        ));
+        let readiness = pool.readiness();
+        assert_eq!(readiness.state, ReadyState::Draining);
+        assert_eq!((readiness.active, readiness.queued), (1, 1));
+
+        // Queued jobs still run while draining
+        release.send(()).unwrap();
+        settle().await;
+        assert_eq!(pool.readiness().state, ReadyState::Drained);
+    }
+}